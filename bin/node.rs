@@ -1,35 +1,328 @@
-use tburn_chain_v4_0::core::Blockchain;
-use tburn_chain_v4_0::core::rpc::RpcServer;
-use std::sync::Arc;
-use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
-
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
-
-    println!("🔥 Starting BURN Chain v4.0 Node...");
-
-    // Initialize Database
-    let db_url = "sqlite://explorer.db?mode=rwc";
-    let pool = SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect(db_url)
-        .await?;
-
-    // Run Migrations
-    sqlx::query(include_str!("../migrations/20251122000000_explorer_schema.sql"))
-        .execute(&pool)
-        .await?;
-
-    println!("✅ Database initialized");
-
-    // Initialize Blockchain
-    let blockchain = Arc::new(Blockchain::new());
-
-    // Initialize RPC Server
-    let rpc_server = RpcServer::new(blockchain.clone(), pool);
-
-    // Start RPC Server
-    rpc_server.start_all().await.map_err(|e| e.into())
-}
+use tburn_chain_v4_0::core::blockchain::ChainError;
+use tburn_chain_v4_0::core::{
+    BlockProducer, Blockchain, ChainParams, EmberGasSystem, GasConfig, Genesis, Mempool, MempoolConfig, NetworkProfile, NodeConfig,
+    WorldState,
+};
+use tburn_chain_v4_0::storage::cache::{CacheConfig, CachedStore};
+use tburn_chain_v4_0::storage::mvcc::DEFAULT_RETAINED_VERSIONS;
+use tburn_chain_v4_0::storage::pruning::{Pruner, PruningConfig, PruningMode};
+use tburn_chain_v4_0::storage::rocksdb::RocksStore;
+use tburn_chain_v4_0::storage::snapshot::{self, SnapshotManifest, SnapshotOptions};
+use tburn_chain_v4_0::core::rpc::RpcServer;
+use tburn_chain_v4_0::core::types::{to_hex, H256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+
+/// Time between read cache statistics in the log.
+const CACHE_STATS_INTERVAL: Duration = Duration::from_secs(300);
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    tracing_subscriber::fmt::init();
+
+    println!("🔥 Starting BURN Chain v4.0 Node...");
+
+    // Load Configuration
+    let profile: NetworkProfile = arg_value("--network")
+        .or_else(|| std::env::var("TBURN_NETWORK").ok())
+        .unwrap_or_else(|| "mainnet".to_string())
+        .parse()?;
+    let config_dir = arg_value("--config-dir").unwrap_or_else(|| "config".to_string());
+    let config = NodeConfig::load(&config_dir, profile)?;
+
+    println!("✅ Loaded {} configuration ({})", profile, config.network.name);
+
+    // Initialize Database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(config.database.max_connections)
+        .acquire_timeout(Duration::from_secs(config.database.connection_timeout_seconds))
+        .connect(&config.database.url)
+        .await?;
+
+    // Run Migrations
+    run_migrations(&pool).await?;
+
+    println!("✅ Database initialized");
+
+    // Build Genesis
+    let genesis_spec = Genesis::load(&config.network.genesis_file)?;
+    if genesis_spec.chain_id != config.network.chain_id {
+        return Err(format!(
+            "genesis chainId {} does not match network.chain_id {}",
+            genesis_spec.chain_id, config.network.chain_id
+        )
+        .into());
+    }
+    let genesis = genesis_spec.build_and_verify()?;
+    let genesis_hash = to_hex(&genesis.hash());
+    if let Some(expected) = &config.network.genesis_hash {
+        if !expected.eq_ignore_ascii_case(&genesis_hash) {
+            return Err(format!(
+                "network.genesis_hash {} does not match computed genesis {}",
+                expected, genesis_hash
+            )
+            .into());
+        }
+    }
+    check_stored_genesis(&pool, &genesis_hash).await?;
+
+    println!("✅ Genesis {}", genesis_hash);
+
+    // Open Chain Storage, bootstrapping an empty database from a state
+    // snapshot when one is given
+    let genesis_block_hash = genesis.block.hash();
+    let db = Arc::new(CachedStore::new(
+        Arc::new(RocksStore::open(&config.database.data_dir)?),
+        CacheConfig::from_node_config(&config),
+    ));
+    let blockchain = match arg_value("--snapshot") {
+        Some(path) => {
+            let (manifest, snapshot_state) = snapshot::read(Path::new(&path))?;
+            if manifest.genesis_hash != genesis_block_hash {
+                return Err(format!(
+                    "snapshot genesis {} does not match configured genesis {}",
+                    to_hex(&manifest.genesis_hash),
+                    genesis_hash
+                )
+                .into());
+            }
+            let height = manifest.height();
+            match Blockchain::bootstrap(db.clone(), genesis.block.clone(), manifest.block, &snapshot_state) {
+                Ok(chain) => {
+                    println!("✅ Bootstrapped from snapshot {} at #{}", path, height);
+                    chain
+                }
+                Err(ChainError::NotEmpty) => {
+                    println!("ℹ️  Chain storage is not empty, ignoring --snapshot");
+                    Blockchain::open(db.clone(), genesis.block, &genesis.state)?
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+        None => Blockchain::open(db.clone(), genesis.block, &genesis.state)?,
+    };
+    let blockchain = Arc::new(blockchain);
+    let head = blockchain.head().await;
+    let head_state = WorldState::load(db.as_ref(), &head.header.state_root)?;
+    blockchain.track_history(&head_state, DEFAULT_RETAINED_VERSIONS).await;
+    let state = Arc::new(tokio::sync::RwLock::new(head_state));
+
+    println!(
+        "✅ Chain storage at {} (head #{}, {} MiB read cache)",
+        config.database.data_dir.display(),
+        head.number(),
+        config.database.cache_size_mb
+    );
+    let mut txpool = Mempool::new(MempoolConfig::from_node_config(&config));
+    txpool.set_base_fee(GasConfig::from_node_config(&config).next_base_fee(&head.header));
+    let mempool = Arc::new(parking_lot::RwLock::new(txpool));
+
+    println!("✅ Mempool ready (capacity {})", config.network.performance.transaction_pool_size);
+
+    // Start Block Production
+    match config.consensus.proposer {
+        Some(proposer) => {
+            let producer = BlockProducer::new(
+                ChainParams::from_node_config(&config),
+                proposer,
+                blockchain.clone(),
+                state.clone(),
+                mempool.clone(),
+            );
+            tokio::spawn(produce_blocks(producer, Duration::from_millis(config.consensus.block_time_ms)));
+            println!("✅ Producing blocks every {}ms as {}", config.consensus.block_time_ms, proposer);
+        }
+        None => println!("ℹ️  No consensus.proposer configured, block production disabled"),
+    }
+
+    tokio::spawn(log_cache_stats(db.clone(), CACHE_STATS_INTERVAL));
+
+    // Start Pruning
+    let pruning = PruningConfig::from_node_config(&config);
+    if pruning.mode != PruningMode::Archive {
+        println!(
+            "✅ Pruning in {:?} mode, keeping the last {} states",
+            pruning.mode, pruning.keep_recent
+        );
+        tokio::spawn(Arc::new(Pruner::new(blockchain.clone(), db.clone(), pruning)).run());
+    }
+
+    // Schedule State Snapshots
+    if config.deployment.backup_enabled {
+        let dir = config.deployment.backup_dir.clone();
+        let interval = Duration::from_secs(config.deployment.backup_interval_hours * 3600);
+        println!("✅ State snapshots every {}h to {}", config.deployment.backup_interval_hours, dir.display());
+        let keep = config.deployment.backup_keep_recent;
+        tokio::spawn(write_snapshots(blockchain.clone(), genesis_block_hash, dir, interval, keep));
+    }
+
+    // Initialize RPC Server
+    let gas = EmberGasSystem::new(GasConfig::from_node_config(&config));
+    let rpc_server = RpcServer::new(blockchain.clone(), mempool, gas, pool, db, config.api.clone());
+
+    // Start RPC Server
+    rpc_server.start_all().await
+}
+
+/// Produce a block every `block_time` until the process exits.
+async fn produce_blocks(producer: BlockProducer, block_time: Duration) {
+    let mut interval = tokio::time::interval(block_time);
+    loop {
+        interval.tick().await;
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        match producer.produce(now_ms).await {
+            Ok(executed) => tracing::debug!(
+                number = executed.block.number(),
+                txs = executed.block.tx_count(),
+                "produced block"
+            ),
+            Err(err) => tracing::error!("block production failed: {}", err),
+        }
+    }
+}
+
+/// Log the read cache hit rates and sizes every `interval` until the
+/// process exits.
+async fn log_cache_stats(db: Arc<CachedStore>, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    // The first tick completes immediately
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let stats = db.stats();
+        tracing::info!(
+            node_hit_rate = stats.nodes.hit_rate(),
+            node_bytes = stats.nodes.bytes,
+            account_hit_rate = stats.accounts.hit_rate(),
+            account_bytes = stats.accounts.bytes,
+            code_hit_rate = stats.code.hit_rate(),
+            code_bytes = stats.code.bytes,
+            "read cache"
+        );
+    }
+}
+
+/// Snapshot the head state every `interval` until the process exits,
+/// keeping the `keep` most recent snapshots.
+async fn write_snapshots(blockchain: Arc<Blockchain>, genesis_hash: H256, dir: PathBuf, interval: Duration, keep: usize) {
+    let mut interval = tokio::time::interval(interval);
+    // The first tick completes immediately
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        match write_snapshot(&blockchain, genesis_hash, &dir).await {
+            Ok(manifest) => tracing::info!(
+                number = manifest.height(),
+                chunks = manifest.chunks.len(),
+                "wrote state snapshot"
+            ),
+            Err(err) => tracing::error!("state snapshot failed: {}", err),
+        }
+        match snapshot::rotate(&dir, keep) {
+            Ok(removed) if !removed.is_empty() => tracing::info!(removed = removed.len(), "deleted old state snapshots"),
+            Ok(_) => {}
+            Err(err) => tracing::error!("deleting old state snapshots failed: {}", err),
+        }
+    }
+}
+
+/// Write `<dir>/snapshot-<height>.tbsnap` from the head state. The state
+/// is read through an MVCC snapshot, so block import carries on meanwhile.
+async fn write_snapshot(
+    blockchain: &Blockchain,
+    genesis_hash: H256,
+    dir: &Path,
+) -> Result<SnapshotManifest, Box<dyn std::error::Error + Send + Sync>> {
+    let state = blockchain.latest_state().await?;
+    let block = blockchain
+        .get_block_by_number(state.height())
+        .await
+        .ok_or("head block is missing")?;
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(snapshot::file_name(state.height()));
+    let manifest = tokio::task::spawn_blocking(move || {
+        snapshot::write(&path, &genesis_hash, &block, &state.to_world_state(), SnapshotOptions::default())
+    })
+    .await??;
+    Ok(manifest)
+}
+
+/// Value following `flag` on the command line, e.g. `--network devnet`.
+fn arg_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(flag).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Schema migrations in the order they apply. Each runs once per database;
+/// released ones must never change, fixes go in a new one.
+const MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "20251122000000_explorer_schema",
+        include_str!("../migrations/20251122000000_explorer_schema.sql"),
+    ),
+    (
+        "20251201000000_chain_metadata",
+        include_str!("../migrations/20251201000000_chain_metadata.sql"),
+    ),
+];
+
+/// Apply the migrations not yet recorded in `schema_migrations`.
+async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query(include_str!("../migrations/20251202000000_schema_migrations.sql"))
+        .execute(pool)
+        .await?;
+    for (version, sql) in MIGRATIONS {
+        let applied = sqlx::query("SELECT 1 FROM schema_migrations WHERE version = ?")
+            .bind(version)
+            .fetch_optional(pool)
+            .await?
+            .is_some();
+        if applied {
+            continue;
+        }
+        let mut tx = pool.begin().await?;
+        sqlx::query(sql).execute(&mut *tx).await?;
+        sqlx::query("INSERT INTO schema_migrations (version, applied_at) VALUES (?, strftime('%s', 'now'))")
+            .bind(version)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+/// Refuse to run against a database initialized with a different genesis.
+async fn check_stored_genesis(pool: &SqlitePool, genesis_hash: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let stored: Option<(String,)> = sqlx::query_as("SELECT value FROM chain_metadata WHERE key = 'genesis_hash'")
+        .fetch_optional(pool)
+        .await?;
+
+    match stored {
+        Some((stored,)) if stored != genesis_hash => Err(format!(
+            "stored genesis {} does not match configured genesis {}",
+            stored, genesis_hash
+        )
+        .into()),
+        Some(_) => Ok(()),
+        None => {
+            sqlx::query("INSERT INTO chain_metadata (key, value) VALUES ('genesis_hash', ?)")
+                .bind(genesis_hash)
+                .execute(pool)
+                .await?;
+            Ok(())
+        }
+    }
+}
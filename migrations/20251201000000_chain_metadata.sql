@@ -0,0 +1,6 @@
+-- Chain Metadata
+
+CREATE TABLE IF NOT EXISTS chain_metadata (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
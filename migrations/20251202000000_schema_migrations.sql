@@ -0,0 +1,12 @@
+-- Schema Migrations
+
+CREATE TABLE IF NOT EXISTS schema_migrations (
+    version TEXT PRIMARY KEY,
+    applied_at INTEGER NOT NULL
+);
+
+-- Databases created before migrations were tracked already have the
+-- explorer schema and its seed data
+INSERT OR IGNORE INTO schema_migrations (version, applied_at)
+SELECT '20251122000000_explorer_schema', strftime('%s', 'now')
+WHERE EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'network_stats');
@@ -0,0 +1,8 @@
+pub mod cache_manager;
+pub mod claude_integration;
+pub mod cost_optimizer;
+pub mod gpt_integration;
+pub mod llama_integration;
+pub mod load_balancer;
+pub mod model_router;
+pub mod orchestrator;
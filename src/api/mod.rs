@@ -0,0 +1,5 @@
+pub mod auth;
+pub mod graphql;
+pub mod rest;
+pub mod rpc;
+pub mod websocket;
@@ -0,0 +1,6 @@
+pub mod bft;
+pub mod finality;
+pub mod quorum;
+pub mod reward;
+pub mod validator;
+pub mod voting;
@@ -0,0 +1,775 @@
+// ==========================================
+// TBURN Contract ABI
+// ==========================================
+
+use std::fmt;
+
+use primitive_types::U256;
+use serde::Deserialize;
+
+use crate::core::address::Address;
+use crate::core::receipt::Log;
+use crate::core::types::{keccak256, to_hex, H256};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AbiError {
+    #[error("invalid type `{0}`")]
+    InvalidType(String),
+    #[error("invalid ABI data: {0}")]
+    InvalidData(&'static str),
+    #[error("expected {expected} values, got {got}")]
+    WrongCount { expected: usize, got: usize },
+    #[error("value does not match type {0}")]
+    TypeMismatch(String),
+    #[error("invalid JSON ABI: {0}")]
+    Json(String),
+    #[error("no function with selector 0x{}", hex::encode(.0))]
+    UnknownSelector([u8; 4]),
+    #[error("no event with topic {}", to_hex(.0))]
+    UnknownEvent(H256),
+}
+
+/// First four bytes of the keccak256 of a canonical signature such as
+/// `transfer(address,uint256)`.
+pub fn selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+// ==========================================
+// Types
+// ==========================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamType {
+    Address,
+    Bool,
+    /// `uintN`, with N in bits
+    Uint(usize),
+    /// `intN`, with N in bits
+    Int(usize),
+    /// `bytesN`, with N in bytes
+    FixedBytes(usize),
+    /// An address followed by a selector, encoded as `bytes24`
+    Function,
+    Bytes,
+    String,
+    Array(Box<ParamType>),
+    FixedArray(Box<ParamType>, usize),
+    Tuple(Vec<ParamType>),
+}
+
+impl ParamType {
+    /// Parse a canonical type such as `uint256`, `bytes32[]` or
+    /// `(address,uint256)[2]`.
+    pub fn parse(s: &str) -> Result<Self, AbiError> {
+        let s = s.trim();
+        let invalid = || AbiError::InvalidType(s.to_string());
+
+        if let Some(inner) = s.strip_suffix(']') {
+            let open = inner.rfind('[').ok_or_else(invalid)?;
+            let element = Box::new(Self::parse(&inner[..open])?);
+            let len = &inner[open + 1..];
+            return if len.is_empty() {
+                Ok(ParamType::Array(element))
+            } else {
+                Ok(ParamType::FixedArray(element, len.parse().map_err(|_| invalid())?))
+            };
+        }
+        if let Some(inner) = s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+            return split_top_level(inner)
+                .ok_or_else(invalid)?
+                .into_iter()
+                .map(Self::parse)
+                .collect::<Result<_, _>>()
+                .map(ParamType::Tuple);
+        }
+
+        let bits = |digits: &str| -> Result<usize, AbiError> {
+            if digits.is_empty() {
+                return Ok(256);
+            }
+            match digits.parse::<usize>() {
+                Ok(n) if n > 0 && n <= 256 && n % 8 == 0 && !digits.starts_with('0') => Ok(n),
+                _ => Err(invalid()),
+            }
+        };
+        match s {
+            "address" => Ok(ParamType::Address),
+            "bool" => Ok(ParamType::Bool),
+            "string" => Ok(ParamType::String),
+            "bytes" => Ok(ParamType::Bytes),
+            "function" => Ok(ParamType::Function),
+            _ => {
+                if let Some(digits) = s.strip_prefix("uint") {
+                    Ok(ParamType::Uint(bits(digits)?))
+                } else if let Some(digits) = s.strip_prefix("int") {
+                    Ok(ParamType::Int(bits(digits)?))
+                } else if let Some(digits) = s.strip_prefix("bytes") {
+                    match digits.parse::<usize>() {
+                        Ok(n) if (1..=32).contains(&n) && !digits.starts_with('0') => Ok(ParamType::FixedBytes(n)),
+                        _ => Err(invalid()),
+                    }
+                } else {
+                    Err(invalid())
+                }
+            }
+        }
+    }
+
+    /// Whether values are stored in the tail and referenced by offset.
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            ParamType::Bytes | ParamType::String | ParamType::Array(_) => true,
+            ParamType::FixedArray(element, len) => *len > 0 && element.is_dynamic(),
+            ParamType::Tuple(types) => types.iter().any(ParamType::is_dynamic),
+            _ => false,
+        }
+    }
+
+    /// Bytes a value takes in the head of its enclosing sequence.
+    fn head_size(&self) -> usize {
+        if self.is_dynamic() {
+            return 32;
+        }
+        match self {
+            ParamType::FixedArray(element, len) => element.head_size().saturating_mul(*len),
+            ParamType::Tuple(types) => types.iter().map(ParamType::head_size).sum(),
+            _ => 32,
+        }
+    }
+}
+
+impl fmt::Display for ParamType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamType::Address => f.write_str("address"),
+            ParamType::Bool => f.write_str("bool"),
+            ParamType::Uint(bits) => write!(f, "uint{bits}"),
+            ParamType::Int(bits) => write!(f, "int{bits}"),
+            ParamType::FixedBytes(len) => write!(f, "bytes{len}"),
+            ParamType::Function => f.write_str("function"),
+            ParamType::Bytes => f.write_str("bytes"),
+            ParamType::String => f.write_str("string"),
+            ParamType::Array(element) => write!(f, "{element}[]"),
+            ParamType::FixedArray(element, len) => write!(f, "{element}[{len}]"),
+            ParamType::Tuple(types) => write!(f, "({})", join(types, ",")),
+        }
+    }
+}
+
+/// Split `a,(b,c),d[]` at commas outside parentheses. `None` if the
+/// parentheses do not balance.
+fn split_top_level(s: &str) -> Option<Vec<&str>> {
+    if s.trim().is_empty() {
+        return Some(Vec::new());
+    }
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1)?,
+            ',' if depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return None;
+    }
+    parts.push(&s[start..]);
+    Some(parts)
+}
+
+fn join<T: fmt::Display>(items: &[T], separator: &str) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+// ==========================================
+// Values
+// ==========================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Address(Address),
+    Bool(bool),
+    Uint(U256),
+    /// Two's complement
+    Int(U256),
+    FixedBytes(Vec<u8>),
+    Bytes(Vec<u8>),
+    String(String),
+    Array(Vec<Token>),
+    FixedArray(Vec<Token>),
+    Tuple(Vec<Token>),
+}
+
+impl Token {
+    /// Whether this value can be encoded as `kind`.
+    pub fn matches(&self, kind: &ParamType) -> bool {
+        match (self, kind) {
+            (Token::Address(_), ParamType::Address)
+            | (Token::Bool(_), ParamType::Bool)
+            | (Token::Bytes(_), ParamType::Bytes)
+            | (Token::String(_), ParamType::String) => true,
+            (Token::Uint(value), ParamType::Uint(bits)) => value.bits() <= *bits,
+            (Token::Int(_), ParamType::Int(_)) => true,
+            (Token::FixedBytes(bytes), ParamType::FixedBytes(len)) => bytes.len() == *len,
+            (Token::FixedBytes(bytes), ParamType::Function) => bytes.len() == 24,
+            (Token::Array(items), ParamType::Array(element)) => items.iter().all(|t| t.matches(element)),
+            (Token::FixedArray(items), ParamType::FixedArray(element, len)) => {
+                items.len() == *len && items.iter().all(|t| t.matches(element))
+            }
+            (Token::Tuple(items), ParamType::Tuple(types)) => {
+                items.len() == types.len() && items.iter().zip(types).all(|(t, k)| t.matches(k))
+            }
+            _ => false,
+        }
+    }
+
+    pub fn into_address(self) -> Option<Address> {
+        match self {
+            Token::Address(address) => Some(address),
+            _ => None,
+        }
+    }
+
+    pub fn into_bool(self) -> Option<bool> {
+        match self {
+            Token::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Value of a `Uint` or the raw bits of an `Int`.
+    pub fn into_uint(self) -> Option<U256> {
+        match self {
+            Token::Uint(value) | Token::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn into_bytes(self) -> Option<Vec<u8>> {
+        match self {
+            Token::Bytes(bytes) | Token::FixedBytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn into_string(self) -> Option<String> {
+        match self {
+            Token::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Elements of an array, fixed array or tuple.
+    pub fn into_tokens(self) -> Option<Vec<Token>> {
+        match self {
+            Token::Array(items) | Token::FixedArray(items) | Token::Tuple(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    fn is_dynamic(&self) -> bool {
+        match self {
+            Token::Bytes(_) | Token::String(_) | Token::Array(_) => true,
+            Token::FixedArray(items) | Token::Tuple(items) => items.iter().any(Token::is_dynamic),
+            _ => false,
+        }
+    }
+
+    fn head_size(&self) -> usize {
+        match self {
+            _ if self.is_dynamic() => 32,
+            Token::FixedArray(items) | Token::Tuple(items) => items.iter().map(Token::head_size).sum(),
+            _ => 32,
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Address(address) => write!(f, "{address}"),
+            Token::Bool(value) => write!(f, "{value}"),
+            Token::Uint(value) => write!(f, "{value}"),
+            Token::Int(value) if value.bit(255) => write!(f, "-{}", (!*value).overflowing_add(U256::one()).0),
+            Token::Int(value) => write!(f, "{value}"),
+            Token::FixedBytes(bytes) | Token::Bytes(bytes) => write!(f, "0x{}", hex::encode(bytes)),
+            Token::String(s) => write!(f, "{s:?}"),
+            Token::Array(items) | Token::FixedArray(items) => write!(f, "[{}]", join(items, ", ")),
+            Token::Tuple(items) => write!(f, "({})", join(items, ", ")),
+        }
+    }
+}
+
+// ==========================================
+// Encoding
+// ==========================================
+
+/// Encode `tokens` as a sequence, as for function arguments.
+pub fn encode(tokens: &[Token]) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode_sequence(tokens, &mut out);
+    out
+}
+
+/// Encode `tokens` after checking them against `types`.
+pub fn encode_params(types: &[ParamType], tokens: &[Token]) -> Result<Vec<u8>, AbiError> {
+    if types.len() != tokens.len() {
+        return Err(AbiError::WrongCount {
+            expected: types.len(),
+            got: tokens.len(),
+        });
+    }
+    if let Some(kind) = types
+        .iter()
+        .zip(tokens)
+        .find(|(kind, token)| !token.matches(kind))
+        .map(|(k, _)| k)
+    {
+        return Err(AbiError::TypeMismatch(kind.to_string()));
+    }
+    Ok(encode(tokens))
+}
+
+fn encode_sequence(tokens: &[Token], out: &mut Vec<u8>) {
+    let head_size: usize = tokens.iter().map(Token::head_size).sum();
+    let mut tail = Vec::new();
+    for token in tokens {
+        if token.is_dynamic() {
+            out.extend_from_slice(&usize_word(head_size + tail.len()));
+            encode_token(token, &mut tail);
+        } else {
+            encode_token(token, out);
+        }
+    }
+    out.extend_from_slice(&tail);
+}
+
+fn encode_token(token: &Token, out: &mut Vec<u8>) {
+    match token {
+        Token::Address(address) => {
+            out.extend_from_slice(&[0u8; 12]);
+            out.extend_from_slice(address.as_bytes());
+        }
+        Token::Bool(value) => out.extend_from_slice(&usize_word(*value as usize)),
+        Token::Uint(value) | Token::Int(value) => {
+            let mut word = [0u8; 32];
+            value.to_big_endian(&mut word);
+            out.extend_from_slice(&word);
+        }
+        Token::FixedBytes(bytes) => pad_right(bytes, out),
+        Token::Bytes(bytes) => {
+            out.extend_from_slice(&usize_word(bytes.len()));
+            pad_right(bytes, out);
+        }
+        Token::String(s) => {
+            out.extend_from_slice(&usize_word(s.len()));
+            pad_right(s.as_bytes(), out);
+        }
+        Token::Array(items) => {
+            out.extend_from_slice(&usize_word(items.len()));
+            encode_sequence(items, out);
+        }
+        Token::FixedArray(items) | Token::Tuple(items) => encode_sequence(items, out),
+    }
+}
+
+fn usize_word(value: usize) -> H256 {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&(value as u64).to_be_bytes());
+    word
+}
+
+/// `bytes` followed by zeros up to a multiple of 32.
+fn pad_right(bytes: &[u8], out: &mut Vec<u8>) {
+    out.extend_from_slice(bytes);
+    let padding = (32 - bytes.len() % 32) % 32;
+    out.resize(out.len() + padding, 0);
+}
+
+// ==========================================
+// Decoding
+// ==========================================
+
+/// Decode a sequence of `types`, as for function arguments. Values must be
+/// canonically encoded; trailing data is ignored.
+pub fn decode(types: &[ParamType], data: &[u8]) -> Result<Vec<Token>, AbiError> {
+    decode_sequence(types, data)
+}
+
+fn decode_sequence(types: &[ParamType], data: &[u8]) -> Result<Vec<Token>, AbiError> {
+    let mut tokens = Vec::with_capacity(types.len());
+    let mut offset = 0usize;
+    for kind in types {
+        if kind.is_dynamic() {
+            let start = read_usize(data, offset)?;
+            let tail = data.get(start..).ok_or(AbiError::InvalidData("offset out of bounds"))?;
+            tokens.push(decode_token(kind, tail)?);
+            offset += 32;
+        } else {
+            let head = data.get(offset..).ok_or(AbiError::InvalidData("data too short"))?;
+            tokens.push(decode_token(kind, head)?);
+            offset = offset.saturating_add(kind.head_size());
+        }
+    }
+    Ok(tokens)
+}
+
+/// Decode one value of `kind` from the start of `data`.
+fn decode_token(kind: &ParamType, data: &[u8]) -> Result<Token, AbiError> {
+    match kind {
+        ParamType::Address => {
+            let word = read_word(data, 0)?;
+            if word[..12].iter().any(|b| *b != 0) {
+                return Err(AbiError::InvalidData("address has dirty high bytes"));
+            }
+            Ok(Token::Address(Address::from_slice(&word[12..]).expect("20 byte slice")))
+        }
+        ParamType::Bool => {
+            let word = read_word(data, 0)?;
+            if word[..31].iter().any(|b| *b != 0) || word[31] > 1 {
+                return Err(AbiError::InvalidData("bool out of range"));
+            }
+            Ok(Token::Bool(word[31] == 1))
+        }
+        ParamType::Uint(bits) => {
+            let value = U256::from_big_endian(read_word(data, 0)?);
+            if value.bits() > *bits {
+                return Err(AbiError::InvalidData("uint out of range"));
+            }
+            Ok(Token::Uint(value))
+        }
+        ParamType::Int(bits) => {
+            let word = read_word(data, 0)?;
+            // Bytes above the value must all copy its sign bit
+            let unused = 32 - bits / 8;
+            let fill = if word[unused] & 0x80 != 0 { 0xff } else { 0x00 };
+            if word[..unused].iter().any(|b| *b != fill) {
+                return Err(AbiError::InvalidData("int out of range"));
+            }
+            Ok(Token::Int(U256::from_big_endian(word)))
+        }
+        ParamType::FixedBytes(len) => {
+            let word = read_word(data, 0)?;
+            if word[*len..].iter().any(|b| *b != 0) {
+                return Err(AbiError::InvalidData("fixed bytes have dirty padding"));
+            }
+            Ok(Token::FixedBytes(word[..*len].to_vec()))
+        }
+        ParamType::Function => decode_token(&ParamType::FixedBytes(24), data),
+        ParamType::Bytes => read_bytes(data).map(|bytes| Token::Bytes(bytes.to_vec())),
+        ParamType::String => {
+            let bytes = read_bytes(data)?;
+            let s = std::str::from_utf8(bytes).map_err(|_| AbiError::InvalidData("string is not UTF-8"))?;
+            Ok(Token::String(s.to_string()))
+        }
+        ParamType::Array(element) => {
+            let len = read_usize(data, 0)?;
+            let items = &data[32..];
+            // Every element takes at least one word of head
+            if len > items.len() / 32 {
+                return Err(AbiError::InvalidData("array longer than data"));
+            }
+            decode_sequence(&vec![(**element).clone(); len], items).map(Token::Array)
+        }
+        ParamType::FixedArray(element, len) => {
+            if *len > data.len() / 32 {
+                return Err(AbiError::InvalidData("array longer than data"));
+            }
+            decode_sequence(&vec![(**element).clone(); *len], data).map(Token::FixedArray)
+        }
+        ParamType::Tuple(types) => decode_sequence(types, data).map(Token::Tuple),
+    }
+}
+
+fn read_word(data: &[u8], offset: usize) -> Result<&[u8; 32], AbiError> {
+    data.get(offset..offset.saturating_add(32))
+        .map(|word| word.try_into().expect("32 byte slice"))
+        .ok_or(AbiError::InvalidData("data too short"))
+}
+
+fn read_usize(data: &[u8], offset: usize) -> Result<usize, AbiError> {
+    let word = read_word(data, offset)?;
+    if word[..24].iter().any(|b| *b != 0) {
+        return Err(AbiError::InvalidData("offset or length too large"));
+    }
+    usize::try_from(u64::from_be_bytes(word[24..].try_into().expect("8 byte slice")))
+        .map_err(|_| AbiError::InvalidData("offset or length too large"))
+}
+
+/// Length-prefixed bytes at the start of `data`.
+fn read_bytes(data: &[u8]) -> Result<&[u8], AbiError> {
+    let len = read_usize(data, 0)?;
+    data.get(32..32usize.saturating_add(len))
+        .ok_or(AbiError::InvalidData("bytes longer than data"))
+}
+
+// ==========================================
+// JSON ABI
+// ==========================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Param {
+    pub name: String,
+    pub kind: ParamType,
+    /// Event parameters only: stored in a topic rather than the data
+    pub indexed: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StateMutability {
+    Pure,
+    View,
+    #[default]
+    NonPayable,
+    Payable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    pub inputs: Vec<Param>,
+    pub outputs: Vec<Param>,
+    pub state_mutability: StateMutability,
+}
+
+impl Function {
+    /// Canonical signature, e.g. `transfer(address,uint256)`.
+    pub fn signature(&self) -> String {
+        signature(&self.name, &self.inputs)
+    }
+
+    pub fn selector(&self) -> [u8; 4] {
+        selector(&self.signature())
+    }
+
+    /// Calldata for a call with `args`.
+    pub fn encode_input(&self, args: &[Token]) -> Result<Vec<u8>, AbiError> {
+        let mut data = self.selector().to_vec();
+        data.extend_from_slice(&encode_params(&kinds(&self.inputs), args)?);
+        Ok(data)
+    }
+
+    /// Arguments of calldata for this function, selector included.
+    pub fn decode_input(&self, data: &[u8]) -> Result<Vec<Token>, AbiError> {
+        match data.get(..4) {
+            Some(selector) if selector == self.selector() => decode(&kinds(&self.inputs), &data[4..]),
+            Some(selector) => Err(AbiError::UnknownSelector(selector.try_into().expect("4 byte slice"))),
+            None => Err(AbiError::InvalidData("calldata shorter than a selector")),
+        }
+    }
+
+    pub fn decode_output(&self, data: &[u8]) -> Result<Vec<Token>, AbiError> {
+        decode(&kinds(&self.outputs), data)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub name: String,
+    pub inputs: Vec<Param>,
+    /// Anonymous events do not put their signature in the first topic
+    pub anonymous: bool,
+}
+
+impl Event {
+    pub fn signature(&self) -> String {
+        signature(&self.name, &self.inputs)
+    }
+
+    /// keccak256 of the signature, the first topic of its logs.
+    pub fn topic(&self) -> H256 {
+        keccak256(self.signature().as_bytes())
+    }
+
+    /// Values of every parameter, in declaration order. Indexed strings,
+    /// bytes, arrays and tuples are only stored as their hash, which is
+    /// returned as `bytes32`.
+    pub fn decode_log(&self, log: &Log) -> Result<Vec<Token>, AbiError> {
+        let mut topics = log.topics.iter();
+        if !self.anonymous && topics.next() != Some(&self.topic()) {
+            return Err(AbiError::InvalidData("first topic is not the event signature"));
+        }
+        let indexed = self.inputs.iter().filter(|p| p.indexed).count();
+        if topics.len() != indexed {
+            return Err(AbiError::WrongCount {
+                expected: indexed,
+                got: topics.len(),
+            });
+        }
+        let data_types: Vec<ParamType> = self
+            .inputs
+            .iter()
+            .filter(|p| !p.indexed)
+            .map(|p| p.kind.clone())
+            .collect();
+        let mut data = decode(&data_types, &log.data)?.into_iter();
+
+        self.inputs
+            .iter()
+            .map(|param| match param.indexed {
+                false => Ok(data.next().expect("one value per data parameter")),
+                true => {
+                    let topic = topics.next().expect("one topic per indexed parameter");
+                    match &param.kind {
+                        ParamType::Bytes
+                        | ParamType::String
+                        | ParamType::Array(_)
+                        | ParamType::FixedArray(..)
+                        | ParamType::Tuple(_) => Ok(Token::FixedBytes(topic.to_vec())),
+                        kind => decode_token(kind, topic),
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// Functions and events of a contract, from its JSON ABI.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Abi {
+    pub constructor: Option<Vec<Param>>,
+    pub functions: Vec<Function>,
+    pub events: Vec<Event>,
+}
+
+#[derive(Deserialize)]
+struct JsonParam {
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    indexed: bool,
+    #[serde(default)]
+    components: Vec<JsonParam>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonEntry {
+    #[serde(rename = "type", default = "default_entry_type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    inputs: Vec<JsonParam>,
+    #[serde(default)]
+    outputs: Vec<JsonParam>,
+    #[serde(default)]
+    anonymous: bool,
+    #[serde(default)]
+    state_mutability: StateMutability,
+}
+
+fn default_entry_type() -> String {
+    "function".to_string()
+}
+
+impl Abi {
+    /// Parse a JSON ABI as emitted by solc. Errors, fallback and receive
+    /// entries are skipped.
+    pub fn from_json(json: &str) -> Result<Self, AbiError> {
+        let entries: Vec<JsonEntry> = serde_json::from_str(json).map_err(|e| AbiError::Json(e.to_string()))?;
+        let mut abi = Abi::default();
+        for entry in entries {
+            match entry.kind.as_str() {
+                "function" => abi.functions.push(Function {
+                    name: entry.name,
+                    inputs: params(entry.inputs)?,
+                    outputs: params(entry.outputs)?,
+                    state_mutability: entry.state_mutability,
+                }),
+                "event" => abi.events.push(Event {
+                    name: entry.name,
+                    inputs: params(entry.inputs)?,
+                    anonymous: entry.anonymous,
+                }),
+                "constructor" => abi.constructor = Some(params(entry.inputs)?),
+                _ => {}
+            }
+        }
+        Ok(abi)
+    }
+
+    /// First function called `name`.
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|f| f.name == name)
+    }
+
+    pub fn function_by_selector(&self, selector: &[u8; 4]) -> Option<&Function> {
+        self.functions.iter().find(|f| f.selector() == *selector)
+    }
+
+    /// First event called `name`.
+    pub fn event(&self, name: &str) -> Option<&Event> {
+        self.events.iter().find(|e| e.name == name)
+    }
+
+    pub fn event_by_topic(&self, topic: &H256) -> Option<&Event> {
+        self.events.iter().find(|e| !e.anonymous && e.topic() == *topic)
+    }
+
+    /// The function `data` calls and its arguments.
+    pub fn decode_call(&self, data: &[u8]) -> Result<(&Function, Vec<Token>), AbiError> {
+        let selector: [u8; 4] = data
+            .get(..4)
+            .ok_or(AbiError::InvalidData("calldata shorter than a selector"))?
+            .try_into()
+            .expect("4 byte slice");
+        let function = self
+            .function_by_selector(&selector)
+            .ok_or(AbiError::UnknownSelector(selector))?;
+        Ok((function, decode(&kinds(&function.inputs), &data[4..])?))
+    }
+
+    /// The event `log` was emitted for and its parameters.
+    pub fn decode_log(&self, log: &Log) -> Result<(&Event, Vec<Token>), AbiError> {
+        let topic = log.topics.first().ok_or(AbiError::InvalidData("log has no topics"))?;
+        let event = self.event_by_topic(topic).ok_or(AbiError::UnknownEvent(*topic))?;
+        Ok((event, event.decode_log(log)?))
+    }
+}
+
+fn params(json: Vec<JsonParam>) -> Result<Vec<Param>, AbiError> {
+    json.into_iter()
+        .map(|param| {
+            Ok(Param {
+                kind: param_type(&param)?,
+                name: param.name,
+                indexed: param.indexed,
+            })
+        })
+        .collect()
+}
+
+/// `tuple` types spell out their members in `components`.
+fn param_type(param: &JsonParam) -> Result<ParamType, AbiError> {
+    match param.kind.strip_prefix("tuple") {
+        Some(suffix) => {
+            let components = param.components.iter().map(param_type).collect::<Result<Vec<_>, _>>()?;
+            ParamType::parse(&format!("({}){suffix}", join(&components, ",")))
+        }
+        None => ParamType::parse(&param.kind),
+    }
+}
+
+fn kinds(params: &[Param]) -> Vec<ParamType> {
+    params.iter().map(|p| p.kind.clone()).collect()
+}
+
+fn signature(name: &str, params: &[Param]) -> String {
+    format!("{name}({})", join(&kinds(params), ","))
+}
@@ -0,0 +1,132 @@
+// ==========================================
+// TBURN Contract Deployer
+// ==========================================
+
+use lazy_static::lazy_static;
+
+use crate::contracts::executor::{
+    address_to_bytes, record_registration, registered_token, Tbc20TokenInfo, TokenStandard, TBC1155_FACTORY,
+    TBC20_FACTORY, TBC721_FACTORY,
+};
+use crate::core::address::Address;
+use crate::core::config::SmartContractsConfig;
+use crate::core::executor::{create2_address, create_address};
+use crate::core::state::StateDB;
+use crate::core::types::{keccak256, H256};
+use crate::core::vm::{CallKind, CallOutput, Evm, Message, MAX_CODE_SIZE};
+
+lazy_static! {
+    static ref FACTORIES: [(Address, TokenStandard); 3] = [
+        (factory(TBC20_FACTORY), TokenStandard::TBC20),
+        (factory(TBC721_FACTORY), TokenStandard::TBC721),
+        (factory(TBC1155_FACTORY), TokenStandard::TBC1155),
+    ];
+}
+
+fn factory(address: &str) -> Address {
+    Address(address_to_bytes(address).expect("valid factory address"))
+}
+
+/// Runs init code for new contracts and registers tokens deployed through
+/// the TBC-20 factory in state.
+///
+/// Transactions without a recipient deploy at `create_address(sender,
+/// nonce)`. Transactions sent to a factory carry `salt || init_code` and
+/// deploy at a CREATE2 address of the factory, with the salt mixed with the
+/// sender so nobody can claim another deployer's address.
+#[derive(Debug)]
+pub struct ContractDeployer {
+    /// Largest runtime code a deployment may return
+    max_code_size: usize,
+}
+
+impl Default for ContractDeployer {
+    fn default() -> Self {
+        Self {
+            max_code_size: MAX_CODE_SIZE,
+        }
+    }
+}
+
+impl ContractDeployer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deployer enforcing `max_contract_size_kb`.
+    pub fn from_config(config: &SmartContractsConfig) -> Self {
+        Self {
+            max_code_size: config.max_contract_size_bytes(),
+        }
+    }
+
+    pub fn max_code_size(&self) -> usize {
+        self.max_code_size
+    }
+
+    /// Largest init code a deployment may run (EIP-3860).
+    pub fn max_initcode_size(&self) -> usize {
+        2 * self.max_code_size
+    }
+
+    /// Which factory, if any, `address` is.
+    pub fn factory_standard(&self, address: &Address) -> Option<TokenStandard> {
+        FACTORIES
+            .iter()
+            .find(|(factory, _)| factory == address)
+            .map(|(_, standard)| *standard)
+    }
+
+    /// Address a factory deploys `init_code` to for `sender` under `salt`.
+    pub fn factory_address(&self, factory: &Address, sender: &Address, salt: &H256, init_code: &[u8]) -> Address {
+        let mut preimage = Vec::with_capacity(52);
+        preimage.extend_from_slice(sender.as_bytes());
+        preimage.extend_from_slice(salt);
+        create2_address(factory, &keccak256(&preimage), &keccak256(init_code))
+    }
+
+    /// Run `init_code` from `sender` and store the code it returns.
+    pub fn create(
+        &self,
+        evm: &mut Evm<'_>,
+        sender: Address,
+        nonce: u64,
+        value: u128,
+        init_code: Vec<u8>,
+        gas: u64,
+    ) -> CallOutput {
+        let address = create_address(&sender, nonce);
+        evm.execute(Message::create(sender, address, value, init_code, gas))
+    }
+
+    /// Deploy through `factory` from `data = salt || init_code`. `None` if
+    /// `data` is too short to hold a salt.
+    pub fn factory_create(
+        &self,
+        evm: &mut Evm<'_>,
+        factory: &Address,
+        sender: Address,
+        value: u128,
+        data: &[u8],
+        gas: u64,
+    ) -> Option<CallOutput> {
+        if data.len() < 32 {
+            return None;
+        }
+        let (salt, init_code) = data.split_at(32);
+        let salt: H256 = salt.try_into().expect("32 byte slice");
+        let address = self.factory_address(factory, &sender, &salt, init_code);
+        Some(evm.execute(Message {
+            kind: CallKind::Create2,
+            ..Message::create(sender, address, value, init_code.to_vec(), gas)
+        }))
+    }
+
+    /// Register the TBC-20 token at `address`, deployed in `block`, and
+    /// return its metadata. Registering an already known token keeps its
+    /// original deployment block.
+    pub fn register_token<S: StateDB>(&self, state: &mut S, address: &Address, block: u64) -> Tbc20TokenInfo {
+        record_registration(state, address, block);
+        registered_token(state, address).expect("token was just registered")
+    }
+}
@@ -0,0 +1,769 @@
+// ==========================================
+// TBURN TBC-20 Fast Path Executor
+// ==========================================
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use dashmap::{DashMap, DashSet};
+use lazy_static::lazy_static;
+
+use crate::contracts::abi::{Abi, Token};
+use crate::core::address::Address;
+use crate::core::config::NetworkProfile;
+use crate::core::receipt::Log;
+use crate::core::state::StateDB;
+use crate::core::transaction::Transaction;
+use crate::core::types::H256;
+
+/// TBURN native address string (`tb1...`)
+pub type TburnAddress = String;
+
+/// Raw 20-byte address
+pub type AddressBytes = [u8; 20];
+
+/// 256-bit word as big-endian bytes
+pub type U256 = [u8; 32];
+
+// ==========================================
+// TBC-20 Protocol Constants
+// ==========================================
+
+/// Bech32m human readable part of mainnet addresses
+pub const TBURN_HRP: &str = "tb";
+
+/// TBC-20 factory address, `generate_system_address("TBC20_FACTORY")`
+pub const TBC20_FACTORY: &str = "tb12kc7lfn756xamsg2x3gm4ulv3c45ntfzxhnptg";
+
+/// TBC-721 factory address (NFT), label "TBC721_FACTORY"
+pub const TBC721_FACTORY: &str = "tb13tt8a3cg2snh3dy2hmhj8f56hpg2r3kjuz6w76";
+
+/// TBC-1155 factory address (multi-token), label "TBC1155_FACTORY"
+pub const TBC1155_FACTORY: &str = "tb18y0xst0uutmmya4ahartk9zujjkz962eht2txs";
+
+/// TBC-20 function selectors
+pub mod selectors {
+    /// transfer(address,uint256)
+    pub const TRANSFER: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+    /// transferFrom(address,address,uint256)
+    pub const TRANSFER_FROM: [u8; 4] = [0x23, 0xb8, 0x72, 0xdd];
+    /// approve(address,uint256)
+    pub const APPROVE: [u8; 4] = [0x09, 0x5e, 0xa7, 0xb3];
+    /// burn(uint256)
+    pub const BURN: [u8; 4] = [0x42, 0x96, 0x6c, 0x68];
+    /// mint(address,uint256)
+    pub const MINT: [u8; 4] = [0x40, 0xc1, 0x0f, 0x19];
+    /// balanceOf(address)
+    pub const BALANCE_OF: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+}
+
+/// TBC-20 standard storage slots
+pub mod slots {
+    pub const BALANCES: u8 = 0;
+    pub const ALLOWANCES: u8 = 1;
+    pub const TOTAL_SUPPLY: u8 = 2;
+    pub const NAME: u8 = 3;
+    pub const SYMBOL: u8 = 4;
+    pub const DECIMALS: u8 = 5;
+    pub const MAX_SUPPLY: u8 = 10;
+    pub const FLAGS: u8 = 11;
+    pub const OWNER: u8 = 12;
+    pub const PAUSED: u8 = 13;
+}
+
+/// Bits of the `FLAGS` slot
+pub mod flags {
+    pub const MINTABLE: u8 = 1 << 0;
+    pub const BURNABLE: u8 = 1 << 1;
+    pub const PAUSABLE: u8 = 1 << 2;
+}
+
+/// TBC-20 JSON ABI
+pub const TBC20_ABI: &str = r#"[
+    {"type": "function", "name": "name", "inputs": [], "outputs": [{"name": "", "type": "string"}], "stateMutability": "view"},
+    {"type": "function", "name": "symbol", "inputs": [], "outputs": [{"name": "", "type": "string"}], "stateMutability": "view"},
+    {"type": "function", "name": "decimals", "inputs": [], "outputs": [{"name": "", "type": "uint8"}], "stateMutability": "view"},
+    {"type": "function", "name": "totalSupply", "inputs": [], "outputs": [{"name": "", "type": "uint256"}], "stateMutability": "view"},
+    {"type": "function", "name": "balanceOf", "inputs": [{"name": "account", "type": "address"}], "outputs": [{"name": "", "type": "uint256"}], "stateMutability": "view"},
+    {"type": "function", "name": "allowance", "inputs": [{"name": "owner", "type": "address"}, {"name": "spender", "type": "address"}], "outputs": [{"name": "", "type": "uint256"}], "stateMutability": "view"},
+    {"type": "function", "name": "transfer", "inputs": [{"name": "to", "type": "address"}, {"name": "amount", "type": "uint256"}], "outputs": [{"name": "", "type": "bool"}]},
+    {"type": "function", "name": "transferFrom", "inputs": [{"name": "from", "type": "address"}, {"name": "to", "type": "address"}, {"name": "amount", "type": "uint256"}], "outputs": [{"name": "", "type": "bool"}]},
+    {"type": "function", "name": "approve", "inputs": [{"name": "spender", "type": "address"}, {"name": "amount", "type": "uint256"}], "outputs": [{"name": "", "type": "bool"}]},
+    {"type": "function", "name": "burn", "inputs": [{"name": "amount", "type": "uint256"}], "outputs": []},
+    {"type": "function", "name": "mint", "inputs": [{"name": "to", "type": "address"}, {"name": "amount", "type": "uint256"}], "outputs": []},
+    {"type": "event", "name": "Transfer", "anonymous": false, "inputs": [{"name": "from", "type": "address", "indexed": true}, {"name": "to", "type": "address", "indexed": true}, {"name": "value", "type": "uint256", "indexed": false}]},
+    {"type": "event", "name": "Approval", "anonymous": false, "inputs": [{"name": "owner", "type": "address", "indexed": true}, {"name": "spender", "type": "address", "indexed": true}, {"name": "value", "type": "uint256", "indexed": false}]}
+]"#;
+
+lazy_static! {
+    static ref TBC20: Abi = Abi::from_json(TBC20_ABI).expect("valid TBC-20 ABI");
+    static ref TBC20_FACTORY_ADDRESS: Address =
+        Address(address_to_bytes(TBC20_FACTORY).expect("valid factory address"));
+}
+
+/// Parsed `TBC20_ABI`
+pub fn tbc20_abi() -> &'static Abi {
+    &TBC20
+}
+
+/// Event signatures
+pub mod events {
+    /// Transfer(address indexed from, address indexed to, uint256 value)
+    pub const TRANSFER: [u8; 32] = [
+        0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b,
+        0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37, 0x8d, 0xaa,
+        0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16,
+        0x28, 0xf5, 0x5a, 0x4d, 0xf5, 0x23, 0xb3, 0xef
+    ];
+
+    /// Approval(address indexed owner, address indexed spender, uint256 value)
+    pub const APPROVAL: [u8; 32] = [
+        0x8c, 0x5b, 0xe1, 0xe5, 0xeb, 0xec, 0x7d, 0x5b,
+        0xd1, 0x4f, 0x71, 0x42, 0x7d, 0x1e, 0x84, 0xf3,
+        0xdd, 0x03, 0x14, 0xc0, 0xf7, 0xb2, 0x29, 0x1e,
+        0x5b, 0x20, 0x0a, 0xc8, 0xc7, 0xc3, 0xb9, 0x25
+    ];
+}
+
+// ==========================================
+// Address Utilities
+// ==========================================
+
+/// Token standard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenStandard {
+    TBC20,
+    TBC721,
+    TBC1155,
+}
+
+/// Deterministic system address; the same label always gives the same address.
+pub fn generate_system_address(label: &str) -> Address {
+    let hash = sha256(label.as_bytes());
+    Address::from_slice(&hash[..20]).expect("20 byte slice")
+}
+
+/// Check a mainnet `tb1` address string, including its checksum
+pub fn is_valid_tburn_address(address: &str) -> bool {
+    address_to_bytes(address).is_some()
+}
+
+/// Bytes of a mainnet `tb1` address string
+pub fn address_to_bytes(address: &str) -> Option<AddressBytes> {
+    match Address::from_bech32(address) {
+        Ok((NetworkProfile::Mainnet, address)) => Some(address.0),
+        _ => None,
+    }
+}
+
+/// Mainnet `tb1` address string for raw bytes
+pub fn bytes_to_address(bytes: &AddressBytes) -> TburnAddress {
+    Address(*bytes).to_bech32(NetworkProfile::Mainnet)
+}
+
+/// Whether two address strings, in either form, name the same address
+#[inline(always)]
+pub fn addresses_equal(a: &str, b: &str) -> bool {
+    match (a.parse::<Address>(), b.parse::<Address>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Which factory, if any, `address` is
+pub fn is_factory_address(address: &str) -> Option<TokenStandard> {
+    if addresses_equal(address, TBC20_FACTORY) {
+        Some(TokenStandard::TBC20)
+    } else if addresses_equal(address, TBC721_FACTORY) {
+        Some(TokenStandard::TBC721)
+    } else if addresses_equal(address, TBC1155_FACTORY) {
+        Some(TokenStandard::TBC1155)
+    } else {
+        None
+    }
+}
+
+// ==========================================
+// TBC-20 Token Info
+// ==========================================
+
+/// TBC-20 token metadata
+#[derive(Debug, Clone)]
+pub struct Tbc20TokenInfo {
+    /// Token contract address
+    pub address: Address,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub initial_supply: u128,
+    pub max_supply: u128,
+
+    // Feature flags
+    pub mintable: bool,
+    pub burnable: bool,
+    pub pausable: bool,
+
+    // TBURN specific features
+    /// Eligible for the fast path
+    pub ai_optimized: bool,
+    pub quantum_resistant: bool,
+    pub mev_protection: bool,
+    pub zk_privacy: bool,
+
+    /// Factory that deployed the token
+    pub factory: TburnAddress,
+    pub deployed_at_block: u64,
+}
+
+impl Default for Tbc20TokenInfo {
+    fn default() -> Self {
+        Self {
+            address: Address::ZERO,
+            name: String::new(),
+            symbol: String::new(),
+            decimals: 18,
+            initial_supply: 0,
+            max_supply: 0,
+            mintable: false,
+            burnable: true,
+            pausable: false,
+            ai_optimized: true,
+            quantum_resistant: true,
+            mev_protection: true,
+            zk_privacy: false,
+            factory: TBC20_FACTORY.to_string(),
+            deployed_at_block: 0,
+        }
+    }
+}
+
+// ==========================================
+// TBC-20 Registry
+// ==========================================
+
+/// Tokens registered by hand, e.g. from node configuration. Tokens the
+/// TBC-20 factory deploys are recorded in state instead, see
+/// `registered_token`.
+#[derive(Debug, Default)]
+pub struct Tbc20Registry {
+    /// Token address to metadata
+    tokens: DashMap<Address, Tbc20TokenInfo>,
+    /// Tokens deployed through the TBC-20 factory
+    factory_tokens: DashSet<Address>,
+    /// Fast path eligible tokens
+    optimizable_tokens: DashSet<Address>,
+    stats: RegistryStats,
+}
+
+#[derive(Debug, Default)]
+struct RegistryStats {
+    total_tokens: AtomicU64,
+    optimizable_count: AtomicU64,
+}
+
+impl Tbc20Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a token
+    pub fn register(&self, info: Tbc20TokenInfo) {
+        let key = info.address;
+
+        if addresses_equal(&info.factory, TBC20_FACTORY) {
+            self.factory_tokens.insert(key);
+        }
+
+        if info.ai_optimized {
+            self.optimizable_tokens.insert(key);
+            self.stats.optimizable_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        self.tokens.insert(key, info);
+        self.stats.total_tokens.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Whether `address` is a factory deployed TBC-20 token (O(1))
+    #[inline(always)]
+    pub fn is_tbc20(&self, address: &Address) -> bool {
+        self.factory_tokens.contains(address)
+    }
+
+    /// Whether the fast path applies to `address` (O(1))
+    #[inline(always)]
+    pub fn is_fast_path_eligible(&self, address: &Address) -> bool {
+        self.optimizable_tokens.contains(address)
+    }
+
+    #[inline(always)]
+    pub fn get(&self, address: &Address) -> Option<Tbc20TokenInfo> {
+        self.tokens.get(address).map(|v| v.clone())
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, address: &Address) -> bool {
+        self.tokens.contains_key(address)
+    }
+
+    /// (total tokens, fast path tokens)
+    pub fn stats(&self) -> (u64, u64) {
+        (
+            self.stats.total_tokens.load(Ordering::Relaxed),
+            self.stats.optimizable_count.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Storage slot of the TBC-20 factory recording that it deployed `token`.
+/// Holds the deployment block plus one, so registered tokens are non-zero.
+pub fn compute_registration_slot(token: &Address) -> H256 {
+    address_to_h256(token)
+}
+
+/// Token slots read to build a registered token's metadata
+pub const METADATA_SLOTS: [u8; 6] = [
+    slots::NAME,
+    slots::SYMBOL,
+    slots::DECIMALS,
+    slots::TOTAL_SUPPLY,
+    slots::MAX_SUPPLY,
+    slots::FLAGS,
+];
+
+/// Storage `registered_token` reads for `token`, as (account, slot)
+pub fn registration_reads(token: &Address) -> impl Iterator<Item = (Address, H256)> + '_ {
+    std::iter::once((*TBC20_FACTORY_ADDRESS, compute_registration_slot(token)))
+        .chain(METADATA_SLOTS.iter().map(|index| (*token, slot_to_u256(*index))))
+}
+
+/// Record in state that the TBC-20 factory deployed `token` in `block`.
+/// The record is journaled with the rest of the state, so it is reverted,
+/// committed and persisted with the block that made it.
+pub fn record_registration<S: StateDB>(state: &mut S, token: &Address, block: u64) {
+    let slot = compute_registration_slot(token);
+    if state.get_storage(&TBC20_FACTORY_ADDRESS, &slot) == [0u8; 32] {
+        state.set_storage(&TBC20_FACTORY_ADDRESS, &slot, u128_to_u256(block as u128 + 1));
+    }
+}
+
+/// Metadata of a token the TBC-20 factory deployed, from its standard
+/// storage slots. `None` if the factory did not deploy `token`.
+pub fn registered_token<S: StateDB>(state: &S, token: &Address) -> Option<Tbc20TokenInfo> {
+    let record = state.get_storage(&TBC20_FACTORY_ADDRESS, &compute_registration_slot(token));
+    let deployed_at_block = u256_to_u128(&record)?.checked_sub(1)? as u64;
+    let slot = |index: u8| state.get_storage(token, &slot_to_u256(index));
+    let amount = |index: u8| u256_to_u128(&slot(index)).unwrap_or(u128::MAX);
+    let token_flags = slot(slots::FLAGS)[31];
+    Some(Tbc20TokenInfo {
+        address: *token,
+        name: short_string(&slot(slots::NAME)),
+        symbol: short_string(&slot(slots::SYMBOL)),
+        decimals: slot(slots::DECIMALS)[31],
+        initial_supply: amount(slots::TOTAL_SUPPLY),
+        max_supply: amount(slots::MAX_SUPPLY),
+        mintable: token_flags & flags::MINTABLE != 0,
+        burnable: token_flags & flags::BURNABLE != 0,
+        pausable: token_flags & flags::PAUSABLE != 0,
+        deployed_at_block,
+        ..Tbc20TokenInfo::default()
+    })
+}
+
+/// Decode a string stored in a single slot: left-aligned bytes with twice
+/// the length in the last byte. Longer strings read as empty.
+fn short_string(word: &H256) -> String {
+    let len = word[31] as usize / 2;
+    if !word[31].is_multiple_of(2) || len > 31 {
+        return String::new();
+    }
+    String::from_utf8_lossy(&word[..len]).into_owned()
+}
+
+// ==========================================
+// Execution Result
+// ==========================================
+
+#[inline(always)]
+fn selector(data: &[u8]) -> Option<[u8; 4]> {
+    data.get(0..4).map(|s| s.try_into().expect("4 byte slice"))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionResult {
+    pub success: bool,
+    pub gas_used: u64,
+    pub output: Vec<u8>,
+    pub logs: Vec<Log>,
+    pub error: Option<String>,
+}
+
+impl ExecutionResult {
+    pub fn success(gas_used: u64, output: Vec<u8>, logs: Vec<Log>) -> Self {
+        Self {
+            success: true,
+            gas_used,
+            output,
+            logs,
+            error: None,
+        }
+    }
+
+    pub fn revert(reason: &str) -> Self {
+        Self {
+            success: false,
+            gas_used: 0,
+            output: vec![],
+            logs: vec![],
+            error: Some(reason.to_string()),
+        }
+    }
+}
+
+// ==========================================
+// TBC-20 Fast Path Executor
+// ==========================================
+
+/// Executes TBC-20 calls directly against token storage, bypassing the VM.
+///
+/// Only token storage is touched; nonce and gas accounting stay with the
+/// transaction executor. A reverted call leaves state unchanged.
+#[derive(Debug, Default)]
+pub struct Tbc20FastPathExecutor {
+    registry: Arc<Tbc20Registry>,
+    stats: ExecutorStats,
+}
+
+#[derive(Debug, Default)]
+pub struct ExecutorStats {
+    pub transfer_count: AtomicU64,
+    pub transfer_from_count: AtomicU64,
+    pub approve_count: AtomicU64,
+    pub burn_count: AtomicU64,
+    pub fail_count: AtomicU64,
+}
+
+impl ExecutorStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Tbc20FastPathExecutor {
+    pub fn new(registry: Arc<Tbc20Registry>) -> Self {
+        Self {
+            registry,
+            stats: ExecutorStats::new(),
+        }
+    }
+
+    pub fn registry(&self) -> &Arc<Tbc20Registry> {
+        &self.registry
+    }
+
+    /// Metadata of `token`, registered by hand or by the TBC-20 factory
+    pub fn token_info<S: StateDB>(&self, state: &S, token: &Address) -> Option<Tbc20TokenInfo> {
+        self.registry.get(token).or_else(|| registered_token(state, token))
+    }
+
+    /// Whether `tx` can take the fast path
+    #[inline(always)]
+    pub fn is_eligible<S: StateDB>(&self, state: &S, tx: &Transaction) -> bool {
+        let to = match &tx.to {
+            Some(addr) => addr,
+            None => return false,
+        };
+        let supported = matches!(
+            selector(&tx.data),
+            Some(selectors::TRANSFER | selectors::TRANSFER_FROM | selectors::APPROVE | selectors::BURN)
+        );
+        supported && self.token_info(state, to).is_some_and(|info| info.ai_optimized)
+    }
+
+    /// Execute a TBC-20 call from `sender`
+    pub fn execute<S: StateDB>(
+        &self,
+        state: &mut S,
+        sender: &Address,
+        tx: &Transaction,
+    ) -> ExecutionResult {
+        let token = match &tx.to {
+            Some(addr) => *addr,
+            None => return ExecutionResult::revert("No target address"),
+        };
+
+        let selector = match selector(&tx.data) {
+            Some(s) => s,
+            None => return ExecutionResult::revert("No function selector"),
+        };
+
+        let info = match self.token_info(state, &token) {
+            Some(i) => i,
+            None => return ExecutionResult::revert("Token not registered"),
+        };
+
+        if info.pausable && is_paused(state, &token) {
+            return ExecutionResult::revert("Token is paused");
+        }
+
+        let result = match selector {
+            selectors::TRANSFER => {
+                self.stats.transfer_count.fetch_add(1, Ordering::Relaxed);
+                execute_transfer(state, sender, &token, &tx.data)
+            }
+            selectors::TRANSFER_FROM => {
+                self.stats.transfer_from_count.fetch_add(1, Ordering::Relaxed);
+                execute_transfer_from(state, sender, &token, &tx.data)
+            }
+            selectors::APPROVE => {
+                self.stats.approve_count.fetch_add(1, Ordering::Relaxed);
+                execute_approve(state, sender, &token, &tx.data)
+            }
+            selectors::BURN => {
+                self.stats.burn_count.fetch_add(1, Ordering::Relaxed);
+                execute_burn(state, sender, &token, &info, &tx.data)
+            }
+            _ => ExecutionResult::revert("Unsupported function"),
+        };
+
+        if !result.success {
+            self.stats.fail_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        result
+    }
+
+    pub fn stats(&self) -> &ExecutorStats {
+        &self.stats
+    }
+}
+
+/// transfer(address to, uint256 amount)
+fn execute_transfer<S: StateDB>(state: &mut S, sender: &Address, token: &Address, data: &[u8]) -> ExecutionResult {
+    let (to, amount) = match decode_args(data).as_deref() {
+        Some([Token::Address(to), Token::Uint(amount)]) => (*to, to_word(amount)),
+        _ => return ExecutionResult::revert("Invalid calldata"),
+    };
+    let amount_u128 = match u256_to_u128(&amount) {
+        Some(a) => a,
+        None => return ExecutionResult::revert("TBC20: amount overflow"),
+    };
+
+    let sender_slot = compute_balance_slot(sender);
+    let to_slot = compute_balance_slot(&to);
+
+    // 1. Check sender balance
+    let sender_balance = u256_to_u128(&state.get_storage(token, &sender_slot)).unwrap_or(u128::MAX);
+    if sender_balance < amount_u128 {
+        return ExecutionResult::revert("TBC20: insufficient balance");
+    }
+
+    // 2. Update balances
+    state.set_storage(token, &sender_slot, u128_to_u256(sender_balance - amount_u128));
+    let to_balance = u256_to_u128(&state.get_storage(token, &to_slot)).unwrap_or(u128::MAX);
+    state.set_storage(token, &to_slot, u128_to_u256(to_balance.saturating_add(amount_u128)));
+
+    // 3. Transfer event
+    let log = create_transfer_log(*token, sender, &to, amount);
+    ExecutionResult::success(51_000, encode_bool(true), vec![log])
+}
+
+/// transferFrom(address from, address to, uint256 amount)
+fn execute_transfer_from<S: StateDB>(
+    state: &mut S,
+    spender: &Address,
+    token: &Address,
+    data: &[u8],
+) -> ExecutionResult {
+    let (from, to, amount) = match decode_args(data).as_deref() {
+        Some([Token::Address(from), Token::Address(to), Token::Uint(amount)]) => (*from, *to, to_word(amount)),
+        _ => return ExecutionResult::revert("Invalid calldata"),
+    };
+    let amount_u128 = match u256_to_u128(&amount) {
+        Some(a) => a,
+        None => return ExecutionResult::revert("TBC20: amount overflow"),
+    };
+
+    // 1. Check allowance
+    let allowance_slot = compute_allowance_slot(&from, spender);
+    let allowance = u256_to_u128(&state.get_storage(token, &allowance_slot)).unwrap_or(u128::MAX);
+    if allowance < amount_u128 {
+        return ExecutionResult::revert("TBC20: insufficient allowance");
+    }
+
+    // 2. Check from balance
+    let from_slot = compute_balance_slot(&from);
+    let from_balance = u256_to_u128(&state.get_storage(token, &from_slot)).unwrap_or(u128::MAX);
+    if from_balance < amount_u128 {
+        return ExecutionResult::revert("TBC20: insufficient balance");
+    }
+
+    // 3. Update state
+    state.set_storage(token, &allowance_slot, u128_to_u256(allowance - amount_u128));
+    state.set_storage(token, &from_slot, u128_to_u256(from_balance - amount_u128));
+    let to_slot = compute_balance_slot(&to);
+    let to_balance = u256_to_u128(&state.get_storage(token, &to_slot)).unwrap_or(u128::MAX);
+    state.set_storage(token, &to_slot, u128_to_u256(to_balance.saturating_add(amount_u128)));
+
+    // 4. Transfer event
+    let log = create_transfer_log(*token, &from, &to, amount);
+    ExecutionResult::success(65_000, encode_bool(true), vec![log])
+}
+
+/// approve(address spender, uint256 amount)
+fn execute_approve<S: StateDB>(state: &mut S, owner: &Address, token: &Address, data: &[u8]) -> ExecutionResult {
+    let (spender, amount) = match decode_args(data).as_deref() {
+        Some([Token::Address(spender), Token::Uint(amount)]) => (*spender, to_word(amount)),
+        _ => return ExecutionResult::revert("Invalid calldata"),
+    };
+
+    let allowance_slot = compute_allowance_slot(owner, &spender);
+    state.set_storage(token, &allowance_slot, amount);
+
+    let log = create_approval_log(*token, owner, &spender, amount);
+    ExecutionResult::success(46_000, encode_bool(true), vec![log])
+}
+
+/// burn(uint256 amount)
+fn execute_burn<S: StateDB>(
+    state: &mut S,
+    sender: &Address,
+    token: &Address,
+    info: &Tbc20TokenInfo,
+    data: &[u8],
+) -> ExecutionResult {
+    if !info.burnable {
+        return ExecutionResult::revert("Token is not burnable");
+    }
+    let amount = match decode_args(data).as_deref() {
+        Some([Token::Uint(amount)]) => to_word(amount),
+        _ => return ExecutionResult::revert("Invalid calldata"),
+    };
+    let amount_u128 = match u256_to_u128(&amount) {
+        Some(a) => a,
+        None => return ExecutionResult::revert("TBC20: amount overflow"),
+    };
+
+    // 1. Check sender balance
+    let sender_slot = compute_balance_slot(sender);
+    let sender_balance = u256_to_u128(&state.get_storage(token, &sender_slot)).unwrap_or(u128::MAX);
+    if sender_balance < amount_u128 {
+        return ExecutionResult::revert("TBC20: insufficient balance for burn");
+    }
+
+    // 2. Reduce balance and total supply
+    state.set_storage(token, &sender_slot, u128_to_u256(sender_balance - amount_u128));
+    let total_supply_slot = slot_to_u256(slots::TOTAL_SUPPLY);
+    let total_supply = u256_to_u128(&state.get_storage(token, &total_supply_slot)).unwrap_or(u128::MAX);
+    state.set_storage(token, &total_supply_slot, u128_to_u256(total_supply.saturating_sub(amount_u128)));
+
+    // 3. Transfer to the zero address
+    let log = create_transfer_log(*token, sender, &Address::ZERO, amount);
+    ExecutionResult::success(35_000, vec![], vec![log])
+}
+
+fn is_paused<S: StateDB>(state: &S, token: &Address) -> bool {
+    let paused = state.get_storage(token, &slot_to_u256(slots::PAUSED));
+    paused[31] == 1
+}
+
+// ==========================================
+// Helper Functions
+// ==========================================
+
+/// Storage slot of `balances[address]`
+#[inline(always)]
+pub fn compute_balance_slot(address: &Address) -> H256 {
+    let mut data = [0u8; 64];
+    data[12..32].copy_from_slice(address.as_bytes());
+    data[63] = slots::BALANCES;
+    sha256(&data)
+}
+
+/// Storage slot of `allowances[owner][spender]`
+#[inline(always)]
+pub fn compute_allowance_slot(owner: &Address, spender: &Address) -> H256 {
+    let mut data1 = [0u8; 64];
+    data1[12..32].copy_from_slice(owner.as_bytes());
+    data1[63] = slots::ALLOWANCES;
+    let owner_slot = sha256(&data1);
+
+    let mut data2 = [0u8; 64];
+    data2[12..32].copy_from_slice(spender.as_bytes());
+    data2[32..64].copy_from_slice(&owner_slot);
+    sha256(&data2)
+}
+
+#[inline(always)]
+fn slot_to_u256(slot: u8) -> U256 {
+    let mut result = [0u8; 32];
+    result[31] = slot;
+    result
+}
+
+/// Arguments of a TBC-20 call, `None` if `data` does not decode
+fn decode_args(data: &[u8]) -> Option<Vec<Token>> {
+    tbc20_abi().decode_call(data).ok().map(|(_, args)| args)
+}
+
+#[inline(always)]
+fn to_word(value: &primitive_types::U256) -> U256 {
+    let mut result = [0u8; 32];
+    value.to_big_endian(&mut result);
+    result
+}
+
+/// `None` if `value` does not fit in a u128
+#[inline(always)]
+pub fn u256_to_u128(value: &U256) -> Option<u128> {
+    if value[..16].iter().any(|b| *b != 0) {
+        return None;
+    }
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&value[16..32]);
+    Some(u128::from_be_bytes(bytes))
+}
+
+#[inline(always)]
+pub fn u128_to_u256(value: u128) -> U256 {
+    let mut result = [0u8; 32];
+    result[16..32].copy_from_slice(&value.to_be_bytes());
+    result
+}
+
+/// Left-pad an address to a 32-byte word
+#[inline(always)]
+fn address_to_h256(address: &Address) -> H256 {
+    let mut result = [0u8; 32];
+    result[12..32].copy_from_slice(address.as_bytes());
+    result
+}
+
+#[inline(always)]
+fn encode_bool(value: bool) -> Vec<u8> {
+    let mut result = vec![0u8; 32];
+    if value {
+        result[31] = 1;
+    }
+    result
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(data).into()
+}
+
+fn create_transfer_log(token: Address, from: &Address, to: &Address, amount: U256) -> Log {
+    Log {
+        address: token,
+        topics: vec![events::TRANSFER, address_to_h256(from), address_to_h256(to)],
+        data: amount.to_vec(),
+    }
+}
+
+fn create_approval_log(token: Address, owner: &Address, spender: &Address, amount: U256) -> Log {
+    Log {
+        address: token,
+        topics: vec![events::APPROVAL, address_to_h256(owner), address_to_h256(spender)],
+        data: amount.to_vec(),
+    }
+}
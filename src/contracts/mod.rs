@@ -0,0 +1,6 @@
+pub mod abi;
+pub mod deployer;
+pub mod executor;
+
+pub use deployer::ContractDeployer;
+pub use executor::{Tbc20FastPathExecutor, Tbc20Registry, Tbc20TokenInfo};
@@ -0,0 +1,69 @@
+use crate::core::codec::{DecodeError, Decoder, Encoder};
+use crate::core::types::{H256, ZERO_HASH};
+
+/// keccak256 of empty code.
+pub const EMPTY_CODE_HASH: H256 = [
+    0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c,
+    0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+    0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b,
+    0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    pub nonce: u64,
+    /// Balance in the smallest TBURN unit
+    pub balance: u128,
+    pub code_hash: H256,
+    pub storage_root: H256,
+}
+
+impl Default for Account {
+    fn default() -> Self {
+        Self {
+            nonce: 0,
+            balance: 0,
+            code_hash: EMPTY_CODE_HASH,
+            storage_root: ZERO_HASH,
+        }
+    }
+}
+
+impl Account {
+    pub fn with_balance(balance: u128) -> Self {
+        Self {
+            balance,
+            ..Default::default()
+        }
+    }
+
+    pub fn has_code(&self) -> bool {
+        self.code_hash != EMPTY_CODE_HASH
+    }
+
+    /// No nonce, balance or code; such accounts are not kept in state.
+    pub fn is_empty(&self) -> bool {
+        self.nonce == 0 && self.balance == 0 && !self.has_code()
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut enc = Encoder::new();
+        enc.put_u64(self.nonce)
+            .put_u128(self.balance)
+            .put_fixed(&self.code_hash)
+            .put_fixed(&self.storage_root);
+        enc.finish()
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut dec = Decoder::new(bytes);
+        let account = Self {
+            nonce: dec.get_u64()?,
+            balance: dec.get_u128()?,
+            code_hash: dec.get_fixed()?,
+            storage_root: dec.get_fixed()?,
+        };
+        dec.finish()?;
+        Ok(account)
+    }
+}
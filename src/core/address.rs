@@ -0,0 +1,143 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bech32::primitives::decode::{CheckedHrpstring, CheckedHrpstringError};
+use bech32::{Bech32m, Hrp};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::core::config::NetworkProfile;
+
+pub const ADDRESS_LENGTH: usize = 20;
+
+/// 20-byte account address.
+///
+/// Written either as `0x`-prefixed hex, the form used in `genesis.json`
+/// and the explorer API, or as Bech32m with a per-network human readable
+/// part (`tb1...` on mainnet). Only the Bech32m form carries a checksum.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Address(pub [u8; ADDRESS_LENGTH]);
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AddressError {
+    #[error("address must be 0x-prefixed hex")]
+    MissingPrefix,
+    #[error("address must be {ADDRESS_LENGTH} bytes, got {0}")]
+    InvalidLength(usize),
+    #[error("invalid hex in address")]
+    InvalidHex,
+    #[error("invalid Bech32m address: {0}")]
+    InvalidBech32(String),
+    #[error("address checksum does not match, check for typos")]
+    InvalidChecksum,
+    #[error("unknown address prefix {0}")]
+    UnknownHrp(String),
+    #[error("address is for {got}, expected {expected}")]
+    WrongNetwork { expected: NetworkProfile, got: NetworkProfile },
+}
+
+impl From<CheckedHrpstringError> for AddressError {
+    fn from(err: CheckedHrpstringError) -> Self {
+        match err {
+            CheckedHrpstringError::Checksum(_) => AddressError::InvalidChecksum,
+            other => AddressError::InvalidBech32(other.to_string()),
+        }
+    }
+}
+
+impl Address {
+    pub const ZERO: Address = Address([0u8; ADDRESS_LENGTH]);
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, AddressError> {
+        let array: [u8; ADDRESS_LENGTH] = bytes
+            .try_into()
+            .map_err(|_| AddressError::InvalidLength(bytes.len()))?;
+        Ok(Self(array))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; ADDRESS_LENGTH] {
+        &self.0
+    }
+
+    /// `0x`-prefixed lowercase hex form.
+    pub fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.0))
+    }
+
+    pub fn from_hex(s: &str) -> Result<Self, AddressError> {
+        let digits = s
+            .strip_prefix("0x")
+            .or_else(|| s.strip_prefix("0X"))
+            .ok_or(AddressError::MissingPrefix)?;
+        let bytes = hex::decode(digits).map_err(|_| AddressError::InvalidHex)?;
+        Self::from_slice(&bytes)
+    }
+
+    /// Bech32m form on `network`, e.g. `tb1...` on mainnet.
+    pub fn to_bech32(&self, network: NetworkProfile) -> String {
+        let hrp = Hrp::parse_unchecked(network.address_hrp());
+        bech32::encode::<Bech32m>(hrp, &self.0).expect("20 bytes fit in a Bech32m string")
+    }
+
+    /// Decode a Bech32m address, returning the network its prefix names.
+    pub fn from_bech32(s: &str) -> Result<(NetworkProfile, Self), AddressError> {
+        let checked = CheckedHrpstring::new::<Bech32m>(s)?;
+        let hrp = checked.hrp().to_lowercase();
+        let network = NetworkProfile::from_address_hrp(&hrp).ok_or(AddressError::UnknownHrp(hrp))?;
+        let bytes: Vec<u8> = checked.byte_iter().collect();
+        Ok((network, Self::from_slice(&bytes)?))
+    }
+
+    /// Parse either form, rejecting Bech32m addresses of other networks.
+    pub fn parse_for(s: &str, network: NetworkProfile) -> Result<Self, AddressError> {
+        if is_hex_form(s) {
+            return Self::from_hex(s);
+        }
+        let (got, address) = Self::from_bech32(s)?;
+        if got != network {
+            return Err(AddressError::WrongNetwork { expected: network, got });
+        }
+        Ok(address)
+    }
+}
+
+fn is_hex_form(s: &str) -> bool {
+    s.starts_with("0x") || s.starts_with("0X")
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Address({})", self.to_hex())
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddressError;
+
+    /// Accepts `0x` hex or Bech32m for any network.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if is_hex_form(s) {
+            Self::from_hex(s)
+        } else {
+            Self::from_bech32(s).map(|(_, address)| address)
+        }
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
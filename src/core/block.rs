@@ -0,0 +1,223 @@
+use crate::core::address::Address;
+use crate::core::bloom::Bloom;
+use crate::core::codec::{DecodeError, Decoder, Encoder};
+use crate::core::transaction::SignedTransaction;
+use crate::core::types::{H256, ZERO_HASH};
+
+/// Header format version, bumped whenever the encoding changes.
+pub const HEADER_VERSION: u8 = 3;
+
+/// Maximum length of `BlockHeader::extra_data`.
+pub const MAX_EXTRA_DATA: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub number: u64,
+    pub parent_hash: H256,
+    pub state_root: H256,
+    pub transactions_root: H256,
+    pub receipts_root: H256,
+    /// Bloom over the addresses and topics of every log in the block
+    pub logs_bloom: Bloom,
+    pub shard_id: u32,
+    pub proposer: Address,
+    /// Unix time in milliseconds
+    pub timestamp: u64,
+    pub gas_used: u64,
+    pub gas_limit: u64,
+    /// Ember per gas burned by every transaction in the block
+    pub base_fee: u128,
+    pub extra_data: Vec<u8>,
+}
+
+impl Default for BlockHeader {
+    fn default() -> Self {
+        Self {
+            number: 0,
+            parent_hash: ZERO_HASH,
+            state_root: ZERO_HASH,
+            transactions_root: ZERO_HASH,
+            receipts_root: ZERO_HASH,
+            logs_bloom: Bloom::default(),
+            shard_id: 0,
+            proposer: Address::ZERO,
+            timestamp: 0,
+            gas_used: 0,
+            gas_limit: 0,
+            base_fee: 0,
+            extra_data: Vec::new(),
+        }
+    }
+}
+
+impl BlockHeader {
+    /// Canonical binary encoding. The block hash commits to exactly these bytes.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut enc = Encoder::new();
+        enc.put_u8(HEADER_VERSION)
+            .put_u64(self.number)
+            .put_fixed(&self.parent_hash)
+            .put_fixed(&self.state_root)
+            .put_fixed(&self.transactions_root)
+            .put_fixed(&self.receipts_root)
+            .put_fixed(&self.logs_bloom.0)
+            .put_u32(self.shard_id)
+            .put_fixed(self.proposer.as_bytes())
+            .put_u64(self.timestamp)
+            .put_u64(self.gas_used)
+            .put_u64(self.gas_limit)
+            .put_u128(self.base_fee)
+            .put_bytes(&self.extra_data);
+        enc.finish()
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut dec = Decoder::new(bytes);
+        let header = Self::decode_from(&mut dec)?;
+        dec.finish()?;
+        Ok(header)
+    }
+
+    pub fn decode_from(dec: &mut Decoder<'_>) -> Result<Self, DecodeError> {
+        if dec.get_u8()? != HEADER_VERSION {
+            return Err(DecodeError::Invalid("unsupported header version"));
+        }
+        let header = Self {
+            number: dec.get_u64()?,
+            parent_hash: dec.get_fixed()?,
+            state_root: dec.get_fixed()?,
+            transactions_root: dec.get_fixed()?,
+            receipts_root: dec.get_fixed()?,
+            logs_bloom: Bloom(dec.get_fixed()?),
+            shard_id: dec.get_u32()?,
+            proposer: Address(dec.get_fixed()?),
+            timestamp: dec.get_u64()?,
+            gas_used: dec.get_u64()?,
+            gas_limit: dec.get_u64()?,
+            base_fee: dec.get_u128()?,
+            extra_data: dec.get_bytes()?,
+        };
+        if header.extra_data.len() > MAX_EXTRA_DATA {
+            return Err(DecodeError::Invalid("extra data too long"));
+        }
+        Ok(header)
+    }
+
+    /// blake3 over the canonical encoding.
+    pub fn hash(&self) -> H256 {
+        *blake3::hash(&self.encode()).as_bytes()
+    }
+
+    /// sha3-256 over the canonical encoding, for consumers that need a
+    /// NIST hash (bridges, external light clients).
+    pub fn sha3_hash(&self) -> H256 {
+        use sha3::{Digest, Sha3_256};
+        Sha3_256::digest(self.encode()).into()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub header: BlockHeader,
+    pub transactions: Vec<SignedTransaction>,
+}
+
+impl Block {
+    /// Build a block from a header template, filling in `transactions_root`.
+    pub fn new(mut header: BlockHeader, transactions: Vec<SignedTransaction>) -> Self {
+        header.transactions_root = transactions_root(&transactions);
+        Self { header, transactions }
+    }
+
+    pub fn genesis(timestamp: u64) -> Self {
+        Self::new(
+            BlockHeader {
+                timestamp,
+                ..Default::default()
+            },
+            Vec::new(),
+        )
+    }
+
+    pub fn number(&self) -> u64 {
+        self.header.number
+    }
+
+    pub fn hash(&self) -> H256 {
+        self.header.hash()
+    }
+
+    pub fn tx_count(&self) -> usize {
+        self.transactions.len()
+    }
+
+    /// Whether the header commits to the transactions in the body.
+    pub fn verify_transactions_root(&self) -> bool {
+        self.header.transactions_root == transactions_root(&self.transactions)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut enc = Encoder::new();
+        enc.put_bytes(&self.header.encode())
+            .put_u32(self.transactions.len() as u32);
+        for tx in &self.transactions {
+            tx.encode_into(&mut enc);
+        }
+        enc.finish()
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut dec = Decoder::new(bytes);
+        let header = BlockHeader::decode(&dec.get_bytes()?)?;
+        let count = dec.get_u32()? as usize;
+        let mut transactions = Vec::with_capacity(count.min(dec.remaining()));
+        for _ in 0..count {
+            transactions.push(SignedTransaction::decode_from(&mut dec)?);
+        }
+        dec.finish()?;
+        Ok(Self { header, transactions })
+    }
+}
+
+pub fn transactions_root(transactions: &[SignedTransaction]) -> H256 {
+    let hashes: Vec<H256> = transactions.iter().map(|tx| tx.hash()).collect();
+    merkle_root(&hashes)
+}
+
+/// Binary Merkle root over `leaves` using blake3.
+///
+/// Leaves and interior nodes are domain-separated so a leaf can never be
+/// reinterpreted as a node. An odd node at the end of a level is promoted
+/// unchanged rather than duplicated, so two different lists never share a
+/// root. The empty list has the zero root.
+pub fn merkle_root(leaves: &[H256]) -> H256 {
+    if leaves.is_empty() {
+        return ZERO_HASH;
+    }
+
+    let mut level: Vec<H256> = leaves
+        .iter()
+        .map(|leaf| {
+            let mut hasher = blake3::Hasher::new();
+            hasher.update(&[0x00]).update(leaf);
+            *hasher.finalize().as_bytes()
+        })
+        .collect();
+
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => {
+                    let mut hasher = blake3::Hasher::new();
+                    hasher.update(&[0x01]).update(left).update(right);
+                    *hasher.finalize().as_bytes()
+                }
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+
+    level[0]
+}
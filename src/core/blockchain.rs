@@ -1,25 +1,700 @@
-use std::sync::Arc;
-use tokio::sync::RwLock;
-
-#[derive(Debug, Clone)]
-pub struct Blockchain {
-    // In a real implementation, this would hold blocks, state, etc.
-    // For now, we'll just use a counter or mock data.
-    height: Arc<RwLock<u64>>,
-}
-
-impl Blockchain {
-    pub fn new() -> Self {
-        Self {
-            height: Arc::new(RwLock::new(1234567)),
-        }
-    }
-
-    pub async fn get_height(&self) -> u64 {
-        *self.height.read().await
-    }
-    
-    pub async fn get_tps(&self) -> u64 {
-        347892 // Mock value matching the dashboard
-    }
-}
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::{OwnedRwLockWriteGuard, RwLock};
+
+use crate::core::block::{Block, BlockHeader};
+use crate::core::codec::{DecodeError, Decoder, Encoder};
+use crate::core::receipt::{receipts_root, LocatedLog, LogFilter, Receipt};
+use crate::core::state::WorldState;
+use crate::core::transaction::SignedTransaction;
+use crate::core::types::{to_hex, H256};
+use crate::storage::mvcc::{MvccError, StateSnapshot, VersionedState};
+use crate::storage::rocksdb::{Column, KvStore, StorageError, WriteBatch};
+
+/// Number of most recent blocks used to compute TPS.
+pub const TPS_WINDOW_BLOCKS: usize = 100;
+
+/// Largest block range a single `get_logs` query may scan.
+pub const MAX_LOG_BLOCK_RANGE: u64 = 10_000;
+
+/// Most recent blocks kept in memory. Older blocks of a persisted chain are
+/// read back from the database when asked for.
+pub const CACHED_BLOCKS: usize = TPS_WINDOW_BLOCKS;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChainError {
+    #[error("block {got} does not extend head {head}")]
+    NonSequentialNumber { head: u64, got: u64 },
+    #[error("parent hash {got} does not match head hash {expected}")]
+    ParentMismatch { expected: String, got: String },
+    #[error("block timestamp {got} is older than parent timestamp {parent}")]
+    TimestampRegression { parent: u64, got: u64 },
+    #[error("block {0} is already known")]
+    AlreadyKnown(String),
+    #[error("transactions root does not match block body")]
+    TransactionsRootMismatch,
+    #[error("gas used {used} exceeds gas limit {limit}")]
+    GasLimitExceeded { used: u64, limit: u64 },
+    #[error("receipts do not match the block receipts root")]
+    ReceiptsMismatch,
+    #[error("log query spans {blocks} blocks, maximum is {max}")]
+    LogRangeTooLarge { blocks: u64, max: u64 },
+    #[error("stored genesis {stored} does not match configured genesis {expected}")]
+    GenesisMismatch { stored: String, expected: String },
+    #[error("database already holds a chain")]
+    NotEmpty,
+    #[error("state root {got} does not match block state root {expected}")]
+    StateRootMismatch { expected: String, got: String },
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    #[error(transparent)]
+    History(#[from] MvccError),
+}
+
+/// Metadata key holding the big-endian number of the head block.
+const HEAD_KEY: &[u8] = b"head";
+
+/// Metadata key holding the big-endian number of the first block after
+/// genesis that is stored, for chains bootstrapped from a snapshot.
+const BASE_KEY: &[u8] = b"base";
+
+/// Metadata key holding the big-endian number of the first block whose
+/// body and receipts were not pruned.
+const PRUNED_KEY: &[u8] = b"pruned";
+
+/// Metadata key holding the big-endian Ember burned by every block stored
+/// with receipts, so it survives pruning and need not be summed on open.
+const BURNED_KEY: &[u8] = b"burned";
+
+/// Header and body checks for `block` as the child of `parent`. Does not
+/// execute transactions; see `BlockImporter` for full verification.
+pub fn validate_child(parent: &Block, block: &Block) -> Result<(), ChainError> {
+    if block.number() != parent.number() + 1 {
+        return Err(ChainError::NonSequentialNumber {
+            head: parent.number(),
+            got: block.number(),
+        });
+    }
+    if block.header.parent_hash != parent.hash() {
+        return Err(ChainError::ParentMismatch {
+            expected: to_hex(&parent.hash()),
+            got: to_hex(&block.header.parent_hash),
+        });
+    }
+    if block.header.timestamp < parent.header.timestamp {
+        return Err(ChainError::TimestampRegression {
+            parent: parent.header.timestamp,
+            got: block.header.timestamp,
+        });
+    }
+
+    if !block.verify_transactions_root() {
+        return Err(ChainError::TransactionsRootMismatch);
+    }
+    if block.header.gas_used > block.header.gas_limit {
+        return Err(ChainError::GasLimitExceeded {
+            used: block.header.gas_used,
+            limit: block.header.gas_limit,
+        });
+    }
+    Ok(())
+}
+
+/// Queue `block` and its receipts, and make it the stored head.
+fn write_block(batch: &mut WriteBatch, block: &Block, receipts: Option<&[Receipt]>) {
+    let key = block.number().to_be_bytes();
+    batch.put(Column::Headers, key, block.header.encode());
+    let mut body = Encoder::new();
+    body.put_u32(block.transactions.len() as u32);
+    for tx in &block.transactions {
+        tx.encode_into(&mut body);
+    }
+    batch.put(Column::Bodies, key, body.finish());
+    if let Some(receipts) = receipts {
+        let mut enc = Encoder::new();
+        enc.put_u32(receipts.len() as u32);
+        for receipt in receipts {
+            enc.put_bytes(&receipt.encode());
+        }
+        batch.put(Column::Receipts, key, enc.finish());
+    }
+    batch.put(Column::BlockNumbers, block.hash(), key);
+    batch.put(Column::Metadata, HEAD_KEY, key);
+}
+
+fn decode_body(bytes: &[u8]) -> Result<Vec<SignedTransaction>, DecodeError> {
+    let mut dec = Decoder::new(bytes);
+    let count = dec.get_u32()? as usize;
+    let mut transactions = Vec::with_capacity(count.min(dec.remaining()));
+    for _ in 0..count {
+        transactions.push(SignedTransaction::decode_from(&mut dec)?);
+    }
+    dec.finish()?;
+    Ok(transactions)
+}
+
+fn decode_receipts(bytes: &[u8]) -> Result<Vec<Receipt>, DecodeError> {
+    let mut dec = Decoder::new(bytes);
+    let count = dec.get_u32()? as usize;
+    let mut receipts = Vec::with_capacity(count.min(dec.remaining()));
+    for _ in 0..count {
+        receipts.push(Receipt::decode(&dec.get_bytes()?)?);
+    }
+    dec.finish()?;
+    Ok(receipts)
+}
+
+/// Big-endian block number stored under `key` in `column`.
+fn load_number(db: &dyn KvStore, column: Column, key: &[u8], what: &'static str) -> Result<Option<u64>, StorageError> {
+    db.get(column, key)?
+        .map(|bytes| {
+            Ok(u64::from_be_bytes(bytes.try_into().map_err(|_| StorageError::Corrupt {
+                what,
+                source: DecodeError::Invalid("block number is not 8 bytes"),
+            })?))
+        })
+        .transpose()
+}
+
+/// Block `number`, with an empty transaction list if its body was pruned.
+fn load_block(db: &dyn KvStore, number: u64, pruned: bool) -> Result<Block, StorageError> {
+    let key = number.to_be_bytes();
+    let missing = |what| StorageError::Missing(format!("{what} of block {number}"));
+    let header = db.get(Column::Headers, &key)?.ok_or_else(|| missing("header"))?;
+    let transactions = match db.get(Column::Bodies, &key)? {
+        Some(body) => decode_body(&body).map_err(|source| StorageError::Corrupt {
+            what: "block body",
+            source,
+        })?,
+        None if pruned => Vec::new(),
+        None => return Err(missing("body")),
+    };
+    Ok(Block {
+        header: BlockHeader::decode(&header).map_err(|source| StorageError::Corrupt {
+            what: "block header",
+            source,
+        })?,
+        transactions,
+    })
+}
+
+fn load_receipts(db: &dyn KvStore, number: u64) -> Result<Option<Vec<Receipt>>, StorageError> {
+    db.get(Column::Receipts, &number.to_be_bytes())?
+        .map(|bytes| decode_receipts(&bytes))
+        .transpose()
+        .map_err(|source| StorageError::Corrupt {
+            what: "receipts",
+            source,
+        })
+}
+
+#[derive(Debug)]
+struct ChainStore {
+    /// Number of the first block available: zero, or the snapshot block a
+    /// bootstrapped chain started from
+    base: u64,
+    /// Most recent blocks up to the head, oldest first. Without a database
+    /// every block from `base` is held; with one, the last `CACHED_BLOCKS`
+    blocks: VecDeque<Arc<Block>>,
+    /// Receipts of the blocks held, when the block was appended with them
+    receipts: VecDeque<Option<Arc<Vec<Receipt>>>>,
+    /// First block after genesis whose body and receipts were not pruned
+    pruned: u64,
+    /// Numbers of the blocks held, by hash
+    by_hash: HashMap<H256, u64>,
+    /// Ember burned by every block appended with receipts
+    burned: u128,
+    /// Where appended blocks are persisted, if anywhere
+    db: Option<Arc<dyn KvStore>>,
+    /// State at each retained height, once `track_history` is called
+    history: Option<Arc<VersionedState>>,
+}
+
+impl ChainStore {
+    fn head(&self) -> &Arc<Block> {
+        // The store always holds at least the head block
+        self.blocks.back().expect("chain store holds the head")
+    }
+
+    /// Position of block `number` in `blocks`, if it is held.
+    fn index(&self, number: u64) -> Option<usize> {
+        let first = self.head().number() + 1 - self.blocks.len() as u64;
+        number
+            .checked_sub(first)
+            .and_then(|index| usize::try_from(index).ok())
+            .filter(|&index| index < self.blocks.len())
+    }
+
+    /// Hold `block` as the new head, dropping the oldest block held once
+    /// there are more than `CACHED_BLOCKS` and it can be read back.
+    fn push(&mut self, block: Arc<Block>, receipts: Option<Arc<Vec<Receipt>>>) {
+        self.by_hash.insert(block.hash(), block.number());
+        self.blocks.push_back(block);
+        self.receipts.push_back(receipts);
+        if self.db.is_some() && self.blocks.len() > CACHED_BLOCKS {
+            if let Some(oldest) = self.blocks.pop_front() {
+                self.by_hash.remove(&oldest.hash());
+            }
+            self.receipts.pop_front();
+        }
+    }
+
+    /// Whether block `number` is on the chain from `base` to the head.
+    fn has(&self, number: u64) -> bool {
+        (self.base..=self.head().number()).contains(&number)
+    }
+
+    fn block(&self, number: u64) -> Result<Option<Arc<Block>>, StorageError> {
+        if let Some(index) = self.index(number) {
+            return Ok(Some(self.blocks[index].clone()));
+        }
+        match &self.db {
+            Some(db) if self.has(number) => Ok(Some(Arc::new(load_block(db.as_ref(), number, number < self.pruned)?))),
+            _ => Ok(None),
+        }
+    }
+
+    fn receipts(&self, number: u64) -> Result<Option<Arc<Vec<Receipt>>>, StorageError> {
+        if let Some(index) = self.index(number) {
+            return Ok(self.receipts[index].clone());
+        }
+        match &self.db {
+            Some(db) if self.has(number) => Ok(load_receipts(db.as_ref(), number)?.map(Arc::new)),
+            _ => Ok(None),
+        }
+    }
+
+    fn number_of(&self, hash: &H256) -> Result<Option<u64>, StorageError> {
+        if let Some(number) = self.by_hash.get(hash) {
+            return Ok(Some(*number));
+        }
+        match &self.db {
+            Some(db) => Ok(load_number(db.as_ref(), Column::BlockNumbers, hash, "block number")?
+                .filter(|&number| self.has(number))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Value read from the chain database, logging a failed read and treating
+/// it as absent.
+fn logged<T>(read: Result<Option<T>, StorageError>) -> Option<T> {
+    read.unwrap_or_else(|err| {
+        tracing::error!("reading chain storage failed: {}", err);
+        None
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct Blockchain {
+    store: Arc<RwLock<ChainStore>>,
+}
+
+impl Blockchain {
+    pub fn new(genesis: Block) -> Self {
+        let mut by_hash = HashMap::new();
+        by_hash.insert(genesis.hash(), genesis.number());
+
+        Self {
+            store: Arc::new(RwLock::new(ChainStore {
+                base: 0,
+                blocks: VecDeque::from([Arc::new(genesis)]),
+                receipts: VecDeque::from([Some(Arc::new(Vec::new()))]),
+                pruned: 0,
+                by_hash,
+                burned: 0,
+                db: None,
+                history: None,
+            })),
+        }
+    }
+
+    /// Chain persisted in `db`. A new database is initialised with
+    /// `genesis` and its state; an existing one has its most recent blocks
+    /// loaded after checking it was started from the same genesis. Load the
+    /// head state with `WorldState::load` at the head's state root.
+    pub fn open(db: Arc<dyn KvStore>, genesis: Block, genesis_state: &WorldState) -> Result<Self, ChainError> {
+        let head = match load_number(db.as_ref(), Column::Metadata, HEAD_KEY, "chain head")? {
+            Some(head) => head,
+            None => {
+                let mut batch = WriteBatch::new();
+                genesis_state.write_to(&mut batch);
+                write_block(&mut batch, &genesis, Some(&[]));
+                db.write(batch)?;
+                0
+            }
+        };
+
+        let chain = Self::new(genesis);
+        {
+            let mut store = chain.store.try_write().expect("new chain is not shared");
+            let stored = load_block(db.as_ref(), 0, false)?;
+            if stored.hash() != store.head().hash() {
+                return Err(ChainError::GenesisMismatch {
+                    stored: to_hex(&stored.hash()),
+                    expected: to_hex(&store.head().hash()),
+                });
+            }
+            store.base = load_number(db.as_ref(), Column::Metadata, BASE_KEY, "chain base")?.unwrap_or(0);
+            store.pruned = load_number(db.as_ref(), Column::Metadata, PRUNED_KEY, "pruned height")?.unwrap_or(0);
+            if let Some(burned) = db.get(Column::Metadata, BURNED_KEY)? {
+                store.burned = u128::from_be_bytes(burned.try_into().map_err(|_| StorageError::Corrupt {
+                    what: "burn total",
+                    source: DecodeError::Invalid("burn total is not 16 bytes"),
+                })?);
+            }
+            let first = head.saturating_sub(CACHED_BLOCKS as u64 - 1).max(store.base);
+            if first > 0 {
+                store.blocks.clear();
+                store.receipts.clear();
+                store.by_hash.clear();
+            }
+            for number in first.max(1)..=head {
+                let block = load_block(db.as_ref(), number, number < store.pruned)?;
+                let receipts = load_receipts(db.as_ref(), number)?;
+                store.push(Arc::new(block), receipts.map(Arc::new));
+            }
+            store.db = Some(db);
+        }
+        Ok(chain)
+    }
+
+    /// Chain starting at `block`, whose state after execution is `state`,
+    /// persisted in the empty database `db`. Used to bootstrap a node from a
+    /// state snapshot rather than replaying from genesis; blocks between
+    /// genesis and `block` are not available.
+    pub fn bootstrap(db: Arc<dyn KvStore>, genesis: Block, block: Block, state: &WorldState) -> Result<Self, ChainError> {
+        if db.contains(Column::Metadata, HEAD_KEY)? {
+            return Err(ChainError::NotEmpty);
+        }
+        let mut batch = WriteBatch::new();
+        let root = state.write_to(&mut batch);
+        if root != block.header.state_root {
+            return Err(ChainError::StateRootMismatch {
+                expected: to_hex(&block.header.state_root),
+                got: to_hex(&root),
+            });
+        }
+        write_block(&mut batch, &genesis, Some(&[]));
+        write_block(&mut batch, &block, None);
+        batch.put(Column::Metadata, BASE_KEY, block.number().to_be_bytes());
+        db.write(batch)?;
+        Self::open(db, genesis, state)
+    }
+
+    /// Exclusive access to the chain for storage maintenance. Appending
+    /// blocks waits until the returned guard is dropped, so keep it briefly.
+    pub async fn pause_import(&self) -> ImportPause {
+        ImportPause {
+            store: self.store.clone().write_owned().await,
+        }
+    }
+
+    /// Keep the state of every block from the head on, starting from
+    /// `head_state`, so readers can take snapshots with `state_at`. Only
+    /// blocks added with `append_executed` carry their state; appending a
+    /// block any other way stops tracking.
+    pub async fn track_history(&self, head_state: &WorldState, retained: u64) -> Arc<VersionedState> {
+        let mut store = self.store.write().await;
+        let history = Arc::new(VersionedState::new(store.head().number(), head_state, retained));
+        store.history = Some(history.clone());
+        history
+    }
+
+    /// State as of block `number`.
+    pub async fn state_at(&self, number: u64) -> Result<StateSnapshot, MvccError> {
+        let history = self.store.read().await.history.clone().ok_or(MvccError::Disabled)?;
+        history.snapshot(number)
+    }
+
+    /// State as of the head block.
+    pub async fn latest_state(&self) -> Result<StateSnapshot, MvccError> {
+        let store = self.store.read().await;
+        let history = store.history.as_ref().ok_or(MvccError::Disabled)?;
+        history.snapshot(store.head().number())
+    }
+
+    /// Validate `block` against the current head and make it the new head.
+    pub async fn append(&self, block: Block) -> Result<(), ChainError> {
+        self.insert(block, None, None).await
+    }
+
+    /// Like `append`, also keeping the receipts produced by executing the
+    /// block so its logs can be queried.
+    pub async fn append_with_receipts(&self, block: Block, receipts: Vec<Receipt>) -> Result<(), ChainError> {
+        if receipts_root(&receipts) != block.header.receipts_root {
+            return Err(ChainError::ReceiptsMismatch);
+        }
+        self.insert(block, Some(Arc::new(receipts)), None).await
+    }
+
+    /// Like `append_with_receipts`, also persisting `state` (the state
+    /// after executing the block) in the same write as the block and
+    /// recording its uncommitted changes as the block's state version.
+    /// Only those changes are written, on top of the stored parent state.
+    pub async fn append_executed(
+        &self,
+        block: Block,
+        receipts: Vec<Receipt>,
+        state: &WorldState,
+    ) -> Result<(), ChainError> {
+        if receipts_root(&receipts) != block.header.receipts_root {
+            return Err(ChainError::ReceiptsMismatch);
+        }
+        self.insert(block, Some(Arc::new(receipts)), Some(state)).await
+    }
+
+    async fn insert(
+        &self,
+        block: Block,
+        receipts: Option<Arc<Vec<Receipt>>>,
+        state: Option<&WorldState>,
+    ) -> Result<(), ChainError> {
+        let mut store = self.store.write().await;
+        let head = store.head().clone();
+        let hash = block.hash();
+
+        if store.number_of(&hash)?.is_some() {
+            return Err(ChainError::AlreadyKnown(to_hex(&hash)));
+        }
+        validate_child(&head, &block)?;
+
+        // Nothing may fail once the block is written
+        if let (Some(history), Some(_)) = (&store.history, state) {
+            history.check_next(block.number())?;
+        }
+        let burned = match &receipts {
+            Some(receipts) => receipts.iter().fold(store.burned, |total, r| total.saturating_add(r.burned)),
+            None => store.burned,
+        };
+        if let Some(db) = &store.db {
+            let mut batch = WriteBatch::new();
+            if let Some(state) = state {
+                state.write_changes_to(&mut batch);
+            }
+            write_block(&mut batch, &block, receipts.as_deref().map(Vec::as_slice));
+            batch.put(Column::Metadata, BURNED_KEY, burned.to_be_bytes());
+            db.write(batch)?;
+        }
+        match (&store.history, state) {
+            (Some(history), Some(state)) => history.commit(block.number(), state.pending_changes())?,
+            (Some(_), None) => store.history = None,
+            (None, _) => {}
+        }
+        store.burned = burned;
+        store.push(Arc::new(block), receipts);
+        Ok(())
+    }
+
+    pub async fn head(&self) -> Arc<Block> {
+        self.store.read().await.head().clone()
+    }
+
+    pub async fn get_height(&self) -> u64 {
+        self.store.read().await.head().number()
+    }
+
+    /// Total Ember burned in transaction fees. Blocks appended without
+    /// receipts are not counted.
+    pub async fn total_burned(&self) -> u128 {
+        self.store.read().await.burned
+    }
+
+    /// Number of the oldest block held: zero unless the chain was
+    /// bootstrapped from a snapshot.
+    pub async fn base(&self) -> u64 {
+        self.store.read().await.base
+    }
+
+    /// First block after genesis that still has its body and receipts.
+    /// Earlier blocks come back with no transactions.
+    pub async fn pruned(&self) -> u64 {
+        self.store.read().await.pruned
+    }
+
+    /// Gather the bodies and receipts of blocks before `below` for
+    /// `ImportPause::prune_bodies`, keeping their headers. Genesis is never
+    /// pruned. Storage is read without pausing import.
+    pub async fn bodies_to_prune(&self, below: u64) -> Result<BodyPrune, StorageError> {
+        let (from, below, db) = {
+            let store = self.store.read().await;
+            let from = store.pruned.max(store.base).max(1);
+            (from, below.min(store.head().number()), store.db.clone())
+        };
+        let mut prune = BodyPrune::default();
+        let Some(db) = db.filter(|_| from < below) else {
+            return Ok(prune);
+        };
+        for number in from..below {
+            let key = number.to_be_bytes();
+            for column in [Column::Bodies, Column::Receipts] {
+                if let Some(value) = db.get(column, &key)? {
+                    prune.pruned.bytes += (key.len() + value.len()) as u64;
+                    prune.batch.delete(column, key);
+                }
+            }
+            prune.pruned.blocks += 1;
+        }
+        prune.from = from;
+        prune.below = below;
+        Ok(prune)
+    }
+
+    pub async fn get_block_by_number(&self, number: u64) -> Option<Arc<Block>> {
+        logged(self.store.read().await.block(number))
+    }
+
+    pub async fn get_block_by_hash(&self, hash: &H256) -> Option<Arc<Block>> {
+        let store = self.store.read().await;
+        let number = logged(store.number_of(hash))?;
+        logged(store.block(number))
+    }
+
+    pub async fn get_receipts(&self, number: u64) -> Option<Arc<Vec<Receipt>>> {
+        logged(self.store.read().await.receipts(number))
+    }
+
+    /// Logs matching `filter`, oldest first. Blocks whose header bloom rules
+    /// out a match are skipped without reading their receipts.
+    pub async fn get_logs(&self, filter: &LogFilter) -> Result<Vec<LocatedLog>, ChainError> {
+        let store = self.store.read().await;
+        let head = store.head().number();
+        let from = filter.from_block.unwrap_or(0).max(store.base);
+        let to = filter.to_block.unwrap_or(head).min(head);
+        if from > to {
+            return Ok(Vec::new());
+        }
+        let blocks = to - from + 1;
+        if blocks > MAX_LOG_BLOCK_RANGE {
+            return Err(ChainError::LogRangeTooLarge {
+                blocks,
+                max: MAX_LOG_BLOCK_RANGE,
+            });
+        }
+
+        let mut logs = Vec::new();
+        for number in from..=to {
+            let Some(block) = store.block(number)? else {
+                continue;
+            };
+            if !filter.may_match(&block.header.logs_bloom) {
+                continue;
+            }
+            let Some(receipts) = store.receipts(number)? else {
+                continue;
+            };
+            let mut log_index = 0;
+            for (tx_index, receipt) in receipts.iter().enumerate() {
+                for log in &receipt.logs {
+                    if filter.matches(log) {
+                        logs.push(LocatedLog {
+                            block_number: number,
+                            block_hash: block.hash(),
+                            tx_hash: receipt.tx_hash,
+                            tx_index,
+                            log_index,
+                            log: log.clone(),
+                        });
+                    }
+                    log_index += 1;
+                }
+            }
+        }
+        Ok(logs)
+    }
+
+    /// Most recent blocks, newest first.
+    pub async fn recent_blocks(&self, limit: usize) -> Vec<Arc<Block>> {
+        let store = self.store.read().await;
+        let head = store.head().number();
+        (0..limit as u64)
+            .map_while(|back| head.checked_sub(back))
+            .map_while(|number| logged(store.block(number)))
+            .collect()
+    }
+
+    /// Transactions per second over the last `TPS_WINDOW_BLOCKS` blocks.
+    pub async fn get_tps(&self) -> u64 {
+        let store = self.store.read().await;
+        let start = store.blocks.len().saturating_sub(TPS_WINDOW_BLOCKS);
+        let mut window = store.blocks.range(start..);
+
+        let (oldest, newest) = match (window.next(), store.blocks.back()) {
+            (Some(oldest), Some(newest)) => (oldest, newest),
+            _ => return 0,
+        };
+        let span_ms = newest.header.timestamp.saturating_sub(oldest.header.timestamp);
+        if span_ms == 0 {
+            return 0;
+        }
+
+        // The oldest block only marks the start of the window; its
+        // transactions were produced before the measured interval.
+        let tx_count: u64 = window.map(|b| b.tx_count() as u64).sum();
+        tx_count * 1000 / span_ms
+    }
+}
+
+/// Chain with block import paused; see `Blockchain::pause_import`.
+pub struct ImportPause {
+    store: OwnedRwLockWriteGuard<ChainStore>,
+}
+
+/// Bodies and receipts removed by `ImportPause::prune_bodies`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrunedBodies {
+    pub blocks: u64,
+    /// Size of the deleted keys and values
+    pub bytes: u64,
+}
+
+/// Deletions gathered by `Blockchain::bodies_to_prune`.
+#[derive(Debug, Default)]
+pub struct BodyPrune {
+    from: u64,
+    below: u64,
+    batch: WriteBatch,
+    pruned: PrunedBodies,
+}
+
+impl ImportPause {
+    pub fn head(&self) -> &Arc<Block> {
+        self.store.head()
+    }
+
+    pub fn block(&self, number: u64) -> Result<Option<Arc<Block>>, StorageError> {
+        self.store.block(number)
+    }
+
+    /// Apply deletions gathered by `Blockchain::bodies_to_prune`. Nothing
+    /// is deleted if bodies were pruned since they were gathered.
+    pub fn prune_bodies(&mut self, prune: BodyPrune) -> Result<PrunedBodies, StorageError> {
+        let BodyPrune {
+            from,
+            below,
+            mut batch,
+            pruned,
+        } = prune;
+        let current = self.store.pruned.max(self.store.base).max(1);
+        let Some(db) = self.store.db.clone().filter(|_| from < below && from == current) else {
+            return Ok(PrunedBodies::default());
+        };
+        batch.put(Column::Metadata, PRUNED_KEY, below.to_be_bytes());
+        db.write(batch)?;
+
+        let store = &mut *self.store;
+        for number in from..below {
+            if let Some(index) = store.index(number) {
+                let header = store.blocks[index].header.clone();
+                store.blocks[index] = Arc::new(Block {
+                    header,
+                    transactions: Vec::new(),
+                });
+                store.receipts[index] = None;
+            }
+        }
+        store.pruned = below;
+        Ok(pruned)
+    }
+}
+
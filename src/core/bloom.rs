@@ -0,0 +1,60 @@
+use std::fmt;
+
+use crate::core::types::keccak256;
+
+pub const BLOOM_BYTES: usize = 256;
+
+/// 2048-bit bloom filter over log addresses and topics.
+///
+/// Each input sets three bits taken from the first six bytes of its
+/// keccak256, the same construction Ethereum uses, so existing tooling
+/// can test membership.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bloom(pub [u8; BLOOM_BYTES]);
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self([0u8; BLOOM_BYTES])
+    }
+}
+
+impl Bloom {
+    fn bits(input: &[u8]) -> [(usize, u8); 3] {
+        let hash = keccak256(input);
+        let mut bits = [(0, 0); 3];
+        for (i, bit) in bits.iter_mut().enumerate() {
+            let index = (((hash[2 * i] as usize) << 8) | hash[2 * i + 1] as usize) & (BLOOM_BYTES * 8 - 1);
+            *bit = (BLOOM_BYTES - 1 - index / 8, 1 << (index % 8));
+        }
+        bits
+    }
+
+    pub fn accrue(&mut self, input: &[u8]) {
+        for (byte, mask) in Self::bits(input) {
+            self.0[byte] |= mask;
+        }
+    }
+
+    pub fn accrue_bloom(&mut self, other: &Bloom) {
+        for (a, b) in self.0.iter_mut().zip(other.0.iter()) {
+            *a |= b;
+        }
+    }
+
+    /// `false` means `input` was definitely never added.
+    pub fn contains_input(&self, input: &[u8]) -> bool {
+        Self::bits(input)
+            .iter()
+            .all(|(byte, mask)| self.0[*byte] & mask == *mask)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|b| *b == 0)
+    }
+}
+
+impl fmt::Debug for Bloom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Bloom(0x{})", hex::encode(self.0))
+    }
+}
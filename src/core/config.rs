@@ -0,0 +1,531 @@
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::core::address::Address;
+use crate::core::gas::{GasSchedule, MAX_GAS_PRICE};
+
+/// Prefix for environment overrides. Nested keys are joined with `__`,
+/// e.g. `TBURN_API__REST_PORT=4000` overrides `[api] rest_port`.
+pub const ENV_PREFIX: &str = "TBURN_";
+const ENV_SEPARATOR: &str = "__";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("failed to parse {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("invalid value for `{key}`: {reason}")]
+    Invalid { key: String, reason: String },
+    #[error("unknown network profile `{0}` (expected mainnet, testnet or devnet)")]
+    UnknownProfile(String),
+}
+
+fn invalid(key: &str, reason: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid {
+        key: key.to_string(),
+        reason: reason.into(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkProfile {
+    Mainnet,
+    Testnet,
+    Devnet,
+}
+
+impl NetworkProfile {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            NetworkProfile::Mainnet => "mainnet.toml",
+            NetworkProfile::Testnet => "testnet.toml",
+            NetworkProfile::Devnet => "devnet.toml",
+        }
+    }
+
+    /// Human readable part of Bech32m addresses on this network.
+    pub fn address_hrp(&self) -> &'static str {
+        match self {
+            NetworkProfile::Mainnet => "tb",
+            NetworkProfile::Testnet => "tbt",
+            NetworkProfile::Devnet => "tbd",
+        }
+    }
+
+    pub fn from_address_hrp(hrp: &str) -> Option<Self> {
+        match hrp {
+            "tb" => Some(NetworkProfile::Mainnet),
+            "tbt" => Some(NetworkProfile::Testnet),
+            "tbd" => Some(NetworkProfile::Devnet),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for NetworkProfile {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mainnet" => Ok(NetworkProfile::Mainnet),
+            "testnet" => Ok(NetworkProfile::Testnet),
+            "devnet" => Ok(NetworkProfile::Devnet),
+            _ => Err(ConfigError::UnknownProfile(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for NetworkProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.file_name().trim_end_matches(".toml"))
+    }
+}
+
+/// Typed view of `config/{mainnet,testnet,devnet}.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeConfig {
+    pub network: NetworkConfig,
+    pub consensus: ConsensusConfig,
+    pub sharding: ShardingConfig,
+    pub validators: ValidatorsConfig,
+    pub smart_contracts: SmartContractsConfig,
+    pub api: ApiConfig,
+    pub database: DatabaseConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub deployment: DeploymentConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NetworkConfig {
+    pub name: String,
+    pub chain_id: u64,
+    pub network_id: u64,
+    /// Expected genesis hash; checked against the built genesis when set
+    #[serde(default)]
+    pub genesis_hash: Option<String>,
+    #[serde(default = "default_genesis_file")]
+    pub genesis_file: PathBuf,
+    pub performance: PerformanceConfig,
+}
+
+fn default_genesis_file() -> PathBuf {
+    PathBuf::from("config/genesis.json")
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PerformanceConfig {
+    pub target_tps: u64,
+    pub avg_block_time_ms: u64,
+    pub max_block_size_mb: u64,
+    pub transaction_pool_size: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConsensusConfig {
+    pub mechanism: String,
+    pub block_time_ms: u64,
+    pub target_tps: u64,
+    pub confirmation_blocks: u64,
+    /// Fee recipient for blocks produced by this node. Without one the
+    /// node does not produce blocks.
+    #[serde(default)]
+    pub proposer: Option<Address>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShardingConfig {
+    pub enabled: bool,
+    pub total_shards: u32,
+    pub shard_names: Vec<String>,
+    #[serde(default)]
+    pub cross_shard_enabled: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ValidatorsConfig {
+    /// Minimum stake in whole BURN tokens
+    pub min_stake: String,
+    pub max_validators: u32,
+    pub commission_range: [f64; 2],
+}
+
+impl ValidatorsConfig {
+    pub fn min_stake(&self) -> u128 {
+        self.min_stake.parse().unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SmartContractsConfig {
+    pub evm_compatible: bool,
+    pub max_contract_size_kb: u64,
+    pub gas_limit_per_block: u64,
+    /// Base gas price in Ember
+    pub base_gas_price_emb: u64,
+    /// Highest base fee in Ember; at least `base_gas_price_emb`
+    #[serde(default = "default_max_gas_price_emb")]
+    pub max_gas_price_emb: u64,
+    /// Per-operation gas costs; unset entries keep the Ethereum values
+    #[serde(default)]
+    pub gas_schedule: GasSchedule,
+}
+
+fn default_max_gas_price_emb() -> u64 {
+    MAX_GAS_PRICE as u64
+}
+
+impl SmartContractsConfig {
+    pub fn max_contract_size_bytes(&self) -> usize {
+        (self.max_contract_size_kb * 1024) as usize
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiConfig {
+    #[serde(default = "default_api_host")]
+    pub host: IpAddr,
+    pub rest_port: u16,
+    pub websocket_port: u16,
+    pub max_connections: u32,
+    pub rate_limit_per_minute: u32,
+}
+
+fn default_api_host() -> IpAddr {
+    IpAddr::from([127, 0, 0, 1])
+}
+
+impl ApiConfig {
+    pub fn rest_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.rest_port)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DatabaseConfig {
+    #[serde(default = "default_database_url")]
+    pub url: String,
+    pub max_connections: u32,
+    pub connection_timeout_seconds: u64,
+    /// RocksDB directory holding blocks, receipts and state
+    #[serde(default = "default_data_dir")]
+    pub data_dir: PathBuf,
+    #[serde(default)]
+    pub pruning: PruningMode,
+    /// Recent blocks whose state (and for light nodes, body and receipts)
+    /// the pruner keeps
+    #[serde(default = "default_pruning_keep_recent")]
+    pub pruning_keep_recent: u64,
+    #[serde(default = "default_pruning_interval_seconds")]
+    pub pruning_interval_seconds: u64,
+    /// Memory for cached trie nodes, accounts and contract code
+    #[serde(default = "default_cache_size_mb")]
+    pub cache_size_mb: u64,
+}
+
+/// What the pruner deletes from chain storage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PruningMode {
+    /// Keep every state, body and receipt
+    #[default]
+    Archive,
+    /// Keep every block, and the state of recent blocks only
+    Full,
+    /// Keep headers, and the state, bodies and receipts of recent blocks
+    Light,
+}
+
+fn default_database_url() -> String {
+    "sqlite://explorer.db?mode=rwc".to_string()
+}
+
+fn default_data_dir() -> PathBuf {
+    PathBuf::from("data/chain")
+}
+
+fn default_pruning_keep_recent() -> u64 {
+    128
+}
+
+fn default_pruning_interval_seconds() -> u64 {
+    600
+}
+
+fn default_cache_size_mb() -> u64 {
+    256
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoggingConfig {
+    pub level: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeploymentConfig {
+    /// Write a state snapshot every `backup_interval_hours`
+    #[serde(default)]
+    pub backup_enabled: bool,
+    #[serde(default)]
+    pub backup_interval_hours: u64,
+    /// Directory the state snapshots are written to
+    #[serde(default = "default_backup_dir")]
+    pub backup_dir: PathBuf,
+    /// Most recent snapshots kept in `backup_dir`; older ones are deleted
+    #[serde(default = "default_backup_keep_recent")]
+    pub backup_keep_recent: usize,
+}
+
+fn default_backup_dir() -> PathBuf {
+    PathBuf::from("data/snapshots")
+}
+
+fn default_backup_keep_recent() -> usize {
+    4
+}
+
+impl Default for DeploymentConfig {
+    fn default() -> Self {
+        Self {
+            backup_enabled: false,
+            backup_interval_hours: 0,
+            backup_dir: default_backup_dir(),
+            backup_keep_recent: default_backup_keep_recent(),
+        }
+    }
+}
+
+impl NodeConfig {
+    /// Load `<config_dir>/<profile>.toml` and apply `TBURN_*` overrides
+    /// from the process environment.
+    pub fn load(config_dir: impl AsRef<Path>, profile: NetworkProfile) -> Result<Self, ConfigError> {
+        let path = config_dir.as_ref().join(profile.file_name());
+        let contents = std::fs::read_to_string(&path).map_err(|source| ConfigError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        Self::from_toml_with_env(&path, &contents, std::env::vars())
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, ConfigError> {
+        Self::from_toml_with_env(Path::new("<inline>"), contents, std::iter::empty())
+    }
+
+    pub fn from_toml_with_env(
+        path: &Path,
+        contents: &str,
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        let mut root: toml::Value = toml::from_str(contents).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        for (name, value) in env {
+            if let Some(key) = name.strip_prefix(ENV_PREFIX) {
+                if key.contains(ENV_SEPARATOR) {
+                    apply_override(&mut root, key, &value)?;
+                }
+            }
+        }
+
+        let config: NodeConfig = deserialize_value(root)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.network.chain_id == 0 {
+            return Err(invalid("network.chain_id", "must be greater than zero"));
+        }
+        if let Some(hash) = &self.network.genesis_hash {
+            let valid = hash
+                .strip_prefix("0x")
+                .is_some_and(|digits| digits.len() == 64 && hex::decode(digits).is_ok());
+            if !valid {
+                return Err(invalid("network.genesis_hash", "must be a 0x-prefixed 32-byte hex string"));
+            }
+        }
+        if self.network.performance.transaction_pool_size == 0 {
+            return Err(invalid(
+                "network.performance.transaction_pool_size",
+                "must be greater than zero",
+            ));
+        }
+        if self.consensus.block_time_ms == 0 {
+            return Err(invalid("consensus.block_time_ms", "must be greater than zero"));
+        }
+
+        if self.sharding.total_shards == 0 {
+            return Err(invalid("sharding.total_shards", "must be greater than zero"));
+        }
+        if self.sharding.shard_names.len() != self.sharding.total_shards as usize {
+            return Err(invalid(
+                "sharding.shard_names",
+                format!(
+                    "has {} names but total_shards is {}",
+                    self.sharding.shard_names.len(),
+                    self.sharding.total_shards
+                ),
+            ));
+        }
+
+        match self.validators.min_stake.parse::<u128>() {
+            Ok(stake) if stake > 0 => {}
+            _ => return Err(invalid("validators.min_stake", "must be a positive integer")),
+        }
+        if self.validators.max_validators == 0 {
+            return Err(invalid("validators.max_validators", "must be greater than zero"));
+        }
+        let [low, high] = self.validators.commission_range;
+        if !(0.0..=1.0).contains(&low) || !(0.0..=1.0).contains(&high) || low > high {
+            return Err(invalid(
+                "validators.commission_range",
+                "must be [min, max] with 0 <= min <= max <= 1",
+            ));
+        }
+
+        if self.smart_contracts.max_contract_size_kb == 0 {
+            return Err(invalid("smart_contracts.max_contract_size_kb", "must be greater than zero"));
+        }
+        if self.smart_contracts.gas_limit_per_block == 0 {
+            return Err(invalid("smart_contracts.gas_limit_per_block", "must be greater than zero"));
+        }
+        let schedule = &self.smart_contracts.gas_schedule;
+        for (key, divisor) in [
+            ("smart_contracts.gas_schedule.memory_quad_divisor", schedule.memory_quad_divisor),
+            ("smart_contracts.gas_schedule.max_refund_quotient", schedule.max_refund_quotient),
+            ("smart_contracts.gas_schedule.modexp_divisor", schedule.modexp_divisor),
+        ] {
+            if divisor == 0 {
+                return Err(invalid(key, "must be greater than zero"));
+            }
+        }
+        if self.smart_contracts.base_gas_price_emb > self.smart_contracts.max_gas_price_emb {
+            return Err(invalid(
+                "smart_contracts.max_gas_price_emb",
+                "must be at least smart_contracts.base_gas_price_emb",
+            ));
+        }
+
+        if self.api.rest_port == 0 {
+            return Err(invalid("api.rest_port", "must be greater than zero"));
+        }
+        if self.api.rest_port == self.api.websocket_port {
+            return Err(invalid("api.websocket_port", "must differ from api.rest_port"));
+        }
+
+        if self.database.url.is_empty() {
+            return Err(invalid("database.url", "must not be empty"));
+        }
+        if self.database.max_connections == 0 {
+            return Err(invalid("database.max_connections", "must be greater than zero"));
+        }
+
+        if self.database.pruning != PruningMode::Archive {
+            if self.database.pruning_keep_recent == 0 {
+                return Err(invalid("database.pruning_keep_recent", "must be greater than zero when pruning"));
+            }
+            if self.database.pruning_interval_seconds == 0 {
+                return Err(invalid("database.pruning_interval_seconds", "must be greater than zero when pruning"));
+            }
+        }
+
+        if self.deployment.backup_enabled && self.deployment.backup_interval_hours == 0 {
+            return Err(invalid(
+                "deployment.backup_interval_hours",
+                "must be greater than zero when backups are enabled",
+            ));
+        }
+        if self.deployment.backup_enabled && self.deployment.backup_keep_recent == 0 {
+            return Err(invalid(
+                "deployment.backup_keep_recent",
+                "must be greater than zero when backups are enabled",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Deserialize, reporting the dotted path of the first offending key.
+fn deserialize_value<T: DeserializeOwned>(value: toml::Value) -> Result<T, ConfigError> {
+    serde_path_to_error::deserialize(value).map_err(|err| ConfigError::Invalid {
+        key: err.path().to_string(),
+        reason: err.into_inner().message().to_string(),
+    })
+}
+
+/// Set `SECTION__KEY` in `root`, parsing `raw` to the type already at that
+/// key so `TBURN_API__REST_PORT=4000` stays an integer.
+fn apply_override(root: &mut toml::Value, env_key: &str, raw: &str) -> Result<(), ConfigError> {
+    let path: Vec<String> = env_key
+        .split(ENV_SEPARATOR)
+        .map(str::to_ascii_lowercase)
+        .collect();
+    let dotted = path.join(".");
+
+    let (leaf, parents) = path.split_last().expect("split yields at least one part");
+    let mut table = root;
+    for part in parents {
+        table = table
+            .as_table_mut()
+            .ok_or_else(|| invalid(&dotted, "parent is not a table"))?
+            .entry(part.clone())
+            .or_insert_with(|| toml::Value::Table(toml::map::Map::new()));
+    }
+    let table = table
+        .as_table_mut()
+        .ok_or_else(|| invalid(&dotted, "parent is not a table"))?;
+
+    let value = match table.get(leaf) {
+        Some(toml::Value::Integer(_)) => raw
+            .parse()
+            .map(toml::Value::Integer)
+            .map_err(|_| invalid(&dotted, format!("`{raw}` is not an integer")))?,
+        Some(toml::Value::Float(_)) => raw
+            .parse()
+            .map(toml::Value::Float)
+            .map_err(|_| invalid(&dotted, format!("`{raw}` is not a number")))?,
+        Some(toml::Value::Boolean(_)) => raw
+            .parse()
+            .map(toml::Value::Boolean)
+            .map_err(|_| invalid(&dotted, format!("`{raw}` is not a boolean")))?,
+        Some(toml::Value::Array(_)) => {
+            let wrapped = format!("value = {raw}");
+            let parsed: toml::Value = toml::from_str(&wrapped)
+                .map_err(|_| invalid(&dotted, format!("`{raw}` is not an array")))?;
+            parsed["value"].clone()
+        }
+        Some(_) => toml::Value::String(raw.to_string()),
+        // New key: take the most specific type the text parses as
+        None => raw
+            .parse()
+            .map(toml::Value::Integer)
+            .or_else(|_| raw.parse().map(toml::Value::Boolean))
+            .unwrap_or_else(|_| toml::Value::String(raw.to_string())),
+    };
+    table.insert(leaf.clone(), value);
+    Ok(())
+}
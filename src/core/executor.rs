@@ -0,0 +1,291 @@
+use std::sync::Arc;
+
+use crate::contracts::deployer::ContractDeployer;
+use crate::contracts::executor::{Tbc20FastPathExecutor, TokenStandard};
+use crate::core::address::Address;
+use crate::core::gas::{GasMeter, GasSchedule};
+use crate::core::mempool::max_cost;
+use crate::core::receipt::{Log, Receipt};
+use crate::core::state::{ExecutionState, WorldState};
+use crate::core::transaction::{SignedTransaction, Transaction, TxError};
+use crate::core::types::{keccak256, H256};
+use crate::core::vm::{CallOutput, Evm, Message};
+
+/// Block context transactions execute in.
+#[derive(Debug, Clone)]
+pub struct BlockEnv {
+    pub chain_id: u64,
+    pub number: u64,
+    pub timestamp: u64,
+    /// Receives the fees paid by transactions in the block
+    pub proposer: Address,
+    pub gas_limit: u64,
+    /// Burned per unit of gas; the rest of the gas price goes to `proposer`
+    pub base_fee: u128,
+    pub schedule: GasSchedule,
+    /// Handles calls to registered TBC-20 tokens without the VM
+    pub fast_path: Arc<Tbc20FastPathExecutor>,
+    /// Runs contract deployments and registers factory tokens
+    pub deployer: Arc<ContractDeployer>,
+}
+
+/// Reasons a transaction cannot be included in a block at all.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ExecutionError {
+    #[error(transparent)]
+    Transaction(#[from] TxError),
+    #[error("nonce {got} does not match account nonce {expected}")]
+    NonceMismatch { expected: u64, got: u64 },
+    #[error("gas price {gas_price} is below the block base fee {base_fee}")]
+    GasPriceBelowBaseFee { gas_price: u128, base_fee: u128 },
+    #[error("gas limit {gas_limit} is below intrinsic gas {intrinsic}")]
+    IntrinsicGas { gas_limit: u64, intrinsic: u64 },
+    #[error("gas limit {gas_limit} exceeds remaining block gas {remaining}")]
+    BlockGasExceeded { gas_limit: u64, remaining: u64 },
+    #[error("balance {balance} cannot cover cost {cost}")]
+    InsufficientFunds { balance: u128, cost: u128 },
+    #[error("transaction cost overflows")]
+    CostOverflow,
+}
+
+/// Address of a contract created by `sender` with its `nonce`.
+pub fn create_address(sender: &Address, nonce: u64) -> Address {
+    let mut preimage = Vec::with_capacity(28);
+    preimage.extend_from_slice(sender.as_bytes());
+    preimage.extend_from_slice(&nonce.to_be_bytes());
+    let hash = keccak256(&preimage);
+    Address::from_slice(&hash[12..]).expect("20 byte slice")
+}
+
+/// Address of a contract created by `sender` with CREATE2, which depends on
+/// the init code rather than the nonce.
+pub fn create2_address(sender: &Address, salt: &H256, init_code_hash: &H256) -> Address {
+    let mut preimage = Vec::with_capacity(85);
+    preimage.push(0xff);
+    preimage.extend_from_slice(sender.as_bytes());
+    preimage.extend_from_slice(salt);
+    preimage.extend_from_slice(init_code_hash);
+    let hash = keccak256(&preimage);
+    Address::from_slice(&hash[12..]).expect("20 byte slice")
+}
+
+/// Execute `tx` on top of `state`.
+///
+/// An `Err` means the transaction is invalid for this block and `state` is
+/// left untouched. A transaction that is valid but fails during execution
+/// still consumes gas and yields a receipt with `success == false`.
+pub fn execute_transaction<S: ExecutionState>(
+    state: &mut S,
+    env: &BlockEnv,
+    tx: &SignedTransaction,
+    cumulative_gas_used: u64,
+) -> Result<Receipt, ExecutionError> {
+    let sender = tx.verify(env.chain_id)?;
+
+    let nonce = state.get_nonce(&sender);
+    if tx.tx().nonce != nonce {
+        return Err(ExecutionError::NonceMismatch {
+            expected: nonce,
+            got: tx.tx().nonce,
+        });
+    }
+    if tx.tx().gas_price < env.base_fee {
+        return Err(ExecutionError::GasPriceBelowBaseFee {
+            gas_price: tx.tx().gas_price,
+            base_fee: env.base_fee,
+        });
+    }
+    let intrinsic = env.schedule.intrinsic_gas(tx.tx());
+    if tx.tx().gas_limit < intrinsic {
+        return Err(ExecutionError::IntrinsicGas {
+            gas_limit: tx.tx().gas_limit,
+            intrinsic,
+        });
+    }
+    let remaining = env.gas_limit.saturating_sub(cumulative_gas_used);
+    if tx.tx().gas_limit > remaining {
+        return Err(ExecutionError::BlockGasExceeded {
+            gas_limit: tx.tx().gas_limit,
+            remaining,
+        });
+    }
+    let cost = max_cost(tx).ok_or(ExecutionError::CostOverflow)?;
+    let balance = state.get_balance(&sender);
+    if balance < cost {
+        return Err(ExecutionError::InsufficientFunds { balance, cost });
+    }
+
+    // Buy the full gas limit up front and refund what is left afterwards
+    let price = tx.tx().gas_price;
+    state.set_nonce(&sender, nonce + 1);
+    state.set_balance(&sender, balance - tx.tx().gas_limit as u128 * price);
+
+    let mut meter = GasMeter::new(tx.tx().gas_limit);
+    meter.charge(intrinsic).expect("gas limit covers intrinsic gas");
+    let checkpoint = state.checkpoint();
+    let outcome = match tx.tx().to {
+        Some(to) => call(state, env, &mut meter, &sender, &to, tx.tx()),
+        None => create(state, env, &mut meter, &sender, nonce, tx.tx()),
+    };
+    if !outcome.success {
+        // Everything but the gas payment is undone
+        state.revert_to(checkpoint);
+        meter.clear_refund();
+    }
+
+    let gas_used = meter.finalize(&env.schedule);
+    let refund = (tx.tx().gas_limit - gas_used) as u128 * price;
+    state.set_balance(&sender, state.get_balance(&sender) + refund);
+    let burned = gas_used as u128 * env.base_fee;
+    let tip = gas_used as u128 * (price - env.base_fee);
+    state.credit(&env.proposer, tip);
+
+    Ok(Receipt {
+        tx_hash: tx.hash(),
+        success: outcome.success,
+        gas_used,
+        cumulative_gas_used: cumulative_gas_used + gas_used,
+        burned,
+        contract_address: outcome.contract_address,
+        logs: outcome.logs,
+        revert_data: outcome.revert_data,
+    })
+}
+
+/// Result of the execution part of a transaction, after validation. Gas is
+/// tracked by the caller's `GasMeter`.
+struct Outcome {
+    success: bool,
+    contract_address: Option<Address>,
+    logs: Vec<Log>,
+    revert_data: Vec<u8>,
+}
+
+impl Outcome {
+    fn failed() -> Self {
+        Self::reverted(Vec::new())
+    }
+
+    fn reverted(revert_data: Vec<u8>) -> Self {
+        Self {
+            success: false,
+            contract_address: None,
+            logs: Vec::new(),
+            revert_data,
+        }
+    }
+}
+
+fn call<S: ExecutionState>(
+    state: &mut S,
+    env: &BlockEnv,
+    meter: &mut GasMeter,
+    sender: &Address,
+    to: &Address,
+    tx: &Transaction,
+) -> Outcome {
+    if env.fast_path.is_eligible(&*state, tx) {
+        if state.transfer(sender, to, tx.value).is_err() {
+            return Outcome::failed();
+        }
+        // Fast path prices are flat and already include the base transaction cost
+        let result = env.fast_path.execute(state, sender, tx);
+        let charged = meter.charge(result.gas_used.saturating_sub(env.schedule.tx_base));
+        if charged.is_err() || !result.success {
+            return Outcome::failed();
+        }
+        return Outcome {
+            success: true,
+            contract_address: None,
+            logs: result.logs,
+            revert_data: Vec::new(),
+        };
+    }
+    if let Some(standard) = env.deployer.factory_standard(to) {
+        return factory_create(state, env, meter, sender, to, standard, tx);
+    }
+
+    let mut evm = Evm::new(state, env, *sender, tx.gas_price);
+    let message = Message::call(*sender, *to, tx.value, tx.data.clone(), meter.remaining());
+    let output = evm.execute(message);
+    settle(evm, meter, output)
+}
+
+fn create<S: ExecutionState>(
+    state: &mut S,
+    env: &BlockEnv,
+    meter: &mut GasMeter,
+    sender: &Address,
+    nonce: u64,
+    tx: &Transaction,
+) -> Outcome {
+    let mut evm = Evm::new(state, env, *sender, tx.gas_price);
+    let output = env
+        .deployer
+        .create(&mut evm, *sender, nonce, tx.value, tx.data.clone(), meter.remaining());
+    settle(evm, meter, output)
+}
+
+/// Deploy `salt || init_code` through a factory, paying creation gas as a
+/// contract creating transaction would. TBC-20 tokens are registered for
+/// the fast path once deployed.
+fn factory_create<S: ExecutionState>(
+    state: &mut S,
+    env: &BlockEnv,
+    meter: &mut GasMeter,
+    sender: &Address,
+    factory: &Address,
+    standard: TokenStandard,
+    tx: &Transaction,
+) -> Outcome {
+    if meter.charge(env.schedule.tx_create).is_err() {
+        return Outcome::failed();
+    }
+    let mut evm = Evm::new(state, env, *sender, tx.gas_price);
+    let output = match env
+        .deployer
+        .factory_create(&mut evm, factory, *sender, tx.value, &tx.data, meter.remaining())
+    {
+        Some(output) => output,
+        None => return Outcome::failed(),
+    };
+    let outcome = settle(evm, meter, output);
+    if let (true, Some(address)) = (standard == TokenStandard::TBC20, outcome.contract_address) {
+        env.deployer.register_token(state, &address, env.number);
+    }
+    outcome
+}
+
+/// Charge the gas a VM run consumed and collect its logs and refund.
+fn settle(evm: Evm<'_>, meter: &mut GasMeter, output: CallOutput) -> Outcome {
+    meter
+        .charge(meter.remaining() - output.gas_left)
+        .expect("VM spends at most the gas it was given");
+    if !output.is_success() {
+        return Outcome::reverted(output.output);
+    }
+    let (logs, refund) = evm.finish();
+    meter.add_refund(refund);
+    Outcome {
+        success: true,
+        contract_address: output.created,
+        logs,
+        revert_data: Vec::new(),
+    }
+}
+
+/// Execute every transaction of a block in order. Fails on the first
+/// transaction that could not have been included.
+pub fn execute_transactions(
+    state: &mut WorldState,
+    env: &BlockEnv,
+    txs: &[SignedTransaction],
+) -> Result<Vec<Receipt>, (usize, ExecutionError)> {
+    let mut receipts: Vec<Receipt> = Vec::with_capacity(txs.len());
+    for (index, tx) in txs.iter().enumerate() {
+        let cumulative = receipts.last().map_or(0, |r| r.cumulative_gas_used);
+        let receipt = execute_transaction(state, env, tx, cumulative).map_err(|e| (index, e))?;
+        receipts.push(receipt);
+    }
+    Ok(receipts)
+}
@@ -0,0 +1,423 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::block::BlockHeader;
+use crate::core::config::NodeConfig;
+use crate::core::transaction::Transaction;
+use crate::core::types::H256;
+
+/// Ember is TBURN's gas unit and its smallest denomination.
+/// 1 TBURN = 1,000,000,000 Ember (10^9).
+pub const EMBER_PER_TBURN: u128 = 1_000_000_000;
+
+/// Gas charged for every transaction before any execution.
+pub const TX_BASE_GAS: u64 = 21_000;
+
+/// Base fee of the genesis block when the genesis file does not set one.
+pub const INITIAL_BASE_FEE: u128 = 1;
+
+/// Default ceiling for the base fee, in Ember per gas.
+pub const MAX_GAS_PRICE: u128 = 1_000_000;
+
+/// Gas limit to gas target ratio; blocks can be up to twice the target.
+pub const ELASTICITY_MULTIPLIER: u64 = 2;
+
+/// Bounds the base fee change between blocks to 1/8 (12.5%).
+pub const BASE_FEE_MAX_CHANGE_DENOMINATOR: u128 = 8;
+
+/// Protocol gas pricing. Every node must run with the same values.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GasConfig {
+    pub ember_per_tburn: u128,
+    /// Floor for the base fee, in Ember per gas
+    pub min_gas_price: u128,
+    /// Ceiling for the base fee, in Ember per gas
+    pub max_gas_price: u128,
+    pub elasticity_multiplier: u64,
+    pub base_fee_max_change_denominator: u128,
+    /// Tip suggested for `TransactionPriority::Normal`
+    pub priority_fee: u128,
+}
+
+impl Default for GasConfig {
+    fn default() -> Self {
+        Self {
+            ember_per_tburn: EMBER_PER_TBURN,
+            min_gas_price: 1,
+            max_gas_price: MAX_GAS_PRICE,
+            elasticity_multiplier: ELASTICITY_MULTIPLIER,
+            base_fee_max_change_denominator: BASE_FEE_MAX_CHANGE_DENOMINATOR,
+            priority_fee: 1,
+        }
+    }
+}
+
+impl GasConfig {
+    pub fn from_node_config(config: &NodeConfig) -> Self {
+        Self {
+            min_gas_price: config.smart_contracts.base_gas_price_emb as u128,
+            max_gas_price: config.smart_contracts.max_gas_price_emb as u128,
+            ..Default::default()
+        }
+    }
+
+    /// Base fee of the child of `parent`.
+    ///
+    /// Moves towards demand as in EIP-1559: up when the parent used more
+    /// than its gas target, down when it used less, by at most
+    /// `1 / base_fee_max_change_denominator`. A rise is at least 1 Ember
+    /// so small fees still respond to full blocks.
+    pub fn next_base_fee(&self, parent: &BlockHeader) -> u128 {
+        let base_fee = parent.base_fee;
+        let target = (parent.gas_limit / self.elasticity_multiplier.max(1)) as u128;
+        let used = parent.gas_used as u128;
+        let next = if target == 0 || used == target {
+            base_fee
+        } else if used > target {
+            let delta = base_fee.saturating_mul(used - target) / target / self.base_fee_max_change_denominator;
+            base_fee.saturating_add(delta.max(1))
+        } else {
+            let delta = base_fee.saturating_mul(target - used) / target / self.base_fee_max_change_denominator;
+            base_fee - delta
+        };
+        next.clamp(self.min_gas_price, self.max_gas_price)
+    }
+}
+
+/// Gas cost of every metered operation.
+///
+/// Values follow the Ethereum schedule (Berlin storage pricing, London
+/// refunds) so contracts ported from Ethereum keep their gas profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GasSchedule {
+    pub tx_base: u64,
+    /// Extra intrinsic gas for contract creation
+    pub tx_create: u64,
+    pub tx_data_zero_byte: u64,
+    pub tx_data_nonzero_byte: u64,
+
+    /// Opcode tiers
+    pub zero: u64,
+    pub base: u64,
+    pub very_low: u64,
+    pub low: u64,
+    pub mid: u64,
+    pub high: u64,
+    pub jumpdest: u64,
+    pub exp: u64,
+    pub exp_byte: u64,
+    pub keccak256: u64,
+    pub keccak256_word: u64,
+    pub copy_word: u64,
+    pub memory_word: u64,
+    /// Quadratic memory cost divisor
+    pub memory_quad_divisor: u64,
+
+    pub balance: u64,
+    pub ext_code: u64,
+    pub sload: u64,
+    /// Writing a non-zero value to an empty slot
+    pub sstore_set: u64,
+    /// Changing or clearing a non-empty slot
+    pub sstore_reset: u64,
+    /// Refunded when a slot is cleared
+    pub sstore_clear_refund: u64,
+    /// Refunds are capped at `gas_used / max_refund_quotient`
+    pub max_refund_quotient: u64,
+
+    pub log: u64,
+    pub log_topic: u64,
+    pub log_data_byte: u64,
+
+    pub call: u64,
+    pub call_value: u64,
+    /// Stipend passed to the callee of a value-bearing call
+    pub call_stipend: u64,
+    pub new_account: u64,
+    pub create: u64,
+    pub code_deposit_byte: u64,
+    pub blockhash: u64,
+    pub selfdestruct: u64,
+    /// Wasm fuel bought by one unit of gas; the Wasm runtime charges one
+    /// fuel for most instructions
+    pub wasm_fuel_per_gas: u64,
+    /// Loading a Wasm module, per byte of code
+    pub wasm_code_byte: u64,
+    /// Linear memory of a Wasm module, initial or grown, per 64 KiB page
+    pub wasm_memory_page: u64,
+
+    /// Precompiled contracts; hashes charge a base plus a per-word cost
+    pub ecrecover: u64,
+    pub sha256: u64,
+    pub sha256_word: u64,
+    pub blake3: u64,
+    pub blake3_word: u64,
+    /// Minimum MODEXP charge (EIP-2565)
+    pub modexp_min: u64,
+    /// Divides MODEXP's multiplication complexity times iteration count
+    pub modexp_divisor: u64,
+    pub bech32m: u64,
+}
+
+impl Default for GasSchedule {
+    fn default() -> Self {
+        Self {
+            tx_base: TX_BASE_GAS,
+            tx_create: 32_000,
+            tx_data_zero_byte: 4,
+            tx_data_nonzero_byte: 16,
+            zero: 0,
+            base: 2,
+            very_low: 3,
+            low: 5,
+            mid: 8,
+            high: 10,
+            jumpdest: 1,
+            exp: 10,
+            exp_byte: 50,
+            keccak256: 30,
+            keccak256_word: 6,
+            copy_word: 3,
+            memory_word: 3,
+            memory_quad_divisor: 512,
+            balance: 2_600,
+            ext_code: 2_600,
+            sload: 2_100,
+            sstore_set: 20_000,
+            sstore_reset: 2_900,
+            sstore_clear_refund: 4_800,
+            max_refund_quotient: 5,
+            log: 375,
+            log_topic: 375,
+            log_data_byte: 8,
+            call: 2_600,
+            call_value: 9_000,
+            call_stipend: 2_300,
+            new_account: 25_000,
+            create: 32_000,
+            code_deposit_byte: 200,
+            blockhash: 20,
+            selfdestruct: 5_000,
+            wasm_fuel_per_gas: 1,
+            wasm_code_byte: 2,
+            // 3 gas per word, as EVM memory
+            wasm_memory_page: 6_144,
+            ecrecover: 3_000,
+            sha256: 60,
+            sha256_word: 12,
+            blake3: 30,
+            blake3_word: 4,
+            modexp_min: 200,
+            modexp_divisor: 3,
+            bech32m: 300,
+        }
+    }
+}
+
+impl GasSchedule {
+    /// Gas charged before execution: base cost, creation cost and calldata.
+    pub fn intrinsic_gas(&self, tx: &Transaction) -> u64 {
+        let zeros = tx.data.iter().filter(|b| **b == 0).count() as u64;
+        let nonzeros = tx.data.len() as u64 - zeros;
+        let create = if tx.to.is_none() { self.tx_create } else { 0 };
+        self.tx_base
+            .saturating_add(create)
+            .saturating_add(zeros.saturating_mul(self.tx_data_zero_byte))
+            .saturating_add(nonzeros.saturating_mul(self.tx_data_nonzero_byte))
+    }
+
+    /// Cost and refund of writing `new` to a slot currently holding `current`.
+    pub fn sstore_cost(&self, current: &H256, new: &H256) -> (u64, u64) {
+        let empty = |v: &H256| v.iter().all(|b| *b == 0);
+        if current == new {
+            (self.sload, 0)
+        } else if empty(current) {
+            (self.sstore_set, 0)
+        } else if empty(new) {
+            (self.sstore_reset, self.sstore_clear_refund)
+        } else {
+            (self.sstore_reset, 0)
+        }
+    }
+
+    pub fn log_cost(&self, topics: usize, data_len: usize) -> u64 {
+        self.log
+            .saturating_add(self.log_topic.saturating_mul(topics as u64))
+            .saturating_add(self.log_data_byte.saturating_mul(data_len as u64))
+    }
+
+    /// Total cost of a memory of `words` 32-byte words. Callers charge the
+    /// difference when memory grows.
+    pub fn memory_cost(&self, words: u64) -> u64 {
+        self.memory_word
+            .saturating_mul(words)
+            .saturating_add(words.saturating_mul(words) / self.memory_quad_divisor)
+    }
+
+    pub fn code_deposit_cost(&self, code_len: usize) -> u64 {
+        self.code_deposit_byte.saturating_mul(code_len as u64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("out of gas")]
+pub struct OutOfGas;
+
+/// Tracks the gas consumed by one transaction against its limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasMeter {
+    limit: u64,
+    used: u64,
+    refund: u64,
+}
+
+impl GasMeter {
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            used: 0,
+            refund: 0,
+        }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.limit - self.used
+    }
+
+    pub fn refund(&self) -> u64 {
+        self.refund
+    }
+
+    /// Consume `amount` gas. Running out consumes everything that was left.
+    pub fn charge(&mut self, amount: u64) -> Result<(), OutOfGas> {
+        if amount > self.remaining() {
+            self.used = self.limit;
+            return Err(OutOfGas);
+        }
+        self.used += amount;
+        Ok(())
+    }
+
+    /// Hand back gas a sub-call did not use.
+    pub fn return_gas(&mut self, amount: u64) {
+        self.used = self.used.saturating_sub(amount);
+    }
+
+    pub fn add_refund(&mut self, amount: u64) {
+        self.refund = self.refund.saturating_add(amount);
+    }
+
+    /// Drop refunds, e.g. when the call that earned them reverts.
+    pub fn clear_refund(&mut self) {
+        self.refund = 0;
+    }
+
+    /// Gas the transaction pays for after applying refunds, capped at
+    /// `used / max_refund_quotient`.
+    pub fn finalize(&self, schedule: &GasSchedule) -> u64 {
+        let cap = self.used / schedule.max_refund_quotient.max(1);
+        self.used - self.refund.min(cap)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TransactionPriority {
+    Urgent,
+    High,
+    Normal,
+    Low,
+}
+
+impl TransactionPriority {
+    /// Multiple of `GasConfig::priority_fee` suggested as the tip
+    fn tip_multiplier(self) -> u128 {
+        match self {
+            TransactionPriority::Urgent => 4,
+            TransactionPriority::High => 2,
+            TransactionPriority::Normal => 1,
+            TransactionPriority::Low => 0,
+        }
+    }
+}
+
+/// Suggested price for a transaction in the next block.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GasPriceQuote {
+    /// Burned per unit of gas
+    pub base_fee: u128,
+    /// Paid to the proposer per unit of gas
+    pub priority_fee: u128,
+    /// `base_fee + priority_fee`, the `gas_price` to sign
+    pub gas_price: u128,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GasCostEstimate {
+    pub gas_limit: u64,
+    pub gas_price_ember: u128,
+    pub total_cost_ember: u128,
+    pub total_cost_tburn: u128,
+}
+
+/// Ember gas price quotes and unit conversion.
+///
+/// Quotes depend only on the chain head and `GasConfig`, so every node
+/// quotes the same price for the same block.
+#[derive(Debug, Clone)]
+pub struct EmberGasSystem {
+    config: GasConfig,
+}
+
+impl EmberGasSystem {
+    pub fn new(config: GasConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &GasConfig {
+        &self.config
+    }
+
+    /// Price for inclusion in the block after `head`
+    pub fn get_gas_price(&self, priority: TransactionPriority, head: &BlockHeader) -> GasPriceQuote {
+        let base_fee = self.config.next_base_fee(head);
+        let priority_fee = self.config.priority_fee.saturating_mul(priority.tip_multiplier());
+        GasPriceQuote {
+            base_fee,
+            priority_fee,
+            gas_price: base_fee.saturating_add(priority_fee),
+        }
+    }
+
+    pub fn tburn_to_ember(&self, tburn_amount: u128) -> u128 {
+        tburn_amount.saturating_mul(self.config.ember_per_tburn)
+    }
+
+    pub fn ember_to_tburn(&self, ember_amount: u128) -> u128 {
+        ember_amount / self.config.ember_per_tburn
+    }
+
+    /// Cost of spending all of `gas_limit` at the quoted price
+    pub fn estimate_transaction_cost(
+        &self,
+        gas_limit: u64,
+        priority: TransactionPriority,
+        head: &BlockHeader,
+    ) -> GasCostEstimate {
+        let gas_price = self.get_gas_price(priority, head).gas_price;
+        let total_ember = gas_price.saturating_mul(gas_limit as u128);
+        GasCostEstimate {
+            gas_limit,
+            gas_price_ember: gas_price,
+            total_cost_ember: total_ember,
+            total_cost_tburn: self.ember_to_tburn(total_ember),
+        }
+    }
+}
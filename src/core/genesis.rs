@@ -0,0 +1,291 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::core::address::Address;
+use crate::core::block::{Block, BlockHeader, MAX_EXTRA_DATA};
+use crate::core::gas::INITIAL_BASE_FEE;
+use crate::core::state::{StateDB, WorldState};
+use crate::core::types::{to_hex, H256};
+
+/// Shard id used for the beacon chain genesis block, which anchors the
+/// per-shard genesis blocks.
+pub const BEACON_SHARD_ID: u32 = u32::MAX;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GenesisError {
+    #[error("failed to read genesis file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to parse genesis file: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid genesis field `{field}`: {reason}")]
+    Invalid { field: String, reason: String },
+    #[error("genesis hash mismatch: declared {declared}, computed {computed}")]
+    HashMismatch { declared: String, computed: String },
+}
+
+fn invalid(field: impl Into<String>, reason: impl Into<String>) -> GenesisError {
+    GenesisError::Invalid {
+        field: field.into(),
+        reason: reason.into(),
+    }
+}
+
+/// Parsed `config/genesis.json`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Genesis {
+    pub chain_id: u64,
+    pub network_id: u64,
+    pub name: String,
+    /// RFC 3339 launch time
+    pub timestamp: String,
+    pub genesis_hash: Option<String>,
+    pub sharding: ShardingSpec,
+    pub genesis: GenesisParams,
+    #[serde(default)]
+    pub alloc: BTreeMap<String, AllocEntry>,
+    #[serde(default)]
+    pub config: Option<ChainSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShardingSpec {
+    pub enabled: bool,
+    #[serde(default)]
+    pub shards: Vec<ShardSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShardSpec {
+    pub id: u32,
+    pub name: String,
+    pub genesis_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenesisParams {
+    /// Hex-encoded block gas limit
+    pub gas_limit: String,
+    /// Hex-encoded header extra data
+    #[serde(default)]
+    pub extra_data: String,
+    /// Hex-encoded base fee in Ember per gas; `INITIAL_BASE_FEE` if absent
+    #[serde(default)]
+    pub base_fee_per_gas: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AllocEntry {
+    /// Decimal balance in the smallest TBURN unit
+    pub balance: String,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainSpec {
+    pub chain_id: u64,
+}
+
+/// Genesis state and blocks built from a validated `Genesis`.
+#[derive(Debug, Clone)]
+pub struct GenesisBuild {
+    pub state: WorldState,
+    /// Beacon chain block 0; its hash is the network genesis hash
+    pub block: Block,
+    /// Block 0 of each shard, in shard id order
+    pub shard_blocks: Vec<Block>,
+}
+
+impl GenesisBuild {
+    pub fn hash(&self) -> H256 {
+        self.block.hash()
+    }
+}
+
+impl Genesis {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, GenesisError> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::from_json(&contents)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, GenesisError> {
+        let genesis: Genesis = serde_json::from_str(json)?;
+        genesis.validate()?;
+        Ok(genesis)
+    }
+
+    /// Structural checks that do not require building state.
+    pub fn validate(&self) -> Result<(), GenesisError> {
+        if let Some(config) = &self.config {
+            if config.chain_id != self.chain_id {
+                return Err(invalid(
+                    "config.chainId",
+                    format!("{} does not match chainId {}", config.chain_id, self.chain_id),
+                ));
+            }
+        }
+
+        self.timestamp_ms()?;
+        if self.gas_limit()? == 0 {
+            return Err(invalid("genesis.gasLimit", "must be greater than zero"));
+        }
+        self.base_fee()?;
+        self.extra_data()?;
+
+        if self.sharding.enabled && self.sharding.shards.is_empty() {
+            return Err(invalid("sharding.shards", "sharding is enabled but no shards are defined"));
+        }
+        let mut names = HashSet::new();
+        for (index, shard) in self.sharding.shards.iter().enumerate() {
+            if shard.id as usize != index {
+                return Err(invalid(
+                    format!("sharding.shards[{index}].id"),
+                    format!("expected {index}, got {}", shard.id),
+                ));
+            }
+            if !names.insert(shard.name.as_str()) {
+                return Err(invalid(
+                    format!("sharding.shards[{index}].name"),
+                    format!("duplicate shard name {}", shard.name),
+                ));
+            }
+        }
+
+        self.allocations()?;
+        Ok(())
+    }
+
+    pub fn timestamp_ms(&self) -> Result<u64, GenesisError> {
+        let time = chrono::DateTime::parse_from_rfc3339(&self.timestamp)
+            .map_err(|e| invalid("timestamp", e.to_string()))?;
+        u64::try_from(time.timestamp_millis()).map_err(|_| invalid("timestamp", "before 1970"))
+    }
+
+    pub fn gas_limit(&self) -> Result<u64, GenesisError> {
+        let digits = self
+            .genesis
+            .gas_limit
+            .strip_prefix("0x")
+            .ok_or_else(|| invalid("genesis.gasLimit", "must be 0x-prefixed hex"))?;
+        u64::from_str_radix(digits, 16).map_err(|e| invalid("genesis.gasLimit", e.to_string()))
+    }
+
+    pub fn base_fee(&self) -> Result<u128, GenesisError> {
+        let Some(raw) = &self.genesis.base_fee_per_gas else {
+            return Ok(INITIAL_BASE_FEE);
+        };
+        let digits = raw
+            .strip_prefix("0x")
+            .ok_or_else(|| invalid("genesis.baseFeePerGas", "must be 0x-prefixed hex"))?;
+        u128::from_str_radix(digits, 16).map_err(|e| invalid("genesis.baseFeePerGas", e.to_string()))
+    }
+
+    pub fn extra_data(&self) -> Result<Vec<u8>, GenesisError> {
+        let raw = &self.genesis.extra_data;
+        let digits = raw.strip_prefix("0x").unwrap_or(raw);
+        let bytes = hex::decode(digits).map_err(|e| invalid("genesis.extraData", e.to_string()))?;
+        if bytes.len() > MAX_EXTRA_DATA {
+            return Err(invalid(
+                "genesis.extraData",
+                format!("{} bytes exceeds maximum of {MAX_EXTRA_DATA}", bytes.len()),
+            ));
+        }
+        Ok(bytes)
+    }
+
+    pub fn allocations(&self) -> Result<Vec<(Address, u128)>, GenesisError> {
+        self.alloc
+            .iter()
+            .map(|(address, entry)| {
+                let field = format!("alloc.{address}");
+                let parsed = address
+                    .parse::<Address>()
+                    .map_err(|e| invalid(field.clone(), e.to_string()))?;
+                let balance = entry
+                    .balance
+                    .parse::<u128>()
+                    .map_err(|e| invalid(format!("{field}.balance"), e.to_string()))?;
+                Ok((parsed, balance))
+            })
+            .collect()
+    }
+
+    /// Credit `alloc` into a fresh state and build the genesis blocks.
+    pub fn build(&self) -> Result<GenesisBuild, GenesisError> {
+        let mut state = WorldState::new();
+        for (address, balance) in self.allocations()? {
+            state.set_balance(&address, balance);
+        }
+        state.commit();
+
+        let template = BlockHeader {
+            number: 0,
+            state_root: state.state_root(),
+            timestamp: self.timestamp_ms()?,
+            gas_limit: self.gas_limit()?,
+            base_fee: self.base_fee()?,
+            ..Default::default()
+        };
+
+        let block = Block::new(
+            BlockHeader {
+                shard_id: BEACON_SHARD_ID,
+                extra_data: self.extra_data()?,
+                ..template.clone()
+            },
+            Vec::new(),
+        );
+
+        let shard_blocks = self
+            .sharding
+            .shards
+            .iter()
+            .map(|shard| {
+                Block::new(
+                    BlockHeader {
+                        shard_id: shard.id,
+                        parent_hash: block.hash(),
+                        extra_data: shard.name.as_bytes().to_vec(),
+                        ..template.clone()
+                    },
+                    Vec::new(),
+                )
+            })
+            .collect();
+
+        Ok(GenesisBuild {
+            state,
+            block,
+            shard_blocks,
+        })
+    }
+
+    /// Build genesis and check the hashes declared in the file, if any.
+    pub fn build_and_verify(&self) -> Result<GenesisBuild, GenesisError> {
+        let build = self.build()?;
+
+        check_declared(self.genesis_hash.as_deref(), &build.block)?;
+        for (shard, block) in self.sharding.shards.iter().zip(&build.shard_blocks) {
+            check_declared(shard.genesis_hash.as_deref(), block)?;
+        }
+
+        Ok(build)
+    }
+}
+
+fn check_declared(declared: Option<&str>, block: &Block) -> Result<(), GenesisError> {
+    let computed = to_hex(&block.hash());
+    match declared {
+        Some(declared) if !declared.eq_ignore_ascii_case(&computed) => Err(GenesisError::HashMismatch {
+            declared: declared.to_string(),
+            computed,
+        }),
+        _ => Ok(()),
+    }
+}
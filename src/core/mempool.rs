@@ -0,0 +1,369 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap};
+use std::sync::Arc;
+
+use crate::core::address::Address;
+use crate::core::config::NodeConfig;
+use crate::core::gas::GasSchedule;
+use crate::core::state::StateDB;
+use crate::core::transaction::{SignedTransaction, TxError, TxHash};
+use crate::core::types::to_hex;
+use crate::storage::mvcc::StateSnapshot;
+
+/// Minimum gas price increase, in percent, for a replacement transaction.
+pub const DEFAULT_PRICE_BUMP_PERCENT: u128 = 10;
+
+/// Account reads the pool checks transactions against. Implemented by
+/// every `StateDB`, and by MVCC snapshots so the API can check against the
+/// head state without locking the live one.
+pub trait PoolState {
+    fn nonce(&self, address: &Address) -> u64;
+    fn balance(&self, address: &Address) -> u128;
+}
+
+impl<S: StateDB> PoolState for S {
+    fn nonce(&self, address: &Address) -> u64 {
+        self.get_nonce(address)
+    }
+
+    fn balance(&self, address: &Address) -> u128 {
+        self.get_balance(address)
+    }
+}
+
+impl PoolState for StateSnapshot {
+    fn nonce(&self, address: &Address) -> u64 {
+        self.get_nonce(address)
+    }
+
+    fn balance(&self, address: &Address) -> u128 {
+        self.get_balance(address)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MempoolConfig {
+    pub chain_id: u64,
+    /// Maximum number of transactions held (`transaction_pool_size`)
+    pub capacity: usize,
+    /// Lowest gas price in Ember the pool will accept
+    pub min_gas_price: u128,
+    /// Largest gas limit a single transaction may request
+    pub max_tx_gas: u64,
+    pub price_bump_percent: u128,
+    /// Schedule used to price intrinsic gas, matching block execution
+    pub schedule: GasSchedule,
+}
+
+impl MempoolConfig {
+    pub fn from_node_config(config: &NodeConfig) -> Self {
+        Self {
+            chain_id: config.network.chain_id,
+            capacity: config.network.performance.transaction_pool_size,
+            min_gas_price: config.smart_contracts.base_gas_price_emb as u128,
+            max_tx_gas: config.smart_contracts.gas_limit_per_block,
+            price_bump_percent: DEFAULT_PRICE_BUMP_PERCENT,
+            schedule: config.smart_contracts.gas_schedule,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MempoolError {
+    #[error(transparent)]
+    InvalidTransaction(#[from] TxError),
+    #[error("transaction {0} is already in the pool")]
+    AlreadyKnown(String),
+    #[error("nonce {got} is below account nonce {expected}")]
+    NonceTooLow { expected: u64, got: u64 },
+    #[error("gas price {got} is below the minimum of {min}")]
+    GasPriceTooLow { min: u128, got: u128 },
+    #[error("gas limit {got} exceeds the maximum of {max}")]
+    GasLimitTooHigh { max: u64, got: u64 },
+    #[error("gas limit {gas_limit} is below intrinsic gas {intrinsic}")]
+    IntrinsicGas { gas_limit: u64, intrinsic: u64 },
+    #[error("balance {balance} cannot cover cost {cost}")]
+    InsufficientBalance { balance: u128, cost: u128 },
+    #[error("replacement gas price must be at least {required}")]
+    ReplacementUnderpriced { required: u128 },
+    #[error("pool is full and gas price {0} does not beat the cheapest transaction")]
+    PoolFull(u128),
+    #[error("transaction cost overflows")]
+    CostOverflow,
+}
+
+#[derive(Debug, Clone)]
+struct PoolEntry {
+    tx: Arc<SignedTransaction>,
+    sender: Address,
+    /// Insertion order, used to break price ties
+    seq: u64,
+}
+
+/// Eviction order: cheapest first, newest first among equal prices.
+type PriceKey = (u128, Reverse<u64>, TxHash);
+
+/// Pool of verified transactions waiting for inclusion.
+///
+/// Transactions are grouped per sender by nonce. A transaction is
+/// *pending* when every lower nonce of its sender is either already
+/// executed in state or pending itself; otherwise it is *queued*.
+#[derive(Debug)]
+pub struct Mempool {
+    config: MempoolConfig,
+    by_hash: HashMap<TxHash, PoolEntry>,
+    by_sender: HashMap<Address, BTreeMap<u64, TxHash>>,
+    by_price: BTreeSet<PriceKey>,
+    next_seq: u64,
+    /// Base fee of the next block; cheaper transactions cannot be included
+    base_fee: u128,
+}
+
+impl Mempool {
+    pub fn new(config: MempoolConfig) -> Self {
+        Self {
+            config,
+            by_hash: HashMap::new(),
+            by_sender: HashMap::new(),
+            by_price: BTreeSet::new(),
+            next_seq: 0,
+            base_fee: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_hash.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_hash.is_empty()
+    }
+
+    pub fn contains(&self, hash: &TxHash) -> bool {
+        self.by_hash.contains_key(hash)
+    }
+
+    pub fn get(&self, hash: &TxHash) -> Option<Arc<SignedTransaction>> {
+        self.by_hash.get(hash).map(|entry| entry.tx.clone())
+    }
+
+    pub fn base_fee(&self) -> u128 {
+        self.base_fee
+    }
+
+    /// Lowest gas price admitted: the configured minimum or the base fee of
+    /// the next block, whichever is higher.
+    pub fn min_gas_price(&self) -> u128 {
+        self.config.min_gas_price.max(self.base_fee)
+    }
+
+    /// Track the base fee of the next block and evict transactions priced
+    /// below it.
+    pub fn set_base_fee(&mut self, base_fee: u128) {
+        self.base_fee = base_fee;
+        let underpriced: Vec<TxHash> = self
+            .by_price
+            .range(..(base_fee, Reverse(u64::MAX), [0; 32]))
+            .map(|(_, _, hash)| *hash)
+            .collect();
+        for hash in underpriced {
+            self.remove(&hash);
+        }
+    }
+
+    /// Sender of a pooled transaction, recovered when it was admitted.
+    pub fn sender(&self, hash: &TxHash) -> Option<Address> {
+        self.by_hash.get(hash).map(|entry| entry.sender)
+    }
+
+    /// Verify `tx` against `state` and admit it.
+    pub fn add(&mut self, tx: SignedTransaction, state: &impl PoolState) -> Result<TxHash, MempoolError> {
+        let hash = tx.hash();
+        if self.by_hash.contains_key(&hash) {
+            return Err(MempoolError::AlreadyKnown(to_hex(&hash)));
+        }
+
+        let sender = tx.verify(self.config.chain_id)?;
+        let price = tx.tx().gas_price;
+        let nonce = tx.tx().nonce;
+
+        let min = self.min_gas_price();
+        if price < min {
+            return Err(MempoolError::GasPriceTooLow { min, got: price });
+        }
+        if tx.tx().gas_limit > self.config.max_tx_gas {
+            return Err(MempoolError::GasLimitTooHigh {
+                max: self.config.max_tx_gas,
+                got: tx.tx().gas_limit,
+            });
+        }
+        let intrinsic = self.config.schedule.intrinsic_gas(tx.tx());
+        if tx.tx().gas_limit < intrinsic {
+            return Err(MempoolError::IntrinsicGas {
+                gas_limit: tx.tx().gas_limit,
+                intrinsic,
+            });
+        }
+
+        let account_nonce = state.nonce(&sender);
+        if nonce < account_nonce {
+            return Err(MempoolError::NonceTooLow {
+                expected: account_nonce,
+                got: nonce,
+            });
+        }
+
+        // The sender must be able to pay for everything it has queued, not
+        // just this transaction; a replaced one no longer counts
+        let queued = self.by_sender.get(&sender).into_iter().flat_map(|nonces| {
+            nonces
+                .range(account_nonce..)
+                .filter(|(queued_nonce, _)| **queued_nonce != nonce)
+                .map(|(_, hash)| max_cost(&self.by_hash[hash].tx))
+        });
+        let cost = queued
+            .chain([max_cost(&tx)])
+            .try_fold(0u128, |total, cost| total.checked_add(cost?))
+            .ok_or(MempoolError::CostOverflow)?;
+        let balance = state.balance(&sender);
+        if balance < cost {
+            return Err(MempoolError::InsufficientBalance { balance, cost });
+        }
+
+        // Replace-by-fee: same sender and nonce needs a higher price
+        let replaced = self
+            .by_sender
+            .get(&sender)
+            .and_then(|nonces| nonces.get(&nonce))
+            .copied();
+        if let Some(existing) = replaced {
+            let old_price = self.by_hash[&existing].tx.tx().gas_price;
+            let bump = (old_price.saturating_mul(self.config.price_bump_percent) / 100).max(1);
+            let required = old_price.saturating_add(bump);
+            if price < required {
+                return Err(MempoolError::ReplacementUnderpriced { required });
+            }
+            self.remove(&existing);
+        } else if self.by_hash.len() >= self.config.capacity {
+            let cheapest = *self.by_price.iter().next().expect("full pool is not empty");
+            if price <= cheapest.0 {
+                return Err(MempoolError::PoolFull(price));
+            }
+            self.remove(&cheapest.2);
+        }
+
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.by_price.insert((price, Reverse(seq), hash));
+        self.by_sender.entry(sender).or_default().insert(nonce, hash);
+        self.by_hash.insert(
+            hash,
+            PoolEntry {
+                tx: Arc::new(tx),
+                sender,
+                seq,
+            },
+        );
+        Ok(hash)
+    }
+
+    pub fn remove(&mut self, hash: &TxHash) -> Option<Arc<SignedTransaction>> {
+        let entry = self.by_hash.remove(hash)?;
+        self.by_price.remove(&(entry.tx.tx().gas_price, Reverse(entry.seq), *hash));
+        if let Some(nonces) = self.by_sender.get_mut(&entry.sender) {
+            nonces.remove(&entry.tx.tx().nonce);
+            if nonces.is_empty() {
+                self.by_sender.remove(&entry.sender);
+            }
+        }
+        Some(entry.tx)
+    }
+
+    /// Drop transactions whose nonce has been used in `state`, e.g. after
+    /// a block was committed.
+    pub fn prune(&mut self, state: &impl PoolState) {
+        let stale: Vec<TxHash> = self
+            .by_sender
+            .iter()
+            .flat_map(|(sender, nonces)| {
+                let account_nonce = state.nonce(sender);
+                nonces.range(..account_nonce).map(|(_, hash)| *hash)
+            })
+            .collect();
+        for hash in stale {
+            self.remove(&hash);
+        }
+    }
+
+    /// Executable transactions of one sender, in nonce order.
+    fn sender_pending(
+        &self,
+        sender: &Address,
+        nonces: &BTreeMap<u64, TxHash>,
+        state: &impl PoolState,
+    ) -> Vec<Arc<SignedTransaction>> {
+        let account_nonce = state.nonce(sender);
+        (account_nonce..)
+            .zip(nonces.range(account_nonce..))
+            .take_while(|(expected, (nonce, _))| *nonce == expected)
+            .map(|(_, (_, hash))| self.by_hash[hash].tx.clone())
+            .collect()
+    }
+
+    /// Executable transactions, highest gas price first while keeping each
+    /// sender's transactions in nonce order. This is the order block
+    /// producers should pull in.
+    pub fn pending(&self, state: &impl PoolState) -> Vec<Arc<SignedTransaction>> {
+        let mut queues: Vec<Vec<Arc<SignedTransaction>>> = self
+            .by_sender
+            .iter()
+            .map(|(sender, nonces)| {
+                let mut txs = self.sender_pending(sender, nonces, state);
+                txs.reverse();
+                txs
+            })
+            .filter(|txs| !txs.is_empty())
+            .collect();
+
+        // Merge senders by the price of their next transaction; ties go
+        // to the transaction seen first so ordering is deterministic.
+        let mut heap = BinaryHeap::new();
+        for (index, queue) in queues.iter().enumerate() {
+            let next = queue.last().expect("non-empty queue");
+            heap.push((next.tx().gas_price, Reverse(self.by_hash[&next.hash()].seq), index));
+        }
+
+        let mut out = Vec::new();
+        while let Some((_, _, index)) = heap.pop() {
+            let tx = queues[index].pop().expect("queued entry");
+            if let Some(next) = queues[index].last() {
+                heap.push((next.tx().gas_price, Reverse(self.by_hash[&next.hash()].seq), index));
+            }
+            out.push(tx);
+        }
+        out
+    }
+
+    /// Transactions waiting on a nonce gap, grouped by sender.
+    pub fn queued(&self, state: &impl PoolState) -> Vec<Arc<SignedTransaction>> {
+        let mut out = Vec::new();
+        for (sender, nonces) in &self.by_sender {
+            let pending = self.sender_pending(sender, nonces, state).len();
+            let account_nonce = state.nonce(sender);
+            out.extend(
+                nonces
+                    .range(account_nonce..)
+                    .skip(pending)
+                    .map(|(_, hash)| self.by_hash[hash].tx.clone()),
+            );
+        }
+        out
+    }
+}
+
+/// Most a transaction can cost its sender: value plus full gas at its price.
+pub fn max_cost(tx: &SignedTransaction) -> Option<u128> {
+    (tx.tx().gas_limit as u128)
+        .checked_mul(tx.tx().gas_price)?
+        .checked_add(tx.tx().value)
+}
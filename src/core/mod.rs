@@ -1,4 +1,32 @@
-pub mod blockchain;
-pub mod rpc;
-
-pub use blockchain::Blockchain;
+pub mod account;
+pub mod address;
+pub mod block;
+pub mod blockchain;
+pub mod bloom;
+pub mod codec;
+pub mod config;
+pub mod executor;
+pub mod gas;
+pub mod genesis;
+pub mod mempool;
+pub mod parallel;
+pub mod producer;
+pub mod receipt;
+pub mod rpc;
+pub mod state;
+pub mod transaction;
+pub mod types;
+pub mod vm;
+
+pub use account::Account;
+pub use address::Address;
+pub use block::{Block, BlockHeader};
+pub use blockchain::Blockchain;
+pub use config::{NetworkProfile, NodeConfig};
+pub use gas::{EmberGasSystem, GasConfig, GasMeter, GasSchedule};
+pub use genesis::Genesis;
+pub use mempool::{Mempool, MempoolConfig};
+pub use producer::{BlockImporter, BlockProducer, ChainParams};
+pub use receipt::{Log, LogFilter, Receipt};
+pub use state::{StateDB, WorldState};
+pub use transaction::{SignedTransaction, Transaction};
@@ -0,0 +1,416 @@
+//! Dependency-aware scheduling of a block's transactions.
+//!
+//! Before anything runs, each transaction's accesses are predicted from
+//! what it is: a plain value transfer touches its sender and recipient, and
+//! a fast path TBC-20 call the token's balance, allowance and supply slots
+//! named by its arguments. Transactions whose accesses overlap are put in
+//! the same group, so groups are independent of each other. Each group runs
+//! in block order on one worker of a dedicated thread pool, and the results
+//! are committed in block order.
+//!
+//! Calls into other contracts and deployments could touch anything. They
+//! split the block into segments and run serially between them, after the
+//! segment before them is committed. The accesses each run actually made
+//! are checked against the prediction, and a segment with a wrong one is
+//! executed again serially, so the result is always the same as with
+//! `core::executor::execute_transactions`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+use crate::contracts::abi::Token;
+use crate::contracts::executor::{
+    compute_allowance_slot, compute_balance_slot, registration_reads, selectors, slots, tbc20_abi,
+};
+use crate::core::account::EMPTY_CODE_HASH;
+use crate::core::address::Address;
+use crate::core::executor::{execute_transaction, BlockEnv, ExecutionError};
+use crate::core::parallel::mvcc::{ReadSet, StateKey, TxView, Versions, WriteSet};
+use crate::core::receipt::Receipt;
+use crate::core::state::WorldState;
+use crate::core::transaction::{SignedTransaction, Transaction};
+use crate::core::types::H256;
+use crate::core::vm::precompiles;
+
+/// State a transaction is predicted to touch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessSet {
+    pub reads: BTreeSet<StateKey>,
+    pub writes: BTreeSet<StateKey>,
+    /// Accounts only paid a fee, which commutes with other fees
+    pub credits: BTreeSet<Address>,
+}
+
+impl AccessSet {
+    fn write_slot(&mut self, token: &Address, slot: H256) {
+        self.writes.insert(StateKey::Storage(*token, slot));
+    }
+
+    /// Whether a run with `reads` and `writes` stayed within the prediction.
+    pub fn covers(&self, reads: &ReadSet, writes: &WriteSet) -> bool {
+        let writes_account = |address: &Address| self.writes.contains(&StateKey::Account(*address));
+        reads
+            .keys()
+            .all(|key| self.reads.contains(key) || self.writes.contains(key))
+            && writes.accounts.keys().all(writes_account)
+            && writes
+                .storage
+                .keys()
+                .all(|(address, slot)| self.writes.contains(&StateKey::Storage(*address, *slot)))
+            && writes
+                .credits
+                .keys()
+                .all(|address| self.credits.contains(address) || writes_account(address))
+    }
+}
+
+/// Accesses of `tx` sent by `sender`, or `None` if they cannot be known
+/// without running it.
+pub fn predict(state: &WorldState, env: &BlockEnv, sender: &Address, tx: &Transaction) -> Option<AccessSet> {
+    let to = tx.to?;
+    let mut access = AccessSet::default();
+    access.writes.insert(StateKey::Account(*sender));
+    access.credits.insert(env.proposer);
+    if env.fast_path.is_eligible(state, tx) {
+        // The token account only changes if the call carries value
+        let token_key = StateKey::Account(to);
+        if tx.value > 0 {
+            access.writes.insert(token_key);
+        } else {
+            access.reads.insert(token_key);
+        }
+        token_accesses(&mut access, state, env, sender, &to, &tx.data);
+        return Some(access);
+    }
+    let plain = state.code_hash(&to) == EMPTY_CODE_HASH
+        && precompiles::get(&to).is_none()
+        && env.deployer.factory_standard(&to).is_none();
+    if !plain {
+        return None;
+    }
+    access.writes.insert(StateKey::Account(to));
+    Some(access)
+}
+
+/// Token storage a fast path call touches. Calls whose arguments do not
+/// decode revert before touching any.
+fn token_accesses(
+    access: &mut AccessSet,
+    state: &WorldState,
+    env: &BlockEnv,
+    sender: &Address,
+    token: &Address,
+    data: &[u8],
+) {
+    let Some(info) = env.fast_path.token_info(state, token) else {
+        return;
+    };
+    if !env.fast_path.registry().contains(token) {
+        // Factory tokens are looked up in state on every call
+        for (address, slot) in registration_reads(token) {
+            access.reads.insert(StateKey::Storage(address, slot));
+        }
+    }
+    if info.pausable {
+        access.reads.insert(StateKey::Storage(*token, slot(slots::PAUSED)));
+    }
+    let args = tbc20_abi().decode_call(data).ok().map(|(_, args)| args);
+    let selector: Option<[u8; 4]> = data.get(..4).and_then(|selector| selector.try_into().ok());
+    match (selector, args.as_deref()) {
+        (Some(selectors::TRANSFER), Some([Token::Address(to), _])) => {
+            access.write_slot(token, compute_balance_slot(sender));
+            access.write_slot(token, compute_balance_slot(to));
+        }
+        (Some(selectors::TRANSFER_FROM), Some([Token::Address(from), Token::Address(to), _])) => {
+            access.write_slot(token, compute_allowance_slot(from, sender));
+            access.write_slot(token, compute_balance_slot(from));
+            access.write_slot(token, compute_balance_slot(to));
+        }
+        (Some(selectors::APPROVE), Some([Token::Address(spender), _])) => {
+            access.write_slot(token, compute_allowance_slot(sender, spender));
+        }
+        (Some(selectors::BURN), Some([_])) => {
+            access.write_slot(token, compute_balance_slot(sender));
+            access.write_slot(token, slot(slots::TOTAL_SUPPLY));
+        }
+        _ => {}
+    }
+}
+
+fn slot(index: u8) -> H256 {
+    let mut slot = [0u8; 32];
+    slot[31] = index;
+    slot
+}
+
+/// Split transactions into groups that touch disjoint state, each in block
+/// order, ordered by their first transaction.
+///
+/// Transactions share a group if one writes what the other reads or
+/// writes, or one reads an account the other pays a fee to.
+pub fn partition(accesses: &[AccessSet]) -> Vec<Vec<usize>> {
+    #[derive(Default)]
+    struct Users {
+        txs: Vec<usize>,
+        written: bool,
+        read: bool,
+        credited: bool,
+    }
+
+    let mut users: BTreeMap<StateKey, Users> = BTreeMap::new();
+    for (index, access) in accesses.iter().enumerate() {
+        for key in &access.reads {
+            let entry = users.entry(*key).or_default();
+            entry.txs.push(index);
+            entry.read = true;
+        }
+        for key in &access.writes {
+            let entry = users.entry(*key).or_default();
+            entry.txs.push(index);
+            entry.written = true;
+        }
+        for address in &access.credits {
+            let entry = users.entry(StateKey::Account(*address)).or_default();
+            entry.txs.push(index);
+            entry.credited = true;
+        }
+    }
+
+    let mut parents: Vec<usize> = (0..accesses.len()).collect();
+    for entry in users.values() {
+        if !(entry.written || entry.credited && entry.read) {
+            continue;
+        }
+        for pair in entry.txs.windows(2) {
+            let (a, b) = (root(&mut parents, pair[0]), root(&mut parents, pair[1]));
+            // The lowest index is the root, which keeps groups in order
+            parents[a.max(b)] = a.min(b);
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for index in 0..accesses.len() {
+        let root = root(&mut parents, index);
+        groups.entry(root).or_default().push(index);
+    }
+    groups.into_values().collect()
+}
+
+fn root(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+/// How a block was scheduled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchReport {
+    pub transactions: usize,
+    /// Independent groups run on the pool
+    pub groups: usize,
+    /// Transactions run serially, because their accesses were unknown or
+    /// mispredicted
+    pub serial: usize,
+    /// Transactions run one after another on the longest path through the
+    /// block: the largest group of each segment, and every serial one
+    pub critical_path: usize,
+}
+
+impl BatchReport {
+    /// Transactions per step of the critical path, 1 for a serial block.
+    pub fn parallelism(&self) -> f64 {
+        if self.critical_path == 0 {
+            0.0
+        } else {
+            self.transactions as f64 / self.critical_path as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchOutput {
+    pub receipts: Vec<Receipt>,
+    pub report: BatchReport,
+}
+
+/// One execution of a transaction within its group.
+#[derive(Debug)]
+struct Run {
+    reads: ReadSet,
+    writes: WriteSet,
+    result: Result<Receipt, ExecutionError>,
+}
+
+/// Runs the groups of a block on its own worker threads.
+#[derive(Debug)]
+pub struct BatchProcessor {
+    pool: ThreadPool,
+}
+
+impl BatchProcessor {
+    pub fn new(workers: usize) -> Result<Self, ThreadPoolBuildError> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(workers)
+            .thread_name(|i| format!("tburn-batch-{i}"))
+            .build()?;
+        Ok(Self { pool })
+    }
+
+    /// One worker per available core.
+    pub fn per_core() -> Result<Self, ThreadPoolBuildError> {
+        Self::new(std::thread::available_parallelism().map_or(1, usize::from))
+    }
+
+    pub fn workers(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    /// Execute every transaction of a block on `state`, with the same
+    /// result as `core::executor::execute_transactions`. Fails on the first
+    /// transaction that could not have been included.
+    pub fn execute(
+        &self,
+        state: &mut WorldState,
+        env: &BlockEnv,
+        txs: &[SignedTransaction],
+    ) -> Result<BatchOutput, (usize, ExecutionError)> {
+        let senders: Vec<Option<Address>> = self
+            .pool
+            .install(|| txs.par_iter().map(|tx| tx.verify(env.chain_id).ok()).collect());
+
+        let mut report = BatchReport {
+            transactions: txs.len(),
+            ..BatchReport::default()
+        };
+        let mut receipts: Vec<Receipt> = Vec::with_capacity(txs.len());
+        let mut start = 0;
+        while start < txs.len() {
+            // Predictions hold until the next unknown transaction, which
+            // may deploy code or register a token
+            let mut accesses = Vec::new();
+            for (tx, sender) in txs[start..].iter().zip(&senders[start..]) {
+                match sender.and_then(|sender| predict(state, env, &sender, tx.tx())) {
+                    Some(access) => accesses.push(access),
+                    None => break,
+                }
+            }
+            let end = start + accesses.len();
+            if !accesses.is_empty() {
+                self.execute_segment(
+                    state,
+                    env,
+                    start,
+                    &txs[start..end],
+                    &accesses,
+                    &mut receipts,
+                    &mut report,
+                )?;
+            }
+            if let Some(tx) = txs.get(end) {
+                report.serial += 1;
+                report.critical_path += 1;
+                receipts.push(execute_serially(state, env, end, tx, &receipts)?);
+            }
+            start = end + 1;
+        }
+        Ok(BatchOutput { receipts, report })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn execute_segment(
+        &self,
+        state: &mut WorldState,
+        env: &BlockEnv,
+        offset: usize,
+        txs: &[SignedTransaction],
+        accesses: &[AccessSet],
+        receipts: &mut Vec<Receipt>,
+        report: &mut BatchReport,
+    ) -> Result<(), (usize, ExecutionError)> {
+        let groups = partition(accesses);
+        let versions = Versions::new();
+        let mut runs: Vec<Option<Run>> = (0..txs.len()).map(|_| None).collect();
+        let finished: Vec<Vec<(usize, Run)>> = {
+            let (state, versions) = (&*state, &versions);
+            self.pool.install(|| {
+                groups
+                    .par_iter()
+                    .map(|group| run_group(state, versions, env, txs, group))
+                    .collect()
+            })
+        };
+        for (index, run) in finished.into_iter().flatten() {
+            runs[index] = Some(run);
+        }
+
+        let predicted = runs
+            .iter()
+            .zip(accesses)
+            .all(|(run, access)| run.as_ref().is_none_or(|run| access.covers(&run.reads, &run.writes)));
+        if !predicted {
+            report.serial += txs.len();
+            report.critical_path += txs.len();
+            for (index, tx) in txs.iter().enumerate() {
+                receipts.push(execute_serially(state, env, offset + index, tx, receipts)?);
+            }
+            return Ok(());
+        }
+
+        report.groups += groups.len();
+        report.critical_path += groups.iter().map(Vec::len).max().unwrap_or(0);
+        for (index, (tx, run)) in txs.iter().zip(runs).enumerate() {
+            let cumulative = receipts.last().map_or(0, |r| r.cumulative_gas_used);
+            // Groups assumed an empty block when checking block gas
+            if tx.tx().gas_limit > env.gas_limit.saturating_sub(cumulative) {
+                receipts.push(execute_serially(state, env, offset + index, tx, receipts)?);
+                continue;
+            }
+            let run = run.expect("groups run up to their first failure");
+            let mut receipt = run.result.map_err(|err| (offset + index, err))?;
+            receipt.cumulative_gas_used = cumulative + receipt.gas_used;
+            run.writes.apply(state);
+            receipts.push(receipt);
+        }
+        Ok(())
+    }
+}
+
+/// Run `group` in order on views of `state`, stopping at the first
+/// transaction that fails.
+fn run_group(
+    state: &WorldState,
+    versions: &Versions,
+    env: &BlockEnv,
+    txs: &[SignedTransaction],
+    group: &[usize],
+) -> Vec<(usize, Run)> {
+    let mut prior = WriteSet::default();
+    let mut runs = Vec::with_capacity(group.len());
+    for &index in group {
+        let mut view = TxView::after(state, versions, &prior);
+        let result = execute_transaction(&mut view, env, &txs[index], 0);
+        let (reads, writes) = view.finish();
+        prior.merge(&writes);
+        let failed = result.is_err();
+        runs.push((index, Run { reads, writes, result }));
+        if failed {
+            break;
+        }
+    }
+    runs
+}
+
+fn execute_serially(
+    state: &mut WorldState,
+    env: &BlockEnv,
+    index: usize,
+    tx: &SignedTransaction,
+    receipts: &[Receipt],
+) -> Result<Receipt, (usize, ExecutionError)> {
+    let cumulative = receipts.last().map_or(0, |r| r.cumulative_gas_used);
+    execute_transaction(state, env, tx, cumulative).map_err(|err| (index, err))
+}
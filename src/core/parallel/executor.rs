@@ -0,0 +1,121 @@
+//! Optimistic parallel execution of a block, in the style of Block-STM.
+//!
+//! Every transaction first runs speculatively on its own `TxView` of the
+//! state the block starts from, all of them at once on the rayon pool.
+//! Runs are then committed in block order. A run is kept if no transaction
+//! committed before it wrote anything it read; otherwise the transaction is
+//! executed again, on the state left by everything before it. Either way
+//! each transaction sees exactly the state serial execution would give it,
+//! so receipts and the resulting state are the same as with
+//! `core::executor::execute_transactions`, whatever the thread timing.
+//!
+//! Transactions from the same sender, or touching the same account or
+//! storage slot, conflict and are re-executed. Fees paid to the proposer
+//! do not count as a conflict.
+
+use rayon::prelude::*;
+
+use crate::core::executor::{execute_transaction, BlockEnv, ExecutionError};
+use crate::core::parallel::mvcc::{ReadSet, TxView, Versions, WriteSet};
+use crate::core::receipt::Receipt;
+use crate::core::state::WorldState;
+use crate::core::transaction::SignedTransaction;
+
+/// How much of a block ran in parallel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParallelStats {
+    pub transactions: usize,
+    /// Committed from their speculative run
+    pub speculative: usize,
+    /// Executed again in order after a conflict
+    pub reexecuted: usize,
+}
+
+impl ParallelStats {
+    /// Share of transactions whose parallel run was kept, from 0 to 1.
+    pub fn parallelism(&self) -> f64 {
+        if self.transactions == 0 {
+            0.0
+        } else {
+            self.speculative as f64 / self.transactions as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParallelOutput {
+    pub receipts: Vec<Receipt>,
+    pub stats: ParallelStats,
+}
+
+/// One execution of a transaction on a `TxView`.
+#[derive(Debug)]
+struct Run {
+    reads: ReadSet,
+    writes: WriteSet,
+    result: Result<Receipt, ExecutionError>,
+}
+
+fn run_on_view(
+    state: &WorldState,
+    versions: &Versions,
+    env: &BlockEnv,
+    tx: &SignedTransaction,
+    cumulative_gas_used: u64,
+) -> Run {
+    let mut view = TxView::new(state, versions);
+    let result = execute_transaction(&mut view, env, tx, cumulative_gas_used);
+    let (reads, writes) = view.finish();
+    Run { reads, writes, result }
+}
+
+/// Execute every transaction of a block on `state`, with the same result
+/// as `core::executor::execute_transactions`. Fails on the first
+/// transaction that could not have been included.
+///
+/// Deployments through a token factory register the token for the fast
+/// path, which later transactions depend on, so they are never run
+/// speculatively. Fast path counters include discarded runs.
+pub fn execute_transactions(
+    state: &mut WorldState,
+    env: &BlockEnv,
+    txs: &[SignedTransaction],
+) -> Result<ParallelOutput, (usize, ExecutionError)> {
+    let mut versions = Versions::new();
+    let speculative: Vec<Option<Run>> = {
+        let (state, versions) = (&*state, &versions);
+        txs.par_iter()
+            .map(|tx| {
+                let factory = tx.tx().to.is_some_and(|to| env.deployer.factory_standard(&to).is_some());
+                (!factory).then(|| run_on_view(state, versions, env, tx, 0))
+            })
+            .collect()
+    };
+
+    let mut stats = ParallelStats {
+        transactions: txs.len(),
+        ..ParallelStats::default()
+    };
+    let mut receipts: Vec<Receipt> = Vec::with_capacity(txs.len());
+    for (index, (tx, speculative)) in txs.iter().zip(speculative).enumerate() {
+        let cumulative = receipts.last().map_or(0, |r| r.cumulative_gas_used);
+        // Speculative runs assumed an empty block when checking block gas
+        let fits = tx.tx().gas_limit <= env.gas_limit.saturating_sub(cumulative);
+        let run = match speculative {
+            Some(run) if fits && versions.validate(&run.reads) => {
+                stats.speculative += 1;
+                run
+            }
+            _ => {
+                stats.reexecuted += 1;
+                run_on_view(state, &versions, env, tx, cumulative)
+            }
+        };
+        let mut receipt = run.result.map_err(|err| (index, err))?;
+        receipt.cumulative_gas_used = cumulative + receipt.gas_used;
+        run.writes.apply(state);
+        versions.record(index, &run.writes);
+        receipts.push(receipt);
+    }
+    Ok(ParallelOutput { receipts, stats })
+}
@@ -0,0 +1,8 @@
+//! Parallel execution of block transactions.
+
+pub mod batch_processor;
+pub mod executor;
+pub mod mvcc;
+
+pub use batch_processor::{BatchOutput, BatchProcessor, BatchReport};
+pub use executor::{execute_transactions, ParallelOutput, ParallelStats};
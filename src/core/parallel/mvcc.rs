@@ -0,0 +1,405 @@
+//! Versioned reads and buffered writes of speculatively run transactions.
+//!
+//! A `TxView` runs one transaction on top of a base state without changing
+//! it. It records the version of every account and storage slot it reads
+//! and keeps its writes in a `WriteSet`. `Versions` tracks which transaction
+//! of the block last wrote each key, so a run that read something an
+//! earlier transaction has since overwritten is caught before its writes
+//! are applied. A view can also sit on the combined writes of earlier
+//! transactions that have not been applied yet, as when a group of
+//! transactions runs in order on one worker.
+
+use std::collections::{BTreeMap, HashMap};
+
+use parking_lot::Mutex;
+
+use crate::core::account::{Account, EMPTY_CODE_HASH};
+use crate::core::address::Address;
+use crate::core::state::{Checkpoint, ExecutionState, StateDB, WorldState};
+use crate::core::types::{keccak256, H256, ZERO_HASH};
+
+/// Unit of state that conflicts are detected on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StateKey {
+    /// Nonce, balance and code of an account, and whether it exists
+    Account(Address),
+    Storage(Address, H256),
+    /// Whether an account has any storage at all
+    StorageOf(Address),
+}
+
+/// Index in the block of the transaction that last wrote a key; `None` for
+/// the state the block started from.
+pub type Version = Option<usize>;
+
+/// Version of every key a run read.
+pub type ReadSet = HashMap<StateKey, Version>;
+
+/// Last writer of every key written so far in the block.
+#[derive(Debug, Default)]
+pub struct Versions {
+    writers: HashMap<StateKey, usize>,
+}
+
+impl Versions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &StateKey) -> Version {
+        self.writers.get(key).copied()
+    }
+
+    /// Whether nothing in `reads` was written since it was read.
+    pub fn validate(&self, reads: &ReadSet) -> bool {
+        reads.iter().all(|(key, version)| self.get(key) == *version)
+    }
+
+    /// Record transaction `tx` as the last writer of everything in `writes`.
+    pub fn record(&mut self, tx: usize, writes: &WriteSet) {
+        for key in writes.keys() {
+            self.writers.insert(key, tx);
+        }
+    }
+}
+
+/// Changes a run made, in a deterministic order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteSet {
+    /// New account records; `None` removes the account
+    pub accounts: BTreeMap<Address, Option<Account>>,
+    pub storage: BTreeMap<(Address, H256), H256>,
+    /// Code deployed by the run, by hash
+    pub code: BTreeMap<H256, Vec<u8>>,
+    /// Fees added to balances the run never read
+    pub credits: BTreeMap<Address, u128>,
+}
+
+impl WriteSet {
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.storage.is_empty() && self.code.is_empty() && self.credits.is_empty()
+    }
+
+    fn keys(&self) -> impl Iterator<Item = StateKey> + '_ {
+        let accounts = self
+            .accounts
+            .keys()
+            .chain(self.credits.keys())
+            .map(|a| StateKey::Account(*a));
+        let storage = self
+            .storage
+            .keys()
+            .flat_map(|(address, slot)| [StateKey::Storage(*address, *slot), StateKey::StorageOf(*address)]);
+        accounts.chain(storage)
+    }
+
+    /// Add the changes of a run made after this one, on top of it.
+    pub fn merge(&mut self, later: &WriteSet) {
+        for (address, account) in &later.accounts {
+            // The later run saw the pending credit in the account it wrote
+            self.credits.remove(address);
+            self.accounts.insert(*address, account.clone());
+        }
+        self.storage.extend(later.storage.iter().map(|(key, value)| (*key, *value)));
+        self.code.extend(later.code.iter().map(|(hash, code)| (*hash, code.clone())));
+        for (address, amount) in &later.credits {
+            let credit = self.credits.entry(*address).or_insert(0);
+            *credit = credit.saturating_add(*amount);
+        }
+    }
+
+    /// Apply the changes to `state`, leaving it as if the run had executed
+    /// on it directly.
+    pub fn apply(&self, state: &mut WorldState) {
+        for ((address, slot), value) in &self.storage {
+            state.set_storage(address, slot, *value);
+        }
+        for (hash, code) in &self.code {
+            state.put_code(*hash, code.clone());
+        }
+        for (address, account) in &self.accounts {
+            state.put_account(address, account.clone());
+        }
+        for (address, amount) in &self.credits {
+            state.credit(address, *amount);
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Undo {
+    Account {
+        address: Address,
+        prev: Option<Option<Account>>,
+        credit: Option<u128>,
+    },
+    Storage {
+        key: (Address, H256),
+        prev: Option<H256>,
+    },
+    Code {
+        hash: H256,
+    },
+    Credit {
+        address: Address,
+        prev: Option<u128>,
+    },
+}
+
+/// State seen by one transaction: its own writes on top of `base`, and of
+/// `prior` if set.
+///
+/// Behaves exactly like `WorldState` would with the same reads, so
+/// applying the `WriteSet` to a state that still holds every version read
+/// gives the same result as executing there.
+#[derive(Debug)]
+pub struct TxView<'a> {
+    base: &'a WorldState,
+    versions: &'a Versions,
+    prior: Option<&'a WriteSet>,
+    reads: Mutex<ReadSet>,
+    writes: WriteSet,
+    journal: Vec<Undo>,
+}
+
+impl<'a> TxView<'a> {
+    /// View of `base`, whose keys were last written at `versions`.
+    pub fn new(base: &'a WorldState, versions: &'a Versions) -> Self {
+        Self {
+            base,
+            versions,
+            prior: None,
+            reads: Mutex::new(ReadSet::new()),
+            writes: WriteSet::default(),
+            journal: Vec::new(),
+        }
+    }
+
+    /// View of `base` as left by runs whose combined writes are `prior`.
+    pub fn after(base: &'a WorldState, versions: &'a Versions, prior: &'a WriteSet) -> Self {
+        Self {
+            prior: Some(prior),
+            ..Self::new(base, versions)
+        }
+    }
+
+    pub fn finish(self) -> (ReadSet, WriteSet) {
+        (self.reads.into_inner(), self.writes)
+    }
+
+    fn read(&self, key: StateKey) {
+        self.reads.lock().entry(key).or_insert_with(|| self.versions.get(&key));
+    }
+
+    fn account(&self, address: &Address) -> Option<Account> {
+        if let Some(account) = self.writes.accounts.get(address) {
+            return account.clone();
+        }
+        self.read(StateKey::Account(*address));
+        let prior = self.prior.and_then(|prior| prior.accounts.get(address));
+        let account = match prior {
+            Some(account) => account.clone(),
+            None => self.base.stored_account(address).cloned(),
+        };
+        let earlier = self.prior.and_then(|prior| prior.credits.get(address));
+        let credit = earlier
+            .into_iter()
+            .chain(self.writes.credits.get(address))
+            .copied()
+            .reduce(u128::saturating_add);
+        match credit {
+            // As `WorldState::credit` would have left it
+            Some(credit) => match account {
+                Some(account) => Some(Account {
+                    balance: account.balance.saturating_add(credit),
+                    ..account
+                }),
+                None if credit > 0 => Some(Account::with_balance(credit)),
+                None => None,
+            },
+            None => account,
+        }
+    }
+
+    fn has_storage(&self, address: &Address) -> bool {
+        let range = (*address, ZERO_HASH)..=(*address, [0xff; 32]);
+        if self.writes.storage.range(range.clone()).any(|(_, value)| *value != ZERO_HASH) {
+            return true;
+        }
+        self.read(StateKey::StorageOf(*address));
+        let overwritten = |slot: &H256| {
+            let key = (*address, *slot);
+            self.writes.storage.contains_key(&key) || self.prior.is_some_and(|prior| prior.storage.contains_key(&key))
+        };
+        if let Some(prior) = self.prior {
+            let mut earlier = prior
+                .storage
+                .range(range)
+                .filter(|(key, _)| !self.writes.storage.contains_key(key));
+            if earlier.any(|(_, value)| *value != ZERO_HASH) {
+                return true;
+            }
+        }
+        self.base.storage_slots(address).any(|(slot, _)| !overwritten(slot))
+    }
+
+    fn stored_code(&self, hash: &H256) -> Option<&[u8]> {
+        let prior = self.prior.and_then(|prior| prior.code.get(hash));
+        self.writes
+            .code
+            .get(hash)
+            .or(prior)
+            .map(Vec::as_slice)
+            .or_else(|| self.base.code_by_hash(hash))
+    }
+
+    fn write_account(&mut self, address: &Address, account: Account) {
+        if self.account(address).as_ref() == Some(&account) {
+            return;
+        }
+        let stored = if account.is_empty() && !self.has_storage(address) {
+            None
+        } else {
+            Some(account)
+        };
+        self.store_account(address, stored);
+    }
+
+    fn store_account(&mut self, address: &Address, stored: Option<Account>) {
+        let prev = self.writes.accounts.insert(*address, stored);
+        // The pending credit is part of the account now
+        let credit = self.writes.credits.remove(address);
+        self.journal.push(Undo::Account {
+            address: *address,
+            prev,
+            credit,
+        });
+    }
+
+    fn update_account(&mut self, address: &Address, f: impl FnOnce(&mut Account)) {
+        let mut account = self.account(address).unwrap_or_default();
+        f(&mut account);
+        self.write_account(address, account);
+    }
+}
+
+impl StateDB for TxView<'_> {
+    fn get_balance(&self, address: &Address) -> u128 {
+        self.account(address).map_or(0, |account| account.balance)
+    }
+
+    fn set_balance(&mut self, address: &Address, balance: u128) {
+        self.update_account(address, |account| account.balance = balance);
+    }
+
+    fn get_nonce(&self, address: &Address) -> u64 {
+        self.account(address).map_or(0, |account| account.nonce)
+    }
+
+    fn set_nonce(&mut self, address: &Address, nonce: u64) {
+        self.update_account(address, |account| account.nonce = nonce);
+    }
+
+    fn get_storage(&self, address: &Address, slot: &H256) -> H256 {
+        if let Some(value) = self.writes.storage.get(&(*address, *slot)) {
+            return *value;
+        }
+        self.read(StateKey::Storage(*address, *slot));
+        match self.prior.and_then(|prior| prior.storage.get(&(*address, *slot))) {
+            Some(value) => *value,
+            None => self.base.get_storage(address, slot),
+        }
+    }
+
+    fn set_storage(&mut self, address: &Address, slot: &H256, value: H256) {
+        // A blind write: the slot's old value does not affect the result
+        let key = (*address, *slot);
+        let prev = self.writes.storage.insert(key, value);
+        self.journal.push(Undo::Storage { key, prev });
+        match self.account(address) {
+            None if value != ZERO_HASH => self.write_account(address, Account::default()),
+            // Clearing the last slot of an otherwise empty account drops it
+            Some(account) if value == ZERO_HASH && account.is_empty() && !self.has_storage(address) => {
+                self.store_account(address, None)
+            }
+            _ => {}
+        }
+    }
+}
+
+impl ExecutionState for TxView<'_> {
+    fn exists(&self, address: &Address) -> bool {
+        self.account(address).is_some()
+    }
+
+    fn code(&self, address: &Address) -> Vec<u8> {
+        let hash = self.code_hash(address);
+        self.stored_code(&hash).map(<[u8]>::to_vec).unwrap_or_default()
+    }
+
+    fn code_hash(&self, address: &Address) -> H256 {
+        self.account(address)
+            .map_or(EMPTY_CODE_HASH, |account| account.code_hash)
+    }
+
+    fn set_code(&mut self, address: &Address, code: Vec<u8>) {
+        let hash = if code.is_empty() {
+            EMPTY_CODE_HASH
+        } else {
+            keccak256(&code)
+        };
+        if !code.is_empty() && self.stored_code(&hash).is_none() {
+            self.journal.push(Undo::Code { hash });
+            self.writes.code.insert(hash, code);
+        }
+        self.update_account(address, |account| account.code_hash = hash);
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.journal.len())
+    }
+
+    fn revert_to(&mut self, checkpoint: Checkpoint) {
+        while self.journal.len() > checkpoint.0 {
+            match self.journal.pop().expect("journal longer than checkpoint") {
+                Undo::Account { address, prev, credit } => {
+                    restore(&mut self.writes.accounts, address, prev);
+                    restore(&mut self.writes.credits, address, credit);
+                }
+                Undo::Storage { key, prev } => restore(&mut self.writes.storage, key, prev),
+                Undo::Code { hash } => {
+                    self.writes.code.remove(&hash);
+                }
+                Undo::Credit { address, prev } => restore(&mut self.writes.credits, address, prev),
+            }
+        }
+    }
+
+    /// Deferred unless the account was already read or written, so that
+    /// every transaction paying the proposer does not conflict with the
+    /// others.
+    fn credit(&mut self, address: &Address, amount: u128) {
+        let touched =
+            self.writes.accounts.contains_key(address) || self.reads.lock().contains_key(&StateKey::Account(*address));
+        if touched {
+            let balance = self.get_balance(address);
+            self.set_balance(address, balance.saturating_add(amount));
+            return;
+        }
+        let prev = self.writes.credits.get(address).copied();
+        self.writes
+            .credits
+            .insert(*address, prev.unwrap_or(0).saturating_add(amount));
+        self.journal.push(Undo::Credit {
+            address: *address,
+            prev,
+        });
+    }
+}
+
+fn restore<K: Ord, V>(map: &mut BTreeMap<K, V>, key: K, prev: Option<V>) {
+    match prev {
+        Some(value) => map.insert(key, value),
+        None => map.remove(&key),
+    };
+}
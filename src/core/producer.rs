@@ -0,0 +1,306 @@
+use std::sync::Arc;
+
+use tokio::sync::RwLock;
+
+use crate::contracts::deployer::ContractDeployer;
+use crate::contracts::executor::{Tbc20FastPathExecutor, Tbc20Registry};
+use crate::core::address::Address;
+use crate::core::block::{Block, BlockHeader};
+use crate::core::blockchain::{validate_child, Blockchain, ChainError};
+use crate::core::config::NodeConfig;
+use crate::core::executor::{execute_transaction, BlockEnv, ExecutionError};
+use crate::core::gas::{GasConfig, GasSchedule};
+use crate::core::mempool::Mempool;
+use crate::core::parallel::BatchProcessor;
+use crate::core::receipt::{logs_bloom, receipts_root, Receipt};
+use crate::core::state::WorldState;
+use crate::core::transaction::SignedTransaction;
+
+/// Parameters every block on the chain is produced and verified under.
+#[derive(Debug, Clone)]
+pub struct ChainParams {
+    pub chain_id: u64,
+    pub gas_limit: u64,
+    pub gas: GasConfig,
+    pub schedule: GasSchedule,
+    pub fast_path: Arc<Tbc20FastPathExecutor>,
+    pub deployer: Arc<ContractDeployer>,
+}
+
+impl ChainParams {
+    pub fn new(chain_id: u64, gas_limit: u64) -> Self {
+        Self {
+            chain_id,
+            gas_limit,
+            gas: GasConfig::default(),
+            schedule: GasSchedule::default(),
+            fast_path: Arc::new(Tbc20FastPathExecutor::new(Arc::new(Tbc20Registry::new()))),
+            deployer: Arc::new(ContractDeployer::new()),
+        }
+    }
+
+    pub fn from_node_config(config: &NodeConfig) -> Self {
+        let params = Self::new(config.network.chain_id, config.smart_contracts.gas_limit_per_block);
+        Self {
+            gas: GasConfig::from_node_config(config),
+            schedule: config.smart_contracts.gas_schedule,
+            deployer: Arc::new(ContractDeployer::from_config(&config.smart_contracts)),
+            ..params
+        }
+    }
+
+    fn env(&self, header: &BlockHeader) -> BlockEnv {
+        BlockEnv {
+            chain_id: self.chain_id,
+            number: header.number,
+            timestamp: header.timestamp,
+            proposer: header.proposer,
+            gas_limit: self.gas_limit,
+            base_fee: header.base_fee,
+            schedule: self.schedule,
+            fast_path: self.fast_path.clone(),
+            deployer: self.deployer.clone(),
+        }
+    }
+}
+
+/// A block together with the receipts its execution produced.
+#[derive(Debug, Clone)]
+pub struct ExecutedBlock {
+    pub block: Block,
+    pub receipts: Vec<Receipt>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error(transparent)]
+    Chain(#[from] ChainError),
+    #[error("gas limit {got} does not match chain gas limit {expected}")]
+    GasLimitMismatch { expected: u64, got: u64 },
+    #[error("base fee {got} does not match expected base fee {expected}")]
+    BaseFeeMismatch { expected: u128, got: u128 },
+    #[error("transaction {index} is invalid: {source}")]
+    InvalidTransaction { index: usize, source: ExecutionError },
+    #[error("header gas used {header} does not match executed gas {computed}")]
+    GasUsedMismatch { header: u64, computed: u64 },
+    #[error("receipts root does not match executed receipts")]
+    ReceiptsRootMismatch,
+    #[error("logs bloom does not match executed receipts")]
+    LogsBloomMismatch,
+    #[error("state root does not match executed state")]
+    StateRootMismatch,
+}
+
+/// Execute `candidates` in order on top of `parent` and assemble the block.
+///
+/// Transactions that are invalid at their position, or do not fit in the
+/// remaining block gas, are skipped. `state` is left holding the block's
+/// changes, uncommitted.
+pub fn build_block(
+    params: &ChainParams,
+    proposer: Address,
+    parent: &Block,
+    state: &mut WorldState,
+    candidates: impl IntoIterator<Item = Arc<SignedTransaction>>,
+    timestamp: u64,
+) -> ExecutedBlock {
+    let mut header = BlockHeader {
+        number: parent.number() + 1,
+        parent_hash: parent.hash(),
+        shard_id: parent.header.shard_id,
+        proposer,
+        timestamp: timestamp.max(parent.header.timestamp),
+        gas_limit: params.gas_limit,
+        base_fee: params.gas.next_base_fee(&parent.header),
+        ..Default::default()
+    };
+    let env = params.env(&header);
+
+    let mut transactions = Vec::new();
+    let mut receipts: Vec<Receipt> = Vec::new();
+    let mut gas_used = 0;
+    for tx in candidates {
+        if params.gas_limit - gas_used < params.schedule.tx_base {
+            break;
+        }
+        // Errors leave state untouched, so the transaction is just dropped
+        if let Ok(receipt) = execute_transaction(state, &env, &tx, gas_used) {
+            gas_used = receipt.cumulative_gas_used;
+            receipts.push(receipt);
+            transactions.push(Arc::unwrap_or_clone(tx));
+        }
+    }
+
+    header.gas_used = gas_used;
+    header.receipts_root = receipts_root(&receipts);
+    header.logs_bloom = logs_bloom(&receipts);
+    header.state_root = state.state_root();
+    ExecutedBlock {
+        block: Block::new(header, transactions),
+        receipts,
+    }
+}
+
+/// Re-execute `block` on top of `parent` with `processor` and check every
+/// commitment in its header. On error `state` may hold partial changes
+/// that the caller must revert.
+pub fn verify_block(
+    params: &ChainParams,
+    processor: &BatchProcessor,
+    parent: &Block,
+    state: &mut WorldState,
+    block: &Block,
+) -> Result<Vec<Receipt>, ImportError> {
+    validate_child(parent, block)?;
+    if block.header.gas_limit != params.gas_limit {
+        return Err(ImportError::GasLimitMismatch {
+            expected: params.gas_limit,
+            got: block.header.gas_limit,
+        });
+    }
+    let expected = params.gas.next_base_fee(&parent.header);
+    if block.header.base_fee != expected {
+        return Err(ImportError::BaseFeeMismatch {
+            expected,
+            got: block.header.base_fee,
+        });
+    }
+
+    // The transactions are known up front, so they can run in parallel
+    let env = params.env(&block.header);
+    let output = processor
+        .execute(state, &env, &block.transactions)
+        .map_err(|(index, source)| ImportError::InvalidTransaction { index, source })?;
+    tracing::debug!(
+        number = block.number(),
+        groups = output.report.groups,
+        serial = output.report.serial,
+        parallelism = output.report.parallelism(),
+        "executed block transactions"
+    );
+    let receipts = output.receipts;
+
+    let computed = receipts.last().map_or(0, |r| r.cumulative_gas_used);
+    if computed != block.header.gas_used {
+        return Err(ImportError::GasUsedMismatch {
+            header: block.header.gas_used,
+            computed,
+        });
+    }
+    if receipts_root(&receipts) != block.header.receipts_root {
+        return Err(ImportError::ReceiptsRootMismatch);
+    }
+    if logs_bloom(&receipts) != block.header.logs_bloom {
+        return Err(ImportError::LogsBloomMismatch);
+    }
+    if state.state_root() != block.header.state_root {
+        return Err(ImportError::StateRootMismatch);
+    }
+    Ok(receipts)
+}
+
+/// Produces blocks from the mempool on top of the chain head.
+pub struct BlockProducer {
+    params: ChainParams,
+    proposer: Address,
+    chain: Arc<Blockchain>,
+    state: Arc<RwLock<WorldState>>,
+    mempool: Arc<parking_lot::RwLock<Mempool>>,
+}
+
+impl BlockProducer {
+    pub fn new(
+        params: ChainParams,
+        proposer: Address,
+        chain: Arc<Blockchain>,
+        state: Arc<RwLock<WorldState>>,
+        mempool: Arc<parking_lot::RwLock<Mempool>>,
+    ) -> Self {
+        Self {
+            params,
+            proposer,
+            chain,
+            state,
+            mempool,
+        }
+    }
+
+    /// Build the next block from pending transactions, append it to the
+    /// chain and commit its state.
+    pub async fn produce(&self, timestamp: u64) -> Result<ExecutedBlock, ImportError> {
+        let mut state = self.state.write().await;
+        let parent = self.chain.head().await;
+        let candidates = self.mempool.read().pending(&*state);
+
+        let checkpoint = state.checkpoint();
+        let executed = build_block(&self.params, self.proposer, &parent, &mut state, candidates, timestamp);
+        let appended = self
+            .chain
+            .append_executed(executed.block.clone(), executed.receipts.clone(), &state)
+            .await;
+        if let Err(err) = appended {
+            state.revert_to(checkpoint);
+            return Err(err.into());
+        }
+        state.commit();
+
+        let mut mempool = self.mempool.write();
+        mempool.prune(&*state);
+        mempool.set_base_fee(self.params.gas.next_base_fee(&executed.block.header));
+        Ok(executed)
+    }
+}
+
+/// Verifies blocks received from other nodes and commits them.
+pub struct BlockImporter {
+    params: ChainParams,
+    processor: Arc<BatchProcessor>,
+    chain: Arc<Blockchain>,
+    state: Arc<RwLock<WorldState>>,
+    mempool: Arc<parking_lot::RwLock<Mempool>>,
+}
+
+impl BlockImporter {
+    pub fn new(
+        params: ChainParams,
+        processor: Arc<BatchProcessor>,
+        chain: Arc<Blockchain>,
+        state: Arc<RwLock<WorldState>>,
+        mempool: Arc<parking_lot::RwLock<Mempool>>,
+    ) -> Self {
+        Self {
+            params,
+            processor,
+            chain,
+            state,
+            mempool,
+        }
+    }
+
+    /// Re-execute `block` against the head state and make it the new head.
+    /// State is unchanged if the block is rejected.
+    pub async fn import(&self, block: Block) -> Result<Vec<Receipt>, ImportError> {
+        let mut state = self.state.write().await;
+        let parent = self.chain.head().await;
+
+        let checkpoint = state.checkpoint();
+        let receipts = match verify_block(&self.params, &self.processor, &parent, &mut state, &block) {
+            Ok(receipts) => receipts,
+            Err(err) => {
+                state.revert_to(checkpoint);
+                return Err(err);
+            }
+        };
+        let base_fee = self.params.gas.next_base_fee(&block.header);
+        if let Err(err) = self.chain.append_executed(block, receipts.clone(), &state).await {
+            state.revert_to(checkpoint);
+            return Err(err.into());
+        }
+        state.commit();
+
+        let mut mempool = self.mempool.write();
+        mempool.prune(&*state);
+        mempool.set_base_fee(base_fee);
+        Ok(receipts)
+    }
+}
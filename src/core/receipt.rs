@@ -0,0 +1,194 @@
+use crate::core::address::Address;
+use crate::core::block::merkle_root;
+use crate::core::bloom::Bloom;
+use crate::core::codec::{DecodeError, Decoder, Encoder};
+use crate::core::transaction::TxHash;
+use crate::core::types::H256;
+
+/// Event emitted by a contract during execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Log {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Vec<u8>,
+}
+
+impl Log {
+    fn encode_into(&self, enc: &mut Encoder) {
+        enc.put_fixed(self.address.as_bytes()).put_u32(self.topics.len() as u32);
+        for topic in &self.topics {
+            enc.put_fixed(topic);
+        }
+        enc.put_bytes(&self.data);
+    }
+
+    fn decode_from(dec: &mut Decoder<'_>) -> Result<Self, DecodeError> {
+        let address = Address(dec.get_fixed()?);
+        let count = dec.get_u32()?;
+        if count > 4 {
+            return Err(DecodeError::Invalid("more than four log topics"));
+        }
+        let topics = (0..count).map(|_| dec.get_fixed()).collect::<Result<_, _>>()?;
+        Ok(Self {
+            address,
+            topics,
+            data: dec.get_bytes()?,
+        })
+    }
+
+    pub fn bloom(&self) -> Bloom {
+        let mut bloom = Bloom::default();
+        bloom.accrue(self.address.as_bytes());
+        for topic in &self.topics {
+            bloom.accrue(topic);
+        }
+        bloom
+    }
+}
+
+/// Outcome of executing one transaction in a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Receipt {
+    pub tx_hash: TxHash,
+    /// `false` when execution failed; gas is still charged
+    pub success: bool,
+    pub gas_used: u64,
+    /// Gas used by this and every earlier transaction in the block
+    pub cumulative_gas_used: u64,
+    /// Base fee portion of the fee, burned rather than paid to the proposer
+    pub burned: u128,
+    /// Address of the contract created by this transaction, if any
+    pub contract_address: Option<Address>,
+    /// Empty when `success` is false
+    pub logs: Vec<Log>,
+    /// Data returned by a reverting call; empty on success
+    pub revert_data: Vec<u8>,
+}
+
+impl Receipt {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut enc = Encoder::new();
+        enc.put_fixed(&self.tx_hash)
+            .put_u8(self.success as u8)
+            .put_u64(self.gas_used)
+            .put_u64(self.cumulative_gas_used)
+            .put_u128(self.burned);
+        match &self.contract_address {
+            Some(address) => enc.put_u8(1).put_fixed(address.as_bytes()),
+            None => enc.put_u8(0),
+        };
+        enc.put_u32(self.logs.len() as u32);
+        for log in &self.logs {
+            log.encode_into(&mut enc);
+        }
+        enc.put_bytes(&self.revert_data);
+        enc.finish()
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut dec = Decoder::new(bytes);
+        let mut receipt = Self {
+            tx_hash: dec.get_fixed()?,
+            success: match dec.get_u8()? {
+                0 => false,
+                1 => true,
+                _ => return Err(DecodeError::Invalid("bad receipt status")),
+            },
+            gas_used: dec.get_u64()?,
+            cumulative_gas_used: dec.get_u64()?,
+            burned: dec.get_u128()?,
+            contract_address: match dec.get_u8()? {
+                0 => None,
+                1 => Some(Address(dec.get_fixed()?)),
+                _ => return Err(DecodeError::Invalid("bad contract address tag")),
+            },
+            logs: Vec::new(),
+            revert_data: Vec::new(),
+        };
+        let count = dec.get_u32()?;
+        for _ in 0..count {
+            receipt.logs.push(Log::decode_from(&mut dec)?);
+        }
+        receipt.revert_data = dec.get_bytes()?;
+        dec.finish()?;
+        Ok(receipt)
+    }
+
+    /// blake3 of the encoded receipt.
+    pub fn hash(&self) -> H256 {
+        *blake3::hash(&self.encode()).as_bytes()
+    }
+
+    pub fn bloom(&self) -> Bloom {
+        let mut bloom = Bloom::default();
+        for log in &self.logs {
+            bloom.accrue_bloom(&log.bloom());
+        }
+        bloom
+    }
+}
+
+/// Merkle root over receipt hashes in block order.
+pub fn receipts_root(receipts: &[Receipt]) -> H256 {
+    let leaves: Vec<H256> = receipts.iter().map(Receipt::hash).collect();
+    merkle_root(&leaves)
+}
+
+/// Union of the blooms of every receipt in a block.
+pub fn logs_bloom(receipts: &[Receipt]) -> Bloom {
+    let mut bloom = Bloom::default();
+    for receipt in receipts {
+        bloom.accrue_bloom(&receipt.bloom());
+    }
+    bloom
+}
+
+/// Log query over a block range.
+///
+/// A log matches when its address is one of `addresses` (or `addresses`
+/// is empty) and, for each position `i`, `topics[i]` is `None` or contains
+/// the log's topic at `i`.
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// First block, inclusive; defaults to genesis
+    pub from_block: Option<u64>,
+    /// Last block, inclusive; defaults to the head
+    pub to_block: Option<u64>,
+    pub addresses: Vec<Address>,
+    pub topics: Vec<Option<Vec<H256>>>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, log: &Log) -> bool {
+        if !self.addresses.is_empty() && !self.addresses.contains(&log.address) {
+            return false;
+        }
+        self.topics.iter().enumerate().all(|(i, wanted)| match wanted {
+            None => true,
+            Some(options) => log.topics.get(i).is_some_and(|topic| options.contains(topic)),
+        })
+    }
+
+    /// Whether a block with `bloom` can hold a matching log.
+    pub fn may_match(&self, bloom: &Bloom) -> bool {
+        let address_ok = self.addresses.is_empty()
+            || self.addresses.iter().any(|a| bloom.contains_input(a.as_bytes()));
+        address_ok
+            && self.topics.iter().all(|wanted| match wanted {
+                None => true,
+                Some(options) => options.iter().any(|topic| bloom.contains_input(topic)),
+            })
+    }
+}
+
+/// A log together with where it was emitted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocatedLog {
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub tx_hash: TxHash,
+    pub tx_index: usize,
+    /// Position of the log within its block
+    pub log_index: usize,
+    pub log: Log,
+}
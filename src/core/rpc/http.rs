@@ -1,107 +1,488 @@
-use axum::{
-    routing::get,
-    Router,
-    Json,
-    extract::State,
-};
-use std::sync::Arc;
-use std::net::SocketAddr;
-use crate::core::Blockchain;
-use serde::{Serialize};
-use tower_http::cors::{CorsLayer, Any};
-use sqlx::SqlitePool;
-
-#[derive(Clone)]
-pub struct AppState {
-    blockchain: Arc<Blockchain>,
-    db_pool: SqlitePool,
-}
-
-pub async fn start_server(blockchain: Arc<Blockchain>, db_pool: SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
-    let state = AppState { blockchain, db_pool };
-
-    let app = Router::new()
-        .route("/api/stats", get(get_stats))
-        .route("/api/blocks", get(get_blocks))
-        .route("/api/txs", get(get_txs))
-        .route("/api/validators", get(get_validators))
-        .layer(CorsLayer::new().allow_origin(Any).allow_methods(Any))
-        .with_state(state);
-
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
-    println!("🌍 HTTP API listening on {}", addr);
-    
-    axum::Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await?;
-        
-    Ok(())
-}
-
-#[derive(Serialize, sqlx::FromRow)]
-struct StatsResponse {
-    height: i64,
-    tps: i64,
-    active_shards: i64,
-    active_validators: i64,
-}
-
-async fn get_stats(State(state): State<AppState>) -> Json<StatsResponse> {
-    let stats = sqlx::query_as::<_, StatsResponse>("SELECT height, tps, active_shards, active_validators FROM network_stats WHERE id = 1")
-        .fetch_one(&state.db_pool)
-        .await
-        .unwrap_or(StatsResponse { height: 0, tps: 0, active_shards: 0, active_validators: 0 });
-    
-    Json(stats)
-}
-
-#[derive(Serialize, sqlx::FromRow)]
-struct Block {
-    number: i64,
-    tx_count: i64,
-    shard_id: i64,
-    timestamp: i64,
-}
-
-async fn get_blocks(State(state): State<AppState>) -> Json<Vec<Block>> {
-    let blocks = sqlx::query_as::<_, Block>("SELECT number, tx_count, shard_id, timestamp FROM blocks ORDER BY number DESC LIMIT 10")
-        .fetch_all(&state.db_pool)
-        .await
-        .unwrap_or_default();
-    Json(blocks)
-}
-
-#[derive(Serialize, sqlx::FromRow)]
-struct Tx {
-    hash: String,
-    from_addr: String,
-    to_addr: String,
-    value: String,
-    status: String,
-    tx_type: String,
-}
-
-async fn get_txs(State(state): State<AppState>) -> Json<Vec<Tx>> {
-    let txs = sqlx::query_as::<_, Tx>("SELECT hash, from_addr, to_addr, value, status, tx_type FROM transactions ORDER BY timestamp DESC LIMIT 10")
-        .fetch_all(&state.db_pool)
-        .await
-        .unwrap_or_default();
-    Json(txs)
-}
-
-#[derive(Serialize, sqlx::FromRow)]
-struct Validator {
-    address: String,
-    stake: String,
-    power: f64,
-    status: String,
-    type_: String, // Mapped from 'type' column
-}
-
-async fn get_validators(State(state): State<AppState>) -> Json<Vec<Validator>> {
-    let validators = sqlx::query_as::<_, Validator>("SELECT address, stake, power, status, type as type_ FROM validators ORDER BY power DESC LIMIT 20")
-        .fetch_all(&state.db_pool)
-        .await
-        .unwrap_or_default();
-    Json(validators)
-}
+use axum::{
+    routing::get,
+    Router,
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use std::sync::Arc;
+use std::net::SocketAddr;
+use crate::contracts::abi::{Param, Token};
+use crate::contracts::executor::tbc20_abi;
+use crate::core::{Address, Blockchain, EmberGasSystem, LogFilter, Mempool, SignedTransaction};
+use crate::core::gas::TransactionPriority;
+use crate::core::state::{stored_account_proof, stored_storage_proof};
+use crate::core::types::{to_hex, H256};
+use crate::storage::cache::CachedStore;
+use crate::storage::rocksdb::StorageError;
+use serde::{Deserialize, Serialize};
+use tower_http::cors::{CorsLayer, Any};
+use sqlx::SqlitePool;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub(crate) blockchain: Arc<Blockchain>,
+    pub(crate) mempool: Arc<parking_lot::RwLock<Mempool>>,
+    pub(crate) gas: EmberGasSystem,
+    pub(crate) db_pool: SqlitePool,
+    /// Cached chain storage, for reading state tries without loading them
+    pub(crate) db: Arc<CachedStore>,
+}
+
+pub async fn start_server(state: AppState, addr: SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
+    let app = Router::new()
+        .route("/api/stats", get(get_stats))
+        .route("/api/blocks", get(get_blocks))
+        .route("/api/txs", get(get_txs).post(submit_tx))
+        .route("/api/txs/pending", get(get_pending_txs))
+        .route("/api/logs", get(get_logs))
+        .route("/api/account", get(get_account))
+        .route("/api/proof", get(get_proof))
+        .route("/api/gas", get(get_gas_price))
+        .route("/api/validators", get(get_validators))
+        .layer(CorsLayer::new().allow_origin(Any).allow_methods(Any))
+        .with_state(state);
+
+    println!("🌍 HTTP API listening on {}", addr);
+    
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await?;
+        
+    Ok(())
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+struct StatsResponse {
+    height: i64,
+    tps: i64,
+    active_shards: i64,
+    active_validators: i64,
+}
+
+async fn get_stats(State(state): State<AppState>) -> Json<StatsResponse> {
+    let mut stats = sqlx::query_as::<_, StatsResponse>("SELECT height, tps, active_shards, active_validators FROM network_stats WHERE id = 1")
+        .fetch_one(&state.db_pool)
+        .await
+        .unwrap_or(StatsResponse { height: 0, tps: 0, active_shards: 0, active_validators: 0 });
+
+    // Height and TPS come from the live chain, not the seeded row
+    stats.height = state.blockchain.get_height().await as i64;
+    stats.tps = state.blockchain.get_tps().await as i64;
+
+    Json(stats)
+}
+
+#[derive(Serialize)]
+struct Block {
+    number: i64,
+    hash: String,
+    parent_hash: String,
+    tx_count: i64,
+    shard_id: i64,
+    timestamp: i64,
+    validator: String,
+}
+
+async fn get_blocks(State(state): State<AppState>) -> Json<Vec<Block>> {
+    let blocks = state.blockchain.recent_blocks(10).await
+        .iter()
+        .map(|block| Block {
+            number: block.number() as i64,
+            hash: to_hex(&block.hash()),
+            parent_hash: to_hex(&block.header.parent_hash),
+            tx_count: block.tx_count() as i64,
+            shard_id: block.header.shard_id as i64,
+            // Explorer timestamps are in seconds
+            timestamp: (block.header.timestamp / 1000) as i64,
+            validator: block.header.proposer.to_string(),
+        })
+        .collect();
+    Json(blocks)
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+struct Tx {
+    hash: String,
+    from_addr: String,
+    to_addr: String,
+    value: String,
+    status: String,
+    tx_type: String,
+}
+
+async fn get_txs(State(state): State<AppState>) -> Json<Vec<Tx>> {
+    let txs = sqlx::query_as::<_, Tx>("SELECT hash, from_addr, to_addr, value, status, tx_type FROM transactions ORDER BY timestamp DESC LIMIT 10")
+        .fetch_all(&state.db_pool)
+        .await
+        .unwrap_or_default();
+    Json(txs)
+}
+
+#[derive(Serialize)]
+struct PendingTx {
+    hash: String,
+    from_addr: String,
+    to_addr: Option<String>,
+    value: String,
+    nonce: u64,
+    gas_price: String,
+    /// "pending" when executable, "queued" when waiting on a nonce gap
+    status: &'static str,
+    /// Set when the calldata is a TBC-20 call
+    call: Option<Decoded>,
+}
+
+impl PendingTx {
+    fn new(tx: &SignedTransaction, sender: Option<Address>, status: &'static str) -> Self {
+        Self {
+            hash: to_hex(&tx.hash()),
+            from_addr: sender.map(|a| a.to_string()).unwrap_or_default(),
+            to_addr: tx.tx().to.map(|a| a.to_string()),
+            value: tx.tx().value.to_string(),
+            nonce: tx.tx().nonce,
+            gas_price: tx.tx().gas_price.to_string(),
+            status,
+            call: tbc20_abi().decode_call(&tx.tx().data).ok().map(|(function, args)| {
+                Decoded::new(&function.name, function.signature(), &function.inputs, args)
+            }),
+        }
+    }
+}
+
+async fn get_pending_txs(State(state): State<AppState>) -> Result<Json<Vec<PendingTx>>, (StatusCode, String)> {
+    // Checked against the head snapshot, so block import is not held up
+    let head = state.blockchain.latest_state().await.map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e.to_string()))?;
+    let mempool = state.mempool.read();
+    // Senders were recovered on admission
+    let entry = |tx: Arc<SignedTransaction>, status| PendingTx::new(&tx, mempool.sender(&tx.hash()), status);
+    let pending = mempool.pending(&head).into_iter().map(|tx| entry(tx, "pending"));
+    let queued = mempool.queued(&head).into_iter().map(|tx| entry(tx, "queued"));
+    Ok(Json(pending.chain(queued).collect()))
+}
+
+#[derive(Deserialize)]
+struct SubmitTx {
+    /// Hex-encoded signed transaction
+    raw: String,
+}
+
+#[derive(Serialize)]
+struct SubmitTxResponse {
+    hash: Option<String>,
+    error: Option<String>,
+}
+
+async fn submit_tx(State(state): State<AppState>, Json(body): Json<SubmitTx>) -> (StatusCode, Json<SubmitTxResponse>) {
+    let rejected = |error: String| {
+        (StatusCode::BAD_REQUEST, Json(SubmitTxResponse { hash: None, error: Some(error) }))
+    };
+
+    let raw = body.raw.strip_prefix("0x").unwrap_or(&body.raw);
+    let tx = match hex::decode(raw).map_err(|e| e.to_string()).and_then(|bytes| {
+        SignedTransaction::decode(&bytes).map_err(|e| e.to_string())
+    }) {
+        Ok(tx) => tx,
+        Err(error) => return rejected(error),
+    };
+
+    let head = match state.blockchain.latest_state().await {
+        Ok(head) => head,
+        Err(error) => {
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(SubmitTxResponse { hash: None, error: Some(error.to_string()) }))
+        }
+    };
+    match state.mempool.write().add(tx, &head) {
+        Ok(hash) => (StatusCode::OK, Json(SubmitTxResponse { hash: Some(to_hex(&hash)), error: None })),
+        Err(error) => rejected(error.to_string()),
+    }
+}
+
+/// Gas prices in Ember for the next block. The tiers differ only in tip.
+#[derive(Serialize)]
+struct GasPriceResponse {
+    block_number: u64,
+    base_fee: String,
+    low: String,
+    normal: String,
+    high: String,
+    urgent: String,
+}
+
+async fn get_gas_price(State(state): State<AppState>) -> Json<GasPriceResponse> {
+    let head = state.blockchain.head().await;
+    let quote = |priority| state.gas.get_gas_price(priority, &head.header).gas_price.to_string();
+    Json(GasPriceResponse {
+        block_number: head.number() + 1,
+        base_fee: state.gas.config().next_base_fee(&head.header).to_string(),
+        low: quote(TransactionPriority::Low),
+        normal: quote(TransactionPriority::Normal),
+        high: quote(TransactionPriority::High),
+        urgent: quote(TransactionPriority::Urgent),
+    })
+}
+
+/// Query string for `/api/logs`. List parameters are comma-separated; a
+/// topic position accepts any of its values.
+#[derive(Deserialize)]
+struct LogQuery {
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+    address: Option<String>,
+    topic0: Option<String>,
+    topic1: Option<String>,
+    topic2: Option<String>,
+    topic3: Option<String>,
+}
+
+impl LogQuery {
+    fn into_filter(self) -> Result<LogFilter, String> {
+        let addresses = match &self.address {
+            Some(list) => list.split(',').map(|a| a.trim().parse::<Address>().map_err(|e| e.to_string())).collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        let mut topics = vec![self.topic0, self.topic1, self.topic2, self.topic3]
+            .into_iter()
+            .map(|topic| topic.map(|list| list.split(',').map(parse_topic).collect::<Result<Vec<_>, _>>()).transpose())
+            .collect::<Result<Vec<_>, _>>()?;
+        while topics.last().is_some_and(Option::is_none) {
+            topics.pop();
+        }
+        Ok(LogFilter {
+            from_block: self.from_block,
+            to_block: self.to_block,
+            addresses,
+            topics,
+        })
+    }
+}
+
+fn parse_topic(s: &str) -> Result<H256, String> {
+    let s = s.trim();
+    let bytes = hex::decode(s.strip_prefix("0x").unwrap_or(s)).map_err(|e| e.to_string())?;
+    bytes.try_into().map_err(|_| format!("topic {s} is not 32 bytes"))
+}
+
+#[derive(Serialize)]
+struct LogEntry {
+    block_number: u64,
+    block_hash: String,
+    tx_hash: String,
+    tx_index: usize,
+    log_index: usize,
+    address: String,
+    topics: Vec<String>,
+    data: String,
+    /// Set when the log is a TBC-20 event
+    event: Option<Decoded>,
+}
+
+/// A function call or event decoded against its ABI, for the explorer to
+/// render instead of raw hex.
+#[derive(Serialize)]
+struct Decoded {
+    name: String,
+    signature: String,
+    params: Vec<DecodedParam>,
+}
+
+#[derive(Serialize)]
+struct DecodedParam {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    value: String,
+}
+
+impl Decoded {
+    fn new(name: &str, signature: String, params: &[Param], values: Vec<Token>) -> Self {
+        Self {
+            name: name.to_string(),
+            signature,
+            params: params.iter().zip(values).map(|(param, value)| DecodedParam {
+                name: param.name.clone(),
+                kind: param.kind.to_string(),
+                value: value.to_string(),
+            }).collect(),
+        }
+    }
+}
+
+async fn get_logs(State(state): State<AppState>, Query(query): Query<LogQuery>) -> Result<Json<Vec<LogEntry>>, (StatusCode, String)> {
+    let filter = query.into_filter().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let logs = state.blockchain.get_logs(&filter).await.map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(Json(logs.into_iter().map(|located| LogEntry {
+        block_number: located.block_number,
+        block_hash: to_hex(&located.block_hash),
+        tx_hash: to_hex(&located.tx_hash),
+        tx_index: located.tx_index,
+        log_index: located.log_index,
+        address: located.log.address.to_string(),
+        topics: located.log.topics.iter().map(to_hex).collect(),
+        data: format!("0x{}", hex::encode(&located.log.data)),
+        event: tbc20_abi().decode_log(&located.log).ok().map(|(event, values)| {
+            Decoded::new(&event.name, event.signature(), &event.inputs, values)
+        }),
+    }).collect()))
+}
+
+/// Query string for `/api/account`: an address and the block to read at
+/// (default head).
+#[derive(Deserialize)]
+struct AccountQuery {
+    address: String,
+    block: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct AccountResponse {
+    block_number: u64,
+    address: String,
+    balance: String,
+    nonce: u64,
+    code_hash: String,
+    code: String,
+}
+
+async fn get_account(State(state): State<AppState>, Query(query): Query<AccountQuery>) -> Result<Json<AccountResponse>, (StatusCode, String)> {
+    let address = query.address.trim().parse::<Address>().map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let block = match query.block {
+        Some(number) => state.blockchain.get_block_by_number(number).await,
+        None => Some(state.blockchain.head().await),
+    }.ok_or((StatusCode::NOT_FOUND, "unknown block".to_string()))?;
+    let state_root = block.header.state_root;
+
+    // Served from the account and code caches where possible
+    let db = state.db.clone();
+    let read = tokio::task::spawn_blocking(move || {
+        let account = db.account(&state_root, &address)?.unwrap_or_default();
+        let code = if account.has_code() {
+            db.code(&account.code_hash)?
+                .ok_or_else(|| StorageError::Missing(format!("code {}", to_hex(&account.code_hash))))?
+        } else {
+            Default::default()
+        };
+        Ok((account, code))
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let (account, code) = read.map_err(storage_status)?;
+    Ok(Json(AccountResponse {
+        block_number: block.number(),
+        address: address.to_string(),
+        balance: account.balance.to_string(),
+        nonce: account.nonce,
+        code_hash: to_hex(&account.code_hash),
+        code: format!("0x{}", hex::encode(code.as_slice())),
+    }))
+}
+
+/// Query string for `/api/proof`: an address, optional comma-separated
+/// storage slots and the block to prove against (default head).
+#[derive(Deserialize)]
+struct ProofQuery {
+    address: String,
+    slots: Option<String>,
+    block: Option<u64>,
+}
+
+/// Merkle proofs of an account and some of its storage, for light clients
+/// and bridges to check against `state_root`.
+#[derive(Serialize)]
+struct ProofResponse {
+    block_number: u64,
+    state_root: String,
+    address: String,
+    balance: String,
+    nonce: u64,
+    code_hash: String,
+    storage_root: String,
+    account_proof: Vec<String>,
+    storage: Vec<StorageProof>,
+}
+
+#[derive(Serialize)]
+struct StorageProof {
+    slot: String,
+    value: String,
+    proof: Vec<String>,
+}
+
+/// Most storage slots one `/api/proof` request may ask for.
+const MAX_PROOF_SLOTS: usize = 64;
+
+fn proof_hex(proof: Vec<Vec<u8>>) -> Vec<String> {
+    proof.iter().map(|node| format!("0x{}", hex::encode(node))).collect()
+}
+
+fn storage_status(err: StorageError) -> (StatusCode, String) {
+    match err {
+        // The block's state was pruned
+        StorageError::Missing(_) => (StatusCode::NOT_FOUND, err.to_string()),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+    }
+}
+
+async fn get_proof(State(state): State<AppState>, Query(query): Query<ProofQuery>) -> Result<Json<ProofResponse>, (StatusCode, String)> {
+    let address = query.address.trim().parse::<Address>().map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let slots = match &query.slots {
+        Some(list) => list.split(',').map(parse_topic).collect::<Result<Vec<_>, _>>().map_err(|e| (StatusCode::BAD_REQUEST, e))?,
+        None => Vec::new(),
+    };
+    if slots.len() > MAX_PROOF_SLOTS {
+        return Err((StatusCode::BAD_REQUEST, format!("at most {MAX_PROOF_SLOTS} slots can be proven at once")));
+    }
+    let block = match query.block {
+        Some(number) => state.blockchain.get_block_by_number(number).await,
+        None => Some(state.blockchain.head().await),
+    }.ok_or((StatusCode::NOT_FOUND, "unknown block".to_string()))?;
+    let state_root = block.header.state_root;
+
+    // Proofs walk the stored tries from the block's state root, one node
+    // read at a time; keep those reads off the async workers
+    let db = state.db.clone();
+    let proven = tokio::task::spawn_blocking(move || {
+        let (account, account_proof) = stored_account_proof(db.as_ref(), &state_root, &address)?;
+        let account = account.unwrap_or_default();
+        let storage = slots.iter().map(|slot| {
+            let (value, proof) = stored_storage_proof(db.as_ref(), &account.storage_root, slot)?;
+            Ok(StorageProof {
+                slot: to_hex(slot),
+                value: to_hex(&value),
+                proof: proof_hex(proof),
+            })
+        }).collect::<Result<Vec<_>, StorageError>>()?;
+        Ok((account, account_proof, storage))
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let (account, account_proof, storage) = proven.map_err(storage_status)?;
+    Ok(Json(ProofResponse {
+        block_number: block.number(),
+        state_root: to_hex(&state_root),
+        address: address.to_string(),
+        balance: account.balance.to_string(),
+        nonce: account.nonce,
+        code_hash: to_hex(&account.code_hash),
+        storage_root: to_hex(&account.storage_root),
+        account_proof: proof_hex(account_proof),
+        storage,
+    }))
+}
+
+#[derive(Serialize, sqlx::FromRow)]
+struct Validator {
+    address: String,
+    stake: String,
+    power: f64,
+    status: String,
+    type_: String, // Mapped from 'type' column
+}
+
+async fn get_validators(State(state): State<AppState>) -> Json<Vec<Validator>> {
+    let validators = sqlx::query_as::<_, Validator>("SELECT address, stake, power, status, type as type_ FROM validators ORDER BY power DESC LIMIT 20")
+        .fetch_all(&state.db_pool)
+        .await
+        .unwrap_or_default();
+    Json(validators)
+}
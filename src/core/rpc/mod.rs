@@ -1,30 +1,52 @@
-pub mod http;
-pub mod websocket;
-pub mod ipc;
-
-use std::sync::Arc;
-use crate::core::Blockchain;
-use sqlx::sqlite::SqlitePool;
-
-pub struct RpcServer {
-    blockchain: Arc<Blockchain>,
-    db_pool: SqlitePool,
-}
-
-impl RpcServer {
-    pub fn new(blockchain: Arc<Blockchain>, db_pool: SqlitePool) -> Self {
-        Self { blockchain, db_pool }
-    }
-
-    pub async fn start_all(&self) -> Result<(), Box<dyn std::error::Error>> {
-        println!("🚀 Starting RPC Server...");
-        
-        // Start HTTP Server
-        let http_server = http::start_server(self.blockchain.clone(), self.db_pool.clone());
-        
-        // Wait for server
-        http_server.await?;
-        
-        Ok(())
-    }
-}
+pub mod http;
+pub mod websocket;
+pub mod ipc;
+
+use std::sync::Arc;
+use crate::core::{Blockchain, EmberGasSystem, Mempool};
+use crate::core::config::ApiConfig;
+use crate::storage::cache::CachedStore;
+use sqlx::sqlite::SqlitePool;
+
+pub struct RpcServer {
+    blockchain: Arc<Blockchain>,
+    mempool: Arc<parking_lot::RwLock<Mempool>>,
+    gas: EmberGasSystem,
+    db_pool: SqlitePool,
+    db: Arc<CachedStore>,
+    config: ApiConfig,
+}
+
+impl RpcServer {
+    pub fn new(
+        blockchain: Arc<Blockchain>,
+        mempool: Arc<parking_lot::RwLock<Mempool>>,
+        gas: EmberGasSystem,
+        db_pool: SqlitePool,
+        db: Arc<CachedStore>,
+        config: ApiConfig,
+    ) -> Self {
+        Self { blockchain, mempool, gas, db_pool, db, config }
+    }
+
+    pub async fn start_all(&self) -> Result<(), Box<dyn std::error::Error>> {
+        println!("🚀 Starting RPC Server...");
+        
+        // Start HTTP Server
+        let http_server = http::start_server(
+            http::AppState {
+                blockchain: self.blockchain.clone(),
+                mempool: self.mempool.clone(),
+                gas: self.gas.clone(),
+                db_pool: self.db_pool.clone(),
+                db: self.db.clone(),
+            },
+            self.config.rest_addr(),
+        );
+        
+        // Wait for server
+        http_server.await?;
+        
+        Ok(())
+    }
+}
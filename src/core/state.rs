@@ -0,0 +1,676 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use parking_lot::Mutex;
+
+use crate::core::account::{Account, EMPTY_CODE_HASH};
+use crate::core::address::Address;
+use crate::core::codec::DecodeError;
+use crate::core::types::{keccak256, to_hex, H256, ZERO_HASH};
+use crate::storage::mvcc::StateChanges;
+use crate::storage::rocksdb::{Column, KvStore, StorageError, WriteBatch};
+use crate::storage::trie::{self, MerkleTrie, Proof, ProofError};
+
+/// State access used by transaction and contract execution.
+pub trait StateDB: Send + Sync {
+    fn get_balance(&self, address: &Address) -> u128;
+    fn set_balance(&mut self, address: &Address, balance: u128);
+    fn get_nonce(&self, address: &Address) -> u64;
+    fn set_nonce(&mut self, address: &Address, nonce: u64);
+    fn get_storage(&self, address: &Address, slot: &H256) -> H256;
+    fn set_storage(&mut self, address: &Address, slot: &H256, value: H256);
+}
+
+/// Everything transaction execution needs from state. Implemented by
+/// `WorldState`, and by the views `core::parallel` runs transactions on.
+pub trait ExecutionState: StateDB {
+    fn exists(&self, address: &Address) -> bool;
+    fn code(&self, address: &Address) -> Vec<u8>;
+    fn code_hash(&self, address: &Address) -> H256;
+    fn set_code(&mut self, address: &Address, code: Vec<u8>);
+    fn checkpoint(&self) -> Checkpoint;
+    fn revert_to(&mut self, checkpoint: Checkpoint);
+
+    /// Move `amount` between accounts, failing if `from` cannot cover it.
+    fn transfer(&mut self, from: &Address, to: &Address, amount: u128) -> Result<(), StateError> {
+        let from_balance = self.get_balance(from);
+        if from_balance < amount {
+            return Err(StateError::InsufficientBalance {
+                address: *from,
+                balance: from_balance,
+                required: amount,
+            });
+        }
+        if from == to {
+            return Ok(());
+        }
+        let to_balance = self
+            .get_balance(to)
+            .checked_add(amount)
+            .ok_or(StateError::BalanceOverflow(*to))?;
+        self.set_balance(from, from_balance - amount);
+        self.set_balance(to, to_balance);
+        Ok(())
+    }
+
+    /// Add a fee to the balance of `address`, saturating. Nothing reads
+    /// the balance in between, so parallel execution can defer these.
+    fn credit(&mut self, address: &Address, amount: u128) {
+        let balance = self.get_balance(address);
+        self.set_balance(address, balance.saturating_add(amount));
+    }
+}
+
+/// Position in the journal that `WorldState::revert_to` can roll back to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint(pub(crate) usize);
+
+#[derive(Debug, Clone)]
+enum JournalEntry {
+    Account { address: Address, prev: Option<Account> },
+    Storage { address: Address, slot: H256, prev: Option<H256> },
+    Code { hash: H256 },
+}
+
+/// Tries derived from the account and storage maps, brought up to date
+/// when a root is needed rather than on every write.
+#[derive(Debug, Default, Clone)]
+struct Tries {
+    /// Storage root of each account whose storage has not changed since
+    /// it was computed
+    storage_roots: HashMap<Address, H256>,
+    /// State trie, `None` until first built
+    state: Option<MerkleTrie>,
+    /// Accounts whose state trie entry is out of date
+    dirty: BTreeSet<Address>,
+}
+
+#[derive(Debug, Default)]
+struct TrieCache(Mutex<Tries>);
+
+impl Clone for TrieCache {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.0.lock().clone()))
+    }
+}
+
+/// In-memory world state with a change journal.
+///
+/// Every mutation records the previous value, so a failed call or
+/// transaction can be undone with `revert_to` without copying state.
+/// Roots are computed from cached tries, re-hashing only the accounts
+/// changed since the last root.
+#[derive(Debug, Default, Clone)]
+pub struct WorldState {
+    accounts: BTreeMap<Address, Account>,
+    storage: BTreeMap<Address, BTreeMap<H256, H256>>,
+    code: HashMap<H256, Vec<u8>>,
+    journal: Vec<JournalEntry>,
+    tries: TrieCache,
+}
+
+impl WorldState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Account at `address` with an up to date `storage_root`, if it exists.
+    pub fn account(&self, address: &Address) -> Option<Account> {
+        let account = self.accounts.get(address)?;
+        let mut tries = self.tries.0.lock();
+        Some(Account {
+            storage_root: self.cached_storage_root(&mut tries, address),
+            ..account.clone()
+        })
+    }
+
+    /// Mark the state trie entry of `address` out of date.
+    fn touch(&mut self, address: &Address) {
+        self.tries.0.get_mut().dirty.insert(*address);
+    }
+
+    /// Mark the storage root of `address` out of date.
+    fn touch_storage(&mut self, address: &Address) {
+        let tries = self.tries.0.get_mut();
+        tries.storage_roots.remove(address);
+        tries.dirty.insert(*address);
+    }
+
+    pub fn exists(&self, address: &Address) -> bool {
+        self.accounts.contains_key(address)
+    }
+
+    pub fn accounts(&self) -> impl Iterator<Item = (&Address, &Account)> {
+        self.accounts.iter()
+    }
+
+    fn write_account(&mut self, address: &Address, account: Account) {
+        let prev = self.accounts.get(address).cloned();
+        if prev.as_ref() == Some(&account) {
+            return;
+        }
+        self.journal.push(JournalEntry::Account { address: *address, prev });
+        self.touch(address);
+
+        let has_storage = self.storage.get(address).is_some_and(|s| !s.is_empty());
+        if account.is_empty() && !has_storage {
+            self.accounts.remove(address);
+        } else {
+            self.accounts.insert(*address, account);
+        }
+    }
+
+    fn update_account(&mut self, address: &Address, f: impl FnOnce(&mut Account)) {
+        let mut account = self.accounts.get(address).cloned().unwrap_or_default();
+        f(&mut account);
+        self.write_account(address, account);
+    }
+
+    pub fn code(&self, address: &Address) -> Vec<u8> {
+        self.accounts
+            .get(address)
+            .and_then(|account| self.code.get(&account.code_hash))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn code_hash(&self, address: &Address) -> H256 {
+        self.accounts
+            .get(address)
+            .map_or(EMPTY_CODE_HASH, |account| account.code_hash)
+    }
+
+    pub fn code_by_hash(&self, hash: &H256) -> Option<&[u8]> {
+        self.code.get(hash).map(Vec::as_slice)
+    }
+
+    pub fn set_code(&mut self, address: &Address, code: Vec<u8>) {
+        let hash = if code.is_empty() { EMPTY_CODE_HASH } else { keccak256(&code) };
+        if !code.is_empty() && !self.code.contains_key(&hash) {
+            self.journal.push(JournalEntry::Code { hash });
+            self.code.insert(hash, code);
+        }
+        self.update_account(address, |account| account.code_hash = hash);
+    }
+
+    /// Move `amount` between accounts, failing if `from` cannot cover it.
+    pub fn transfer(&mut self, from: &Address, to: &Address, amount: u128) -> Result<(), StateError> {
+        ExecutionState::transfer(self, from, to, amount)
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.journal.len())
+    }
+
+    /// Undo every change made since `checkpoint` was taken.
+    pub fn revert_to(&mut self, checkpoint: Checkpoint) {
+        while self.journal.len() > checkpoint.0 {
+            match self.journal.pop().expect("journal longer than checkpoint") {
+                JournalEntry::Account { address, prev } => {
+                    match prev {
+                        Some(account) => self.accounts.insert(address, account),
+                        None => self.accounts.remove(&address),
+                    };
+                    self.touch(&address);
+                }
+                JournalEntry::Storage { address, slot, prev } => {
+                    self.touch_storage(&address);
+                    let slots = self.storage.entry(address).or_default();
+                    match prev {
+                        Some(value) => {
+                            slots.insert(slot, value);
+                        }
+                        None => {
+                            slots.remove(&slot);
+                        }
+                    }
+                    if slots.is_empty() {
+                        self.storage.remove(&address);
+                    }
+                }
+                JournalEntry::Code { hash } => {
+                    self.code.remove(&hash);
+                }
+            }
+        }
+    }
+
+    /// Account at `address` as stored, without computing its storage root.
+    pub(crate) fn stored_account(&self, address: &Address) -> Option<&Account> {
+        self.accounts.get(address)
+    }
+
+    /// Non-zero storage slots of `address`.
+    pub(crate) fn storage_slots(&self, address: &Address) -> impl Iterator<Item = (&H256, &H256)> {
+        self.storage.get(address).into_iter().flatten()
+    }
+
+    /// Store `account` at `address` as is, or remove it. Unlike the setters
+    /// this does not drop empty accounts, so a change computed elsewhere is
+    /// applied exactly.
+    pub(crate) fn put_account(&mut self, address: &Address, account: Option<Account>) {
+        let prev = self.accounts.get(address).cloned();
+        if prev == account {
+            return;
+        }
+        self.journal.push(JournalEntry::Account { address: *address, prev });
+        self.touch(address);
+        match account {
+            Some(account) => self.accounts.insert(*address, account),
+            None => self.accounts.remove(address),
+        };
+    }
+
+    pub(crate) fn put_code(&mut self, hash: H256, code: Vec<u8>) {
+        if !self.code.contains_key(&hash) {
+            self.journal.push(JournalEntry::Code { hash });
+            self.code.insert(hash, code);
+        }
+    }
+
+    /// Drop the journal. Changes made so far can no longer be reverted.
+    pub fn commit(&mut self) {
+        self.journal.clear();
+    }
+
+    /// Current values of everything changed since the last `commit`.
+    pub fn pending_changes(&self) -> StateChanges {
+        let mut changes = StateChanges::default();
+        for entry in &self.journal {
+            match entry {
+                JournalEntry::Account { address, .. } => {
+                    changes.accounts.insert(*address, self.accounts.get(address).cloned());
+                }
+                JournalEntry::Storage { address, slot, .. } => {
+                    changes.storage.insert((*address, *slot), self.get_storage(address, slot));
+                }
+                JournalEntry::Code { hash } => {
+                    if let Some(code) = self.code.get(hash) {
+                        changes.code.insert(*hash, code.clone());
+                    }
+                }
+            }
+        }
+        changes
+    }
+
+    /// The whole state as changes from an empty state.
+    pub fn to_changes(&self) -> StateChanges {
+        StateChanges {
+            accounts: self
+                .accounts
+                .iter()
+                .map(|(address, account)| (*address, Some(account.clone())))
+                .collect(),
+            storage: self
+                .storage
+                .iter()
+                .flat_map(|(address, slots)| slots.iter().map(move |(slot, value)| ((*address, *slot), *value)))
+                .collect(),
+            code: self.code.iter().map(|(hash, code)| (*hash, code.clone())).collect(),
+        }
+    }
+
+    fn storage_trie(&self, address: &Address) -> MerkleTrie {
+        let mut trie = MerkleTrie::new();
+        for (slot, value) in self.storage.get(address).into_iter().flatten() {
+            trie.insert(&storage_key(slot), value.to_vec());
+        }
+        trie
+    }
+
+    fn cached_storage_root(&self, tries: &mut Tries, address: &Address) -> H256 {
+        if !self.storage.contains_key(address) {
+            return ZERO_HASH;
+        }
+        *tries
+            .storage_roots
+            .entry(*address)
+            .or_insert_with(|| self.storage_trie(address).root())
+    }
+
+    /// Encoded account at `address` with its storage root filled in.
+    fn encoded_account(&self, tries: &mut Tries, address: &Address) -> Option<Vec<u8>> {
+        let account = self.accounts.get(address)?;
+        let storage_root = self.cached_storage_root(tries, address);
+        Some(Account {
+            storage_root,
+            ..account.clone()
+        }
+        .encode())
+    }
+
+    /// Run `f` on the state trie after updating the entries of the accounts
+    /// changed since it was last used.
+    fn with_state_trie<R>(&self, f: impl FnOnce(&MerkleTrie) -> R) -> R {
+        let mut guard = self.tries.0.lock();
+        let tries = &mut *guard;
+        let trie = match tries.state.take() {
+            Some(mut trie) => {
+                for address in std::mem::take(&mut tries.dirty) {
+                    match self.encoded_account(tries, &address) {
+                        Some(encoded) => trie.insert(&account_key(&address), encoded),
+                        None => trie.remove(&account_key(&address)),
+                    };
+                }
+                trie
+            }
+            None => {
+                tries.dirty.clear();
+                let mut trie = MerkleTrie::new();
+                for address in self.accounts.keys() {
+                    let encoded = self.encoded_account(tries, address).expect("key from accounts map");
+                    trie.insert(&account_key(address), encoded);
+                }
+                trie
+            }
+        };
+        let out = f(&trie);
+        tries.state = Some(trie);
+        out
+    }
+
+    pub fn storage_root(&self, address: &Address) -> H256 {
+        self.cached_storage_root(&mut self.tries.0.lock(), address)
+    }
+
+    /// Commitment to every account and storage slot in the state.
+    pub fn state_root(&self) -> H256 {
+        self.with_state_trie(MerkleTrie::root)
+    }
+
+    /// Proof of the account at `address`, or of its absence, against
+    /// `state_root()`.
+    pub fn account_proof(&self, address: &Address) -> Proof {
+        self.with_state_trie(|trie| trie.prove(&account_key(address)))
+    }
+
+    /// Proof of a storage slot against the account's `storage_root`.
+    pub fn storage_proof(&self, address: &Address, slot: &H256) -> Proof {
+        self.storage_trie(address).prove(&storage_key(slot))
+    }
+
+    /// Add the trie nodes, key preimages and contract code needed to
+    /// rebuild this state with `load` to `batch`, returning the state root.
+    pub fn write_to(&self, batch: &mut WriteBatch) -> H256 {
+        for (address, slots) in &self.storage {
+            for (hash, node) in self.storage_trie(address).nodes() {
+                batch.put(Column::TrieNodes, hash, node);
+            }
+            for slot in slots.keys() {
+                batch.put(Column::Preimages, storage_key(slot), *slot);
+            }
+        }
+        for (hash, code) in &self.code {
+            batch.put(Column::TrieNodes, *hash, code.clone());
+        }
+        for address in self.accounts.keys() {
+            batch.put(Column::Preimages, account_key(address), address.as_bytes());
+        }
+        self.with_state_trie(|trie| {
+            for (hash, node) in trie.nodes() {
+                batch.put(Column::TrieNodes, hash, node);
+            }
+            trie.root()
+        })
+    }
+
+    /// Like `write_to`, but only adds what changed since the last `commit`.
+    /// The state committed then must already be stored, as every node off
+    /// the changed paths is shared with it.
+    pub fn write_changes_to(&self, batch: &mut WriteBatch) -> H256 {
+        let changes = self.pending_changes();
+        let mut slot_keys: BTreeMap<Address, Vec<H256>> = BTreeMap::new();
+        for (address, slot) in changes.storage.keys() {
+            slot_keys.entry(*address).or_default().push(storage_key(slot));
+            batch.put(Column::Preimages, storage_key(slot), *slot);
+        }
+        for (address, keys) in &slot_keys {
+            for (hash, node) in self.storage_trie(address).nodes_along(keys.iter().map(|key| &key[..])) {
+                batch.put(Column::TrieNodes, hash, node);
+            }
+        }
+        for (hash, code) in changes.code {
+            batch.put(Column::TrieNodes, hash, code);
+        }
+        let addresses: BTreeSet<Address> = changes.accounts.into_keys().chain(slot_keys.into_keys()).collect();
+        let account_keys: Vec<H256> = addresses.iter().map(account_key).collect();
+        for (address, key) in addresses.iter().zip(&account_keys) {
+            batch.put(Column::Preimages, *key, address.as_bytes());
+        }
+        self.with_state_trie(|trie| {
+            for (hash, node) in trie.nodes_along(account_keys.iter().map(|key| &key[..])) {
+                batch.put(Column::TrieNodes, hash, node);
+            }
+            trie.root()
+        })
+    }
+
+    /// Rebuild the state committed to by `root` from the nodes, preimages
+    /// and code written by `write_to`.
+    pub fn load(store: &dyn KvStore, root: &H256) -> Result<Self, StorageError> {
+        let mut state = Self::new();
+        for (key, value) in load_trie(store, root)?.entries() {
+            let address = Address(preimage(store, &key, "account key")?);
+            let mut account = Account::decode(value).map_err(|source| StorageError::Corrupt {
+                what: "account",
+                source,
+            })?;
+            let mut slots = BTreeMap::new();
+            for (key, value) in load_trie(store, &account.storage_root)?.entries() {
+                let slot = preimage(store, &key, "storage key")?;
+                slots.insert(slot, value.try_into().map_err(|_| corrupt("storage slot"))?);
+            }
+            if !slots.is_empty() {
+                state.storage.insert(address, slots);
+            }
+            if account.has_code() {
+                let code = store
+                    .get(Column::TrieNodes, &account.code_hash)?
+                    .ok_or_else(|| StorageError::Missing(format!("code {}", to_hex(&account.code_hash))))?;
+                state.code.insert(account.code_hash, code);
+            }
+            // Kept at its default; `account` fills in the live root
+            account.storage_root = ZERO_HASH;
+            state.accounts.insert(address, account);
+        }
+        Ok(state)
+    }
+}
+
+/// Trie key of an account. Keys are hashed so paths stay evenly spread
+/// whatever addresses are chosen.
+pub fn account_key(address: &Address) -> H256 {
+    keccak256(address.as_bytes())
+}
+
+/// Trie key of a storage slot within its account's storage trie.
+pub fn storage_key(slot: &H256) -> H256 {
+    keccak256(slot)
+}
+
+fn corrupt(what: &'static str) -> StorageError {
+    StorageError::Corrupt {
+        what,
+        source: DecodeError::Invalid("wrong length"),
+    }
+}
+
+/// Address or slot stored under the hashed trie key `key`.
+fn preimage<const N: usize>(store: &dyn KvStore, key: &[u8], what: &'static str) -> Result<[u8; N], StorageError> {
+    store
+        .get(Column::Preimages, key)?
+        .ok_or_else(|| StorageError::Missing(format!("preimage of {what} 0x{}", hex::encode(key))))?
+        .try_into()
+        .map_err(|_| corrupt(what))
+}
+
+/// Trie at `root`, reporting the first storage error or missing node.
+fn load_trie(store: &dyn KvStore, root: &H256) -> Result<MerkleTrie, StorageError> {
+    let mut failure = None;
+    let trie = MerkleTrie::load(root, |hash| match store.get(Column::TrieNodes, hash) {
+        Ok(node) => node,
+        Err(err) => {
+            failure.get_or_insert(err);
+            None
+        }
+    });
+    match (trie, failure) {
+        (_, Some(err)) => Err(err),
+        (Some(trie), None) => Ok(trie),
+        (None, None) => Err(StorageError::Missing(format!("trie {}", to_hex(root)))),
+    }
+}
+
+/// Account at `address` in the stored state with root `state_root`, and its
+/// `account_proof`, reading only the nodes on its path from `store`.
+pub fn stored_account_proof(
+    store: &dyn KvStore,
+    state_root: &H256,
+    address: &Address,
+) -> Result<(Option<Account>, Proof), StorageError> {
+    let (encoded, proof) = trie::prove_stored(state_root, &account_key(address), |hash| {
+        store.get(Column::TrieNodes, hash)
+    })?;
+    let account = encoded
+        .map(|encoded| Account::decode(&encoded))
+        .transpose()
+        .map_err(|source| StorageError::Corrupt {
+            what: "account",
+            source,
+        })?;
+    Ok((account, proof))
+}
+
+/// Value of `slot` in the stored storage trie with root `storage_root`, and
+/// its `storage_proof`.
+pub fn stored_storage_proof(store: &dyn KvStore, storage_root: &H256, slot: &H256) -> Result<(H256, Proof), StorageError> {
+    let (value, proof) = trie::prove_stored(storage_root, &storage_key(slot), |hash| {
+        store.get(Column::TrieNodes, hash)
+    })?;
+    let value = match value {
+        Some(value) => value.try_into().map_err(|_| corrupt("storage slot"))?,
+        None => ZERO_HASH,
+    };
+    Ok((value, proof))
+}
+
+/// Check an `account_proof` against a header's state root. `None` if the
+/// proof shows there is no account at `address`.
+pub fn verify_account_proof(
+    state_root: &H256,
+    address: &Address,
+    proof: &[Vec<u8>],
+) -> Result<Option<Account>, ProofError> {
+    trie::verify_proof(state_root, &account_key(address), proof)?
+        .map(|encoded| Account::decode(&encoded).map_err(ProofError::from))
+        .transpose()
+}
+
+/// Check a `storage_proof` against an account's storage root. Absent slots
+/// read as zero.
+pub fn verify_storage_proof(storage_root: &H256, slot: &H256, proof: &[Vec<u8>]) -> Result<H256, ProofError> {
+    match trie::verify_proof(storage_root, &storage_key(slot), proof)? {
+        None => Ok(ZERO_HASH),
+        Some(value) => value
+            .try_into()
+            .map_err(|_| ProofError::Decode(DecodeError::Invalid("storage value is not 32 bytes"))),
+    }
+}
+
+impl ExecutionState for WorldState {
+    fn exists(&self, address: &Address) -> bool {
+        self.exists(address)
+    }
+
+    fn code(&self, address: &Address) -> Vec<u8> {
+        self.code(address)
+    }
+
+    fn code_hash(&self, address: &Address) -> H256 {
+        self.code_hash(address)
+    }
+
+    fn set_code(&mut self, address: &Address, code: Vec<u8>) {
+        self.set_code(address, code)
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        self.checkpoint()
+    }
+
+    fn revert_to(&mut self, checkpoint: Checkpoint) {
+        self.revert_to(checkpoint)
+    }
+}
+
+impl StateDB for WorldState {
+    fn get_balance(&self, address: &Address) -> u128 {
+        self.accounts.get(address).map_or(0, |account| account.balance)
+    }
+
+    fn set_balance(&mut self, address: &Address, balance: u128) {
+        self.update_account(address, |account| account.balance = balance);
+    }
+
+    fn get_nonce(&self, address: &Address) -> u64 {
+        self.accounts.get(address).map_or(0, |account| account.nonce)
+    }
+
+    fn set_nonce(&mut self, address: &Address, nonce: u64) {
+        self.update_account(address, |account| account.nonce = nonce);
+    }
+
+    fn get_storage(&self, address: &Address, slot: &H256) -> H256 {
+        self.storage
+            .get(address)
+            .and_then(|slots| slots.get(slot))
+            .copied()
+            .unwrap_or(ZERO_HASH)
+    }
+
+    fn set_storage(&mut self, address: &Address, slot: &H256, value: H256) {
+        let slots = self.storage.entry(*address).or_default();
+        // Zero slots are not stored, so reading and clearing agree
+        let prev = if value == ZERO_HASH {
+            slots.remove(slot)
+        } else {
+            slots.insert(*slot, value)
+        };
+        if slots.is_empty() {
+            self.storage.remove(address);
+        }
+        if prev == Some(value) || (prev.is_none() && value == ZERO_HASH) {
+            return;
+        }
+        self.journal.push(JournalEntry::Storage {
+            address: *address,
+            slot: *slot,
+            prev,
+        });
+        self.touch_storage(address);
+        // Storage only counts towards the state root through its account,
+        // which exists exactly while it has a nonce, balance, code or storage
+        let has_storage = self.storage.contains_key(address);
+        match self.accounts.get(address) {
+            None if has_storage => self.write_account(address, Account::default()),
+            Some(account) if account.is_empty() && !has_storage => {
+                let prev = self.accounts.remove(address);
+                self.journal.push(JournalEntry::Account { address: *address, prev });
+                self.touch(address);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum StateError {
+    #[error("account {address} has balance {balance}, needs {required}")]
+    InsufficientBalance {
+        address: Address,
+        balance: u128,
+        required: u128,
+    },
+    #[error("balance of {0} would overflow")]
+    BalanceOverflow(Address),
+}
@@ -0,0 +1,238 @@
+use lazy_static::lazy_static;
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use secp256k1::{All, Message, PublicKey, Secp256k1, SecretKey};
+
+use crate::core::address::Address;
+use crate::core::codec::{DecodeError, Decoder, Encoder};
+use crate::core::types::{keccak256, H256};
+
+/// Transaction hash
+pub type TxHash = H256;
+
+/// Prefix mixed into the signing payload so a transaction signature can
+/// never be replayed as a signature over some other message type.
+const SIGNING_DOMAIN: &[u8] = b"TBURN_TX_V1";
+
+/// Half the secp256k1 curve order. Signatures with `s` above this are
+/// rejected so every transaction has exactly one valid signature.
+const SECP256K1_HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d,
+    0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+lazy_static! {
+    static ref SECP256K1: Secp256k1<All> = Secp256k1::new();
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TxError {
+    #[error("transaction is for chain {got}, expected {expected}")]
+    WrongChainId { expected: u64, got: u64 },
+    #[error("invalid signature")]
+    InvalidSignature,
+    #[error("signature s value is not canonical")]
+    NonCanonicalSignature,
+    #[error("malformed transaction: {0}")]
+    Decode(#[from] DecodeError),
+}
+
+/// Unsigned transaction payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    /// Replay protection, must match `chainId` in genesis
+    pub chain_id: u64,
+    pub nonce: u64,
+    /// Price per unit of gas in Ember (1 TBURN = 10^9 Ember)
+    pub gas_price: u128,
+    pub gas_limit: u64,
+    /// `None` deploys a contract
+    pub to: Option<Address>,
+    pub value: u128,
+    pub data: Vec<u8>,
+}
+
+impl Transaction {
+    fn encode_into(&self, enc: &mut Encoder) {
+        enc.put_u64(self.chain_id)
+            .put_u64(self.nonce)
+            .put_u128(self.gas_price)
+            .put_u64(self.gas_limit);
+        match &self.to {
+            Some(to) => enc.put_u8(1).put_fixed(to.as_bytes()),
+            None => enc.put_u8(0),
+        };
+        enc.put_u128(self.value).put_bytes(&self.data);
+    }
+
+    fn decode_from(dec: &mut Decoder<'_>) -> Result<Self, DecodeError> {
+        Ok(Self {
+            chain_id: dec.get_u64()?,
+            nonce: dec.get_u64()?,
+            gas_price: dec.get_u128()?,
+            gas_limit: dec.get_u64()?,
+            to: match dec.get_u8()? {
+                0 => None,
+                1 => Some(Address(dec.get_fixed()?)),
+                _ => return Err(DecodeError::Invalid("bad recipient tag")),
+            },
+            value: dec.get_u128()?,
+            data: dec.get_bytes()?,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut enc = Encoder::new();
+        self.encode_into(&mut enc);
+        enc.finish()
+    }
+
+    /// Digest that the sender signs. It commits to `chain_id`, so a
+    /// signature is only valid on one network.
+    pub fn signing_hash(&self) -> H256 {
+        let mut payload = SIGNING_DOMAIN.to_vec();
+        payload.extend_from_slice(&self.encode());
+        keccak256(&payload)
+    }
+
+    pub fn sign(self, secret_key: &SecretKey) -> SignedTransaction {
+        let message = Message::from_slice(&self.signing_hash()).expect("32-byte digest");
+        let signature = SECP256K1.sign_ecdsa_recoverable(&message, secret_key);
+        SignedTransaction::new(self, Signature::from_recoverable(&signature))
+    }
+}
+
+/// Compact recoverable secp256k1 signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+    /// Recovery id (0 or 1)
+    pub v: u8,
+}
+
+impl Signature {
+    fn from_recoverable(signature: &RecoverableSignature) -> Self {
+        let (recovery_id, bytes) = signature.serialize_compact();
+        let mut r = [0u8; 32];
+        let mut s = [0u8; 32];
+        r.copy_from_slice(&bytes[..32]);
+        s.copy_from_slice(&bytes[32..]);
+        Self { r, s, v: recovery_id.to_i32() as u8 }
+    }
+
+    fn to_recoverable(self) -> Result<RecoverableSignature, TxError> {
+        if self.s > SECP256K1_HALF_ORDER {
+            return Err(TxError::NonCanonicalSignature);
+        }
+        let recovery_id =
+            RecoveryId::from_i32(self.v as i32).map_err(|_| TxError::InvalidSignature)?;
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&self.r);
+        bytes[32..].copy_from_slice(&self.s);
+        RecoverableSignature::from_compact(&bytes, recovery_id)
+            .map_err(|_| TxError::InvalidSignature)
+    }
+}
+
+/// Transaction together with the sender's signature. Immutable, so the
+/// cached hash always matches the contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedTransaction {
+    tx: Transaction,
+    signature: Signature,
+    hash: TxHash,
+}
+
+impl SignedTransaction {
+    pub fn new(tx: Transaction, signature: Signature) -> Self {
+        let mut signed = Self { tx, signature, hash: [0u8; 32] };
+        signed.hash = keccak256(&signed.encode());
+        signed
+    }
+
+    pub fn tx(&self) -> &Transaction {
+        &self.tx
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    /// keccak256 over the full signed encoding.
+    pub fn hash(&self) -> TxHash {
+        self.hash
+    }
+
+    /// Recover the address that signed this transaction.
+    pub fn recover_sender(&self) -> Result<Address, TxError> {
+        let signature = self.signature.to_recoverable()?;
+        let message = Message::from_slice(&self.tx.signing_hash()).expect("32-byte digest");
+        let public_key = SECP256K1
+            .recover_ecdsa(&message, &signature)
+            .map_err(|_| TxError::InvalidSignature)?;
+        Ok(public_key_to_address(&public_key))
+    }
+
+    /// Reject transactions signed for another network.
+    pub fn verify_chain_id(&self, expected: u64) -> Result<(), TxError> {
+        if self.tx.chain_id != expected {
+            return Err(TxError::WrongChainId {
+                expected,
+                got: self.tx.chain_id,
+            });
+        }
+        Ok(())
+    }
+
+    /// Check chain id and signature, returning the sender.
+    pub fn verify(&self, chain_id: u64) -> Result<Address, TxError> {
+        self.verify_chain_id(chain_id)?;
+        self.recover_sender()
+    }
+
+    pub fn encode_into(&self, enc: &mut Encoder) {
+        self.tx.encode_into(enc);
+        enc.put_fixed(&self.signature.r)
+            .put_fixed(&self.signature.s)
+            .put_u8(self.signature.v);
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut enc = Encoder::new();
+        self.encode_into(&mut enc);
+        enc.finish()
+    }
+
+    pub fn decode_from(dec: &mut Decoder<'_>) -> Result<Self, DecodeError> {
+        let tx = Transaction::decode_from(dec)?;
+        let signature = Signature {
+            r: dec.get_fixed()?,
+            s: dec.get_fixed()?,
+            v: dec.get_u8()?,
+        };
+        Ok(Self::new(tx, signature))
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, TxError> {
+        let mut dec = Decoder::new(bytes);
+        let signed = Self::decode_from(&mut dec)?;
+        dec.finish()?;
+        Ok(signed)
+    }
+}
+
+/// Address of a secp256k1 public key: the last 20 bytes of the keccak256
+/// of the uncompressed key, matching the `0x` addresses in genesis.
+pub fn public_key_to_address(public_key: &PublicKey) -> Address {
+    let uncompressed = public_key.serialize_uncompressed();
+    let hash = keccak256(&uncompressed[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    Address(address)
+}
+
+pub fn secret_key_to_address(secret_key: &SecretKey) -> Address {
+    public_key_to_address(&PublicKey::from_secret_key(&SECP256K1, secret_key))
+}
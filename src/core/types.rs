@@ -0,0 +1,14 @@
+/// 32-byte hash used for blocks, transactions and state roots.
+pub type H256 = [u8; 32];
+
+pub const ZERO_HASH: H256 = [0u8; 32];
+
+/// Render a hash as a `0x`-prefixed hex string for the API and logs.
+pub fn to_hex(hash: &H256) -> String {
+    format!("0x{}", hex::encode(hash))
+}
+
+pub fn keccak256(data: &[u8]) -> H256 {
+    use sha3::{Digest, Keccak256};
+    Keccak256::digest(data).into()
+}
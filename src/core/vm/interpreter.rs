@@ -0,0 +1,962 @@
+use primitive_types::{U256, U512};
+
+use crate::core::account::EMPTY_CODE_HASH;
+use crate::core::address::Address;
+use crate::core::executor::{create2_address, create_address, BlockEnv};
+use crate::core::gas::{GasMeter, GasSchedule};
+use crate::core::receipt::Log;
+use crate::core::state::ExecutionState;
+use crate::core::types::{keccak256, H256, ZERO_HASH};
+use crate::core::vm::{
+    precompiles, wasm_vm, CallKind, CallOutput, ExitReason, Message, VmError, MAX_CALL_DEPTH,
+};
+
+/// Maximum number of words on the stack.
+pub const STACK_LIMIT: usize = 1024;
+
+/// Memory offsets and lengths above this fail with out of gas before any
+/// expansion is priced; no block gas limit could pay for them.
+const MEMORY_LIMIT: u64 = 32 * 1024 * 1024;
+
+/// Native stack that must remain before entering a nested frame.
+const STACK_RED_ZONE: usize = 256 * 1024;
+
+/// Size of each native stack segment allocated for nested frames.
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+
+mod op {
+    pub const STOP: u8 = 0x00;
+    pub const ADD: u8 = 0x01;
+    pub const MUL: u8 = 0x02;
+    pub const SUB: u8 = 0x03;
+    pub const DIV: u8 = 0x04;
+    pub const SDIV: u8 = 0x05;
+    pub const MOD: u8 = 0x06;
+    pub const SMOD: u8 = 0x07;
+    pub const ADDMOD: u8 = 0x08;
+    pub const MULMOD: u8 = 0x09;
+    pub const EXP: u8 = 0x0a;
+    pub const SIGNEXTEND: u8 = 0x0b;
+    pub const LT: u8 = 0x10;
+    pub const GT: u8 = 0x11;
+    pub const SLT: u8 = 0x12;
+    pub const SGT: u8 = 0x13;
+    pub const EQ: u8 = 0x14;
+    pub const ISZERO: u8 = 0x15;
+    pub const AND: u8 = 0x16;
+    pub const OR: u8 = 0x17;
+    pub const XOR: u8 = 0x18;
+    pub const NOT: u8 = 0x19;
+    pub const BYTE: u8 = 0x1a;
+    pub const SHL: u8 = 0x1b;
+    pub const SHR: u8 = 0x1c;
+    pub const SAR: u8 = 0x1d;
+    pub const KECCAK256: u8 = 0x20;
+    pub const ADDRESS: u8 = 0x30;
+    pub const BALANCE: u8 = 0x31;
+    pub const ORIGIN: u8 = 0x32;
+    pub const CALLER: u8 = 0x33;
+    pub const CALLVALUE: u8 = 0x34;
+    pub const CALLDATALOAD: u8 = 0x35;
+    pub const CALLDATASIZE: u8 = 0x36;
+    pub const CALLDATACOPY: u8 = 0x37;
+    pub const CODESIZE: u8 = 0x38;
+    pub const CODECOPY: u8 = 0x39;
+    pub const GASPRICE: u8 = 0x3a;
+    pub const EXTCODESIZE: u8 = 0x3b;
+    pub const EXTCODECOPY: u8 = 0x3c;
+    pub const RETURNDATASIZE: u8 = 0x3d;
+    pub const RETURNDATACOPY: u8 = 0x3e;
+    pub const EXTCODEHASH: u8 = 0x3f;
+    pub const BLOCKHASH: u8 = 0x40;
+    pub const COINBASE: u8 = 0x41;
+    pub const TIMESTAMP: u8 = 0x42;
+    pub const NUMBER: u8 = 0x43;
+    pub const PREVRANDAO: u8 = 0x44;
+    pub const GASLIMIT: u8 = 0x45;
+    pub const CHAINID: u8 = 0x46;
+    pub const SELFBALANCE: u8 = 0x47;
+    pub const BASEFEE: u8 = 0x48;
+    pub const POP: u8 = 0x50;
+    pub const MLOAD: u8 = 0x51;
+    pub const MSTORE: u8 = 0x52;
+    pub const MSTORE8: u8 = 0x53;
+    pub const SLOAD: u8 = 0x54;
+    pub const SSTORE: u8 = 0x55;
+    pub const JUMP: u8 = 0x56;
+    pub const JUMPI: u8 = 0x57;
+    pub const PC: u8 = 0x58;
+    pub const MSIZE: u8 = 0x59;
+    pub const GAS: u8 = 0x5a;
+    pub const JUMPDEST: u8 = 0x5b;
+    pub const MCOPY: u8 = 0x5e;
+    pub const PUSH0: u8 = 0x5f;
+    pub const PUSH1: u8 = 0x60;
+    pub const PUSH32: u8 = 0x7f;
+    pub const DUP1: u8 = 0x80;
+    pub const DUP16: u8 = 0x8f;
+    pub const SWAP1: u8 = 0x90;
+    pub const SWAP16: u8 = 0x9f;
+    pub const LOG0: u8 = 0xa0;
+    pub const LOG4: u8 = 0xa4;
+    pub const CREATE: u8 = 0xf0;
+    pub const CALL: u8 = 0xf1;
+    pub const CALLCODE: u8 = 0xf2;
+    pub const RETURN: u8 = 0xf3;
+    pub const DELEGATECALL: u8 = 0xf4;
+    pub const CREATE2: u8 = 0xf5;
+    pub const STATICCALL: u8 = 0xfa;
+    pub const REVERT: u8 = 0xfd;
+    pub const SELFDESTRUCT: u8 = 0xff;
+}
+
+/// EVM-compatible bytecode interpreter.
+///
+/// Runs one transaction's worth of messages against an `ExecutionState`.
+/// Each call or create frame takes a state checkpoint and is rolled back,
+/// together with the logs and refunds it produced, if it reverts or halts.
+///
+/// Code starting with the Wasm magic bytes runs in `wasm_vm` instead, so the
+/// two kinds of contract can call each other.
+///
+/// Differences from Ethereum: accounts are always priced cold (there are no
+/// access lists), BLOCKHASH and PREVRANDAO read as zero, TIMESTAMP is the
+/// block timestamp in seconds, and SELFDESTRUCT only moves the balance.
+pub struct Evm<'a> {
+    pub(super) state: &'a mut dyn ExecutionState,
+    pub(super) env: &'a BlockEnv,
+    pub(super) origin: Address,
+    gas_price: u128,
+    pub(super) logs: Vec<Log>,
+    pub(super) refund: u64,
+    pub(super) depth: usize,
+}
+
+impl<'a> Evm<'a> {
+    pub fn new(state: &'a mut dyn ExecutionState, env: &'a BlockEnv, origin: Address, gas_price: u128) -> Self {
+        Self {
+            state,
+            env,
+            origin,
+            gas_price,
+            logs: Vec::new(),
+            refund: 0,
+            depth: 0,
+        }
+    }
+
+    /// Logs emitted by frames that have not been rolled back.
+    pub fn logs(&self) -> &[Log] {
+        &self.logs
+    }
+
+    /// Gas refund earned so far, before the end of transaction cap.
+    pub fn refund(&self) -> u64 {
+        self.refund
+    }
+
+    /// Logs and refund of the transaction.
+    pub fn finish(self) -> (Vec<Log>, u64) {
+        (self.logs, self.refund)
+    }
+
+    /// Run `msg` as a call or a create, depending on its kind.
+    pub fn execute(&mut self, msg: Message) -> CallOutput {
+        let checkpoint = self.state.checkpoint();
+        let logs = self.logs.len();
+        let refund = self.refund;
+
+        let output = if msg.kind.is_create() {
+            self.create(&msg)
+        } else {
+            self.call(&msg)
+        };
+        if !output.is_success() {
+            self.state.revert_to(checkpoint);
+            self.logs.truncate(logs);
+            self.refund = refund;
+        }
+        output
+    }
+
+    fn call(&mut self, msg: &Message) -> CallOutput {
+        // DELEGATECALL only passes the value along for CALLVALUE
+        if matches!(msg.kind, CallKind::Call | CallKind::CallCode)
+            && self.state.transfer(&msg.caller, &msg.address, msg.value).is_err()
+        {
+            return CallOutput::halt(VmError::InsufficientBalance);
+        }
+        if let Some(precompile) = precompiles::get(&msg.code_address) {
+            return precompile.execute(&self.env.schedule, &msg.input, msg.gas);
+        }
+        let code = self.state.code(&msg.code_address);
+        if code.is_empty() {
+            return CallOutput {
+                exit: ExitReason::Success,
+                gas_left: msg.gas,
+                output: Vec::new(),
+                created: None,
+            };
+        }
+        if wasm_vm::is_wasm(&code) {
+            return wasm_vm::call(self, msg, &code);
+        }
+        self.run(msg, &code, &msg.input)
+    }
+
+    fn create(&mut self, msg: &Message) -> CallOutput {
+        if msg.input.len() > self.env.deployer.max_initcode_size() {
+            return CallOutput::halt(VmError::CodeSizeLimit);
+        }
+        let address = msg.address;
+        if self.state.get_nonce(&address) != 0 || self.state.code_hash(&address) != EMPTY_CODE_HASH {
+            return CallOutput::halt(VmError::CreateCollision);
+        }
+        self.state.set_nonce(&address, 1);
+        if self.state.transfer(&msg.caller, &address, msg.value).is_err() {
+            return CallOutput::halt(VmError::InsufficientBalance);
+        }
+
+        let mut output = if wasm_vm::is_wasm(&msg.input) {
+            wasm_vm::deploy(self, msg)
+        } else {
+            self.run(msg, &msg.input, &[])
+        };
+        if !output.is_success() {
+            return output;
+        }
+        let code = std::mem::take(&mut output.output);
+        if code.len() > self.env.deployer.max_code_size() {
+            return CallOutput::halt(VmError::CodeSizeLimit);
+        }
+        if code.first() == Some(&0xef) {
+            return CallOutput::halt(VmError::InvalidCodePrefix);
+        }
+        let deposit = self.env.schedule.code_deposit_cost(code.len());
+        if deposit > output.gas_left {
+            return CallOutput::halt(VmError::OutOfGas);
+        }
+        output.gas_left -= deposit;
+        self.state.set_code(&address, code);
+        output.created = Some(address);
+        output
+    }
+
+    /// Execute a message one level deeper than the current frame.
+    ///
+    /// Frames recurse on the native stack, so it is grown on demand rather
+    /// than letting a deep call chain overflow the thread's stack.
+    pub(super) fn nested(&mut self, msg: Message) -> CallOutput {
+        self.depth += 1;
+        let output = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || self.execute(msg));
+        self.depth -= 1;
+        output
+    }
+
+    fn run(&mut self, msg: &Message, code: &[u8], input: &[u8]) -> CallOutput {
+        let mut frame = Frame::new(code, input, msg.gas);
+        match self.interpret(msg, &mut frame) {
+            Ok((exit, output)) => CallOutput {
+                exit,
+                gas_left: frame.meter.remaining(),
+                output,
+                created: None,
+            },
+            Err(error) => CallOutput::halt(error),
+        }
+    }
+
+    fn interpret(&mut self, msg: &Message, frame: &mut Frame) -> Result<(ExitReason, Vec<u8>), VmError> {
+        let env = self.env;
+        let schedule = &env.schedule;
+
+        while let Some(&opcode) = frame.code.get(frame.pc) {
+            frame.pc += 1;
+            match opcode {
+                op::STOP => return Ok((ExitReason::Success, Vec::new())),
+
+                op::ADD => frame.binary(schedule.very_low, |a, b| a.overflowing_add(b).0)?,
+                op::MUL => frame.binary(schedule.low, |a, b| a.overflowing_mul(b).0)?,
+                op::SUB => frame.binary(schedule.very_low, |a, b| a.overflowing_sub(b).0)?,
+                op::DIV => frame.binary(schedule.low, |a, b| if b.is_zero() { b } else { a / b })?,
+                op::SDIV => frame.binary(schedule.low, signed_div)?,
+                op::MOD => frame.binary(schedule.low, |a, b| if b.is_zero() { b } else { a % b })?,
+                op::SMOD => frame.binary(schedule.low, signed_mod)?,
+                op::ADDMOD | op::MULMOD => {
+                    frame.charge(schedule.mid)?;
+                    let (a, b, n) = (frame.pop()?, frame.pop()?, frame.pop()?);
+                    let result = if n.is_zero() {
+                        n
+                    } else if opcode == op::ADDMOD {
+                        truncate((U512::from(a) + U512::from(b)) % U512::from(n))
+                    } else {
+                        truncate(a.full_mul(b) % U512::from(n))
+                    };
+                    frame.push(result)?;
+                }
+                op::EXP => {
+                    let (base, exponent) = (frame.pop()?, frame.pop()?);
+                    let bytes = (exponent.bits() as u64).div_ceil(8);
+                    frame.charge(schedule.exp.saturating_add(schedule.exp_byte.saturating_mul(bytes)))?;
+                    frame.push(base.overflowing_pow(exponent).0)?;
+                }
+                op::SIGNEXTEND => frame.binary(schedule.low, sign_extend)?,
+
+                op::LT => frame.binary(schedule.very_low, |a, b| flag(a < b))?,
+                op::GT => frame.binary(schedule.very_low, |a, b| flag(a > b))?,
+                op::SLT => frame.binary(schedule.very_low, |a, b| flag(signed_lt(a, b)))?,
+                op::SGT => frame.binary(schedule.very_low, |a, b| flag(signed_lt(b, a)))?,
+                op::EQ => frame.binary(schedule.very_low, |a, b| flag(a == b))?,
+                op::ISZERO => frame.unary(schedule.very_low, |a| flag(a.is_zero()))?,
+                op::AND => frame.binary(schedule.very_low, |a, b| a & b)?,
+                op::OR => frame.binary(schedule.very_low, |a, b| a | b)?,
+                op::XOR => frame.binary(schedule.very_low, |a, b| a ^ b)?,
+                op::NOT => frame.unary(schedule.very_low, |a| !a)?,
+                op::BYTE => frame.binary(schedule.very_low, |i, x| {
+                    if i < U256::from(32) {
+                        U256::from(x.byte(31 - i.as_usize()))
+                    } else {
+                        U256::zero()
+                    }
+                })?,
+                op::SHL => frame.binary(schedule.very_low, |shift, x| {
+                    if shift < U256::from(256) {
+                        x << shift.as_usize()
+                    } else {
+                        U256::zero()
+                    }
+                })?,
+                op::SHR => frame.binary(schedule.very_low, |shift, x| {
+                    if shift < U256::from(256) {
+                        x >> shift.as_usize()
+                    } else {
+                        U256::zero()
+                    }
+                })?,
+                op::SAR => frame.binary(schedule.very_low, arithmetic_shr)?,
+
+                op::KECCAK256 => {
+                    let (offset, len) = (frame.pop()?, frame.pop()?);
+                    let (offset, len) = frame.expand(schedule, offset, len)?;
+                    frame.charge(word_cost(schedule.keccak256, schedule.keccak256_word, len))?;
+                    let hash = keccak256(&frame.memory[offset..offset + len]);
+                    frame.push(U256::from_big_endian(&hash))?;
+                }
+
+                op::ADDRESS => frame.constant(schedule.base, address_word(&msg.address))?,
+                op::BALANCE => {
+                    frame.charge(schedule.balance)?;
+                    let address = word_address(frame.pop()?);
+                    frame.push(U256::from(self.state.get_balance(&address)))?;
+                }
+                op::ORIGIN => frame.constant(schedule.base, address_word(&self.origin))?,
+                op::CALLER => frame.constant(schedule.base, address_word(&msg.caller))?,
+                op::CALLVALUE => frame.constant(schedule.base, U256::from(msg.value))?,
+                op::CALLDATALOAD => {
+                    frame.charge(schedule.very_low)?;
+                    let offset = frame.pop()?;
+                    let mut word = [0u8; 32];
+                    copy_padded(&mut word, frame.input, offset);
+                    frame.push(U256::from_big_endian(&word))?;
+                }
+                op::CALLDATASIZE => frame.constant(schedule.base, U256::from(frame.input.len()))?,
+                op::CALLDATACOPY | op::CODECOPY => {
+                    let (dest, offset, len) = (frame.pop()?, frame.pop()?, frame.pop()?);
+                    let (dest, len) = frame.expand(schedule, dest, len)?;
+                    frame.charge(word_cost(schedule.very_low, schedule.copy_word, len))?;
+                    let source = if opcode == op::CALLDATACOPY {
+                        frame.input
+                    } else {
+                        frame.code
+                    };
+                    copy_padded(&mut frame.memory[dest..dest + len], source, offset);
+                }
+                op::CODESIZE => frame.constant(schedule.base, U256::from(frame.code.len()))?,
+                op::GASPRICE => frame.constant(schedule.base, U256::from(self.gas_price))?,
+                op::EXTCODESIZE => {
+                    frame.charge(schedule.ext_code)?;
+                    let address = word_address(frame.pop()?);
+                    frame.push(U256::from(self.state.code(&address).len()))?;
+                }
+                op::EXTCODECOPY => {
+                    let address = word_address(frame.pop()?);
+                    let (dest, offset, len) = (frame.pop()?, frame.pop()?, frame.pop()?);
+                    let (dest, len) = frame.expand(schedule, dest, len)?;
+                    frame.charge(word_cost(schedule.ext_code, schedule.copy_word, len))?;
+                    let code = self.state.code(&address);
+                    copy_padded(&mut frame.memory[dest..dest + len], &code, offset);
+                }
+                op::RETURNDATASIZE => frame.constant(schedule.base, U256::from(frame.return_data.len()))?,
+                op::RETURNDATACOPY => {
+                    let (dest, offset, len) = (frame.pop()?, frame.pop()?, frame.pop()?);
+                    let end = offset.checked_add(len).ok_or(VmError::ReturnDataOutOfBounds)?;
+                    if end > U256::from(frame.return_data.len()) {
+                        return Err(VmError::ReturnDataOutOfBounds);
+                    }
+                    let (dest, len) = frame.expand(schedule, dest, len)?;
+                    frame.charge(word_cost(schedule.very_low, schedule.copy_word, len))?;
+                    let offset = offset.as_usize();
+                    frame.memory[dest..dest + len].copy_from_slice(&frame.return_data[offset..offset + len]);
+                }
+                op::EXTCODEHASH => {
+                    frame.charge(schedule.ext_code)?;
+                    let address = word_address(frame.pop()?);
+                    let hash = if self.state.exists(&address) {
+                        self.state.code_hash(&address)
+                    } else {
+                        ZERO_HASH
+                    };
+                    frame.push(U256::from_big_endian(&hash))?;
+                }
+
+                op::BLOCKHASH => frame.unary(schedule.blockhash, |_| U256::zero())?,
+                op::COINBASE => frame.constant(schedule.base, address_word(&env.proposer))?,
+                op::TIMESTAMP => frame.constant(schedule.base, U256::from(env.timestamp / 1000))?,
+                op::NUMBER => frame.constant(schedule.base, U256::from(env.number))?,
+                op::PREVRANDAO => frame.constant(schedule.base, U256::zero())?,
+                op::GASLIMIT => frame.constant(schedule.base, U256::from(env.gas_limit))?,
+                op::CHAINID => frame.constant(schedule.base, U256::from(env.chain_id))?,
+                op::SELFBALANCE => {
+                    let balance = self.state.get_balance(&msg.address);
+                    frame.constant(schedule.low, U256::from(balance))?;
+                }
+                op::BASEFEE => frame.constant(schedule.base, U256::from(env.base_fee))?,
+
+                op::POP => {
+                    frame.charge(schedule.base)?;
+                    frame.pop()?;
+                }
+                op::MLOAD => {
+                    frame.charge(schedule.very_low)?;
+                    let offset = frame.pop()?;
+                    let (offset, _) = frame.expand(schedule, offset, U256::from(32))?;
+                    let word = U256::from_big_endian(&frame.memory[offset..offset + 32]);
+                    frame.push(word)?;
+                }
+                op::MSTORE => {
+                    frame.charge(schedule.very_low)?;
+                    let (offset, value) = (frame.pop()?, frame.pop()?);
+                    let (offset, _) = frame.expand(schedule, offset, U256::from(32))?;
+                    value.to_big_endian(&mut frame.memory[offset..offset + 32]);
+                }
+                op::MSTORE8 => {
+                    frame.charge(schedule.very_low)?;
+                    let (offset, value) = (frame.pop()?, frame.pop()?);
+                    let (offset, _) = frame.expand(schedule, offset, U256::one())?;
+                    frame.memory[offset] = value.byte(0);
+                }
+                op::SLOAD => {
+                    frame.charge(schedule.sload)?;
+                    let slot = word_hash(frame.pop()?);
+                    let value = self.state.get_storage(&msg.address, &slot);
+                    frame.push(U256::from_big_endian(&value))?;
+                }
+                op::SSTORE => {
+                    if msg.is_static {
+                        return Err(VmError::StaticViolation);
+                    }
+                    // EIP-2200: a call left with only the stipend cannot write
+                    if frame.meter.remaining() <= schedule.call_stipend {
+                        return Err(VmError::OutOfGas);
+                    }
+                    let (slot, value) = (word_hash(frame.pop()?), word_hash(frame.pop()?));
+                    let current = self.state.get_storage(&msg.address, &slot);
+                    let (cost, refund) = schedule.sstore_cost(&current, &value);
+                    frame.charge(cost)?;
+                    self.refund = self.refund.saturating_add(refund);
+                    self.state.set_storage(&msg.address, &slot, value);
+                }
+                op::JUMP => {
+                    frame.charge(schedule.mid)?;
+                    let target = frame.pop()?;
+                    frame.jump(target)?;
+                }
+                op::JUMPI => {
+                    frame.charge(schedule.high)?;
+                    let (target, condition) = (frame.pop()?, frame.pop()?);
+                    if !condition.is_zero() {
+                        frame.jump(target)?;
+                    }
+                }
+                op::PC => frame.constant(schedule.base, U256::from(frame.pc - 1))?,
+                op::MSIZE => frame.constant(schedule.base, U256::from(frame.memory.len()))?,
+                op::GAS => {
+                    frame.charge(schedule.base)?;
+                    frame.push(U256::from(frame.meter.remaining()))?;
+                }
+                op::JUMPDEST => frame.charge(schedule.jumpdest)?,
+                op::MCOPY => {
+                    let (dest, src, len) = (frame.pop()?, frame.pop()?, frame.pop()?);
+                    // Expanding to the larger end covers both ranges
+                    let end = if dest > src { dest } else { src };
+                    let (_, len) = frame.expand(schedule, end, len)?;
+                    frame.charge(word_cost(schedule.very_low, schedule.copy_word, len))?;
+                    if len > 0 {
+                        let (dest, src) = (dest.as_usize(), src.as_usize());
+                        frame.memory.copy_within(src..src + len, dest);
+                    }
+                }
+                op::PUSH0 => frame.constant(schedule.base, U256::zero())?,
+                op::PUSH1..=op::PUSH32 => {
+                    frame.charge(schedule.very_low)?;
+                    let size = (opcode - op::PUSH1 + 1) as usize;
+                    let mut word = [0u8; 32];
+                    let start = frame.pc.min(frame.code.len());
+                    let end = (frame.pc + size).min(frame.code.len());
+                    // Immediates cut off by the end of the code read as zero
+                    word[32 - size..32 - size + end - start].copy_from_slice(&frame.code[start..end]);
+                    frame.pc += size;
+                    frame.push(U256::from_big_endian(&word))?;
+                }
+                op::DUP1..=op::DUP16 => {
+                    frame.charge(schedule.very_low)?;
+                    let value = frame.peek((opcode - op::DUP1) as usize)?;
+                    frame.push(value)?;
+                }
+                op::SWAP1..=op::SWAP16 => {
+                    frame.charge(schedule.very_low)?;
+                    let depth = (opcode - op::SWAP1 + 1) as usize;
+                    let top = frame.stack.len();
+                    if depth >= top {
+                        return Err(VmError::StackUnderflow);
+                    }
+                    frame.stack.swap(top - 1, top - 1 - depth);
+                }
+                op::LOG0..=op::LOG4 => {
+                    if msg.is_static {
+                        return Err(VmError::StaticViolation);
+                    }
+                    let (offset, len) = (frame.pop()?, frame.pop()?);
+                    let topic_count = (opcode - op::LOG0) as usize;
+                    let topics = (0..topic_count)
+                        .map(|_| frame.pop().map(word_hash))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let (offset, len) = frame.expand(schedule, offset, len)?;
+                    frame.charge(schedule.log_cost(topic_count, len))?;
+                    self.logs.push(Log {
+                        address: msg.address,
+                        topics,
+                        data: frame.memory[offset..offset + len].to_vec(),
+                    });
+                }
+
+                op::CREATE | op::CREATE2 => self.create_op(msg, frame, opcode == op::CREATE2)?,
+                op::CALL | op::CALLCODE | op::DELEGATECALL | op::STATICCALL => {
+                    let kind = match opcode {
+                        op::CALL => CallKind::Call,
+                        op::CALLCODE => CallKind::CallCode,
+                        op::DELEGATECALL => CallKind::DelegateCall,
+                        _ => CallKind::StaticCall,
+                    };
+                    self.call_op(msg, frame, kind)?;
+                }
+                op::RETURN | op::REVERT => {
+                    let (offset, len) = (frame.pop()?, frame.pop()?);
+                    let (offset, len) = frame.expand(schedule, offset, len)?;
+                    let data = frame.memory[offset..offset + len].to_vec();
+                    let exit = if opcode == op::RETURN {
+                        ExitReason::Success
+                    } else {
+                        ExitReason::Revert
+                    };
+                    return Ok((exit, data));
+                }
+                op::SELFDESTRUCT => {
+                    if msg.is_static {
+                        return Err(VmError::StaticViolation);
+                    }
+                    let beneficiary = word_address(frame.pop()?);
+                    let balance = self.state.get_balance(&msg.address);
+                    let mut cost = schedule.selfdestruct;
+                    if balance > 0 && !self.state.exists(&beneficiary) {
+                        cost = cost.saturating_add(schedule.new_account);
+                    }
+                    frame.charge(cost)?;
+                    self.state
+                        .transfer(&msg.address, &beneficiary, balance)
+                        .map_err(|_| VmError::InsufficientBalance)?;
+                    return Ok((ExitReason::Success, Vec::new()));
+                }
+                _ => return Err(VmError::InvalidOpcode(opcode)),
+            }
+        }
+        Ok((ExitReason::Success, Vec::new()))
+    }
+
+    fn create_op(&mut self, msg: &Message, frame: &mut Frame, create2: bool) -> Result<(), VmError> {
+        if msg.is_static {
+            return Err(VmError::StaticViolation);
+        }
+        let schedule = &self.env.schedule;
+        let (value, offset, len) = (frame.pop()?, frame.pop()?, frame.pop()?);
+        let salt = if create2 { Some(word_hash(frame.pop()?)) } else { None };
+        let (offset, len) = frame.expand(schedule, offset, len)?;
+        if len > self.env.deployer.max_initcode_size() {
+            return Err(VmError::CodeSizeLimit);
+        }
+        let mut cost = schedule.create;
+        if create2 {
+            cost = word_cost(cost, schedule.keccak256_word, len);
+        }
+        frame.charge(cost)?;
+        frame.return_data.clear();
+
+        let value = u128::try_from(value).map_err(|_| VmError::InsufficientBalance);
+        let nonce = self.state.get_nonce(&msg.address);
+        let affordable = value
+            .as_ref()
+            .is_ok_and(|value| self.state.get_balance(&msg.address) >= *value);
+        if self.depth >= MAX_CALL_DEPTH || !affordable || nonce == u64::MAX {
+            return frame.push(U256::zero());
+        }
+        let value = value?;
+
+        self.state.set_nonce(&msg.address, nonce + 1);
+        let init_code = frame.memory[offset..offset + len].to_vec();
+        let address = match salt {
+            Some(salt) => create2_address(&msg.address, &salt, &keccak256(&init_code)),
+            None => create_address(&msg.address, nonce),
+        };
+        let remaining = frame.meter.remaining();
+        let gas = remaining - remaining / 64;
+        frame.charge(gas)?;
+
+        let child = Message {
+            kind: if create2 { CallKind::Create2 } else { CallKind::Create },
+            ..Message::create(msg.address, address, value, init_code, gas)
+        };
+        let output = self.nested(child);
+        frame.meter.return_gas(output.gas_left);
+        match output.created {
+            Some(created) => frame.push(address_word(&created)),
+            None => {
+                if output.exit == ExitReason::Revert {
+                    frame.return_data = output.output;
+                }
+                frame.push(U256::zero())
+            }
+        }
+    }
+
+    fn call_op(&mut self, msg: &Message, frame: &mut Frame, kind: CallKind) -> Result<(), VmError> {
+        let schedule = &self.env.schedule;
+        let requested = frame.pop()?;
+        let to = word_address(frame.pop()?);
+        let value = match kind {
+            CallKind::Call | CallKind::CallCode => frame.pop()?,
+            _ => U256::zero(),
+        };
+        let (in_offset, in_len, out_offset, out_len) = (frame.pop()?, frame.pop()?, frame.pop()?, frame.pop()?);
+        if kind == CallKind::Call && msg.is_static && !value.is_zero() {
+            return Err(VmError::StaticViolation);
+        }
+        let (in_offset, in_len) = frame.expand(schedule, in_offset, in_len)?;
+        let (out_offset, out_len) = frame.expand(schedule, out_offset, out_len)?;
+
+        let mut cost = schedule.call;
+        if !value.is_zero() {
+            cost = cost.saturating_add(schedule.call_value);
+            if kind == CallKind::Call && !self.state.exists(&to) {
+                cost = cost.saturating_add(schedule.new_account);
+            }
+        }
+        frame.charge(cost)?;
+
+        // Callers keep at least 1/64 of their gas (EIP-150)
+        let remaining = frame.meter.remaining();
+        let available = remaining - remaining / 64;
+        let gas = if requested > U256::from(available) {
+            available
+        } else {
+            requested.as_u64()
+        };
+        frame.charge(gas)?;
+        frame.return_data.clear();
+
+        let value = u128::try_from(value).map_err(|_| VmError::InsufficientBalance);
+        let affordable = match &value {
+            Ok(value) => {
+                *value == 0 || kind == CallKind::DelegateCall || self.state.get_balance(&msg.address) >= *value
+            }
+            Err(_) => false,
+        };
+        if self.depth >= MAX_CALL_DEPTH || !affordable {
+            frame.meter.return_gas(gas);
+            return frame.push(U256::zero());
+        }
+        let value = value?;
+        let stipend = if value > 0 { schedule.call_stipend } else { 0 };
+
+        let input = frame.memory[in_offset..in_offset + in_len].to_vec();
+        let child = match kind {
+            CallKind::Call => Message {
+                is_static: msg.is_static,
+                ..Message::call(msg.address, to, value, input, gas + stipend)
+            },
+            CallKind::CallCode => Message {
+                kind,
+                code_address: to,
+                is_static: msg.is_static,
+                ..Message::call(msg.address, msg.address, value, input, gas + stipend)
+            },
+            CallKind::DelegateCall => Message {
+                kind,
+                code_address: to,
+                is_static: msg.is_static,
+                ..Message::call(msg.caller, msg.address, msg.value, input, gas)
+            },
+            _ => Message {
+                kind,
+                is_static: true,
+                ..Message::call(msg.address, to, 0, input, gas)
+            },
+        };
+        let output = self.nested(child);
+        frame.meter.return_gas(output.gas_left);
+
+        let copied = out_len.min(output.output.len());
+        frame.memory[out_offset..out_offset + copied].copy_from_slice(&output.output[..copied]);
+        let success = output.is_success();
+        frame.return_data = output.output;
+        frame.push(flag(success))
+    }
+}
+
+/// Execution state of one call frame.
+struct Frame<'c> {
+    code: &'c [u8],
+    input: &'c [u8],
+    jumpdests: Vec<bool>,
+    pc: usize,
+    stack: Vec<U256>,
+    memory: Vec<u8>,
+    meter: GasMeter,
+    /// Output of the most recent sub-call or reverted create
+    return_data: Vec<u8>,
+}
+
+impl<'c> Frame<'c> {
+    fn new(code: &'c [u8], input: &'c [u8], gas: u64) -> Self {
+        Self {
+            code,
+            input,
+            jumpdests: jumpdests(code),
+            pc: 0,
+            stack: Vec::with_capacity(32),
+            memory: Vec::new(),
+            meter: GasMeter::new(gas),
+            return_data: Vec::new(),
+        }
+    }
+
+    fn charge(&mut self, gas: u64) -> Result<(), VmError> {
+        self.meter.charge(gas).map_err(|_| VmError::OutOfGas)
+    }
+
+    fn push(&mut self, value: U256) -> Result<(), VmError> {
+        if self.stack.len() >= STACK_LIMIT {
+            return Err(VmError::StackOverflow);
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<U256, VmError> {
+        self.stack.pop().ok_or(VmError::StackUnderflow)
+    }
+
+    /// Word `depth` below the top of the stack.
+    fn peek(&self, depth: usize) -> Result<U256, VmError> {
+        self.stack
+            .len()
+            .checked_sub(depth + 1)
+            .map(|index| self.stack[index])
+            .ok_or(VmError::StackUnderflow)
+    }
+
+    fn constant(&mut self, gas: u64, value: U256) -> Result<(), VmError> {
+        self.charge(gas)?;
+        self.push(value)
+    }
+
+    fn unary(&mut self, gas: u64, f: impl FnOnce(U256) -> U256) -> Result<(), VmError> {
+        self.charge(gas)?;
+        let a = self.pop()?;
+        self.push(f(a))
+    }
+
+    fn binary(&mut self, gas: u64, f: impl FnOnce(U256, U256) -> U256) -> Result<(), VmError> {
+        self.charge(gas)?;
+        let (a, b) = (self.pop()?, self.pop()?);
+        self.push(f(a, b))
+    }
+
+    fn jump(&mut self, target: U256) -> Result<(), VmError> {
+        if target >= U256::from(self.code.len()) || !self.jumpdests[target.as_usize()] {
+            return Err(VmError::InvalidJump);
+        }
+        self.pc = target.as_usize();
+        Ok(())
+    }
+
+    /// Grow memory to cover `len` bytes at `offset`, charging for the new
+    /// words. Returns the range as `usize`; an empty range never expands.
+    fn expand(&mut self, schedule: &GasSchedule, offset: U256, len: U256) -> Result<(usize, usize), VmError> {
+        if len.is_zero() {
+            return Ok((0, 0));
+        }
+        if offset > U256::from(MEMORY_LIMIT) || len > U256::from(MEMORY_LIMIT) {
+            return Err(VmError::OutOfGas);
+        }
+        let (offset, len) = (offset.as_u64(), len.as_u64());
+        let new_words = words((offset + len) as usize);
+        let old_words = (self.memory.len() / 32) as u64;
+        if new_words > old_words {
+            self.charge(schedule.memory_cost(new_words) - schedule.memory_cost(old_words))?;
+            self.memory.resize(new_words as usize * 32, 0);
+        }
+        Ok((offset as usize, len as usize))
+    }
+}
+
+/// Positions in `code` that hold a JUMPDEST opcode rather than push data.
+fn jumpdests(code: &[u8]) -> Vec<bool> {
+    let mut valid = vec![false; code.len()];
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = code[pc];
+        if opcode == op::JUMPDEST {
+            valid[pc] = true;
+        } else if (op::PUSH1..=op::PUSH32).contains(&opcode) {
+            pc += (opcode - op::PUSH1 + 1) as usize;
+        }
+        pc += 1;
+    }
+    valid
+}
+
+/// Copy `src` from `offset` into `dest`, zero filling past the end of `src`.
+fn copy_padded(dest: &mut [u8], src: &[u8], offset: U256) {
+    let start = if offset > U256::from(src.len()) {
+        src.len()
+    } else {
+        offset.as_usize()
+    };
+    let available = &src[start..];
+    let n = available.len().min(dest.len());
+    dest[..n].copy_from_slice(&available[..n]);
+    dest[n..].fill(0);
+}
+
+fn words(len: usize) -> u64 {
+    (len as u64).div_ceil(32)
+}
+
+/// `base` plus `per_word` for every word touched by `len` bytes.
+pub(super) fn word_cost(base: u64, per_word: u64, len: usize) -> u64 {
+    base.saturating_add(per_word.saturating_mul(words(len)))
+}
+
+fn flag(value: bool) -> U256 {
+    if value {
+        U256::one()
+    } else {
+        U256::zero()
+    }
+}
+
+fn truncate(value: U512) -> U256 {
+    let mut bytes = [0u8; 64];
+    value.to_big_endian(&mut bytes);
+    U256::from_big_endian(&bytes[32..])
+}
+
+fn word_hash(value: U256) -> H256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes
+}
+
+fn word_address(value: U256) -> Address {
+    Address::from_slice(&word_hash(value)[12..]).expect("20 byte slice")
+}
+
+fn address_word(address: &Address) -> U256 {
+    U256::from_big_endian(address.as_bytes())
+}
+
+fn is_negative(value: U256) -> bool {
+    value.bit(255)
+}
+
+/// Two's complement negation.
+fn negate(value: U256) -> U256 {
+    (!value).overflowing_add(U256::one()).0
+}
+
+fn abs(value: U256) -> U256 {
+    if is_negative(value) {
+        negate(value)
+    } else {
+        value
+    }
+}
+
+fn signed_lt(a: U256, b: U256) -> bool {
+    match (is_negative(a), is_negative(b)) {
+        (true, false) => true,
+        (false, true) => false,
+        _ => a < b,
+    }
+}
+
+fn signed_div(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return b;
+    }
+    // MIN / -1 wraps back to MIN, as abs and negate both leave MIN alone
+    let quotient = abs(a) / abs(b);
+    if is_negative(a) != is_negative(b) {
+        negate(quotient)
+    } else {
+        quotient
+    }
+}
+
+fn signed_mod(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return b;
+    }
+    let remainder = abs(a) % abs(b);
+    if is_negative(a) {
+        negate(remainder)
+    } else {
+        remainder
+    }
+}
+
+fn sign_extend(byte: U256, value: U256) -> U256 {
+    if byte >= U256::from(31) {
+        return value;
+    }
+    let bit = byte.as_usize() * 8 + 7;
+    let mask = (U256::one() << (bit + 1)) - U256::one();
+    if value.bit(bit) {
+        value | !mask
+    } else {
+        value & mask
+    }
+}
+
+fn arithmetic_shr(shift: U256, value: U256) -> U256 {
+    let negative = is_negative(value);
+    if shift >= U256::from(256) {
+        return if negative { U256::MAX } else { U256::zero() };
+    }
+    let shift = shift.as_usize();
+    if negative {
+        !((!value) >> shift)
+    } else {
+        value >> shift
+    }
+}
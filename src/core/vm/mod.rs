@@ -0,0 +1,151 @@
+pub mod interpreter;
+pub mod precompiles;
+pub mod wasm_vm;
+
+pub use interpreter::Evm;
+
+use crate::core::address::Address;
+
+/// Deepest nesting of calls and creates.
+pub const MAX_CALL_DEPTH: usize = 1024;
+
+/// Largest deployed contract (EIP-170), unless the node config sets
+/// another limit.
+pub const MAX_CODE_SIZE: usize = 24 * 1024;
+
+/// Largest init code accepted by a create (EIP-3860).
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind {
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+    Create,
+    Create2,
+}
+
+impl CallKind {
+    pub fn is_create(self) -> bool {
+        matches!(self, CallKind::Create | CallKind::Create2)
+    }
+}
+
+/// A call or create frame to execute.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub kind: CallKind,
+    pub caller: Address,
+    /// Account whose storage and balance the code runs against; the new
+    /// contract for creates
+    pub address: Address,
+    /// Account whose code runs; differs from `address` for DELEGATECALL and
+    /// CALLCODE
+    pub code_address: Address,
+    /// Ember moved from `caller` to `address`
+    pub value: u128,
+    /// Calldata, or init code for creates
+    pub input: Vec<u8>,
+    pub gas: u64,
+    pub is_static: bool,
+}
+
+impl Message {
+    /// Plain call from `caller` to `to`.
+    pub fn call(caller: Address, to: Address, value: u128, input: Vec<u8>, gas: u64) -> Self {
+        Self {
+            kind: CallKind::Call,
+            caller,
+            address: to,
+            code_address: to,
+            value,
+            input,
+            gas,
+            is_static: false,
+        }
+    }
+
+    /// Run `init_code` to deploy a contract at `address`.
+    pub fn create(caller: Address, address: Address, value: u128, init_code: Vec<u8>, gas: u64) -> Self {
+        Self {
+            kind: CallKind::Create,
+            caller,
+            address,
+            code_address: address,
+            value,
+            input: init_code,
+            gas,
+            is_static: false,
+        }
+    }
+}
+
+/// Why a frame stopped abnormally. All remaining gas is consumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum VmError {
+    #[error("out of gas")]
+    OutOfGas,
+    #[error("stack underflow")]
+    StackUnderflow,
+    #[error("stack overflow")]
+    StackOverflow,
+    #[error("invalid jump destination")]
+    InvalidJump,
+    #[error("invalid opcode 0x{0:02x}")]
+    InvalidOpcode(u8),
+    #[error("state change in static call")]
+    StaticViolation,
+    #[error("call depth exceeded")]
+    CallDepth,
+    #[error("insufficient balance for transfer")]
+    InsufficientBalance,
+    #[error("contract address already in use")]
+    CreateCollision,
+    #[error("contract code exceeds size limit")]
+    CodeSizeLimit,
+    #[error("contract code starts with 0xef")]
+    InvalidCodePrefix,
+    #[error("return data read out of bounds")]
+    ReturnDataOutOfBounds,
+    #[error("invalid wasm module")]
+    InvalidWasm,
+    #[error("wasm trap")]
+    WasmTrap,
+    #[error("precompile rejected its input")]
+    PrecompileFailed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExitReason {
+    Success,
+    /// REVERT: state changes are undone but unused gas is returned
+    Revert,
+    Halt(VmError),
+}
+
+/// Result of running a `Message`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallOutput {
+    pub exit: ExitReason,
+    pub gas_left: u64,
+    /// Return data, or revert data on `Revert`
+    pub output: Vec<u8>,
+    /// Address of the deployed contract, for successful creates
+    pub created: Option<Address>,
+}
+
+impl CallOutput {
+    pub fn is_success(&self) -> bool {
+        self.exit == ExitReason::Success
+    }
+
+    pub(crate) fn halt(error: VmError) -> Self {
+        Self {
+            exit: ExitReason::Halt(error),
+            gas_left: 0,
+            output: Vec::new(),
+            created: None,
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Precompiled contracts at reserved addresses.
+//!
+//! Calls to these addresses run native code instead of loading account
+//! code. Ethereum's ecrecover, sha256 and modexp keep their usual addresses
+//! so ported contracts work unchanged; TBURN's own precompiles start at
+//! `0x0100`. Both the interpreter and the Wasm runtime reach them through
+//! the shared call path.
+
+use lazy_static::lazy_static;
+use num_bigint::BigUint;
+use primitive_types::U256;
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use secp256k1::{Secp256k1, VerifyOnly};
+use sha2::{Digest, Sha256};
+
+use crate::core::address::Address;
+use crate::core::config::NetworkProfile;
+use crate::core::gas::GasSchedule;
+use crate::core::transaction::public_key_to_address;
+use crate::core::types::keccak256;
+use crate::core::vm::interpreter::word_cost;
+use crate::core::vm::{CallOutput, ExitReason, VmError};
+
+pub const ECRECOVER: Address = reserved(0x01);
+pub const SHA256: Address = reserved(0x02);
+pub const MODEXP: Address = reserved(0x05);
+pub const KECCAK256: Address = reserved(0x0100);
+pub const BLAKE3: Address = reserved(0x0101);
+pub const BECH32M: Address = reserved(0x0102);
+
+/// Networks by the byte that selects them in BECH32M input and output.
+const NETWORKS: [NetworkProfile; 3] = [NetworkProfile::Mainnet, NetworkProfile::Testnet, NetworkProfile::Devnet];
+
+lazy_static! {
+    static ref SECP256K1: Secp256k1<VerifyOnly> = Secp256k1::verification_only();
+}
+
+const fn reserved(index: u16) -> Address {
+    let mut address = [0u8; 20];
+    address[18] = (index >> 8) as u8;
+    address[19] = index as u8;
+    Address(address)
+}
+
+/// A native contract and its pricing.
+pub struct Precompile {
+    pub address: Address,
+    pub name: &'static str,
+    gas: fn(&GasSchedule, &[u8]) -> u64,
+    run: fn(&[u8]) -> Result<Vec<u8>, VmError>,
+}
+
+impl Precompile {
+    /// Gas charged for running on `input`.
+    pub fn gas(&self, schedule: &GasSchedule, input: &[u8]) -> u64 {
+        (self.gas)(schedule, input)
+    }
+
+    /// Charge for `input` out of `gas`, then run. Rejected input halts and
+    /// consumes all gas, like any other failed frame.
+    pub fn execute(&self, schedule: &GasSchedule, input: &[u8], gas: u64) -> CallOutput {
+        let cost = self.gas(schedule, input);
+        if cost > gas {
+            return CallOutput::halt(VmError::OutOfGas);
+        }
+        match (self.run)(input) {
+            Ok(output) => CallOutput {
+                exit: ExitReason::Success,
+                gas_left: gas - cost,
+                output,
+                created: None,
+            },
+            Err(err) => CallOutput::halt(err),
+        }
+    }
+}
+
+static PRECOMPILES: [Precompile; 6] = [
+    Precompile {
+        address: ECRECOVER,
+        name: "ecrecover",
+        gas: |schedule, _| schedule.ecrecover,
+        run: ecrecover,
+    },
+    Precompile {
+        address: SHA256,
+        name: "sha256",
+        gas: |schedule, input| word_cost(schedule.sha256, schedule.sha256_word, input.len()),
+        run: |input| Ok(Sha256::digest(input).to_vec()),
+    },
+    Precompile {
+        address: MODEXP,
+        name: "modexp",
+        gas: modexp_gas,
+        run: modexp,
+    },
+    Precompile {
+        address: KECCAK256,
+        name: "keccak256",
+        gas: |schedule, input| word_cost(schedule.keccak256, schedule.keccak256_word, input.len()),
+        run: |input| Ok(keccak256(input).to_vec()),
+    },
+    Precompile {
+        address: BLAKE3,
+        name: "blake3",
+        gas: |schedule, input| word_cost(schedule.blake3, schedule.blake3_word, input.len()),
+        run: |input| Ok(blake3::hash(input).as_bytes().to_vec()),
+    },
+    Precompile {
+        address: BECH32M,
+        name: "bech32m",
+        gas: |schedule, input| word_cost(schedule.bech32m, schedule.copy_word, input.len()),
+        run: bech32m,
+    },
+];
+
+/// Every registered precompile.
+pub fn all() -> &'static [Precompile] {
+    &PRECOMPILES
+}
+
+/// The precompile at `address`, if any.
+pub fn get(address: &Address) -> Option<&'static Precompile> {
+    if address.0[..18].iter().any(|b| *b != 0) {
+        return None;
+    }
+    PRECOMPILES.iter().find(|p| p.address == *address)
+}
+
+pub fn is_precompile(address: &Address) -> bool {
+    get(address).is_some()
+}
+
+/// `len` bytes of `input` from `offset`, zero-padded past the end.
+fn read(input: &[u8], offset: u64, len: usize) -> Vec<u8> {
+    let mut out = vec![0u8; len];
+    if let Ok(offset) = usize::try_from(offset) {
+        if offset < input.len() {
+            let available = (input.len() - offset).min(len);
+            out[..available].copy_from_slice(&input[offset..offset + available]);
+        }
+    }
+    out
+}
+
+/// Input is `hash || v || r || s`, one word each, with `v` 27 or 28.
+/// Returns the signer as a word, or nothing if the signature is invalid.
+fn ecrecover(input: &[u8]) -> Result<Vec<u8>, VmError> {
+    let input = read(input, 0, 128);
+    let v = &input[32..64];
+    if v[..31].iter().any(|b| *b != 0) || !(27..=28).contains(&v[31]) {
+        return Ok(Vec::new());
+    }
+    let recovery_id = RecoveryId::from_i32(v[31] as i32 - 27).expect("0 or 1");
+    let message = secp256k1::Message::from_slice(&input[..32]).expect("32-byte digest");
+    let public_key = RecoverableSignature::from_compact(&input[64..], recovery_id)
+        .and_then(|signature| SECP256K1.recover_ecdsa(&message, &signature));
+    Ok(match public_key {
+        Ok(public_key) => {
+            let mut word = vec![0u8; 32];
+            word[12..].copy_from_slice(&public_key_to_address(&public_key).0);
+            word
+        }
+        Err(_) => Vec::new(),
+    })
+}
+
+/// Base, exponent and modulus lengths from the first three words, saturated
+/// to `u64::MAX` so oversized requests price themselves out.
+fn modexp_lengths(input: &[u8]) -> (u64, u64, u64) {
+    let length = |index: u64| {
+        let word = U256::from_big_endian(&read(input, index * 32, 32));
+        if word > U256::from(u64::MAX) {
+            u64::MAX
+        } else {
+            word.as_u64()
+        }
+    };
+    (length(0), length(1), length(2))
+}
+
+/// EIP-2565 pricing.
+fn modexp_gas(schedule: &GasSchedule, input: &[u8]) -> u64 {
+    let (base_len, exp_len, mod_len) = modexp_lengths(input);
+    let words = base_len.max(mod_len).saturating_add(7) / 8;
+    let complexity = words.saturating_mul(words);
+
+    let head_len = exp_len.min(32) as usize;
+    let head = U256::from_big_endian(&read(input, 96u64.saturating_add(base_len), head_len));
+    let head_bits = (head.bits() as u64).saturating_sub(1);
+    let iterations = if exp_len <= 32 {
+        head_bits
+    } else {
+        (exp_len - 32).saturating_mul(8).saturating_add(head_bits)
+    };
+    let cost = complexity.saturating_mul(iterations.max(1)) / schedule.modexp_divisor;
+    cost.max(schedule.modexp_min)
+}
+
+/// Input is the three lengths followed by base, exponent and modulus,
+/// big-endian. Returns `base ** exp % modulus` padded to the modulus length.
+fn modexp(input: &[u8]) -> Result<Vec<u8>, VmError> {
+    let (base_len, exp_len, mod_len) = modexp_lengths(input);
+    if mod_len == 0 {
+        return Ok(Vec::new());
+    }
+    // Gas has already been charged, which bounds all three lengths
+    let base = read(input, 96, base_len as usize);
+    let exp = read(input, 96u64.saturating_add(base_len), exp_len as usize);
+    let modulus = BigUint::from_bytes_be(&read(
+        input,
+        96u64.saturating_add(base_len).saturating_add(exp_len),
+        mod_len as usize,
+    ));
+
+    let mut output = vec![0u8; mod_len as usize];
+    if modulus.bits() == 0 {
+        return Ok(output);
+    }
+    let result = BigUint::from_bytes_be(&base)
+        .modpow(&BigUint::from_bytes_be(&exp), &modulus)
+        .to_bytes_be();
+    let start = output.len() - result.len();
+    output[start..].copy_from_slice(&result);
+    Ok(output)
+}
+
+/// `0x00 || network || address` encodes the 20-byte address as a Bech32m
+/// string; `0x01 || string` decodes one back to `network || address`.
+/// Networks are numbered mainnet, testnet, devnet from zero.
+fn bech32m(input: &[u8]) -> Result<Vec<u8>, VmError> {
+    match input.split_first() {
+        Some((0, rest)) if rest.len() == 21 => {
+            let network = NETWORKS.get(rest[0] as usize).ok_or(VmError::PrecompileFailed)?;
+            let address = Address::from_slice(&rest[1..]).map_err(|_| VmError::PrecompileFailed)?;
+            Ok(address.to_bech32(*network).into_bytes())
+        }
+        Some((1, rest)) => {
+            let text = std::str::from_utf8(rest).map_err(|_| VmError::PrecompileFailed)?;
+            let (network, address) = Address::from_bech32(text).map_err(|_| VmError::PrecompileFailed)?;
+            let index = NETWORKS.iter().position(|n| *n == network).expect("known network");
+            let mut output = vec![index as u8];
+            output.extend_from_slice(&address.0);
+            Ok(output)
+        }
+        _ => Err(VmError::PrecompileFailed),
+    }
+}
@@ -0,0 +1,617 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use wasmi::core::{HostError, TrapCode};
+use wasmi::errors::{MemoryError, TableError};
+use wasmi::{
+    Caller, Config, Engine, Error, Extern, Linker, Memory, Module, ResourceLimiter, Store, StoreLimits,
+    StoreLimitsBuilder,
+};
+
+use crate::core::address::Address;
+use crate::core::receipt::Log;
+use crate::core::types::{keccak256, H256};
+use crate::core::vm::interpreter::Evm;
+use crate::core::vm::{CallOutput, ExitReason, Message, VmError, MAX_CALL_DEPTH};
+
+/// Every Wasm module starts with these bytes; code that does is run by the
+/// Wasm runtime instead of the bytecode interpreter.
+pub const WASM_MAGIC: [u8; 4] = *b"\0asm";
+
+/// Import module the host functions are provided under.
+pub const HOST_MODULE: &str = "env";
+
+/// Export run for every call. Takes no parameters and returns nothing;
+/// calldata and return data go through host functions.
+pub const CALL_EXPORT: &str = "call";
+
+/// Optional export run once when the contract is deployed.
+pub const DEPLOY_EXPORT: &str = "deploy";
+
+/// Largest linear memory a contract may use (64 pages).
+pub const MAX_MEMORY_BYTES: usize = 4 * 1024 * 1024;
+
+/// Size of a Wasm memory page.
+const PAGE_BYTES: usize = 64 * 1024;
+
+/// Most topics `emit_event` accepts, as for LOG4.
+pub const MAX_TOPICS: u32 = 4;
+
+/// Compiled modules kept in memory, oldest dropped first.
+pub const MODULE_CACHE_SIZE: usize = 256;
+
+lazy_static! {
+    static ref ENGINE: Engine = {
+        let mut config = Config::default();
+        // Floats are the only nondeterministic part of Wasm; modules using
+        // them fail validation
+        config.floats(false).consume_fuel(true);
+        Engine::new(&config)
+    };
+    static ref MODULES: Mutex<ModuleCache> = Mutex::new(ModuleCache::default());
+}
+
+/// Compiled modules by code hash. Gas does not depend on whether a module
+/// was cached, so the cache is local to each node.
+#[derive(Default)]
+struct ModuleCache {
+    modules: HashMap<H256, Arc<Module>>,
+    order: VecDeque<H256>,
+}
+
+/// Compile `code`, or reuse the module compiled for the same code earlier.
+fn compile(code: &[u8]) -> Result<Arc<Module>, VmError> {
+    let hash = keccak256(code);
+    if let Some(module) = MODULES.lock().modules.get(&hash) {
+        return Ok(module.clone());
+    }
+    let module = Arc::new(Module::new(&ENGINE, code).map_err(|_| VmError::InvalidWasm)?);
+    let mut cache = MODULES.lock();
+    if cache.modules.insert(hash, module.clone()).is_none() {
+        cache.order.push_back(hash);
+        if cache.order.len() > MODULE_CACHE_SIZE {
+            let oldest = cache.order.pop_front().expect("cache is not empty");
+            cache.modules.remove(&oldest);
+        }
+    }
+    Ok(module)
+}
+
+/// Gas charged before a module runs: its code size and initial memory.
+/// Memory grown later is charged by `Limits`.
+fn load_cost(evm: &Evm<'_>, module: &Module, code: &[u8]) -> u64 {
+    let schedule = &evm.env.schedule;
+    let pages: u64 = module
+        .exports()
+        .filter_map(|export| export.ty().memory().map(|ty| u32::from(ty.initial_pages()) as u64))
+        .sum();
+    (code.len() as u64)
+        .saturating_mul(schedule.wasm_code_byte)
+        .saturating_add(pages.saturating_mul(schedule.wasm_memory_page))
+}
+
+pub fn is_wasm(code: &[u8]) -> bool {
+    code.starts_with(&WASM_MAGIC)
+}
+
+/// Check that `code` is a deployable contract: it must validate, export its
+/// memory and the `call` entry point.
+pub fn validate(code: &[u8]) -> Result<(), VmError> {
+    let module = compile(code)?;
+    let mut memory = false;
+    let mut call = false;
+    for export in module.exports() {
+        match (export.name(), export.ty()) {
+            ("memory", wasmi::ExternType::Memory(_)) => memory = true,
+            (CALL_EXPORT, wasmi::ExternType::Func(ty)) => {
+                call = ty.params().is_empty() && ty.results().is_empty();
+            }
+            _ => {}
+        }
+    }
+    if memory && call {
+        Ok(())
+    } else {
+        Err(VmError::InvalidWasm)
+    }
+}
+
+/// Run the `call` export of the module `code` for `msg`.
+pub(super) fn call(evm: &mut Evm<'_>, msg: &Message, code: &[u8]) -> CallOutput {
+    run(evm, msg, code, &msg.input, CALL_EXPORT)
+}
+
+/// Validate the module in `msg.input` and run its `deploy` export, if it has
+/// one. On success the output is the module, which becomes the contract code.
+pub(super) fn deploy(evm: &mut Evm<'_>, msg: &Message) -> CallOutput {
+    if let Err(error) = validate(&msg.input) {
+        return CallOutput::halt(error);
+    }
+    let mut output = run(evm, msg, &msg.input, &[], DEPLOY_EXPORT);
+    if output.is_success() {
+        output.output = msg.input.clone();
+    }
+    output
+}
+
+/// Host side of one Wasm frame.
+struct Host<'e, 'a> {
+    evm: &'e mut Evm<'a>,
+    msg: &'e Message,
+    input: &'e [u8],
+    fuel_per_gas: u64,
+    output: Vec<u8>,
+    /// Output of the most recent cross-contract call
+    return_data: Vec<u8>,
+    limits: Limits,
+}
+
+/// Store limits that also meter memory growth. The growth hook cannot
+/// touch fuel, so pages granted are owed as gas and paid by the next host
+/// call or when the frame ends. Growth the gas left at the last payment
+/// could not cover is refused.
+struct Limits {
+    store: StoreLimits,
+    /// Gas per page grown; zero while instantiating, as `load_cost` covers
+    /// the initial pages
+    page_gas: u64,
+    /// Gas left when owed gas was last paid
+    budget: u64,
+    /// Gas owed for pages grown since
+    owed: u64,
+}
+
+impl ResourceLimiter for Limits {
+    fn memory_growing(&mut self, current: usize, desired: usize, maximum: Option<usize>) -> Result<bool, MemoryError> {
+        if !self.store.memory_growing(current, desired, maximum)? {
+            return Ok(false);
+        }
+        let pages = (desired.saturating_sub(current) / PAGE_BYTES) as u64;
+        let owed = self.owed.saturating_add(pages.saturating_mul(self.page_gas));
+        if owed > self.budget {
+            return Ok(false);
+        }
+        self.owed = owed;
+        Ok(true)
+    }
+
+    fn table_growing(&mut self, current: u32, desired: u32, maximum: Option<u32>) -> Result<bool, TableError> {
+        self.store.table_growing(current, desired, maximum)
+    }
+
+    fn instances(&self) -> usize {
+        self.store.instances()
+    }
+
+    fn tables(&self) -> usize {
+        self.store.tables()
+    }
+
+    fn memories(&self) -> usize {
+        self.store.memories()
+    }
+}
+
+/// Raised by the `revert` host function to unwind the contract.
+#[derive(Debug)]
+struct Reverted;
+
+impl std::fmt::Display for Reverted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("contract reverted")
+    }
+}
+
+impl HostError for Reverted {}
+
+impl HostError for VmError {}
+
+fn limiter<'h>(host: &'h mut Host<'_, '_>) -> &'h mut dyn ResourceLimiter {
+    &mut host.limits
+}
+
+fn run(evm: &mut Evm<'_>, msg: &Message, code: &[u8], input: &[u8], export: &str) -> CallOutput {
+    let fuel_per_gas = evm.env.schedule.wasm_fuel_per_gas.max(1);
+    let module = match compile(code) {
+        Ok(module) => module,
+        Err(error) => return CallOutput::halt(error),
+    };
+    let gas = match msg.gas.checked_sub(load_cost(evm, &module, code)) {
+        Some(gas) => gas,
+        None => return CallOutput::halt(VmError::OutOfGas),
+    };
+    let limits = Limits {
+        store: StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY_BYTES)
+            .instances(1)
+            .memories(1)
+            .tables(1)
+            .build(),
+        page_gas: 0,
+        budget: gas,
+        owed: 0,
+    };
+    let page_gas = evm.env.schedule.wasm_memory_page;
+    let host = Host {
+        evm,
+        msg,
+        input,
+        fuel_per_gas,
+        output: Vec::new(),
+        return_data: Vec::new(),
+        limits,
+    };
+    let mut store = Store::new(&ENGINE, host);
+    store.limiter(limiter);
+    store
+        .set_fuel(gas.saturating_mul(fuel_per_gas))
+        .expect("engine meters fuel");
+
+    let result = linker()
+        .instantiate(&mut store, &module)
+        .and_then(|pre| {
+            store.data_mut().limits.page_gas = page_gas;
+            pre.start(&mut store)
+        })
+        .and_then(|instance| match instance.get_typed_func::<(), ()>(&store, export) {
+            Ok(entry) => entry.call(&mut store, ()),
+            // Only `deploy` is optional, and `validate` checked for `call`
+            Err(_) => Ok(()),
+        });
+
+    let exit = match result {
+        Ok(()) => ExitReason::Success,
+        Err(error) if error.downcast_ref::<Reverted>().is_some() => ExitReason::Revert,
+        Err(error) => return CallOutput::halt(halt_reason(&error)),
+    };
+    let gas_left = store.get_fuel().expect("engine meters fuel") / fuel_per_gas;
+    let host = store.into_data();
+    // Memory grown since the last host call is paid for last
+    match gas_left.checked_sub(host.limits.owed) {
+        Some(gas_left) => CallOutput {
+            exit,
+            gas_left,
+            output: host.output,
+            created: None,
+        },
+        None => CallOutput::halt(VmError::OutOfGas),
+    }
+}
+
+fn halt_reason(error: &Error) -> VmError {
+    if let Some(reason) = error.downcast_ref::<VmError>() {
+        return *reason;
+    }
+    match error.as_trap_code() {
+        Some(TrapCode::OutOfFuel) => VmError::OutOfGas,
+        Some(TrapCode::StackOverflow) => VmError::StackOverflow,
+        Some(_) => VmError::WasmTrap,
+        None => VmError::InvalidWasm,
+    }
+}
+
+fn linker<'e, 'a>() -> Linker<Host<'e, 'a>> {
+    let mut linker = Linker::new(&ENGINE);
+    linker
+        .func_wrap(HOST_MODULE, "input_size", input_size)
+        .and_then(|l| l.func_wrap(HOST_MODULE, "input_copy", input_copy))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "set_return", set_return))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "revert", revert))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "storage_read", storage_read))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "storage_write", storage_write))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "caller", caller))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "address", address))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "origin", origin))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "value", value))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "balance", balance))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "block_number", block_number))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "timestamp", timestamp))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "chain_id", chain_id))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "gas_left", gas_left))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "emit_event", emit_event))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "call", call_contract))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "return_data_size", return_data_size))
+        .and_then(|l| l.func_wrap(HOST_MODULE, "return_data_copy", return_data_copy))
+        .expect("host function names are unique");
+    linker
+}
+
+type HostCaller<'c, 'e, 'a> = Caller<'c, Host<'e, 'a>>;
+
+fn remaining_gas(caller: &HostCaller<'_, '_, '_>) -> u64 {
+    caller.get_fuel().expect("engine meters fuel") / caller.data().fuel_per_gas
+}
+
+/// Burn `gas` worth of fuel, plus the gas owed for grown memory, trapping
+/// with out of gas if there is not enough.
+fn charge(caller: &mut HostCaller<'_, '_, '_>, gas: u64) -> Result<(), Error> {
+    let owed = std::mem::take(&mut caller.data_mut().limits.owed);
+    let fuel = caller.get_fuel().expect("engine meters fuel");
+    let cost = gas.saturating_add(owed).saturating_mul(caller.data().fuel_per_gas);
+    if cost > fuel {
+        caller.set_fuel(0).expect("engine meters fuel");
+        return Err(Error::host(VmError::OutOfGas));
+    }
+    caller.set_fuel(fuel - cost).expect("engine meters fuel");
+    caller.data_mut().limits.budget = remaining_gas(caller);
+    Ok(())
+}
+
+fn return_fuel(caller: &mut HostCaller<'_, '_, '_>, gas: u64) {
+    let fuel = caller.get_fuel().expect("engine meters fuel");
+    let refund = gas.saturating_mul(caller.data().fuel_per_gas);
+    caller
+        .set_fuel(fuel.saturating_add(refund))
+        .expect("engine meters fuel");
+    caller.data_mut().limits.budget = remaining_gas(caller);
+}
+
+fn copy_cost(caller: &HostCaller<'_, '_, '_>, len: usize) -> u64 {
+    let schedule = &caller.data().evm.env.schedule;
+    schedule
+        .very_low
+        .saturating_add(schedule.copy_word.saturating_mul((len as u64).div_ceil(32)))
+}
+
+fn memory(caller: &HostCaller<'_, '_, '_>) -> Result<Memory, Error> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| Error::host(VmError::InvalidWasm))
+}
+
+/// Read `len` bytes at `ptr`, bounds checked before anything is allocated.
+fn read(caller: &HostCaller<'_, '_, '_>, ptr: u32, len: u32) -> Result<Vec<u8>, Error> {
+    let memory = memory(caller)?;
+    let (ptr, len) = (ptr as usize, len as usize);
+    let data = memory.data(caller);
+    data.get(ptr..ptr.saturating_add(len))
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| Error::from(TrapCode::MemoryOutOfBounds))
+}
+
+fn read_array<const N: usize>(caller: &HostCaller<'_, '_, '_>, ptr: u32) -> Result<[u8; N], Error> {
+    Ok(read(caller, ptr, N as u32)?.try_into().expect("read returns N bytes"))
+}
+
+fn write(caller: &mut HostCaller<'_, '_, '_>, ptr: u32, bytes: &[u8]) -> Result<(), Error> {
+    memory(caller)?
+        .write(caller, ptr as usize, bytes)
+        .map_err(|_| Error::from(TrapCode::MemoryOutOfBounds))
+}
+
+fn ensure_mutable(caller: &HostCaller<'_, '_, '_>) -> Result<(), Error> {
+    if caller.data().msg.is_static {
+        return Err(Error::host(VmError::StaticViolation));
+    }
+    Ok(())
+}
+
+fn input_size(mut caller: HostCaller<'_, '_, '_>) -> Result<u32, Error> {
+    let cost = caller.data().evm.env.schedule.base;
+    charge(&mut caller, cost)?;
+    Ok(caller.data().input.len() as u32)
+}
+
+fn input_copy(mut caller: HostCaller<'_, '_, '_>, dest: u32) -> Result<(), Error> {
+    let input = caller.data().input;
+    let cost = copy_cost(&caller, input.len());
+    charge(&mut caller, cost)?;
+    write(&mut caller, dest, input)
+}
+
+fn set_return(mut caller: HostCaller<'_, '_, '_>, ptr: u32, len: u32) -> Result<(), Error> {
+    let cost = copy_cost(&caller, len as usize);
+    charge(&mut caller, cost)?;
+    caller.data_mut().output = read(&caller, ptr, len)?;
+    Ok(())
+}
+
+/// Stop with `len` bytes at `ptr` as revert data, undoing the frame.
+fn revert(caller: HostCaller<'_, '_, '_>, ptr: u32, len: u32) -> Result<(), Error> {
+    set_return(caller, ptr, len)?;
+    Err(Error::host(Reverted))
+}
+
+fn storage_read(mut caller: HostCaller<'_, '_, '_>, key: u32, dest: u32) -> Result<(), Error> {
+    let cost = caller.data().evm.env.schedule.sload;
+    charge(&mut caller, cost)?;
+    let slot = read_array::<32>(&caller, key)?;
+    let host = caller.data();
+    let value = host.evm.state.get_storage(&host.msg.address, &slot);
+    write(&mut caller, dest, &value)
+}
+
+fn storage_write(mut caller: HostCaller<'_, '_, '_>, key: u32, value: u32) -> Result<(), Error> {
+    ensure_mutable(&caller)?;
+    let schedule = caller.data().evm.env.schedule;
+    // Pay for grown memory first; as for SSTORE, a frame left with only
+    // the call stipend cannot write
+    charge(&mut caller, 0)?;
+    if remaining_gas(&caller) <= schedule.call_stipend {
+        return Err(Error::host(VmError::OutOfGas));
+    }
+    let slot = read_array::<32>(&caller, key)?;
+    let value = read_array::<32>(&caller, value)?;
+    let host = caller.data();
+    let current = host.evm.state.get_storage(&host.msg.address, &slot);
+    let (cost, refund) = schedule.sstore_cost(&current, &value);
+    charge(&mut caller, cost)?;
+
+    let host = caller.data_mut();
+    host.evm.refund = host.evm.refund.saturating_add(refund);
+    let address = host.msg.address;
+    host.evm.state.set_storage(&address, &slot, value);
+    Ok(())
+}
+
+fn write_address(
+    mut caller: HostCaller<'_, '_, '_>,
+    dest: u32,
+    address: impl FnOnce(&Host<'_, '_>) -> Address,
+) -> Result<(), Error> {
+    let cost = caller.data().evm.env.schedule.base;
+    charge(&mut caller, cost)?;
+    let address = address(caller.data());
+    write(&mut caller, dest, address.as_bytes())
+}
+
+fn caller(caller: HostCaller<'_, '_, '_>, dest: u32) -> Result<(), Error> {
+    write_address(caller, dest, |host| host.msg.caller)
+}
+
+fn address(caller: HostCaller<'_, '_, '_>, dest: u32) -> Result<(), Error> {
+    write_address(caller, dest, |host| host.msg.address)
+}
+
+fn origin(caller: HostCaller<'_, '_, '_>, dest: u32) -> Result<(), Error> {
+    write_address(caller, dest, |host| host.evm.origin)
+}
+
+/// Writes the Ember sent with the call as a little-endian u128.
+fn value(mut caller: HostCaller<'_, '_, '_>, dest: u32) -> Result<(), Error> {
+    let cost = caller.data().evm.env.schedule.base;
+    charge(&mut caller, cost)?;
+    let value = caller.data().msg.value;
+    write(&mut caller, dest, &value.to_le_bytes())
+}
+
+/// Writes the balance of the account at `account` as a little-endian u128.
+fn balance(mut caller: HostCaller<'_, '_, '_>, account: u32, dest: u32) -> Result<(), Error> {
+    let cost = caller.data().evm.env.schedule.balance;
+    charge(&mut caller, cost)?;
+    let account = Address(read_array::<20>(&caller, account)?);
+    let balance = caller.data().evm.state.get_balance(&account);
+    write(&mut caller, dest, &balance.to_le_bytes())
+}
+
+fn context(mut caller: HostCaller<'_, '_, '_>, value: impl FnOnce(&Host<'_, '_>) -> u64) -> Result<u64, Error> {
+    let cost = caller.data().evm.env.schedule.base;
+    charge(&mut caller, cost)?;
+    Ok(value(caller.data()))
+}
+
+fn block_number(caller: HostCaller<'_, '_, '_>) -> Result<u64, Error> {
+    context(caller, |host| host.evm.env.number)
+}
+
+/// Block timestamp in seconds, as TIMESTAMP.
+fn timestamp(caller: HostCaller<'_, '_, '_>) -> Result<u64, Error> {
+    context(caller, |host| host.evm.env.timestamp / 1000)
+}
+
+fn chain_id(caller: HostCaller<'_, '_, '_>) -> Result<u64, Error> {
+    context(caller, |host| host.evm.env.chain_id)
+}
+
+fn gas_left(mut caller: HostCaller<'_, '_, '_>) -> Result<u64, Error> {
+    let cost = caller.data().evm.env.schedule.base;
+    charge(&mut caller, cost)?;
+    Ok(remaining_gas(&caller))
+}
+
+/// Emit a log with `topic_count` 32-byte topics stored contiguously at
+/// `topics`.
+fn emit_event(
+    mut caller: HostCaller<'_, '_, '_>,
+    topics: u32,
+    topic_count: u32,
+    data: u32,
+    data_len: u32,
+) -> Result<(), Error> {
+    ensure_mutable(&caller)?;
+    if topic_count > MAX_TOPICS {
+        return Err(Error::host(VmError::WasmTrap));
+    }
+    let cost = caller
+        .data()
+        .evm
+        .env
+        .schedule
+        .log_cost(topic_count as usize, data_len as usize);
+    charge(&mut caller, cost)?;
+    let raw = read(&caller, topics, topic_count * 32)?;
+    let topics = raw
+        .chunks_exact(32)
+        .map(|topic| topic.try_into().expect("32 byte chunk"))
+        .collect();
+    let data = read(&caller, data, data_len)?;
+    let host = caller.data_mut();
+    let address = host.msg.address;
+    host.evm.logs.push(Log { address, topics, data });
+    Ok(())
+}
+
+/// Call the contract at `to` (20 bytes), sending the little-endian u128 at
+/// `value`. Returns 0 on success, 1 if the callee reverted and 2 if it
+/// failed; its output is then available through `return_data_copy`.
+fn call_contract(
+    mut caller: HostCaller<'_, '_, '_>,
+    to: u32,
+    value: u32,
+    input: u32,
+    input_len: u32,
+    gas: u64,
+) -> Result<u32, Error> {
+    let schedule = caller.data().evm.env.schedule;
+    let to = Address(read_array::<20>(&caller, to)?);
+    let value = u128::from_le_bytes(read_array::<16>(&caller, value)?);
+    if value > 0 {
+        ensure_mutable(&caller)?;
+    }
+
+    let mut cost = schedule.call.saturating_add(copy_cost(&caller, input_len as usize));
+    if value > 0 {
+        cost = cost.saturating_add(schedule.call_value);
+        if !caller.data().evm.state.exists(&to) {
+            cost = cost.saturating_add(schedule.new_account);
+        }
+    }
+    charge(&mut caller, cost)?;
+    let input = read(&caller, input, input_len)?;
+
+    // Callers keep at least 1/64 of their gas, as for CALL
+    let remaining = remaining_gas(&caller);
+    let gas = gas.min(remaining - remaining / 64);
+    charge(&mut caller, gas)?;
+    caller.data_mut().return_data.clear();
+
+    let host = caller.data_mut();
+    let address = host.msg.address;
+    if host.evm.depth >= MAX_CALL_DEPTH || host.evm.state.get_balance(&address) < value {
+        return_fuel(&mut caller, gas);
+        return Ok(2);
+    }
+    let stipend = if value > 0 { schedule.call_stipend } else { 0 };
+    let child = Message {
+        is_static: host.msg.is_static,
+        ..Message::call(address, to, value, input, gas + stipend)
+    };
+    let output = host.evm.nested(child);
+    return_fuel(&mut caller, output.gas_left);
+
+    let status = match output.exit {
+        ExitReason::Success => 0,
+        ExitReason::Revert => 1,
+        ExitReason::Halt(_) => 2,
+    };
+    caller.data_mut().return_data = output.output;
+    Ok(status)
+}
+
+fn return_data_size(mut caller: HostCaller<'_, '_, '_>) -> Result<u32, Error> {
+    let cost = caller.data().evm.env.schedule.base;
+    charge(&mut caller, cost)?;
+    Ok(caller.data().return_data.len() as u32)
+}
+
+fn return_data_copy(mut caller: HostCaller<'_, '_, '_>, dest: u32) -> Result<(), Error> {
+    let data = std::mem::take(&mut caller.data_mut().return_data);
+    let cost = copy_cost(&caller, data.len());
+    charge(&mut caller, cost)?;
+    let written = write(&mut caller, dest, &data);
+    caller.data_mut().return_data = data;
+    written
+}
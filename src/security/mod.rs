@@ -0,0 +1,8 @@
+pub mod audit;
+pub mod contract_security;
+pub mod crypto;
+pub mod hashing;
+pub mod key_management;
+pub mod rate_limiter;
+pub mod signature;
+pub mod vulnerability_scanner;
@@ -0,0 +1,332 @@
+//! Read cache in front of a `KvStore`.
+//!
+//! `CachedStore` keeps three size-bounded LRU tiers, so large contract code
+//! cannot push out the trie nodes every state read walks through:
+//!
+//! - trie nodes, by hash
+//! - decoded account records, by state root and address
+//! - contract code, by code hash
+//!
+//! Every key names content that never changes: a node or code hash, or a
+//! state root. Committing a block or switching to another fork therefore
+//! never makes an entry wrong, it only adds new roots. What can go stale is
+//! data the pruner deletes; deletions pass through `write`, which evicts
+//! them, and drop cached accounts because their tries may be incomplete.
+//! Puts store the content their key names, so cached entries stay valid.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use crate::core::account::Account;
+use crate::core::address::Address;
+use crate::core::config::NodeConfig;
+use crate::core::state::account_key;
+use crate::core::types::H256;
+use crate::storage::rocksdb::{BatchOp, Column, Entry, KvStore, StorageError, WriteBatch};
+use crate::storage::trie;
+
+/// Bytes charged for an account record, including its key.
+const ACCOUNT_WEIGHT: usize = 32 + 20 + 8 + 16 + 32 + 32;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+impl CacheStats {
+    /// Share of lookups served from the cache, from 0 to 1.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Slot<V> {
+    value: V,
+    weight: usize,
+    last_used: u64,
+}
+
+#[derive(Debug)]
+struct LruInner<K, V> {
+    slots: HashMap<K, Slot<V>>,
+    /// Keys by the tick they were last used at, oldest first
+    order: BTreeMap<u64, K>,
+    tick: u64,
+    bytes: usize,
+}
+
+/// Least recently used cache bounded by the total weight of its entries.
+#[derive(Debug)]
+pub struct LruCache<K, V> {
+    inner: Mutex<LruInner<K, V>>,
+    capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> LruCache<K, V> {
+    /// Cache holding entries weighing up to `capacity` in total.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(LruInner {
+                slots: HashMap::new(),
+                order: BTreeMap::new(),
+                tick: 0,
+                bytes: 0,
+            }),
+            capacity,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut inner = self.inner.lock();
+        let inner = &mut *inner;
+        inner.tick += 1;
+        let Some(slot) = inner.slots.get_mut(key) else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        inner.order.remove(&slot.last_used);
+        slot.last_used = inner.tick;
+        inner.order.insert(inner.tick, key.clone());
+        Some(slot.value.clone())
+    }
+
+    /// Cache `value`, evicting the least recently used entries to make
+    /// room. Values heavier than the whole cache are not kept.
+    pub fn insert(&self, key: K, value: V, weight: usize) {
+        if weight > self.capacity {
+            return;
+        }
+        let mut inner = self.inner.lock();
+        Self::take(&mut inner, &key);
+        while inner.bytes + weight > self.capacity {
+            let Some((_, oldest)) = inner.order.pop_first() else {
+                break;
+            };
+            if let Some(slot) = inner.slots.remove(&oldest) {
+                inner.bytes -= slot.weight;
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        inner.tick += 1;
+        let tick = inner.tick;
+        inner.order.insert(tick, key.clone());
+        inner.bytes += weight;
+        inner.slots.insert(
+            key,
+            Slot {
+                value,
+                weight,
+                last_used: tick,
+            },
+        );
+    }
+
+    pub fn remove(&self, key: &K) {
+        Self::take(&mut self.inner.lock(), key);
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock();
+        inner.slots.clear();
+        inner.order.clear();
+        inner.bytes = 0;
+    }
+
+    fn take(inner: &mut LruInner<K, V>, key: &K) {
+        if let Some(slot) = inner.slots.remove(key) {
+            inner.order.remove(&slot.last_used);
+            inner.bytes -= slot.weight;
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let inner = self.inner.lock();
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: inner.slots.len(),
+            bytes: inner.bytes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    pub node_bytes: usize,
+    pub account_bytes: usize,
+    pub code_bytes: usize,
+}
+
+impl CacheConfig {
+    /// Half of `database.cache_size_mb` for trie nodes, a quarter each for
+    /// accounts and code.
+    pub fn from_node_config(config: &NodeConfig) -> Self {
+        Self::with_total(config.database.cache_size_mb as usize * 1024 * 1024)
+    }
+
+    pub fn with_total(bytes: usize) -> Self {
+        Self {
+            node_bytes: bytes / 2,
+            account_bytes: bytes / 4,
+            code_bytes: bytes / 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StoreCacheStats {
+    pub nodes: CacheStats,
+    pub accounts: CacheStats,
+    pub code: CacheStats,
+}
+
+/// `KvStore` with cached reads of trie nodes, accounts and code.
+#[derive(Debug)]
+pub struct CachedStore {
+    inner: Arc<dyn KvStore>,
+    nodes: LruCache<H256, Arc<Vec<u8>>>,
+    accounts: LruCache<(H256, Address), Option<Account>>,
+    code: LruCache<H256, Arc<Vec<u8>>>,
+}
+
+impl CachedStore {
+    pub fn new(inner: Arc<dyn KvStore>, config: CacheConfig) -> Self {
+        Self {
+            inner,
+            nodes: LruCache::new(config.node_bytes),
+            accounts: LruCache::new(config.account_bytes),
+            code: LruCache::new(config.code_bytes),
+        }
+    }
+
+    /// Trie node with hash `hash`.
+    fn node(&self, hash: &H256) -> Result<Option<Arc<Vec<u8>>>, StorageError> {
+        if let Some(node) = self.nodes.get(hash) {
+            return Ok(Some(node));
+        }
+        let Some(node) = self.inner.get(Column::TrieNodes, hash)? else {
+            return Ok(None);
+        };
+        let node = Arc::new(node);
+        self.nodes.insert(*hash, node.clone(), hash.len() + node.len());
+        Ok(Some(node))
+    }
+
+    /// Account at `address` in the state with root `state_root`, read
+    /// without loading the rest of the state. Its `storage_root` is the
+    /// stored one.
+    pub fn account(&self, state_root: &H256, address: &Address) -> Result<Option<Account>, StorageError> {
+        let key = (*state_root, *address);
+        if let Some(account) = self.accounts.get(&key) {
+            return Ok(account);
+        }
+        let account = trie::lookup(state_root, &account_key(address), |hash| {
+            Ok(self.node(hash)?.map(|node| node.to_vec()))
+        })?
+        .map(|encoded| Account::decode(&encoded))
+        .transpose()
+        .map_err(|source| StorageError::Corrupt {
+            what: "account",
+            source,
+        })?;
+        self.accounts.insert(key, account.clone(), ACCOUNT_WEIGHT);
+        Ok(account)
+    }
+
+    /// Contract code with hash `hash`.
+    pub fn code(&self, hash: &H256) -> Result<Option<Arc<Vec<u8>>>, StorageError> {
+        if let Some(code) = self.code.get(hash) {
+            return Ok(Some(code));
+        }
+        let Some(code) = self.inner.get(Column::TrieNodes, hash)? else {
+            return Ok(None);
+        };
+        let code = Arc::new(code);
+        self.code.insert(*hash, code.clone(), hash.len() + code.len());
+        Ok(Some(code))
+    }
+
+    /// Drop everything cached, e.g. after the store was changed behind the
+    /// cache's back.
+    pub fn invalidate_all(&self) {
+        self.nodes.clear();
+        self.accounts.clear();
+        self.code.clear();
+    }
+
+    pub fn stats(&self) -> StoreCacheStats {
+        StoreCacheStats {
+            nodes: self.nodes.stats(),
+            accounts: self.accounts.stats(),
+            code: self.code.stats(),
+        }
+    }
+}
+
+impl KvStore for CachedStore {
+    fn get(&self, column: Column, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        match (column, H256::try_from(key)) {
+            (Column::TrieNodes, Ok(hash)) => Ok(self.node(&hash)?.map(|node| node.to_vec())),
+            _ => self.inner.get(column, key),
+        }
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError> {
+        let mut deleted = false;
+        let mut hashes = Vec::new();
+        for op in batch.ops() {
+            let BatchOp::Delete {
+                column: Column::TrieNodes,
+                key,
+            } = op
+            else {
+                continue;
+            };
+            deleted = true;
+            if let Ok(hash) = H256::try_from(&key[..]) {
+                hashes.push(hash);
+            }
+        }
+        // Evict only once the deletes are stored, so a concurrent miss
+        // cannot cache an entry again from the store before it is gone
+        self.inner.write(batch)?;
+        for hash in &hashes {
+            self.nodes.remove(hash);
+            self.code.remove(hash);
+        }
+        if deleted {
+            self.accounts.clear();
+        }
+        Ok(())
+    }
+
+    fn entries(&self, column: Column) -> Result<Vec<Entry>, StorageError> {
+        self.inner.entries(column)
+    }
+
+    fn for_each(&self, column: Column, visit: &mut dyn FnMut(&[u8], &[u8])) -> Result<(), StorageError> {
+        self.inner.for_each(column, visit)
+    }
+}
@@ -0,0 +1,6 @@
+pub mod cache;
+pub mod mvcc;
+pub mod pruning;
+pub mod rocksdb;
+pub mod snapshot;
+pub mod trie;
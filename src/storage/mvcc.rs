@@ -0,0 +1,353 @@
+//! Multi-version state.
+//!
+//! Every committed block adds a version holding the accounts, slots and
+//! code it changed. A `StateSnapshot` reads the state as of one height and
+//! is unaffected by later commits, so readers see a consistent state while
+//! the importer keeps writing. Old versions are dropped once more than
+//! `retained` versions exist, except heights pinned by a live snapshot.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+
+use crate::core::account::{Account, EMPTY_CODE_HASH};
+use crate::core::address::Address;
+use crate::core::state::{StateDB, WorldState};
+use crate::core::types::{H256, ZERO_HASH};
+
+/// Versions kept by default: a little over an hour of blocks at one second
+/// block time.
+pub const DEFAULT_RETAINED_VERSIONS: u64 = 4_096;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MvccError {
+    #[error("state history is not tracked")]
+    Disabled,
+    #[error("state at height {height} was pruned, oldest retained is {oldest}")]
+    Pruned { height: u64, oldest: u64 },
+    #[error("state at height {height} is ahead of latest version {latest}")]
+    Future { height: u64, latest: u64 },
+    #[error("version {got} does not follow latest version {latest}")]
+    NonSequential { latest: u64, got: u64 },
+}
+
+/// State written by one version. Accounts are stored with a zero
+/// `storage_root`, as in `WorldState`; `None` marks a deleted account.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateChanges {
+    pub accounts: BTreeMap<Address, Option<Account>>,
+    pub storage: BTreeMap<(Address, H256), H256>,
+    pub code: BTreeMap<H256, Vec<u8>>,
+}
+
+impl StateChanges {
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.storage.is_empty() && self.code.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Key {
+    Account(Address),
+    Storage(Address, H256),
+}
+
+/// Values of one key by the version that wrote them.
+type Chain<V> = BTreeMap<u64, V>;
+
+/// Value of `chain` as of `height`.
+fn value_at<V>(chain: &Chain<V>, height: u64) -> Option<&V> {
+    chain.range(..=height).next_back().map(|(_, value)| value)
+}
+
+#[derive(Debug)]
+struct Versions {
+    accounts: HashMap<Address, Chain<Option<Account>>>,
+    storage: HashMap<(Address, H256), Chain<H256>>,
+    /// Content addressed, so never rewritten
+    code: HashMap<H256, Vec<u8>>,
+    /// Keys written by each retained version
+    written: BTreeMap<u64, Vec<Key>>,
+    oldest: u64,
+    latest: u64,
+    retained: u64,
+    /// Live snapshots per height
+    pins: BTreeMap<u64, usize>,
+}
+
+impl Versions {
+    fn check(&self, height: u64) -> Result<(), MvccError> {
+        if height < self.oldest {
+            return Err(MvccError::Pruned {
+                height,
+                oldest: self.oldest,
+            });
+        }
+        if height > self.latest {
+            return Err(MvccError::Future {
+                height,
+                latest: self.latest,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_next(&self, height: u64) -> Result<(), MvccError> {
+        if height != self.latest + 1 {
+            return Err(MvccError::NonSequential {
+                latest: self.latest,
+                got: height,
+            });
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, version: u64, changes: StateChanges) {
+        let mut keys = Vec::with_capacity(changes.accounts.len() + changes.storage.len());
+        for (address, account) in changes.accounts {
+            self.accounts.entry(address).or_default().insert(version, account);
+            keys.push(Key::Account(address));
+        }
+        for ((address, slot), value) in changes.storage {
+            self.storage.entry((address, slot)).or_default().insert(version, value);
+            keys.push(Key::Storage(address, slot));
+        }
+        self.code.extend(changes.code);
+        if !keys.is_empty() {
+            self.written.insert(version, keys);
+        }
+    }
+
+    /// Drop versions older than `oldest`. Values still visible at `oldest`
+    /// are moved to it so reads at retained heights are unchanged.
+    fn prune(&mut self, oldest: u64) {
+        if oldest <= self.oldest {
+            return;
+        }
+        let newer = self.written.split_off(&oldest);
+        let dropped = std::mem::replace(&mut self.written, newer);
+        let mut moved = Vec::new();
+        // Newest first, so the value visible at `oldest` is the first one
+        // found for each key
+        for (version, keys) in dropped.into_iter().rev() {
+            for key in keys {
+                let kept = match key {
+                    Key::Account(address) => retire(&mut self.accounts, address, version, oldest),
+                    Key::Storage(address, slot) => retire(&mut self.storage, (address, slot), version, oldest),
+                };
+                if kept {
+                    moved.push(key);
+                }
+            }
+        }
+        if !moved.is_empty() {
+            self.written.entry(oldest).or_default().extend(moved);
+        }
+        self.oldest = oldest;
+    }
+}
+
+/// Remove the value `version` wrote for `key`, re-inserting it at `oldest`
+/// if nothing newer is visible there. Returns whether it was kept.
+fn retire<K: std::hash::Hash + Eq, V>(chains: &mut HashMap<K, Chain<V>>, key: K, version: u64, oldest: u64) -> bool {
+    let Some(chain) = chains.get_mut(&key) else {
+        return false;
+    };
+    let Some(value) = chain.remove(&version) else {
+        return false;
+    };
+    let kept = chain.range(version..=oldest).next().is_none();
+    if kept {
+        chain.insert(oldest, value);
+    } else if chain.is_empty() {
+        chains.remove(&key);
+    }
+    kept
+}
+
+/// State history from a base height onwards.
+#[derive(Debug)]
+pub struct VersionedState {
+    inner: RwLock<Versions>,
+}
+
+impl VersionedState {
+    /// History starting with `state` as the version at `height`, keeping
+    /// the last `retained` versions (at least one).
+    pub fn new(height: u64, state: &WorldState, retained: u64) -> Self {
+        let mut versions = Versions {
+            accounts: HashMap::new(),
+            storage: HashMap::new(),
+            code: HashMap::new(),
+            written: BTreeMap::new(),
+            oldest: height,
+            latest: height,
+            retained: retained.max(1),
+            pins: BTreeMap::new(),
+        };
+        versions.apply(height, state.to_changes());
+        Self {
+            inner: RwLock::new(versions),
+        }
+    }
+
+    /// Oldest and latest readable heights.
+    pub fn range(&self) -> (u64, u64) {
+        let inner = self.inner.read();
+        (inner.oldest, inner.latest)
+    }
+
+    pub fn latest(&self) -> u64 {
+        self.inner.read().latest
+    }
+
+    /// Whether `commit` would accept a version at `height`.
+    pub fn check_next(&self, height: u64) -> Result<(), MvccError> {
+        self.inner.read().check_next(height)
+    }
+
+    /// Record `changes` as the version at `height`, which must directly
+    /// follow the latest version, then drop versions past retention.
+    pub fn commit(&self, height: u64, changes: StateChanges) -> Result<(), MvccError> {
+        let mut inner = self.inner.write();
+        inner.check_next(height)?;
+        inner.apply(height, changes);
+        inner.latest = height;
+
+        let mut oldest = height + 1 - inner.retained.min(height + 1);
+        if let Some((&pinned, _)) = inner.pins.first_key_value() {
+            oldest = oldest.min(pinned);
+        }
+        inner.prune(oldest);
+        Ok(())
+    }
+
+    /// Consistent view of the state at `height`. Versions from `height`
+    /// on are kept until the snapshot is dropped.
+    pub fn snapshot(self: &Arc<Self>, height: u64) -> Result<StateSnapshot, MvccError> {
+        let mut inner = self.inner.write();
+        inner.check(height)?;
+        *inner.pins.entry(height).or_default() += 1;
+        Ok(StateSnapshot {
+            versions: self.clone(),
+            height,
+        })
+    }
+
+    /// Snapshot of the latest version.
+    pub fn latest_snapshot(self: &Arc<Self>) -> StateSnapshot {
+        let height = self.latest();
+        self.snapshot(height).expect("latest version is always retained")
+    }
+
+    /// Number of live snapshots.
+    pub fn pinned(&self) -> usize {
+        self.inner.read().pins.values().sum()
+    }
+
+    fn unpin(&self, height: u64) {
+        let mut inner = self.inner.write();
+        if let Some(count) = inner.pins.get_mut(&height) {
+            *count -= 1;
+            if *count == 0 {
+                inner.pins.remove(&height);
+            }
+        }
+    }
+}
+
+/// Read-only state as of one height.
+#[derive(Debug)]
+pub struct StateSnapshot {
+    versions: Arc<VersionedState>,
+    height: u64,
+}
+
+impl StateSnapshot {
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    fn account(&self, address: &Address) -> Option<Account> {
+        let inner = self.versions.inner.read();
+        inner
+            .accounts
+            .get(address)
+            .and_then(|chain| value_at(chain, self.height))
+            .cloned()
+            .flatten()
+    }
+
+    pub fn exists(&self, address: &Address) -> bool {
+        self.account(address).is_some()
+    }
+
+    pub fn get_balance(&self, address: &Address) -> u128 {
+        self.account(address).map_or(0, |account| account.balance)
+    }
+
+    pub fn get_nonce(&self, address: &Address) -> u64 {
+        self.account(address).map_or(0, |account| account.nonce)
+    }
+
+    pub fn get_storage(&self, address: &Address, slot: &H256) -> H256 {
+        let inner = self.versions.inner.read();
+        inner
+            .storage
+            .get(&(*address, *slot))
+            .and_then(|chain| value_at(chain, self.height))
+            .copied()
+            .unwrap_or(ZERO_HASH)
+    }
+
+    pub fn code_hash(&self, address: &Address) -> H256 {
+        self.account(address)
+            .map_or(EMPTY_CODE_HASH, |account| account.code_hash)
+    }
+
+    pub fn code(&self, address: &Address) -> Vec<u8> {
+        let hash = self.code_hash(address);
+        self.versions.inner.read().code.get(&hash).cloned().unwrap_or_default()
+    }
+
+    /// Full copy of the state at this height, e.g. to execute a call or
+    /// build proofs against it. Changes to the copy are not recorded.
+    pub fn to_world_state(&self) -> WorldState {
+        let inner = self.versions.inner.read();
+        let mut state = WorldState::new();
+        for (address, chain) in &inner.accounts {
+            let Some(Some(account)) = value_at(chain, self.height) else {
+                continue;
+            };
+            state.set_balance(address, account.balance);
+            state.set_nonce(address, account.nonce);
+            if let Some(code) = inner.code.get(&account.code_hash) {
+                state.set_code(address, code.clone());
+            }
+        }
+        for ((address, slot), chain) in &inner.storage {
+            if let Some(value) = value_at(chain, self.height) {
+                state.set_storage(address, slot, *value);
+            }
+        }
+        state.commit();
+        state
+    }
+}
+
+impl Clone for StateSnapshot {
+    fn clone(&self) -> Self {
+        *self.versions.inner.write().pins.entry(self.height).or_default() += 1;
+        Self {
+            versions: self.versions.clone(),
+            height: self.height,
+        }
+    }
+}
+
+impl Drop for StateSnapshot {
+    fn drop(&mut self) {
+        self.versions.unpin(self.height);
+    }
+}
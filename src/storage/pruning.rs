@@ -0,0 +1,230 @@
+//! Background pruning of chain storage.
+//!
+//! Trie nodes are shared between states and keyed by hash, so they are
+//! collected by mark and sweep: every node reachable from the state roots
+//! of the kept blocks is marked, then every other node and contract code
+//! is deleted. Marking, the sweep scan and gathering pruned bodies run
+//! while blocks keep being imported. Import is only paused to mark the
+//! states of blocks imported in the meantime and apply the deletions, so a
+//! node that a new state wrote again is never removed.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::core::account::Account;
+use crate::core::blockchain::Blockchain;
+use crate::core::config::NodeConfig;
+pub use crate::core::config::PruningMode;
+use crate::core::types::{to_hex, H256, ZERO_HASH};
+use crate::storage::rocksdb::{Column, KvStore, StorageError, WriteBatch};
+use crate::storage::trie;
+
+/// Trie nodes deleted per write batch.
+const DELETE_BATCH: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PruningConfig {
+    pub mode: PruningMode,
+    /// Blocks behind and including the head whose state is kept
+    pub keep_recent: u64,
+    /// Time between pruning runs
+    pub interval: Duration,
+}
+
+impl PruningConfig {
+    pub fn from_node_config(config: &NodeConfig) -> Self {
+        Self {
+            mode: config.database.pruning,
+            keep_recent: config.database.pruning_keep_recent.max(1),
+            interval: Duration::from_secs(config.database.pruning_interval_seconds),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PruneError {
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    #[error("pruning task failed: {0}")]
+    Task(String),
+}
+
+/// What one pruning run removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    /// Oldest block whose state is still stored
+    pub kept_from: u64,
+    /// Trie nodes and contract code still referenced
+    pub live_nodes: u64,
+    /// Trie nodes and contract code deleted
+    pub nodes: u64,
+    /// Blocks whose body and receipts were deleted
+    pub bodies: u64,
+    pub bytes_reclaimed: u64,
+}
+
+/// Deletes state and block data the configured mode does not keep.
+#[derive(Debug)]
+pub struct Pruner {
+    chain: Arc<Blockchain>,
+    db: Arc<dyn KvStore>,
+    config: PruningConfig,
+    runs: AtomicU64,
+    reclaimed: AtomicU64,
+}
+
+impl Pruner {
+    /// Pruner for `chain`, which must be persisted in `db`.
+    pub fn new(chain: Arc<Blockchain>, db: Arc<dyn KvStore>, config: PruningConfig) -> Self {
+        Self {
+            chain,
+            db,
+            config,
+            runs: AtomicU64::new(0),
+            reclaimed: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &PruningConfig {
+        &self.config
+    }
+
+    /// Completed runs and the bytes they reclaimed in total.
+    pub fn stats(&self) -> (u64, u64) {
+        (
+            self.runs.load(Ordering::Relaxed),
+            self.reclaimed.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Prune once. Does nothing in archive mode.
+    pub async fn prune(&self) -> Result<PruneReport, PruneError> {
+        if self.config.mode == PruningMode::Archive {
+            return Ok(PruneReport::default());
+        }
+        let head = self.chain.head().await.number();
+        let kept_from = head
+            .saturating_sub(self.config.keep_recent - 1)
+            .max(self.chain.base().await);
+        let mut roots = Vec::new();
+        for number in kept_from..=head {
+            if let Some(block) = self.chain.get_block_by_number(number).await {
+                roots.push(block.header.state_root);
+            }
+        }
+
+        let db = self.db.clone();
+        let (mut live, unmarked) = tokio::task::spawn_blocking(move || {
+            let mut live = HashSet::new();
+            for root in &roots {
+                mark(db.as_ref(), root, &mut live)?;
+            }
+            // Keys and sizes of nodes not marked so far
+            let mut unmarked = Vec::new();
+            db.for_each(Column::TrieNodes, &mut |key, value| {
+                if <H256>::try_from(key).map_or(true, |hash| !live.contains(&hash)) {
+                    unmarked.push((key.to_vec(), (key.len() + value.len()) as u64));
+                }
+            })?;
+            Ok::<_, StorageError>((live, unmarked))
+        })
+        .await
+        .map_err(|err| PruneError::Task(err.to_string()))??;
+        let bodies = match self.config.mode {
+            PruningMode::Light => Some(self.chain.bodies_to_prune(kept_from).await?),
+            _ => None,
+        };
+
+        let mut pause = self.chain.pause_import().await;
+        for number in head + 1..=pause.head().number() {
+            let block = pause.block(number)?.expect("imported block is stored");
+            mark(self.db.as_ref(), &block.header.state_root, &mut live)?;
+        }
+        let mut report = PruneReport {
+            kept_from,
+            live_nodes: live.len() as u64,
+            ..PruneReport::default()
+        };
+        let mut batch = WriteBatch::new();
+        for (key, size) in unmarked {
+            let dead = <H256>::try_from(&key[..]).map_or(true, |hash| !live.contains(&hash));
+            if dead {
+                report.nodes += 1;
+                report.bytes_reclaimed += size;
+                batch.delete(Column::TrieNodes, key);
+            }
+            if batch.len() == DELETE_BATCH {
+                self.db.write(std::mem::take(&mut batch))?;
+            }
+        }
+        self.db.write(batch)?;
+        if let Some(bodies) = bodies {
+            let bodies = pause.prune_bodies(bodies)?;
+            report.bodies = bodies.blocks;
+            report.bytes_reclaimed += bodies.bytes;
+        }
+        drop(pause);
+
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        self.reclaimed.fetch_add(report.bytes_reclaimed, Ordering::Relaxed);
+        Ok(report)
+    }
+
+    /// Prune every `interval` until the process exits.
+    pub async fn run(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(self.config.interval);
+        loop {
+            interval.tick().await;
+            match self.prune().await {
+                Ok(report) => tracing::info!(
+                    kept_from = report.kept_from,
+                    nodes = report.nodes,
+                    bodies = report.bodies,
+                    bytes = report.bytes_reclaimed,
+                    total_bytes = self.stats().1,
+                    "pruned chain storage"
+                ),
+                Err(err) => tracing::error!("pruning failed: {}", err),
+            }
+        }
+    }
+}
+
+/// Add every trie node and contract code reachable from the state root
+/// `root` to `live`. Subtrees already in `live` are skipped, as they were
+/// marked in full when first reached.
+fn mark(db: &dyn KvStore, root: &H256, live: &mut HashSet<H256>) -> Result<(), StorageError> {
+    let corrupt = |source| StorageError::Corrupt {
+        what: "trie node",
+        source,
+    };
+    // Node hash, and whether it belongs to the state trie (whose values
+    // are accounts) rather than a storage trie
+    let mut pending = vec![(*root, true)];
+    while let Some((hash, is_state)) = pending.pop() {
+        if hash == ZERO_HASH || !live.insert(hash) {
+            continue;
+        }
+        let node = db
+            .get(Column::TrieNodes, &hash)?
+            .ok_or_else(|| StorageError::Missing(format!("trie node {}", to_hex(&hash))))?;
+        let (children, values) = trie::node_references(&node).map_err(corrupt)?;
+        pending.extend(children.into_iter().map(|child| (child, is_state)));
+        if !is_state {
+            continue;
+        }
+        for value in values {
+            let account = Account::decode(&value).map_err(|source| StorageError::Corrupt {
+                what: "account",
+                source,
+            })?;
+            pending.push((account.storage_root, false));
+            if account.has_code() {
+                live.insert(account.code_hash);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,285 @@
+//! Key-value storage for chain data.
+//!
+//! Data is split into columns (RocksDB column families). `KvStore` is
+//! implemented by `RocksStore` for nodes and by `MemoryStore` for tests.
+//! All writes go through a `WriteBatch` and are applied atomically, so a
+//! crash never leaves a block half written.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use ::rocksdb::{ColumnFamily, ColumnFamilyDescriptor, IteratorMode, Options, DB};
+use parking_lot::RwLock;
+
+use crate::core::codec::DecodeError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Column {
+    /// Encoded block headers by big-endian block number
+    Headers,
+    /// Encoded transaction lists by big-endian block number
+    Bodies,
+    /// Encoded receipt lists by big-endian block number
+    Receipts,
+    /// Big-endian block numbers by block hash
+    BlockNumbers,
+    /// Trie nodes and contract code, both keyed by their hash
+    TrieNodes,
+    /// Addresses and storage slots by the hashed trie key they are stored
+    /// under
+    Preimages,
+    /// Chain head and other singletons
+    Metadata,
+}
+
+impl Column {
+    pub const ALL: [Column; 7] = [
+        Column::Headers,
+        Column::Bodies,
+        Column::Receipts,
+        Column::BlockNumbers,
+        Column::TrieNodes,
+        Column::Preimages,
+        Column::Metadata,
+    ];
+
+    /// Column family name.
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Headers => "headers",
+            Column::Bodies => "bodies",
+            Column::Receipts => "receipts",
+            Column::BlockNumbers => "block_numbers",
+            Column::TrieNodes => "trie_nodes",
+            Column::Preimages => "preimages",
+            Column::Metadata => "metadata",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("rocksdb: {0}")]
+    Backend(#[from] ::rocksdb::Error),
+    #[error("column family {0} is missing")]
+    MissingColumn(&'static str),
+    #[error("stored {what} is corrupt: {source}")]
+    Corrupt { what: &'static str, source: DecodeError },
+    #[error("{0} is missing from storage")]
+    Missing(String),
+}
+
+/// Key and value read back from a column.
+pub type Entry = (Vec<u8>, Vec<u8>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchOp {
+    Put {
+        column: Column,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Delete {
+        column: Column,
+        key: Vec<u8>,
+    },
+}
+
+/// Writes applied together by `KvStore::write`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteBatch {
+    ops: Vec<BatchOp>,
+}
+
+impl WriteBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn put(&mut self, column: Column, key: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) -> &mut Self {
+        self.ops.push(BatchOp::Put {
+            column,
+            key: key.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    pub fn delete(&mut self, column: Column, key: impl Into<Vec<u8>>) -> &mut Self {
+        self.ops.push(BatchOp::Delete {
+            column,
+            key: key.into(),
+        });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    pub fn ops(&self) -> &[BatchOp] {
+        &self.ops
+    }
+}
+
+pub trait KvStore: Send + Sync + std::fmt::Debug {
+    fn get(&self, column: Column, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Apply every operation in `batch`, or none of them.
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError>;
+
+    /// Every entry in `column` in key order.
+    fn entries(&self, column: Column) -> Result<Vec<Entry>, StorageError>;
+
+    /// Call `visit` with every entry in `column` in key order, reading the
+    /// column incrementally. `visit` must not write to the store.
+    fn for_each(&self, column: Column, visit: &mut dyn FnMut(&[u8], &[u8])) -> Result<(), StorageError>;
+
+    fn contains(&self, column: Column, key: &[u8]) -> Result<bool, StorageError> {
+        Ok(self.get(column, key)?.is_some())
+    }
+
+    fn put(&self, column: Column, key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+        let mut batch = WriteBatch::new();
+        batch.put(column, key, value);
+        self.write(batch)
+    }
+
+    fn delete(&self, column: Column, key: &[u8]) -> Result<(), StorageError> {
+        let mut batch = WriteBatch::new();
+        batch.delete(column, key);
+        self.write(batch)
+    }
+}
+
+/// RocksDB database with one column family per `Column`.
+pub struct RocksStore {
+    db: DB,
+}
+
+impl std::fmt::Debug for RocksStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RocksStore").field("path", &self.db.path()).finish()
+    }
+}
+
+impl RocksStore {
+    /// Open the database at `path`, creating it and any missing column
+    /// families.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        let columns = Column::ALL
+            .iter()
+            .map(|column| ColumnFamilyDescriptor::new(column.name(), Options::default()));
+        let db = DB::open_cf_descriptors(&options, path, columns)?;
+        Ok(Self { db })
+    }
+
+    fn handle(&self, column: Column) -> Result<&ColumnFamily, StorageError> {
+        self.db
+            .cf_handle(column.name())
+            .ok_or(StorageError::MissingColumn(column.name()))
+    }
+
+    /// Flush memtables to disk.
+    pub fn flush(&self) -> Result<(), StorageError> {
+        Ok(self.db.flush()?)
+    }
+}
+
+impl KvStore for RocksStore {
+    fn get(&self, column: Column, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.db.get_cf(self.handle(column)?, key)?)
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError> {
+        let mut rocks = ::rocksdb::WriteBatch::default();
+        for op in batch.ops {
+            match op {
+                BatchOp::Put { column, key, value } => rocks.put_cf(self.handle(column)?, key, value),
+                BatchOp::Delete { column, key } => rocks.delete_cf(self.handle(column)?, key),
+            }
+        }
+        Ok(self.db.write(rocks)?)
+    }
+
+    fn entries(&self, column: Column) -> Result<Vec<Entry>, StorageError> {
+        self.db
+            .iterator_cf(self.handle(column)?, IteratorMode::Start)
+            .map(|entry| {
+                let (key, value) = entry?;
+                Ok((key.into_vec(), value.into_vec()))
+            })
+            .collect()
+    }
+
+    fn for_each(&self, column: Column, visit: &mut dyn FnMut(&[u8], &[u8])) -> Result<(), StorageError> {
+        for entry in self.db.iterator_cf(self.handle(column)?, IteratorMode::Start) {
+            let (key, value) = entry?;
+            visit(&key, &value);
+        }
+        Ok(())
+    }
+}
+
+/// In-memory store for tests and throwaway nodes.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    columns: RwLock<[BTreeMap<Vec<u8>, Vec<u8>>; Column::ALL.len()]>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of entries in `column`.
+    pub fn len(&self, column: Column) -> usize {
+        self.columns.read()[column.index()].len()
+    }
+}
+
+impl KvStore for MemoryStore {
+    fn get(&self, column: Column, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.columns.read()[column.index()].get(key).cloned())
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError> {
+        let mut columns = self.columns.write();
+        for op in batch.ops {
+            match op {
+                BatchOp::Put { column, key, value } => {
+                    columns[column.index()].insert(key, value);
+                }
+                BatchOp::Delete { column, key } => {
+                    columns[column.index()].remove(&key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn entries(&self, column: Column) -> Result<Vec<Entry>, StorageError> {
+        Ok(self.columns.read()[column.index()]
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    fn for_each(&self, column: Column, visit: &mut dyn FnMut(&[u8], &[u8])) -> Result<(), StorageError> {
+        for (key, value) in &self.columns.read()[column.index()] {
+            visit(key, value);
+        }
+        Ok(())
+    }
+}
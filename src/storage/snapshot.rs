@@ -0,0 +1,494 @@
+//! Full-state snapshot files.
+//!
+//! A snapshot holds a block and every account, storage slot and contract
+//! code in the state after it. Records are grouped into chunks that are
+//! compressed and checksummed one by one, so corruption is found before a
+//! chunk is decoded. The file is laid out as
+//!
+//! ```text
+//! MAGIC | chunk... | manifest | blake3(manifest) | manifest length (u64)
+//! ```
+//!
+//! with the manifest last so chunks can be streamed out as they fill up.
+//! `read` only returns a state that hashes to the block's state root, so a
+//! node can start from it with `Blockchain::bootstrap` instead of replaying
+//! every block since genesis.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::core::account::Account;
+use crate::core::address::Address;
+use crate::core::block::Block;
+use crate::core::codec::{DecodeError, Decoder, Encoder};
+use crate::core::state::{StateDB, WorldState};
+use crate::core::types::{keccak256, to_hex, H256};
+
+pub const MAGIC: &[u8; 8] = b"TBSNAPv1";
+
+/// Chunk size before compression used unless configured otherwise.
+pub const DEFAULT_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Largest chunk or manifest `read` accepts, compressed or not.
+pub const MAX_SECTION_SIZE: u64 = 256 * 1024 * 1024;
+
+const ZSTD_LEVEL: i32 = 3;
+
+/// Record tags. Code comes first so accounts can be restored with it.
+const CODE: u8 = 0;
+const ACCOUNT: u8 = 1;
+const SLOT: u8 = 2;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("snapshot i/o: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("not a snapshot file")]
+    BadMagic,
+    #[error("unknown compression {0}")]
+    UnknownCompression(u8),
+    #[error("manifest checksum does not match")]
+    ManifestChecksum,
+    #[error("chunk {0} checksum does not match")]
+    ChunkChecksum(usize),
+    #[error("chunk {index} is corrupt: {reason}")]
+    CorruptChunk { index: usize, reason: String },
+    #[error("section of {0} bytes exceeds the size limit")]
+    TooLarge(u64),
+    #[error("malformed snapshot: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("snapshot holds {got} {what}, manifest lists {expected}")]
+    CountMismatch {
+        what: &'static str,
+        expected: u64,
+        got: u64,
+    },
+    #[error("code {0} is missing or does not match its hash")]
+    BadCode(String),
+    #[error("state root {got} does not match block state root {expected}")]
+    StateRootMismatch { expected: String, got: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    Zstd,
+    Snappy,
+}
+
+impl Compression {
+    fn tag(self) -> u8 {
+        match self {
+            Compression::Zstd => 0,
+            Compression::Snappy => 1,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Result<Self, SnapshotError> {
+        match tag {
+            0 => Ok(Compression::Zstd),
+            1 => Ok(Compression::Snappy),
+            other => Err(SnapshotError::UnknownCompression(other)),
+        }
+    }
+
+    fn compress(self, raw: &[u8]) -> Result<Vec<u8>, SnapshotError> {
+        match self {
+            Compression::Zstd => Ok(zstd::bulk::compress(raw, ZSTD_LEVEL)?),
+            Compression::Snappy => snap::raw::Encoder::new()
+                .compress_vec(raw)
+                .map_err(|err| SnapshotError::Io(std::io::Error::other(err))),
+        }
+    }
+
+    fn decompress(self, data: &[u8], raw_size: usize) -> Result<Vec<u8>, String> {
+        let raw = match self {
+            Compression::Zstd => zstd::bulk::decompress(data, raw_size).map_err(|err| err.to_string())?,
+            Compression::Snappy => snap::raw::Decoder::new()
+                .decompress_vec(data)
+                .map_err(|err| err.to_string())?,
+        };
+        if raw.len() != raw_size {
+            return Err(format!("{} bytes after decompression, expected {raw_size}", raw.len()));
+        }
+        Ok(raw)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotOptions {
+    pub compression: Compression,
+    /// Records are cut into a new chunk once this many bytes are pending
+    pub chunk_size: usize,
+}
+
+impl Default for SnapshotOptions {
+    fn default() -> Self {
+        Self {
+            compression: Compression::default(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkInfo {
+    /// Compressed size in the file
+    pub size: u64,
+    /// Size after decompression
+    pub raw_size: u64,
+    /// blake3 of the compressed bytes
+    pub checksum: H256,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotManifest {
+    /// Genesis of the chain the snapshot was taken from
+    pub genesis_hash: H256,
+    /// Block whose post-state the snapshot holds
+    pub block: Block,
+    pub compression: Compression,
+    pub accounts: u64,
+    pub slots: u64,
+    pub codes: u64,
+    pub chunks: Vec<ChunkInfo>,
+}
+
+impl SnapshotManifest {
+    pub fn height(&self) -> u64 {
+        self.block.number()
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut enc = Encoder::new();
+        enc.put_fixed(&self.genesis_hash)
+            .put_bytes(&self.block.encode())
+            .put_u8(self.compression.tag())
+            .put_u64(self.accounts)
+            .put_u64(self.slots)
+            .put_u64(self.codes)
+            .put_u32(self.chunks.len() as u32);
+        for chunk in &self.chunks {
+            enc.put_u64(chunk.size)
+                .put_u64(chunk.raw_size)
+                .put_fixed(&chunk.checksum);
+        }
+        enc.finish()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut dec = Decoder::new(bytes);
+        let genesis_hash = dec.get_fixed()?;
+        let block = Block::decode(&dec.get_bytes()?)?;
+        let compression = Compression::from_tag(dec.get_u8()?)?;
+        let accounts = dec.get_u64()?;
+        let slots = dec.get_u64()?;
+        let codes = dec.get_u64()?;
+        let count = dec.get_u32()? as usize;
+        let mut chunks = Vec::with_capacity(count.min(dec.remaining()));
+        for _ in 0..count {
+            chunks.push(ChunkInfo {
+                size: dec.get_u64()?,
+                raw_size: dec.get_u64()?,
+                checksum: dec.get_fixed()?,
+            });
+        }
+        dec.finish()?;
+        Ok(Self {
+            genesis_hash,
+            block,
+            compression,
+            accounts,
+            slots,
+            codes,
+            chunks,
+        })
+    }
+}
+
+/// Fills chunks with records and writes each out once it is full.
+struct ChunkWriter<W> {
+    out: W,
+    options: SnapshotOptions,
+    pending: Vec<u8>,
+    chunks: Vec<ChunkInfo>,
+}
+
+impl<W: Write> ChunkWriter<W> {
+    fn push(&mut self, record: Encoder) -> Result<(), SnapshotError> {
+        self.pending.extend_from_slice(&record.finish());
+        if self.pending.len() >= self.options.chunk_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), SnapshotError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let data = self.options.compression.compress(&self.pending)?;
+        self.out.write_all(&data)?;
+        self.chunks.push(ChunkInfo {
+            size: data.len() as u64,
+            raw_size: self.pending.len() as u64,
+            checksum: *blake3::hash(&data).as_bytes(),
+        });
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+/// File name of the snapshot at `height` within a snapshot directory.
+/// Heights are zero-padded so names sort by height.
+pub fn file_name(height: u64) -> String {
+    format!("snapshot-{height:012}.tbsnap")
+}
+
+/// Delete all but the `keep` highest snapshots named by `file_name` in
+/// `dir`, returning the paths removed.
+pub fn rotate(dir: &Path, keep: usize) -> Result<Vec<PathBuf>, SnapshotError> {
+    let mut snapshots = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if name.starts_with("snapshot-") && name.ends_with(".tbsnap") {
+            snapshots.push(path);
+        }
+    }
+    snapshots.sort();
+    let stale = snapshots.len().saturating_sub(keep);
+    let removed: Vec<PathBuf> = snapshots.drain(..stale).collect();
+    for path in &removed {
+        std::fs::remove_file(path)?;
+    }
+    Ok(removed)
+}
+
+/// Write a snapshot of `state`, the state after `block`, to `path`. The
+/// file is written under a temporary name and renamed into place, so a
+/// crash never leaves a partial snapshot at `path`; on failure the
+/// temporary file is removed.
+pub fn write(
+    path: &Path,
+    genesis_hash: &H256,
+    block: &Block,
+    state: &WorldState,
+    options: SnapshotOptions,
+) -> Result<SnapshotManifest, SnapshotError> {
+    let root = state.state_root();
+    if root != block.header.state_root {
+        return Err(SnapshotError::StateRootMismatch {
+            expected: to_hex(&block.header.state_root),
+            got: to_hex(&root),
+        });
+    }
+
+    let partial = path.with_extension("partial");
+    let written = write_partial(&partial, genesis_hash, block, state, options).and_then(|manifest| {
+        std::fs::rename(&partial, path)?;
+        Ok(manifest)
+    });
+    if written.is_err() {
+        // Best effort; the write error is the one to report
+        let _ = std::fs::remove_file(&partial);
+    }
+    written
+}
+
+fn write_partial(
+    partial: &Path,
+    genesis_hash: &H256,
+    block: &Block,
+    state: &WorldState,
+    options: SnapshotOptions,
+) -> Result<SnapshotManifest, SnapshotError> {
+    let mut out = BufWriter::new(File::create(partial)?);
+    out.write_all(MAGIC)?;
+    let mut writer = ChunkWriter {
+        out,
+        options,
+        pending: Vec::with_capacity(options.chunk_size),
+        chunks: Vec::new(),
+    };
+
+    let changes = state.to_changes();
+    for (hash, code) in &changes.code {
+        let mut record = Encoder::new();
+        record.put_u8(CODE).put_fixed(hash).put_bytes(code);
+        writer.push(record)?;
+    }
+    for (address, account) in &changes.accounts {
+        let Some(account) = account else { continue };
+        let mut record = Encoder::new();
+        record
+            .put_u8(ACCOUNT)
+            .put_fixed(address.as_bytes())
+            .put_u64(account.nonce)
+            .put_u128(account.balance)
+            .put_fixed(&account.code_hash);
+        writer.push(record)?;
+    }
+    for ((address, slot), value) in &changes.storage {
+        let mut record = Encoder::new();
+        record
+            .put_u8(SLOT)
+            .put_fixed(address.as_bytes())
+            .put_fixed(slot)
+            .put_fixed(value);
+        writer.push(record)?;
+    }
+    writer.flush()?;
+
+    let manifest = SnapshotManifest {
+        genesis_hash: *genesis_hash,
+        block: block.clone(),
+        compression: options.compression,
+        accounts: changes.accounts.len() as u64,
+        slots: changes.storage.len() as u64,
+        codes: changes.code.len() as u64,
+        chunks: writer.chunks,
+    };
+    let encoded = manifest.encode();
+    let mut out = writer.out;
+    out.write_all(&encoded)?;
+    out.write_all(blake3::hash(&encoded).as_bytes())?;
+    out.write_all(&(encoded.len() as u64).to_be_bytes())?;
+    out.into_inner().map_err(|err| err.into_error())?.sync_all()?;
+    Ok(manifest)
+}
+
+fn read_manifest_from(file: &mut File) -> Result<SnapshotManifest, SnapshotError> {
+    let mut magic = [0u8; MAGIC.len()];
+    file.read_exact(&mut magic).map_err(|_| SnapshotError::BadMagic)?;
+    if &magic != MAGIC {
+        return Err(SnapshotError::BadMagic);
+    }
+
+    let trailer = 32 + 8;
+    let file_len = file.seek(SeekFrom::End(0))?;
+    if file_len < (MAGIC.len() + trailer) as u64 {
+        return Err(SnapshotError::Decode(DecodeError::UnexpectedEof {
+            needed: MAGIC.len() + trailer,
+            remaining: file_len as usize,
+        }));
+    }
+    let mut len = [0u8; 8];
+    file.seek(SeekFrom::End(-8))?;
+    file.read_exact(&mut len)?;
+    let len = u64::from_be_bytes(len);
+    if len > MAX_SECTION_SIZE || len + (MAGIC.len() + trailer) as u64 > file_len {
+        return Err(SnapshotError::TooLarge(len));
+    }
+
+    file.seek(SeekFrom::End(-((len as usize + trailer) as i64)))?;
+    let mut encoded = vec![0u8; len as usize];
+    file.read_exact(&mut encoded)?;
+    let mut checksum = [0u8; 32];
+    file.read_exact(&mut checksum)?;
+    if *blake3::hash(&encoded).as_bytes() != checksum {
+        return Err(SnapshotError::ManifestChecksum);
+    }
+    SnapshotManifest::decode(&encoded)
+}
+
+/// Manifest of the snapshot at `path`, without reading its chunks.
+pub fn read_manifest(path: &Path) -> Result<SnapshotManifest, SnapshotError> {
+    read_manifest_from(&mut File::open(path)?)
+}
+
+fn chunk_error(index: usize, reason: impl ToString) -> SnapshotError {
+    SnapshotError::CorruptChunk {
+        index,
+        reason: reason.to_string(),
+    }
+}
+
+/// Read the snapshot at `path`, checking every chunk checksum, the code
+/// hashes and finally the state root against the manifest's block.
+pub fn read(path: &Path) -> Result<(SnapshotManifest, WorldState), SnapshotError> {
+    let mut file = File::open(path)?;
+    let manifest = read_manifest_from(&mut file)?;
+    file.seek(SeekFrom::Start(MAGIC.len() as u64))?;
+    let mut file = BufReader::new(file);
+
+    let mut state = WorldState::new();
+    let mut code_by_hash = HashMap::new();
+    let (mut accounts, mut slots, mut codes) = (0u64, 0u64, 0u64);
+    for (index, chunk) in manifest.chunks.iter().enumerate() {
+        if chunk.size > MAX_SECTION_SIZE || chunk.raw_size > MAX_SECTION_SIZE {
+            return Err(SnapshotError::TooLarge(chunk.size.max(chunk.raw_size)));
+        }
+        let mut data = vec![0u8; chunk.size as usize];
+        file.read_exact(&mut data)?;
+        if *blake3::hash(&data).as_bytes() != chunk.checksum {
+            return Err(SnapshotError::ChunkChecksum(index));
+        }
+        let raw = manifest
+            .compression
+            .decompress(&data, chunk.raw_size as usize)
+            .map_err(|reason| chunk_error(index, reason))?;
+
+        let mut dec = Decoder::new(&raw);
+        while dec.remaining() > 0 {
+            match dec.get_u8().map_err(|err| chunk_error(index, err))? {
+                CODE => {
+                    let hash: H256 = dec.get_fixed().map_err(|err| chunk_error(index, err))?;
+                    let code = dec.get_bytes().map_err(|err| chunk_error(index, err))?;
+                    if keccak256(&code) != hash {
+                        return Err(SnapshotError::BadCode(to_hex(&hash)));
+                    }
+                    code_by_hash.insert(hash, code);
+                    codes += 1;
+                }
+                ACCOUNT => {
+                    let address = Address(dec.get_fixed().map_err(|err| chunk_error(index, err))?);
+                    let account = Account {
+                        nonce: dec.get_u64().map_err(|err| chunk_error(index, err))?,
+                        balance: dec.get_u128().map_err(|err| chunk_error(index, err))?,
+                        code_hash: dec.get_fixed().map_err(|err| chunk_error(index, err))?,
+                        ..Account::default()
+                    };
+                    if account.has_code() {
+                        let code = code_by_hash
+                            .get(&account.code_hash)
+                            .ok_or_else(|| SnapshotError::BadCode(to_hex(&account.code_hash)))?;
+                        state.set_code(&address, code.clone());
+                    }
+                    state.set_balance(&address, account.balance);
+                    state.set_nonce(&address, account.nonce);
+                    accounts += 1;
+                }
+                SLOT => {
+                    let address = Address(dec.get_fixed().map_err(|err| chunk_error(index, err))?);
+                    let slot: H256 = dec.get_fixed().map_err(|err| chunk_error(index, err))?;
+                    let value: H256 = dec.get_fixed().map_err(|err| chunk_error(index, err))?;
+                    state.set_storage(&address, &slot, value);
+                    slots += 1;
+                }
+                tag => return Err(chunk_error(index, format!("unknown record tag {tag}"))),
+            }
+        }
+    }
+    state.commit();
+
+    for (what, expected, got) in [
+        ("accounts", manifest.accounts, accounts),
+        ("slots", manifest.slots, slots),
+        ("codes", manifest.codes, codes),
+    ] {
+        if expected != got {
+            return Err(SnapshotError::CountMismatch { what, expected, got });
+        }
+    }
+    let root = state.state_root();
+    if root != manifest.block.header.state_root {
+        return Err(SnapshotError::StateRootMismatch {
+            expected: to_hex(&manifest.block.header.state_root),
+            got: to_hex(&root),
+        });
+    }
+    Ok((manifest, state))
+}
@@ -0,0 +1,718 @@
+//! Merkle Patricia Trie over byte keys.
+//!
+//! Keys are split into nibbles and stored along leaf, extension and branch
+//! nodes. Every node is serialised with the consensus codec and referenced
+//! by the blake3 hash of that encoding, so the root hash commits to every
+//! key and value. The empty trie has the zero root, like an empty block's
+//! transaction root.
+//!
+//! A proof is the list of encoded nodes on the path from the root towards a
+//! key. `verify_proof` checks it against a root alone and shows either the
+//! value stored under the key or that the key is absent.
+
+use std::collections::HashMap;
+
+use crate::core::codec::{DecodeError, Decoder, Encoder};
+use crate::core::types::{to_hex, H256, ZERO_HASH};
+use crate::storage::rocksdb::StorageError;
+
+const LEAF: u8 = 0;
+const EXTENSION: u8 = 1;
+const BRANCH: u8 = 2;
+
+/// Encoded trie nodes from the root towards a key.
+pub type Proof = Vec<Vec<u8>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ProofError {
+    #[error("proof node does not match the expected hash")]
+    HashMismatch,
+    #[error("proof ends before reaching the key")]
+    Incomplete,
+    #[error("proof has {0} nodes past the key")]
+    TrailingNodes(usize),
+    #[error("invalid proof node: {0}")]
+    Decode(#[from] DecodeError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Leaf {
+        path: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Extension {
+        path: Vec<u8>,
+        child: Box<Node>,
+    },
+    Branch {
+        children: Box<[Option<Node>; 16]>,
+        value: Option<Vec<u8>>,
+    },
+}
+
+/// Node as stored and proven: children are referenced by hash.
+enum RawNode {
+    Leaf {
+        path: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Extension {
+        path: Vec<u8>,
+        child: H256,
+    },
+    Branch {
+        children: Box<[Option<H256>; 16]>,
+        value: Option<Vec<u8>>,
+    },
+}
+
+/// In-memory Merkle Patricia Trie.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MerkleTrie {
+    root: Option<Node>,
+    len: usize,
+}
+
+impl MerkleTrie {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        let mut node = self.root.as_ref()?;
+        let mut path = &nibbles(key)[..];
+        loop {
+            match node {
+                Node::Leaf { path: leaf, value } => {
+                    return (leaf[..] == *path).then_some(value.as_slice());
+                }
+                Node::Extension { path: prefix, child } => {
+                    path = path.strip_prefix(&prefix[..])?;
+                    node = child;
+                }
+                Node::Branch { children, value } => match path.split_first() {
+                    None => return value.as_deref(),
+                    Some((&nibble, rest)) => {
+                        node = children[nibble as usize].as_ref()?;
+                        path = rest;
+                    }
+                },
+            }
+        }
+    }
+
+    /// Store `value` under `key`, returning the value it replaces.
+    pub fn insert(&mut self, key: &[u8], value: Vec<u8>) -> Option<Vec<u8>> {
+        let path = nibbles(key);
+        let (root, prev) = match self.root.take() {
+            None => (Node::Leaf { path, value }, None),
+            Some(node) => insert(node, &path, value),
+        };
+        self.root = Some(root);
+        if prev.is_none() {
+            self.len += 1;
+        }
+        prev
+    }
+
+    /// Remove `key`, returning its value if it was present.
+    pub fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        let root = self.root.take()?;
+        let (root, prev) = remove(root, &nibbles(key));
+        self.root = root;
+        if prev.is_some() {
+            self.len -= 1;
+        }
+        prev
+    }
+
+    pub fn root(&self) -> H256 {
+        self.root
+            .as_ref()
+            .map_or(ZERO_HASH, |node| hash_node(node, &mut |_, _| ()))
+    }
+
+    /// Every node of the trie keyed by hash, for writing to a node store.
+    pub fn nodes(&self) -> HashMap<H256, Vec<u8>> {
+        let mut nodes = HashMap::new();
+        if let Some(root) = &self.root {
+            hash_node(root, &mut |hash, encoded| {
+                nodes.insert(hash, encoded.to_vec());
+            });
+        }
+        nodes
+    }
+
+    /// Nodes on the paths from the root towards `keys`, and the children
+    /// of those. When only those keys changed since the trie was last
+    /// stored, these include every node the store is missing.
+    pub fn nodes_along<'k>(&self, keys: impl IntoIterator<Item = &'k [u8]>) -> HashMap<H256, Vec<u8>> {
+        let mut nodes = HashMap::new();
+        if let Some(root) = &self.root {
+            let paths: Vec<Vec<u8>> = keys.into_iter().map(nibbles).collect();
+            let paths: Vec<&[u8]> = paths.iter().map(Vec::as_slice).collect();
+            hash_along(root, &paths, &mut nodes);
+        }
+        nodes
+    }
+
+    /// Proof of `key`'s value, or of its absence, against `root()`.
+    pub fn prove(&self, key: &[u8]) -> Proof {
+        let mut proof = Vec::new();
+        let Some(mut node) = self.root.as_ref() else {
+            return proof;
+        };
+        let key = nibbles(key);
+        let mut path = &key[..];
+        loop {
+            proof.push(encode_node(node, &mut |_, _| ()));
+            match node {
+                Node::Leaf { .. } => return proof,
+                Node::Extension { path: prefix, child } => match path.strip_prefix(&prefix[..]) {
+                    Some(rest) => {
+                        path = rest;
+                        node = child;
+                    }
+                    None => return proof,
+                },
+                Node::Branch { children, .. } => {
+                    let Some((&nibble, rest)) = path.split_first() else {
+                        return proof;
+                    };
+                    match &children[nibble as usize] {
+                        Some(child) => {
+                            path = rest;
+                            node = child;
+                        }
+                        None => return proof,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Rebuild a trie from its root and a node store such as `nodes()`.
+    /// `None` if a referenced node is missing or malformed.
+    pub fn from_nodes(root: &H256, nodes: &HashMap<H256, Vec<u8>>) -> Option<Self> {
+        Self::load(root, |hash| nodes.get(hash).cloned())
+    }
+
+    /// Like `from_nodes`, fetching each encoded node with `lookup`.
+    pub fn load(root: &H256, mut lookup: impl FnMut(&H256) -> Option<Vec<u8>>) -> Option<Self> {
+        let mut trie = Self::new();
+        if *root == ZERO_HASH {
+            return Some(trie);
+        }
+        let node = load_node(root, &mut lookup, &mut trie.len)?;
+        trie.root = Some(node);
+        Some(trie)
+    }
+
+    /// Every key and value, in key order.
+    pub fn entries(&self) -> Vec<(Vec<u8>, &[u8])> {
+        let mut entries = Vec::with_capacity(self.len);
+        if let Some(root) = &self.root {
+            collect_entries(root, &mut Vec::new(), &mut entries);
+        }
+        entries
+    }
+}
+
+/// Hashes of the children of an encoded node, and the values it holds.
+/// Lets a node store be walked from a root one node at a time, e.g. to
+/// find the nodes still reachable when pruning.
+pub fn node_references(encoded: &[u8]) -> Result<(Vec<H256>, Vec<Vec<u8>>), DecodeError> {
+    Ok(match decode_node(encoded)? {
+        RawNode::Leaf { value, .. } => (Vec::new(), vec![value]),
+        RawNode::Extension { child, .. } => (vec![child], Vec::new()),
+        RawNode::Branch { children, value } => (children.iter().flatten().copied().collect(), value.into_iter().collect()),
+    })
+}
+
+/// One step from an encoded node towards `path`: the child to visit next
+/// and the rest of the path, or the value the walk ends with.
+type Step<'p> = Result<(H256, &'p [u8]), Option<Vec<u8>>>;
+
+fn descend<'p>(encoded: &[u8], path: &'p [u8]) -> Result<Step<'p>, DecodeError> {
+    Ok(match decode_node(encoded)? {
+        RawNode::Leaf { path: leaf, value } => Err((leaf[..] == *path).then_some(value)),
+        RawNode::Extension { path: prefix, child } => match path.strip_prefix(&prefix[..]) {
+            Some(rest) => Ok((child, rest)),
+            None => Err(None),
+        },
+        RawNode::Branch { children, value } => match path.split_first() {
+            None => Err(value),
+            Some((&nibble, rest)) => children[nibble as usize].map(|child| (child, rest)).ok_or(None),
+        },
+    })
+}
+
+/// Check `proof` against `root` and return the value stored under `key`,
+/// or `None` if the proof shows `key` is absent.
+pub fn verify_proof(root: &H256, key: &[u8], proof: &[Vec<u8>]) -> Result<Option<Vec<u8>>, ProofError> {
+    if *root == ZERO_HASH {
+        return match proof.len() {
+            0 => Ok(None),
+            n => Err(ProofError::TrailingNodes(n)),
+        };
+    }
+    let key = nibbles(key);
+    let mut path = &key[..];
+    let mut expected = *root;
+    for (index, encoded) in proof.iter().enumerate() {
+        if hash(encoded) != expected {
+            return Err(ProofError::HashMismatch);
+        }
+        match descend(encoded, path)? {
+            Ok((child, rest)) => {
+                expected = child;
+                path = rest;
+            }
+            Err(value) => {
+                let extra = proof.len() - index - 1;
+                return if extra == 0 {
+                    Ok(value)
+                } else {
+                    Err(ProofError::TrailingNodes(extra))
+                };
+            }
+        }
+    }
+    Err(ProofError::Incomplete)
+}
+
+/// Value under `key` in the stored trie at `root`, reading only the nodes
+/// on its path with `fetch`.
+pub fn lookup(
+    root: &H256,
+    key: &[u8],
+    mut fetch: impl FnMut(&H256) -> Result<Option<Vec<u8>>, StorageError>,
+) -> Result<Option<Vec<u8>>, StorageError> {
+    let key = nibbles(key);
+    let mut path = &key[..];
+    let mut next = *root;
+    while next != ZERO_HASH {
+        let encoded = fetch(&next)?.ok_or_else(|| StorageError::Missing(format!("trie node {}", to_hex(&next))))?;
+        if hash(&encoded) != next {
+            return Err(StorageError::Corrupt {
+                what: "trie node",
+                source: DecodeError::Invalid("node does not match its hash"),
+            });
+        }
+        match descend(&encoded, path).map_err(|source| StorageError::Corrupt {
+            what: "trie node",
+            source,
+        })? {
+            Ok((child, rest)) => {
+                next = child;
+                path = rest;
+            }
+            Err(value) => return Ok(value),
+        }
+    }
+    Ok(None)
+}
+
+/// Like `lookup`, also returning the nodes read as a proof of the value, or
+/// of its absence, for `verify_proof`.
+pub fn prove_stored(
+    root: &H256,
+    key: &[u8],
+    mut fetch: impl FnMut(&H256) -> Result<Option<Vec<u8>>, StorageError>,
+) -> Result<(Option<Vec<u8>>, Proof), StorageError> {
+    let mut proof = Vec::new();
+    let value = lookup(root, key, |hash| {
+        let node = fetch(hash)?;
+        proof.extend(node.clone());
+        Ok(node)
+    })?;
+    Ok((value, proof))
+}
+
+fn nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]).collect()
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+fn empty_children() -> Box<[Option<Node>; 16]> {
+    Box::default()
+}
+
+/// Node standing for `node` below `prefix`, merging adjacent paths.
+fn with_prefix(prefix: &[u8], node: Node) -> Node {
+    if prefix.is_empty() {
+        return node;
+    }
+    match node {
+        Node::Leaf { path, value } => Node::Leaf {
+            path: [prefix, &path].concat(),
+            value,
+        },
+        Node::Extension { path, child } => Node::Extension {
+            path: [prefix, &path].concat(),
+            child,
+        },
+        branch => Node::Extension {
+            path: prefix.to_vec(),
+            child: Box::new(branch),
+        },
+    }
+}
+
+/// Branch holding `node` under the nibble path `path`.
+fn place(children: &mut [Option<Node>; 16], value: &mut Option<Vec<u8>>, path: &[u8], node: Node) {
+    match (path.split_first(), node) {
+        (None, Node::Leaf { value: leaf, .. }) => *value = Some(leaf),
+        (None, _) => unreachable!("only leaves end on a branch"),
+        (Some((&nibble, rest)), node) => children[nibble as usize] = Some(with_prefix(rest, node)),
+    }
+}
+
+fn insert(node: Node, path: &[u8], value: Vec<u8>) -> (Node, Option<Vec<u8>>) {
+    match node {
+        Node::Leaf { path: leaf, value: old } => {
+            if leaf[..] == *path {
+                return (Node::Leaf { path: leaf, value }, Some(old));
+            }
+            let common = common_prefix(&leaf, path);
+            let mut children = empty_children();
+            let mut branch_value = None;
+            place(
+                &mut children,
+                &mut branch_value,
+                &leaf[common..],
+                Node::Leaf {
+                    path: Vec::new(),
+                    value: old,
+                },
+            );
+            place(
+                &mut children,
+                &mut branch_value,
+                &path[common..],
+                Node::Leaf {
+                    path: Vec::new(),
+                    value,
+                },
+            );
+            let branch = Node::Branch {
+                children,
+                value: branch_value,
+            };
+            (with_prefix(&path[..common], branch), None)
+        }
+        Node::Extension { path: prefix, child } => {
+            if let Some(rest) = path.strip_prefix(&prefix[..]) {
+                let (child, prev) = insert(*child, rest, value);
+                return (with_prefix(&prefix, child), prev);
+            }
+            // Split the extension where the key leaves it
+            let common = common_prefix(&prefix, path);
+            let mut children = empty_children();
+            let mut branch_value = None;
+            children[prefix[common] as usize] = Some(with_prefix(&prefix[common + 1..], *child));
+            place(
+                &mut children,
+                &mut branch_value,
+                &path[common..],
+                Node::Leaf {
+                    path: Vec::new(),
+                    value,
+                },
+            );
+            let branch = Node::Branch {
+                children,
+                value: branch_value,
+            };
+            (with_prefix(&path[..common], branch), None)
+        }
+        Node::Branch {
+            mut children,
+            value: mut branch_value,
+        } => {
+            let prev = match path.split_first() {
+                None => branch_value.replace(value),
+                Some((&nibble, rest)) => {
+                    let slot = &mut children[nibble as usize];
+                    let (child, prev) = match slot.take() {
+                        None => (
+                            Node::Leaf {
+                                path: rest.to_vec(),
+                                value,
+                            },
+                            None,
+                        ),
+                        Some(child) => insert(child, rest, value),
+                    };
+                    *slot = Some(child);
+                    prev
+                }
+            };
+            (
+                Node::Branch {
+                    children,
+                    value: branch_value,
+                },
+                prev,
+            )
+        }
+    }
+}
+
+fn remove(node: Node, path: &[u8]) -> (Option<Node>, Option<Vec<u8>>) {
+    match node {
+        Node::Leaf { path: leaf, value } => {
+            if leaf[..] == *path {
+                (None, Some(value))
+            } else {
+                (Some(Node::Leaf { path: leaf, value }), None)
+            }
+        }
+        Node::Extension { path: prefix, child } => {
+            let Some(rest) = path.strip_prefix(&prefix[..]) else {
+                return (Some(Node::Extension { path: prefix, child }), None);
+            };
+            let (child, prev) = remove(*child, rest);
+            (child.map(|child| with_prefix(&prefix, child)), prev)
+        }
+        Node::Branch {
+            mut children,
+            value: mut branch_value,
+        } => {
+            let prev = match path.split_first() {
+                None => branch_value.take(),
+                Some((&nibble, rest)) => match children[nibble as usize].take() {
+                    None => None,
+                    Some(child) => {
+                        let (child, prev) = remove(child, rest);
+                        children[nibble as usize] = child;
+                        prev
+                    }
+                },
+            };
+            if prev.is_none() {
+                return (
+                    Some(Node::Branch {
+                        children,
+                        value: branch_value,
+                    }),
+                    None,
+                );
+            }
+            (Some(collapse(children, branch_value)), prev)
+        }
+    }
+}
+
+/// Normal form of a branch after a removal: a branch with a single entry
+/// left becomes that entry.
+fn collapse(mut children: Box<[Option<Node>; 16]>, value: Option<Vec<u8>>) -> Node {
+    let mut occupied = children.iter().enumerate().filter(|(_, child)| child.is_some());
+    let only = match (occupied.next(), occupied.next()) {
+        (Some((nibble, _)), None) => Some(nibble),
+        _ => None,
+    };
+    match (only, value) {
+        (None, Some(value)) if children.iter().all(Option::is_none) => Node::Leaf {
+            path: Vec::new(),
+            value,
+        },
+        (Some(nibble), None) => {
+            let child = children[nibble].take().expect("occupied child");
+            with_prefix(&[nibble as u8], child)
+        }
+        (_, value) => Node::Branch { children, value },
+    }
+}
+
+fn hash(encoded: &[u8]) -> H256 {
+    *blake3::hash(encoded).as_bytes()
+}
+
+/// Hash `node`, passing every node below it and itself to `visit`.
+fn hash_node(node: &Node, visit: &mut impl FnMut(H256, &[u8])) -> H256 {
+    let encoded = encode_node(node, visit);
+    let hash = hash(&encoded);
+    visit(hash, &encoded);
+    hash
+}
+
+/// Hash `node`, adding it to `nodes` along with the nodes below it that
+/// lie on one of the nibble `paths` and their children. Children off the
+/// paths are included because changing a key can rewrite the sibling it
+/// splits from or merges with.
+fn hash_along(node: &Node, paths: &[&[u8]], nodes: &mut HashMap<H256, Vec<u8>>) -> H256 {
+    let encoded = if paths.is_empty() {
+        encode_node(node, &mut |_, _| ())
+    } else {
+        encode_with(node, &mut |step, child| {
+            let rest: Vec<&[u8]> = paths.iter().filter_map(|path| path.strip_prefix(step)).collect();
+            hash_along(child, &rest, nodes)
+        })
+    };
+    let hash = hash(&encoded);
+    nodes.insert(hash, encoded);
+    hash
+}
+
+fn encode_node(node: &Node, visit: &mut impl FnMut(H256, &[u8])) -> Vec<u8> {
+    encode_with(node, &mut |_, child| hash_node(child, visit))
+}
+
+/// Encode `node`, getting each child's hash from `child_hash` along with
+/// the nibbles leading to it.
+fn encode_with(node: &Node, child_hash: &mut impl FnMut(&[u8], &Node) -> H256) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    match node {
+        Node::Leaf { path, value } => {
+            enc.put_u8(LEAF).put_bytes(path).put_bytes(value);
+        }
+        Node::Extension { path, child } => {
+            enc.put_u8(EXTENSION).put_bytes(path).put_fixed(&child_hash(path, child));
+        }
+        Node::Branch { children, value } => {
+            let bitmap = children
+                .iter()
+                .enumerate()
+                .filter(|(_, child)| child.is_some())
+                .fold(0u32, |bits, (nibble, _)| bits | 1 << nibble);
+            enc.put_u8(BRANCH).put_u32(bitmap);
+            for (nibble, child) in children.iter().enumerate() {
+                if let Some(child) = child {
+                    enc.put_fixed(&child_hash(&[nibble as u8], child));
+                }
+            }
+            match value {
+                Some(value) => enc.put_u8(1).put_bytes(value),
+                None => enc.put_u8(0),
+            };
+        }
+    }
+    enc.finish()
+}
+
+fn decode_node(bytes: &[u8]) -> Result<RawNode, DecodeError> {
+    let mut dec = Decoder::new(bytes);
+    let node = match dec.get_u8()? {
+        LEAF => RawNode::Leaf {
+            path: decode_path(&mut dec)?,
+            value: dec.get_bytes()?,
+        },
+        EXTENSION => {
+            let path = decode_path(&mut dec)?;
+            if path.is_empty() {
+                return Err(DecodeError::Invalid("empty extension path"));
+            }
+            RawNode::Extension {
+                path,
+                child: dec.get_fixed()?,
+            }
+        }
+        BRANCH => {
+            let bitmap = dec.get_u32()?;
+            if bitmap >> 16 != 0 {
+                return Err(DecodeError::Invalid("branch bitmap"));
+            }
+            let mut children = Box::new([None; 16]);
+            for (nibble, child) in children.iter_mut().enumerate() {
+                if bitmap & 1 << nibble != 0 {
+                    *child = Some(dec.get_fixed()?);
+                }
+            }
+            let value = match dec.get_u8()? {
+                0 => None,
+                1 => Some(dec.get_bytes()?),
+                _ => return Err(DecodeError::Invalid("branch value flag")),
+            };
+            RawNode::Branch { children, value }
+        }
+        _ => return Err(DecodeError::Invalid("trie node tag")),
+    };
+    dec.finish()?;
+    Ok(node)
+}
+
+fn decode_path(dec: &mut Decoder<'_>) -> Result<Vec<u8>, DecodeError> {
+    let path = dec.get_bytes()?;
+    if path.iter().any(|&nibble| nibble > 0x0f) {
+        return Err(DecodeError::Invalid("nibble out of range"));
+    }
+    Ok(path)
+}
+
+fn load_node(node_hash: &H256, lookup: &mut impl FnMut(&H256) -> Option<Vec<u8>>, len: &mut usize) -> Option<Node> {
+    let encoded = lookup(node_hash)?;
+    if hash(&encoded) != *node_hash {
+        return None;
+    }
+    let node = match decode_node(&encoded).ok()? {
+        RawNode::Leaf { path, value } => {
+            *len += 1;
+            Node::Leaf { path, value }
+        }
+        RawNode::Extension { path, child } => Node::Extension {
+            path,
+            child: Box::new(load_node(&child, lookup, len)?),
+        },
+        RawNode::Branch {
+            children: hashes,
+            value,
+        } => {
+            let mut children = empty_children();
+            for (slot, child) in children.iter_mut().zip(*hashes) {
+                if let Some(child) = child {
+                    *slot = Some(load_node(&child, lookup, len)?);
+                }
+            }
+            *len += usize::from(value.is_some());
+            Node::Branch { children, value }
+        }
+    };
+    Some(node)
+}
+
+fn collect_entries<'a>(node: &'a Node, path: &mut Vec<u8>, entries: &mut Vec<(Vec<u8>, &'a [u8])>) {
+    let key = |path: &[u8]| {
+        path.chunks(2)
+            .map(|pair| pair[0] << 4 | pair.get(1).unwrap_or(&0))
+            .collect()
+    };
+    match node {
+        Node::Leaf { path: leaf, value } => {
+            let len = path.len();
+            path.extend_from_slice(leaf);
+            entries.push((key(path), value));
+            path.truncate(len);
+        }
+        Node::Extension { path: prefix, child } => {
+            let len = path.len();
+            path.extend_from_slice(prefix);
+            collect_entries(child, path, entries);
+            path.truncate(len);
+        }
+        Node::Branch { children, value } => {
+            if let Some(value) = value {
+                entries.push((key(path), value));
+            }
+            for (nibble, child) in children.iter().enumerate() {
+                if let Some(child) = child {
+                    path.push(nibble as u8);
+                    collect_entries(child, path, entries);
+                    path.pop();
+                }
+            }
+        }
+    }
+}
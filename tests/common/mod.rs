@@ -0,0 +1,134 @@
+//! Fixtures shared by the unit and integration test crates.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use secp256k1::SecretKey;
+use tburn_chain_v4_0::contracts::deployer::ContractDeployer;
+use tburn_chain_v4_0::contracts::executor::{
+    compute_balance_slot, selectors, u128_to_u256, Tbc20FastPathExecutor, Tbc20Registry, Tbc20TokenInfo,
+};
+use tburn_chain_v4_0::core::executor::BlockEnv;
+use tburn_chain_v4_0::core::transaction::secret_key_to_address;
+use tburn_chain_v4_0::core::types::H256;
+use tburn_chain_v4_0::core::{Address, GasSchedule, SignedTransaction, StateDB, Transaction, WorldState};
+
+pub const CHAIN_ID: u64 = 1337;
+pub const PROPOSER: Address = Address([0xfe; 20]);
+/// Fast path TBC-20 registered by `token_env`
+pub const TOKEN: Address = Address([0x70; 20]);
+/// Contract that increments slot 0 on every call
+pub const COUNTER: Address = Address([0xc0; 20]);
+
+/// Environment of block 7 with a 30M gas limit, no registered tokens and
+/// the default deployer.
+pub fn env() -> BlockEnv {
+    BlockEnv {
+        chain_id: CHAIN_ID,
+        number: 7,
+        timestamp: 1_704_067_200_000,
+        proposer: PROPOSER,
+        gas_limit: 30_000_000,
+        base_fee: 1,
+        schedule: GasSchedule::default(),
+        fast_path: Arc::new(Tbc20FastPathExecutor::new(Arc::new(Tbc20Registry::new()))),
+        deployer: Arc::new(ContractDeployer::new()),
+    }
+}
+
+/// `env` with `gas_limit` and `TOKEN` registered for the fast path.
+pub fn token_env(gas_limit: u64) -> BlockEnv {
+    let registry = Arc::new(Tbc20Registry::new());
+    registry.register(Tbc20TokenInfo {
+        address: TOKEN,
+        symbol: "TEST".to_string(),
+        ..Default::default()
+    });
+    BlockEnv {
+        gas_limit,
+        fast_path: Arc::new(Tbc20FastPathExecutor::new(registry)),
+        ..env()
+    }
+}
+
+pub fn key(byte: u8) -> SecretKey {
+    SecretKey::from_slice(&[byte; 32]).unwrap()
+}
+
+pub fn sender(byte: u8) -> Address {
+    secret_key_to_address(&key(byte))
+}
+
+/// Senders 1 to 20 with Ember and `TOKEN` balances, and `COUNTER`.
+pub fn genesis() -> WorldState {
+    let mut state = WorldState::new();
+    for byte in 1..=20 {
+        state.set_balance(&sender(byte), 1_000_000_000);
+        state.set_storage(&TOKEN, &compute_balance_slot(&sender(byte)), u128_to_u256(1_000));
+    }
+    state.set_code(&TOKEN, vec![0x00]);
+    state.set_code(&COUNTER, vec![0x5f, 0x54, 0x60, 0x01, 0x01, 0x5f, 0x55, 0x00]);
+    state.commit();
+    state
+}
+
+pub fn transfer(signer: u8, nonce: u64, to: Address, value: u128) -> SignedTransaction {
+    Transaction {
+        chain_id: CHAIN_ID,
+        nonce,
+        gas_price: 2,
+        gas_limit: 100_000,
+        to: Some(to),
+        value,
+        data: Vec::new(),
+    }
+    .sign(&key(signer))
+}
+
+pub fn token_transfer(signer: u8, nonce: u64, to: Address, amount: u128) -> SignedTransaction {
+    transfer_of(TOKEN, signer, nonce, to, amount)
+}
+
+/// TBC-20 `transfer` of `amount` of `token` to `to`.
+pub fn transfer_of(token: Address, signer: u8, nonce: u64, to: Address, amount: u128) -> SignedTransaction {
+    let mut data = selectors::TRANSFER.to_vec();
+    data.extend_from_slice(&[0u8; 12]);
+    data.extend_from_slice(to.as_bytes());
+    data.extend_from_slice(&u128_to_u256(amount));
+    Transaction {
+        chain_id: CHAIN_ID,
+        nonce,
+        gas_price: 2,
+        gas_limit: 100_000,
+        to: Some(token),
+        value: 0,
+        data,
+    }
+    .sign(&key(signer))
+}
+
+/// Init code that deploys `runtime`, which must be at most 32 bytes.
+pub fn init_code(runtime: &[u8]) -> Vec<u8> {
+    let mut padded = [0u8; 32];
+    padded[..runtime.len()].copy_from_slice(runtime);
+    let mut init = vec![0x7f];
+    init.extend_from_slice(&padded);
+    // MSTORE at 0, RETURN(0, len)
+    init.extend_from_slice(&[0x5f, 0x52, 0x60, runtime.len() as u8, 0x5f, 0xf3]);
+    init
+}
+
+/// Init code that stores each `(slot, value)` and deploys a single STOP.
+pub fn storage_init_code(writes: &[(H256, H256)]) -> Vec<u8> {
+    let mut code = Vec::new();
+    for (slot, value) in writes {
+        code.push(0x7f);
+        code.extend_from_slice(value);
+        code.push(0x7f);
+        code.extend_from_slice(slot);
+        code.push(0x55);
+    }
+    // RETURN(0, 1) of zeroed memory
+    code.extend_from_slice(&[0x60, 0x01, 0x5f, 0xf3]);
+    code
+}
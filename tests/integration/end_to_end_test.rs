@@ -0,0 +1,472 @@
+use std::sync::Arc;
+
+use tburn_chain_v4_0::contracts::executor::{compute_balance_slot, events, selectors, u128_to_u256};
+use tburn_chain_v4_0::contracts::Tbc20TokenInfo;
+use tburn_chain_v4_0::core::block::transactions_root;
+use tburn_chain_v4_0::core::executor::create_address;
+use tburn_chain_v4_0::core::gas::TX_BASE_GAS;
+use tburn_chain_v4_0::core::genesis::GenesisBuild;
+use tburn_chain_v4_0::core::parallel::BatchProcessor;
+use tburn_chain_v4_0::core::producer::ImportError;
+use tburn_chain_v4_0::core::transaction::secret_key_to_address;
+use tburn_chain_v4_0::core::{
+    Address, BlockImporter, BlockProducer, Blockchain, ChainParams, GasSchedule, Genesis, LogFilter, Mempool,
+    MempoolConfig, StateDB, Transaction, WorldState,
+};
+use tburn_chain_v4_0::storage::rocksdb::{KvStore, MemoryStore};
+use tokio::sync::RwLock;
+
+#[path = "../common/mod.rs"]
+mod common;
+
+use common::{init_code, key, CHAIN_ID, PROPOSER};
+
+fn genesis() -> GenesisBuild {
+    let json = format!(
+        r#"{{
+            "chainId": {CHAIN_ID},
+            "networkId": {CHAIN_ID},
+            "name": "End To End",
+            "timestamp": "2024-01-01T00:00:00Z",
+            "sharding": {{ "enabled": false, "shards": [] }},
+            "genesis": {{ "gasLimit": "0x1c9c380", "extraData": "0x" }},
+            "alloc": {{
+                "{}": {{ "balance": "1000000000000" }},
+                "{}": {{ "balance": "1000000000000" }}
+            }}
+        }}"#,
+        secret_key_to_address(&key(1)),
+        secret_key_to_address(&key(2)),
+    );
+    Genesis::from_json(&json).unwrap().build().unwrap()
+}
+
+/// Chain, state and mempool of one node.
+struct Node {
+    chain: Arc<Blockchain>,
+    state: Arc<RwLock<WorldState>>,
+    mempool: Arc<parking_lot::RwLock<Mempool>>,
+    params: ChainParams,
+}
+
+impl Node {
+    fn new(gas_limit: u64) -> Self {
+        let genesis = genesis();
+        Self::with_chain(Blockchain::new(genesis.block), genesis.state, gas_limit)
+    }
+
+    /// Node persisting to `db`, resuming from whatever it already holds.
+    async fn open(db: Arc<dyn KvStore>, gas_limit: u64) -> Self {
+        let genesis = genesis();
+        let chain = Blockchain::open(db.clone(), genesis.block, &genesis.state).unwrap();
+        let state = WorldState::load(db.as_ref(), &chain.head().await.header.state_root).unwrap();
+        Self::with_chain(chain, state, gas_limit)
+    }
+
+    fn with_chain(chain: Blockchain, state: WorldState, gas_limit: u64) -> Self {
+        Self {
+            chain: Arc::new(chain),
+            state: Arc::new(RwLock::new(state)),
+            mempool: Arc::new(parking_lot::RwLock::new(Mempool::new(MempoolConfig {
+                chain_id: CHAIN_ID,
+                capacity: 1_000,
+                min_gas_price: 1,
+                max_tx_gas: gas_limit,
+                price_bump_percent: 10,
+                schedule: GasSchedule::default(),
+            }))),
+            params: ChainParams::new(CHAIN_ID, gas_limit),
+        }
+    }
+
+    fn producer(&self) -> BlockProducer {
+        BlockProducer::new(
+            self.params.clone(),
+            PROPOSER,
+            self.chain.clone(),
+            self.state.clone(),
+            self.mempool.clone(),
+        )
+    }
+
+    fn importer(&self) -> BlockImporter {
+        BlockImporter::new(
+            self.params.clone(),
+            Arc::new(BatchProcessor::new(2).unwrap()),
+            self.chain.clone(),
+            self.state.clone(),
+            self.mempool.clone(),
+        )
+    }
+
+    /// Register `token` as a fast path TBC-20 and credit `holder`.
+    async fn deploy_token(&self, token: Address, holder: Address, balance: u128) {
+        self.params.fast_path.registry().register(Tbc20TokenInfo {
+            address: token,
+            symbol: "TEST".to_string(),
+            ..Default::default()
+        });
+        let mut state = self.state.write().await;
+        state.set_storage(&token, &compute_balance_slot(&holder), u128_to_u256(balance));
+        state.commit();
+    }
+
+    async fn submit(&self, tx: Transaction, signer: u8) {
+        let state = self.state.read().await;
+        self.mempool.write().add(tx.sign(&key(signer)), &*state).unwrap();
+    }
+}
+
+fn transfer(nonce: u64, to: Address, value: u128) -> Transaction {
+    Transaction {
+        chain_id: CHAIN_ID,
+        nonce,
+        gas_price: 2,
+        gas_limit: 50_000,
+        to: Some(to),
+        value,
+        data: Vec::new(),
+    }
+}
+
+#[tokio::test]
+async fn produced_blocks_are_imported_by_a_second_node() {
+    let producer_node = Node::new(30_000_000);
+    let follower = Node::new(30_000_000);
+    let recipient = Address([0x42; 20]);
+
+    producer_node.submit(transfer(0, recipient, 100), 1).await;
+    producer_node.submit(transfer(1, recipient, 200), 1).await;
+    producer_node.submit(transfer(0, recipient, 300), 2).await;
+
+    let executed = producer_node.producer().produce(1_704_067_201_000).await.unwrap();
+    assert_eq!(executed.block.tx_count(), 3);
+    assert_eq!(executed.receipts.len(), 3);
+    assert!(executed.receipts.iter().all(|r| r.success));
+    assert_eq!(executed.block.header.gas_used, 3 * TX_BASE_GAS);
+    assert!(producer_node.mempool.read().is_empty());
+
+    {
+        let state = producer_node.state.read().await;
+        assert_eq!(state.get_balance(&recipient), 600);
+        // Half of every fee is burned
+        assert_eq!(state.get_balance(&PROPOSER), 3 * TX_BASE_GAS as u128);
+        assert_eq!(state.get_nonce(&secret_key_to_address(&key(1))), 2);
+        assert_eq!(
+            state.get_balance(&secret_key_to_address(&key(1))),
+            1_000_000_000_000 - 300 - 2 * TX_BASE_GAS as u128 * 2
+        );
+    }
+
+    let burned: u128 = executed.receipts.iter().map(|r| r.burned).sum();
+    assert_eq!(burned, 3 * TX_BASE_GAS as u128);
+    assert_eq!(producer_node.chain.total_burned().await, burned);
+
+    let receipts = follower.importer().import(executed.block.clone()).await.unwrap();
+    assert_eq!(receipts, executed.receipts);
+    assert_eq!(follower.chain.total_burned().await, burned);
+    assert_eq!(follower.chain.head().await.hash(), executed.block.hash());
+    assert_eq!(
+        follower.state.read().await.state_root(),
+        producer_node.state.read().await.state_root()
+    );
+}
+
+#[tokio::test]
+async fn nodes_resume_from_storage_after_restart() {
+    let db: Arc<dyn KvStore> = Arc::new(MemoryStore::new());
+    let recipient = Address([0x42; 20]);
+    let (head, root) = {
+        let node = Node::open(db.clone(), 30_000_000).await;
+        node.submit(transfer(0, recipient, 100), 1).await;
+        node.producer().produce(1_704_067_201_000).await.unwrap();
+        node.submit(transfer(1, recipient, 200), 1).await;
+        let executed = node.producer().produce(1_704_067_202_000).await.unwrap();
+        let root = node.state.read().await.state_root();
+        (executed.block, root)
+    };
+
+    let node = Node::open(db, 30_000_000).await;
+    assert_eq!(node.chain.head().await.hash(), head.hash());
+    assert_eq!(node.state.read().await.state_root(), root);
+    assert_eq!(node.state.read().await.get_balance(&recipient), 300);
+    assert_eq!(node.chain.get_receipts(2).await.unwrap().len(), 1);
+    assert_eq!(node.chain.total_burned().await, 2 * TX_BASE_GAS as u128);
+
+    // Production carries on from the stored head
+    node.submit(transfer(2, recipient, 300), 1).await;
+    let executed = node.producer().produce(1_704_067_203_000).await.unwrap();
+    assert_eq!(executed.block.number(), 3);
+    assert_eq!(executed.block.header.parent_hash, head.hash());
+}
+
+#[tokio::test]
+async fn block_gas_limit_defers_transactions() {
+    let node = Node::new(2 * TX_BASE_GAS + 10_000);
+    for nonce in 0..3 {
+        let mut tx = transfer(nonce, Address([0x42; 20]), 1);
+        tx.gas_limit = TX_BASE_GAS;
+        node.submit(tx, 1).await;
+    }
+
+    let first = node.producer().produce(1_704_067_201_000).await.unwrap();
+    assert_eq!(first.block.tx_count(), 2);
+    assert_eq!(node.mempool.read().len(), 1);
+
+    let second = node.producer().produce(1_704_067_202_000).await.unwrap();
+    assert_eq!(second.block.tx_count(), 1);
+    assert_eq!(second.block.header.parent_hash, first.block.hash());
+    assert!(node.mempool.read().is_empty());
+}
+
+#[tokio::test]
+async fn full_blocks_raise_the_base_fee_which_is_burned() {
+    let node = Node::new(2 * TX_BASE_GAS);
+    let recipient = Address([0x42; 20]);
+    for nonce in 0..3 {
+        let mut tx = transfer(nonce, recipient, 1);
+        tx.gas_limit = TX_BASE_GAS;
+        node.submit(tx, 1).await;
+    }
+    let mut cheap = transfer(0, recipient, 1);
+    cheap.gas_limit = TX_BASE_GAS;
+    cheap.gas_price = 1;
+    node.submit(cheap, 2).await;
+
+    // Block 1 runs at the genesis base fee and fills the block
+    let first = node.producer().produce(1_704_067_201_000).await.unwrap();
+    assert_eq!(first.block.header.base_fee, 1);
+    assert_eq!(first.block.tx_count(), 2);
+
+    // Twice the gas target: the base fee rises and the cheap transaction is evicted
+    assert_eq!(node.mempool.read().len(), 1);
+    assert_eq!(node.mempool.read().base_fee(), 2);
+    let second = node.producer().produce(1_704_067_202_000).await.unwrap();
+    assert_eq!(second.block.header.base_fee, 2);
+    assert_eq!(second.block.tx_count(), 1);
+    assert_eq!(second.receipts[0].burned, 2 * TX_BASE_GAS as u128);
+    assert!(node.mempool.read().is_empty());
+
+    // Tips: 1 Ember per gas in block 1, none in block 2
+    assert_eq!(node.state.read().await.get_balance(&PROPOSER), 2 * TX_BASE_GAS as u128);
+    assert_eq!(node.chain.total_burned().await, 4 * TX_BASE_GAS as u128);
+
+    // Block 2 hit the target exactly, so the base fee holds
+    let third = node.producer().produce(1_704_067_203_000).await.unwrap();
+    assert_eq!(third.block.header.base_fee, 2);
+    assert_eq!(third.block.tx_count(), 0);
+}
+
+#[tokio::test]
+async fn tampered_blocks_are_rejected_without_touching_state() {
+    let producer_node = Node::new(30_000_000);
+    let follower = Node::new(30_000_000);
+    producer_node.submit(transfer(0, Address([0x42; 20]), 100), 1).await;
+    let executed = producer_node.producer().produce(1_704_067_201_000).await.unwrap();
+
+    let root_before = follower.state.read().await.state_root();
+    let genesis_hash = follower.chain.head().await.hash();
+
+    let mut bad_state = executed.block.clone();
+    bad_state.header.state_root = [0xab; 32];
+    assert!(matches!(
+        follower.importer().import(bad_state).await,
+        Err(ImportError::StateRootMismatch)
+    ));
+
+    let mut bad_base_fee = executed.block.clone();
+    bad_base_fee.header.base_fee += 1;
+    assert!(matches!(
+        follower.importer().import(bad_base_fee).await,
+        Err(ImportError::BaseFeeMismatch { expected: 1, got: 2 })
+    ));
+
+    let mut bad_receipts = executed.block.clone();
+    bad_receipts.header.receipts_root = [0xcd; 32];
+    assert!(matches!(
+        follower.importer().import(bad_receipts).await,
+        Err(ImportError::ReceiptsRootMismatch)
+    ));
+
+    // Replaying the same transaction twice fails on the nonce
+    let mut replay = executed.block.clone();
+    replay.transactions.push(replay.transactions[0].clone());
+    replay.header.transactions_root = transactions_root(&replay.transactions);
+    assert!(matches!(
+        follower.importer().import(replay).await,
+        Err(ImportError::InvalidTransaction { index: 1, .. })
+    ));
+
+    assert_eq!(follower.state.read().await.state_root(), root_before);
+    assert_eq!(follower.chain.head().await.hash(), genesis_hash);
+
+    follower.importer().import(executed.block).await.unwrap();
+    assert_eq!(follower.chain.get_height().await, 1);
+}
+
+fn token_transfer(nonce: u64, token: Address, to: Address, amount: u128) -> Transaction {
+    let mut data = selectors::TRANSFER.to_vec();
+    data.extend_from_slice(&[0u8; 12]);
+    data.extend_from_slice(to.as_bytes());
+    data.extend_from_slice(&u128_to_u256(amount));
+    Transaction {
+        to: Some(token),
+        data,
+        gas_limit: 100_000,
+        ..transfer(nonce, token, 0)
+    }
+}
+
+fn word(address: &Address) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address.as_bytes());
+    word
+}
+
+#[tokio::test]
+async fn token_transfers_emit_logs_that_can_be_queried() {
+    let token = Address([0x70; 20]);
+    let sender = secret_key_to_address(&key(1));
+    let recipient = Address([0x42; 20]);
+    let producer_node = Node::new(30_000_000);
+    let follower = Node::new(30_000_000);
+    for node in [&producer_node, &follower] {
+        node.deploy_token(token, sender, 1_000).await;
+    }
+
+    producer_node.submit(token_transfer(0, token, recipient, 250), 1).await;
+    producer_node.submit(token_transfer(1, token, recipient, 5_000), 1).await;
+    producer_node.submit(transfer(0, recipient, 1), 2).await;
+    let executed = producer_node.producer().produce(1_704_067_201_000).await.unwrap();
+
+    let receipts = &executed.receipts;
+    assert_eq!(receipts.len(), 3);
+    assert!(receipts[0].success);
+    assert_eq!(receipts[0].logs.len(), 1);
+    assert_eq!(
+        receipts[0].logs[0].topics,
+        vec![events::TRANSFER, word(&sender), word(&recipient)]
+    );
+    // Second transfer exceeds the token balance: reverted, no logs
+    assert!(!receipts[1].success);
+    assert!(receipts[1].logs.is_empty());
+    assert_eq!(receipts[2].cumulative_gas_used, receipts.iter().map(|r| r.gas_used).sum::<u64>());
+
+    let bloom = executed.block.header.logs_bloom;
+    assert!(bloom.contains_input(token.as_bytes()));
+    assert!(bloom.contains_input(&events::TRANSFER));
+    assert!(!bloom.contains_input(&events::APPROVAL));
+
+    follower.importer().import(executed.block.clone()).await.unwrap();
+    assert_eq!(
+        follower.state.read().await.get_storage(&token, &compute_balance_slot(&recipient)),
+        u128_to_u256(250)
+    );
+
+    let by_topic = LogFilter {
+        addresses: vec![token],
+        topics: vec![Some(vec![events::TRANSFER]), None, Some(vec![word(&recipient)])],
+        ..Default::default()
+    };
+    let logs = follower.chain.get_logs(&by_topic).await.unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].block_number, 1);
+    assert_eq!(logs[0].tx_hash, receipts[0].tx_hash);
+    assert_eq!(logs[0].log.data, u128_to_u256(250).to_vec());
+
+    let approvals = LogFilter {
+        topics: vec![Some(vec![events::APPROVAL])],
+        ..Default::default()
+    };
+    assert!(follower.chain.get_logs(&approvals).await.unwrap().is_empty());
+
+    let mut bad_bloom = executed.block.clone();
+    bad_bloom.header.logs_bloom = Default::default();
+    let fresh = Node::new(30_000_000);
+    fresh.deploy_token(token, sender, 1_000).await;
+    assert!(matches!(
+        fresh.importer().import(bad_bloom).await,
+        Err(ImportError::LogsBloomMismatch)
+    ));
+}
+
+#[tokio::test]
+async fn out_of_gas_calls_are_charged_the_full_limit() {
+    let token = Address([0x70; 20]);
+    let sender = secret_key_to_address(&key(1));
+    let recipient = Address([0x42; 20]);
+    let node = Node::new(30_000_000);
+    node.deploy_token(token, sender, 1_000).await;
+
+    // Covers intrinsic gas but not the flat fast path price
+    let mut starved = token_transfer(0, token, recipient, 250);
+    starved.gas_limit = 30_000;
+    node.submit(starved, 1).await;
+    let executed = node.producer().produce(1_704_067_201_000).await.unwrap();
+
+    let receipt = &executed.receipts[0];
+    assert!(!receipt.success);
+    assert!(receipt.logs.is_empty());
+    assert_eq!(receipt.gas_used, 30_000);
+    assert_eq!(receipt.burned, 30_000);
+    let state = node.state.read().await;
+    assert_eq!(state.get_storage(&token, &compute_balance_slot(&recipient)), u128_to_u256(0));
+    assert_eq!(state.get_balance(&PROPOSER), 30_000);
+    assert_eq!(state.get_balance(&sender), 1_000_000_000_000 - 60_000);
+}
+
+#[tokio::test]
+async fn deployed_contracts_run_in_the_interpreter() {
+    let sender = secret_key_to_address(&key(1));
+    let contract = create_address(&sender, 0);
+    let producer_node = Node::new(30_000_000);
+    let follower = Node::new(30_000_000);
+
+    // Counter: increments slot 0, logs the new value as a topic and returns it
+    let counter = [
+        0x5f, 0x54, 0x60, 0x01, 0x01, 0x80, 0x5f, 0x55, 0x80, 0x5f, 0x5f, 0xa1, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60,
+        0x00, 0xf3,
+    ];
+    producer_node
+        .submit(
+            Transaction {
+                to: None,
+                data: init_code(&counter),
+                gas_limit: 200_000,
+                ..transfer(0, contract, 0)
+            },
+            1,
+        )
+        .await;
+    for nonce in 1..3 {
+        producer_node
+            .submit(
+                Transaction {
+                    gas_limit: 100_000,
+                    ..transfer(nonce, contract, 0)
+                },
+                1,
+            )
+            .await;
+    }
+    let executed = producer_node.producer().produce(1_704_067_201_000).await.unwrap();
+
+    let receipts = &executed.receipts;
+    assert_eq!(receipts.len(), 3);
+    assert!(receipts.iter().all(|r| r.success));
+    assert_eq!(receipts[0].contract_address, Some(contract));
+    let mut one = [0u8; 32];
+    one[31] = 1;
+    let mut two = [0u8; 32];
+    two[31] = 2;
+    assert_eq!(receipts[1].logs[0].address, contract);
+    assert_eq!(receipts[1].logs[0].topics, vec![one]);
+    assert_eq!(receipts[2].logs[0].topics, vec![two]);
+    // The first increment pays for a fresh slot, the second only for a reset
+    assert!(receipts[1].gas_used > receipts[2].gas_used);
+
+    follower.importer().import(executed.block.clone()).await.unwrap();
+    let state = follower.state.read().await;
+    assert_eq!(state.code(&contract), counter.to_vec());
+    assert_eq!(state.get_storage(&contract, &[0u8; 32]), two);
+}
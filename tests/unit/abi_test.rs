@@ -0,0 +1,241 @@
+use primitive_types::U256;
+use tburn_chain_v4_0::contracts::abi::{self, Abi, AbiError, ParamType, StateMutability, Token};
+use tburn_chain_v4_0::contracts::executor::{events, selectors, tbc20_abi};
+use tburn_chain_v4_0::core::types::keccak256;
+use tburn_chain_v4_0::core::{Address, Log};
+
+fn uint(value: u64) -> Token {
+    Token::Uint(U256::from(value))
+}
+
+fn words(hex_words: &[&str]) -> Vec<u8> {
+    hex::decode(hex_words.concat()).unwrap()
+}
+
+#[test]
+fn tbc20_abi_matches_executor_constants() {
+    let abi = tbc20_abi();
+    assert_eq!(abi::selector("transfer(address,uint256)"), selectors::TRANSFER);
+    for (name, selector) in [
+        ("transfer", selectors::TRANSFER),
+        ("transferFrom", selectors::TRANSFER_FROM),
+        ("approve", selectors::APPROVE),
+        ("burn", selectors::BURN),
+        ("mint", selectors::MINT),
+        ("balanceOf", selectors::BALANCE_OF),
+    ] {
+        assert_eq!(abi.function(name).unwrap().selector(), selector, "{name}");
+    }
+    assert_eq!(abi.event("Transfer").unwrap().topic(), events::TRANSFER);
+    assert_eq!(abi.event("Approval").unwrap().topic(), events::APPROVAL);
+    assert_eq!(
+        abi.function("balanceOf").unwrap().state_mutability,
+        StateMutability::View
+    );
+}
+
+#[test]
+fn types_parse_and_print_canonically() {
+    for (input, canonical) in [
+        ("uint", "uint256"),
+        ("int8", "int8"),
+        ("bytes32[]", "bytes32[]"),
+        ("(address,uint256[])[2]", "(address,uint256[])[2]"),
+        ("((bool,string),bytes)", "((bool,string),bytes)"),
+        ("()", "()"),
+        ("function", "function"),
+        ("(address,function)[]", "(address,function)[]"),
+    ] {
+        assert_eq!(ParamType::parse(input).unwrap().to_string(), canonical);
+    }
+    for invalid in [
+        "uint7",
+        "uint264",
+        "bytes33",
+        "bytes0",
+        "(uint256",
+        "uint256[x]",
+        "float",
+    ] {
+        assert!(
+            matches!(ParamType::parse(invalid), Err(AbiError::InvalidType(_))),
+            "{invalid}"
+        );
+    }
+    assert!(!ParamType::parse("(uint256,bool)[3]").unwrap().is_dynamic());
+    assert!(ParamType::parse("(uint256,string)").unwrap().is_dynamic());
+
+    // Function references are an address and a selector in one bytes24 word
+    let callback = Token::FixedBytes([[0x11; 20].as_slice(), &[0xaa, 0xbb, 0xcc, 0xdd]].concat());
+    let encoded = abi::encode_params(&[ParamType::Function], std::slice::from_ref(&callback)).unwrap();
+    assert_eq!(abi::decode(&[ParamType::Function], &encoded).unwrap(), vec![callback]);
+    assert!(abi::encode_params(&[ParamType::Function], &[Token::FixedBytes(vec![0; 20])]).is_err());
+}
+
+#[test]
+fn encoding_matches_solidity_examples() {
+    // sam(bytes,bool,uint256[]) with ("dave", true, [1, 2, 3])
+    let tokens = vec![
+        Token::Bytes(b"dave".to_vec()),
+        Token::Bool(true),
+        Token::Array(vec![uint(1), uint(2), uint(3)]),
+    ];
+    let expected = words(&[
+        "0000000000000000000000000000000000000000000000000000000000000060",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "00000000000000000000000000000000000000000000000000000000000000a0",
+        "0000000000000000000000000000000000000000000000000000000000000004",
+        "6461766500000000000000000000000000000000000000000000000000000000",
+        "0000000000000000000000000000000000000000000000000000000000000003",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "0000000000000000000000000000000000000000000000000000000000000002",
+        "0000000000000000000000000000000000000000000000000000000000000003",
+    ]);
+    assert_eq!(abi::encode(&tokens), expected);
+    let types = [
+        ParamType::Bytes,
+        ParamType::Bool,
+        ParamType::Array(Box::new(ParamType::Uint(256))),
+    ];
+    assert_eq!(abi::decode(&types, &expected).unwrap(), tokens);
+
+    // f(uint256,uint32[],bytes10,bytes) with (0x123, [0x456, 0x789], "1234567890", "Hello, world!")
+    let tokens = vec![
+        uint(0x123),
+        Token::Array(vec![uint(0x456), uint(0x789)]),
+        Token::FixedBytes(b"1234567890".to_vec()),
+        Token::Bytes(b"Hello, world!".to_vec()),
+    ];
+    let expected = words(&[
+        "0000000000000000000000000000000000000000000000000000000000000123",
+        "0000000000000000000000000000000000000000000000000000000000000080",
+        "3132333435363738393000000000000000000000000000000000000000000000",
+        "00000000000000000000000000000000000000000000000000000000000000e0",
+        "0000000000000000000000000000000000000000000000000000000000000002",
+        "0000000000000000000000000000000000000000000000000000000000000456",
+        "0000000000000000000000000000000000000000000000000000000000000789",
+        "000000000000000000000000000000000000000000000000000000000000000d",
+        "48656c6c6f2c20776f726c642100000000000000000000000000000000000000",
+    ]);
+    assert_eq!(abi::encode(&tokens), expected);
+    let types = ParamType::parse("(uint256,uint32[],bytes10,bytes)").unwrap();
+    let ParamType::Tuple(types) = types else { unreachable!() };
+    assert_eq!(abi::decode(&types, &expected).unwrap(), tokens);
+    assert_eq!(
+        abi::selector("f(uint256,uint32[],bytes10,bytes)"),
+        [0x8b, 0xe6, 0x52, 0x46]
+    );
+}
+
+#[test]
+fn nested_tuples_and_signed_values_round_trip() {
+    let kind = ParamType::parse("(int16,string[],(address,bytes2)[2])").unwrap();
+    let minus_five = Token::Int(!U256::from(4u64));
+    let token = Token::Tuple(vec![
+        minus_five.clone(),
+        Token::Array(vec![Token::String("a,b".into()), Token::String(String::new())]),
+        Token::FixedArray(vec![
+            Token::Tuple(vec![
+                Token::Address(Address([1; 20])),
+                Token::FixedBytes(vec![0xab, 0xcd]),
+            ]),
+            Token::Tuple(vec![Token::Address(Address([2; 20])), Token::FixedBytes(vec![0, 1])]),
+        ]),
+    ]);
+    assert!(token.matches(&kind));
+    let encoded = abi::encode_params(std::slice::from_ref(&kind), std::slice::from_ref(&token)).unwrap();
+    assert_eq!(
+        abi::decode(std::slice::from_ref(&kind), &encoded).unwrap(),
+        vec![token.clone()]
+    );
+
+    assert_eq!(minus_five.to_string(), "-5");
+    assert!(token.to_string().starts_with("(-5, [\"a,b\", \"\"], [("));
+
+    assert_eq!(
+        abi::encode_params(&[kind], &[uint(1)]),
+        Err(AbiError::TypeMismatch("(int16,string[],(address,bytes2)[2])".into()))
+    );
+}
+
+#[test]
+fn non_canonical_or_truncated_data_is_rejected() {
+    let mut dirty_address = [0u8; 32];
+    dirty_address[0] = 1;
+    assert!(abi::decode(&[ParamType::Address], &dirty_address).is_err());
+    assert!(abi::decode(&[ParamType::Bool], &abi::encode(&[uint(2)])).is_err());
+    assert!(abi::decode(&[ParamType::Uint(8)], &abi::encode(&[uint(256)])).is_err());
+    // int8 of 0x80 without sign extension
+    assert!(abi::decode(&[ParamType::Int(8)], &abi::encode(&[uint(0x80)])).is_err());
+    assert!(abi::decode(&[ParamType::Uint(256)], &[0u8; 31]).is_err());
+
+    // An offset past the end, and a length claiming more elements than fit
+    assert!(abi::decode(&[ParamType::Bytes], &abi::encode(&[uint(0x1000)])).is_err());
+    let huge = abi::encode(&[uint(0x20), Token::Uint(U256::from(u64::MAX))]);
+    let array = ParamType::Array(Box::new(ParamType::Uint(256)));
+    assert_eq!(
+        abi::decode(&[array], &huge),
+        Err(AbiError::InvalidData("array longer than data"))
+    );
+}
+
+const TOKEN_ABI: &str = r#"[
+    {"type": "constructor", "inputs": [{"name": "supply", "type": "uint256"}]},
+    {"type": "function", "name": "submit", "stateMutability": "payable",
+     "inputs": [{"name": "order", "type": "tuple", "components": [
+         {"name": "maker", "type": "address"},
+         {"name": "amounts", "type": "uint256[]"}]}],
+     "outputs": [{"name": "id", "type": "bytes32"}]},
+    {"type": "event", "name": "Noted", "anonymous": false, "inputs": [
+        {"name": "tag", "type": "string", "indexed": true},
+        {"name": "who", "type": "address", "indexed": true},
+        {"name": "memo", "type": "string", "indexed": false}]},
+    {"type": "error", "name": "Nope", "inputs": []},
+    {"type": "receive", "stateMutability": "payable"}
+]"#;
+
+#[test]
+fn json_abi_decodes_calls_and_events() {
+    let abi = Abi::from_json(TOKEN_ABI).unwrap();
+    assert_eq!(abi.constructor.as_ref().unwrap()[0].name, "supply");
+    let submit = abi.function("submit").unwrap();
+    assert_eq!(submit.signature(), "submit((address,uint256[]))");
+    assert_eq!(submit.state_mutability, StateMutability::Payable);
+
+    let order = Token::Tuple(vec![Token::Address(Address([7; 20])), Token::Array(vec![uint(5)])]);
+    let data = submit.encode_input(std::slice::from_ref(&order)).unwrap();
+    let (function, args) = abi.decode_call(&data).unwrap();
+    assert_eq!(function.name, "submit");
+    assert_eq!(args, vec![order]);
+    assert_eq!(
+        abi.decode_call(&[1, 2, 3, 4]).unwrap_err(),
+        AbiError::UnknownSelector([1, 2, 3, 4])
+    );
+
+    let event = abi.event("Noted").unwrap();
+    let who = Address([9; 20]);
+    let mut who_topic = [0u8; 32];
+    who_topic[12..].copy_from_slice(&who.0);
+    let log = Log {
+        address: Address([1; 20]),
+        topics: vec![event.topic(), keccak256(b"greeting"), who_topic],
+        data: abi::encode(&[Token::String("hello".into())]),
+    };
+    let (decoded, values) = abi.decode_log(&log).unwrap();
+    assert_eq!(decoded.name, "Noted");
+    assert_eq!(
+        values,
+        vec![
+            Token::FixedBytes(keccak256(b"greeting").to_vec()),
+            Token::Address(who),
+            Token::String("hello".into()),
+        ]
+    );
+
+    let missing_topic = Log {
+        topics: log.topics[..2].to_vec(),
+        ..log
+    };
+    assert!(event.decode_log(&missing_topic).is_err());
+    assert!(Abi::from_json("{}").is_err());
+}
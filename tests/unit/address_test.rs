@@ -0,0 +1,108 @@
+use tburn_chain_v4_0::contracts::executor::{
+    address_to_bytes, addresses_equal, bytes_to_address, generate_system_address, is_factory_address,
+    is_valid_tburn_address, TokenStandard, TBC1155_FACTORY, TBC20_FACTORY, TBC721_FACTORY,
+};
+use tburn_chain_v4_0::core::address::AddressError;
+use tburn_chain_v4_0::core::{Address, NetworkProfile};
+
+const SAMPLE: Address = Address([
+    0x75, 0x1e, 0x76, 0xe8, 0x19, 0x91, 0x96, 0xd4, 0x54, 0x94, 0x1c, 0x45, 0xd1, 0xb3, 0xa3, 0x23, 0xf1, 0x43,
+    0x3b, 0xd6,
+]);
+
+/// Replace the character at `index` with another charset character.
+fn typo(s: &str, index: usize) -> String {
+    let mut chars: Vec<char> = s.chars().collect();
+    chars[index] = if chars[index] == 'q' { 'p' } else { 'q' };
+    chars.into_iter().collect()
+}
+
+#[test]
+fn bech32m_round_trips_on_every_network() {
+    for (network, prefix) in [
+        (NetworkProfile::Mainnet, "tb1"),
+        (NetworkProfile::Testnet, "tbt1"),
+        (NetworkProfile::Devnet, "tbd1"),
+    ] {
+        let encoded = SAMPLE.to_bech32(network);
+        assert!(encoded.starts_with(prefix), "{encoded}");
+        assert_eq!(Address::from_bech32(&encoded).unwrap(), (network, SAMPLE));
+        assert_eq!(Address::from_bech32(&encoded.to_uppercase()).unwrap(), (network, SAMPLE));
+        assert_eq!(Address::parse_for(&encoded, network).unwrap(), SAMPLE);
+    }
+}
+
+#[test]
+fn hex_and_bech32m_forms_parse_to_the_same_address() {
+    let hex = "0x751e76e8199196d454941c45d1b3a323f1433bd6";
+    let bech32 = SAMPLE.to_bech32(NetworkProfile::Mainnet);
+    assert_eq!(hex.parse::<Address>().unwrap(), SAMPLE);
+    assert_eq!(bech32.parse::<Address>().unwrap(), SAMPLE);
+    assert_eq!(SAMPLE.to_string(), hex);
+    assert_eq!(Address::parse_for(hex, NetworkProfile::Devnet).unwrap(), SAMPLE);
+    assert!(addresses_equal(hex, &bech32));
+}
+
+#[test]
+fn typos_fail_the_checksum() {
+    let encoded = SAMPLE.to_bech32(NetworkProfile::Mainnet);
+    for index in [3, 10, encoded.len() - 1] {
+        assert_eq!(Address::from_bech32(&typo(&encoded, index)), Err(AddressError::InvalidChecksum));
+    }
+    assert!(!is_valid_tburn_address(&typo(&encoded, 20)));
+
+    // Transposing two characters is caught too
+    let mut swapped: Vec<char> = encoded.chars().collect();
+    let i = (5..swapped.len() - 1).find(|i| swapped[*i] != swapped[i + 1]).unwrap();
+    swapped.swap(i, i + 1);
+    let swapped: String = swapped.into_iter().collect();
+    assert_eq!(Address::from_bech32(&swapped), Err(AddressError::InvalidChecksum));
+}
+
+#[test]
+fn malformed_and_foreign_addresses_are_rejected() {
+    let devnet = SAMPLE.to_bech32(NetworkProfile::Devnet);
+    assert_eq!(
+        Address::parse_for(&devnet, NetworkProfile::Mainnet),
+        Err(AddressError::WrongNetwork {
+            expected: NetworkProfile::Mainnet,
+            got: NetworkProfile::Devnet,
+        })
+    );
+    assert!(!is_valid_tburn_address(&devnet));
+
+    // Valid Bech32m, but not one of our prefixes
+    let bitcoin = bech32::encode::<bech32::Bech32m>(bech32::Hrp::parse("bc").unwrap(), &SAMPLE.0).unwrap();
+    assert_eq!(Address::from_bech32(&bitcoin), Err(AddressError::UnknownHrp("bc".to_string())));
+
+    // Original Bech32 checksum, not Bech32m
+    let bech32 = bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("tb").unwrap(), &SAMPLE.0).unwrap();
+    assert_eq!(Address::from_bech32(&bech32), Err(AddressError::InvalidChecksum));
+
+    // Wrong payload length
+    let short = bech32::encode::<bech32::Bech32m>(bech32::Hrp::parse("tb").unwrap(), &[1u8; 19]).unwrap();
+    assert_eq!(Address::from_bech32(&short), Err(AddressError::InvalidLength(19)));
+
+    let mixed_case = SAMPLE.to_bech32(NetworkProfile::Mainnet).replacen('q', "Q", 1);
+    assert!(matches!(Address::from_bech32(&mixed_case), Err(AddressError::InvalidBech32(_))));
+
+    // The old unchecked `tb1` + hex format
+    let legacy = format!("tb1{}", hex::encode(SAMPLE.0));
+    assert!(legacy.parse::<Address>().is_err());
+    assert_eq!(address_to_bytes(&legacy), None);
+}
+
+#[test]
+fn system_addresses_match_the_published_factories() {
+    for (label, published, standard) in [
+        ("TBC20_FACTORY", TBC20_FACTORY, TokenStandard::TBC20),
+        ("TBC721_FACTORY", TBC721_FACTORY, TokenStandard::TBC721),
+        ("TBC1155_FACTORY", TBC1155_FACTORY, TokenStandard::TBC1155),
+    ] {
+        let address = generate_system_address(label);
+        assert_eq!(bytes_to_address(&address.0), published);
+        assert_eq!(address_to_bytes(published), Some(address.0));
+        assert_eq!(is_factory_address(&address.to_hex()), Some(standard));
+    }
+    assert_eq!(is_factory_address(&SAMPLE.to_bech32(NetworkProfile::Mainnet)), None);
+}
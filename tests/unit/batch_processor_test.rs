@@ -0,0 +1,160 @@
+use tburn_chain_v4_0::core::executor::{self, BlockEnv, ExecutionError};
+use tburn_chain_v4_0::core::parallel::batch_processor::{partition, AccessSet};
+use tburn_chain_v4_0::core::parallel::mvcc::StateKey;
+use tburn_chain_v4_0::core::parallel::{BatchProcessor, BatchReport};
+use tburn_chain_v4_0::core::{Address, SignedTransaction, WorldState};
+
+#[path = "../common/mod.rs"]
+mod common;
+
+use common::{genesis, sender, token_env, token_transfer, transfer, COUNTER, PROPOSER};
+
+/// Run `txs` serially and through the batch processor on copies of
+/// `state`, check that both agree, and return the batch report.
+fn compare(
+    state: &WorldState,
+    env: &BlockEnv,
+    txs: &[SignedTransaction],
+) -> Result<BatchReport, (usize, ExecutionError)> {
+    let mut serial = state.clone();
+    let mut batched = state.clone();
+    let expected = executor::execute_transactions(&mut serial, env, txs);
+    let output = BatchProcessor::new(4).unwrap().execute(&mut batched, env, txs);
+    match (expected, output) {
+        (Ok(receipts), Ok(output)) => {
+            assert_eq!(output.receipts, receipts);
+            assert_eq!(batched.state_root(), serial.state_root());
+            assert_eq!(batched.pending_changes(), serial.pending_changes());
+            Ok(output.report)
+        }
+        (Err(expected), Err(err)) => {
+            assert_eq!(err, expected);
+            Err(err)
+        }
+        (expected, output) => panic!("serial {expected:?}, batched {:?}", output.map(|o| o.report)),
+    }
+}
+
+#[test]
+fn partition_groups_overlapping_accesses() {
+    let account = |byte: u8| StateKey::Account(Address([byte; 20]));
+    let access = |reads: &[u8], writes: &[u8]| AccessSet {
+        reads: reads.iter().map(|b| account(*b)).collect(),
+        writes: writes.iter().map(|b| account(*b)).collect(),
+        credits: [PROPOSER].into(),
+    };
+    let accesses = [
+        access(&[], &[1]),
+        access(&[9], &[2]),
+        access(&[9], &[3]),
+        access(&[2], &[4]),
+        access(&[], &[1, 5]),
+        access(&[], &[6]),
+    ];
+    // Shared reads and shared fees are not conflicts
+    assert_eq!(partition(&accesses), vec![vec![0, 4], vec![1, 3], vec![2], vec![5]]);
+
+    // Reading the proposer conflicts with everyone paying it
+    let mut accesses = accesses.to_vec();
+    accesses[5].reads.insert(StateKey::Account(PROPOSER));
+    assert_eq!(partition(&accesses), vec![vec![0, 1, 2, 3, 4, 5]]);
+    assert!(partition(&[]).is_empty());
+}
+
+#[test]
+fn independent_transactions_run_in_separate_groups() {
+    let state = genesis();
+    let env = token_env(30_000_000);
+    let mut txs = Vec::new();
+    for byte in 1..=10 {
+        txs.push(transfer(byte, 0, Address([0x90 + byte; 20]), 1_000));
+    }
+    for byte in 11..=20 {
+        txs.push(token_transfer(byte, 0, Address([0xa0 + byte; 20]), 250));
+    }
+
+    let report = compare(&state, &env, &txs).unwrap();
+    assert_eq!(
+        report,
+        BatchReport {
+            transactions: 20,
+            groups: 20,
+            serial: 0,
+            critical_path: 1,
+        }
+    );
+    assert_eq!(report.parallelism(), 20.0);
+}
+
+#[test]
+fn unknown_contracts_split_the_block() {
+    let state = genesis();
+    let env = token_env(30_000_000);
+    let txs = vec![
+        // Chain of nonces from one sender
+        transfer(1, 0, Address([0x91; 20]), 10),
+        transfer(1, 1, Address([0x92; 20]), 20),
+        // Tokens passed on by their recipient
+        token_transfer(2, 0, sender(3), 600),
+        token_transfer(3, 0, sender(4), 1_500),
+        transfer(5, 0, Address([0x95; 20]), 1),
+        // Runs code, so it is executed on its own
+        transfer(6, 0, COUNTER, 0),
+        transfer(7, 0, Address([0x97; 20]), 1),
+        transfer(8, 0, Address([0x98; 20]), 1),
+        // Reads the proposer balance the others pay into
+        transfer(9, 0, PROPOSER, 5),
+    ];
+
+    let report = compare(&state, &env, &txs).unwrap();
+    // Groups {0, 1}, {2, 3} and {4}, then 5 alone, then {6, 7, 8}
+    assert_eq!(
+        report,
+        BatchReport {
+            transactions: 9,
+            groups: 4,
+            serial: 1,
+            critical_path: 6,
+        }
+    );
+    assert_eq!(report.parallelism(), 1.5);
+}
+
+#[test]
+fn mispredicted_segments_run_serially() {
+    let state = genesis();
+    let env = token_env(30_000_000);
+    // Creating an empty account checks it has no storage, which is not
+    // part of the prediction
+    let txs = vec![
+        transfer(1, 0, Address([0x91; 20]), 10),
+        transfer(2, 0, Address([0x92; 20]), 0),
+    ];
+    let report = compare(&state, &env, &txs).unwrap();
+    assert_eq!((report.groups, report.serial, report.critical_path), (0, 2, 2));
+}
+
+#[test]
+fn invalid_transactions_fail_at_the_same_index() {
+    let state = genesis();
+    let txs = vec![
+        transfer(1, 0, Address([0x91; 20]), 10),
+        transfer(2, 0, Address([0x92; 20]), 10),
+        transfer(1, 0, Address([0x93; 20]), 10),
+        transfer(3, 0, Address([0x93; 20]), 10),
+    ];
+    assert!(matches!(
+        compare(&state, &token_env(30_000_000), &txs),
+        Err((2, ExecutionError::NonceMismatch { expected: 1, got: 0 }))
+    ));
+
+    // Only two transactions fit, which the groups cannot know
+    let txs: Vec<_> = (1..=3)
+        .map(|byte| transfer(byte, 0, Address([0x90 + byte; 20]), 1))
+        .collect();
+    assert!(matches!(
+        compare(&state, &token_env(140_000), &txs),
+        Err((2, ExecutionError::BlockGasExceeded { .. }))
+    ));
+    assert_eq!(compare(&state, &token_env(30_000_000), &[]).unwrap(), BatchReport::default());
+}
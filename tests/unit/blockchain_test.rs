@@ -0,0 +1,167 @@
+use secp256k1::SecretKey;
+use tburn_chain_v4_0::core::block::merkle_root;
+use tburn_chain_v4_0::core::genesis::GenesisError;
+use tburn_chain_v4_0::core::types::to_hex;
+use tburn_chain_v4_0::core::{
+    Address, Block, BlockHeader, Blockchain, Genesis, SignedTransaction, StateDB, Transaction,
+};
+
+fn signed_tx(nonce: u64) -> SignedTransaction {
+    let key = SecretKey::from_slice(&[0x11; 32]).unwrap();
+    Transaction {
+        chain_id: 1,
+        nonce,
+        gas_price: 10,
+        gas_limit: 21_000,
+        to: Some(Address([0x22; 20])),
+        value: 1,
+        data: Vec::new(),
+    }
+    .sign(&key)
+}
+
+fn child_of(parent: &Block, timestamp: u64, tx_count: usize) -> Block {
+    Block::new(
+        BlockHeader {
+            number: parent.number() + 1,
+            parent_hash: parent.hash(),
+            timestamp,
+            ..Default::default()
+        },
+        (0..tx_count as u64).map(signed_tx).collect(),
+    )
+}
+
+#[tokio::test]
+async fn append_advances_head_and_indexes_blocks() {
+    let genesis = Block::genesis(1_000);
+    let chain = Blockchain::new(genesis.clone());
+
+    let block1 = child_of(&genesis, 1_100, 3);
+    chain.append(block1.clone()).await.unwrap();
+
+    assert_eq!(chain.get_height().await, 1);
+    assert_eq!(chain.head().await.hash(), block1.hash());
+    assert_eq!(*chain.get_block_by_number(1).await.unwrap(), block1);
+    assert_eq!(*chain.get_block_by_hash(&genesis.hash()).await.unwrap(), genesis);
+    assert!(chain.get_block_by_number(2).await.is_none());
+}
+
+#[tokio::test]
+async fn append_rejects_block_not_extending_head() {
+    let genesis = Block::genesis(1_000);
+    let chain = Blockchain::new(genesis.clone());
+
+    let mut orphan = child_of(&genesis, 1_100, 0);
+    orphan.header.parent_hash = [7u8; 32];
+    assert!(chain.append(orphan).await.is_err());
+
+    let mut skipped = child_of(&genesis, 1_100, 0);
+    skipped.header.number = 5;
+    assert!(chain.append(skipped).await.is_err());
+
+    assert_eq!(chain.get_height().await, 0);
+}
+
+#[tokio::test]
+async fn tps_is_measured_over_recent_blocks() {
+    let genesis = Block::genesis(0);
+    let chain = Blockchain::new(genesis.clone());
+    assert_eq!(chain.get_tps().await, 0);
+
+    // 10 blocks, 100ms apart, 50 txs each => 500 txs per second
+    let mut parent = genesis;
+    for i in 1..=10 {
+        let block = child_of(&parent, i * 100, 50);
+        chain.append(block.clone()).await.unwrap();
+        parent = block;
+    }
+
+    assert_eq!(chain.get_tps().await, 500);
+}
+
+#[tokio::test]
+async fn append_rejects_tampered_body() {
+    let genesis = Block::genesis(0);
+    let chain = Blockchain::new(genesis.clone());
+
+    let mut block = child_of(&genesis, 100, 2);
+    block.transactions.push(signed_tx(99));
+    assert!(chain.append(block).await.is_err());
+}
+
+#[test]
+fn header_encoding_round_trips_and_hash_commits_to_fields() {
+    let header = BlockHeader {
+        number: 42,
+        parent_hash: [1u8; 32],
+        state_root: [2u8; 32],
+        shard_id: 3,
+        proposer: Address([4u8; 20]),
+        timestamp: 1_700_000_000_000,
+        gas_used: 21_000,
+        gas_limit: 30_000_000,
+        extra_data: b"TBURN".to_vec(),
+        ..Default::default()
+    };
+
+    let decoded = BlockHeader::decode(&header.encode()).unwrap();
+    assert_eq!(decoded, header);
+    assert_eq!(decoded.hash(), header.hash());
+
+    let mut other = header.clone();
+    other.shard_id = 4;
+    assert_ne!(other.hash(), header.hash());
+
+    let block = Block::new(header, vec![signed_tx(0), signed_tx(1)]);
+    assert_eq!(Block::decode(&block.encode()).unwrap(), block);
+}
+
+#[test]
+fn merkle_root_depends_on_every_leaf_and_order() {
+    let a = [1u8; 32];
+    let b = [2u8; 32];
+    let c = [3u8; 32];
+
+    assert_eq!(merkle_root(&[]), [0u8; 32]);
+    assert_ne!(merkle_root(&[a, b, c]), merkle_root(&[b, a, c]));
+    assert_ne!(merkle_root(&[a, b]), merkle_root(&[a, b, b]));
+    assert_ne!(merkle_root(&[a]), a);
+}
+
+const GENESIS_JSON: &str = include_str!("../../config/genesis.json");
+
+#[test]
+fn configured_genesis_matches_declared_hashes() {
+    let genesis = Genesis::from_json(GENESIS_JSON).unwrap();
+    let build = genesis.build_and_verify().unwrap();
+
+    let treasury: Address = "0x0000000000000000000000000000000000000001".parse().unwrap();
+    assert_eq!(build.state.get_balance(&treasury), 1_000_000_000_000_000_000_000_000_000);
+    assert_eq!(build.block.header.state_root, build.state.state_root());
+    assert_eq!(build.shard_blocks.len(), 5);
+    for shard in &build.shard_blocks {
+        assert_eq!(shard.header.parent_hash, build.hash());
+    }
+}
+
+#[test]
+fn genesis_hash_changes_with_alloc() {
+    let edited = GENESIS_JSON.replace("1000000000000000000000000000", "1000000000000000000000000001");
+    let genesis = Genesis::from_json(&edited).unwrap();
+    let computed = to_hex(&genesis.build().unwrap().hash());
+
+    match genesis.build_and_verify() {
+        Err(GenesisError::HashMismatch { computed: reported, .. }) => assert_eq!(reported, computed),
+        other => panic!("expected hash mismatch, got {other:?}"),
+    }
+}
+
+#[test]
+fn genesis_rejects_invalid_alloc() {
+    let edited = GENESIS_JSON.replace("0x0000000000000000000000000000000000000004", "0x04");
+    match Genesis::from_json(&edited) {
+        Err(GenesisError::Invalid { field, .. }) => assert_eq!(field, "alloc.0x04"),
+        other => panic!("expected invalid alloc, got {other:?}"),
+    }
+}
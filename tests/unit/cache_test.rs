@@ -0,0 +1,174 @@
+use std::sync::{Arc, OnceLock, Weak};
+
+use tburn_chain_v4_0::core::{Address, StateDB, WorldState};
+use tburn_chain_v4_0::storage::cache::{CacheConfig, CachedStore, LruCache};
+use tburn_chain_v4_0::storage::rocksdb::{Column, Entry, KvStore, MemoryStore, StorageError, WriteBatch};
+
+/// Persist `state` to `store` and return its root.
+fn persist(store: &dyn KvStore, state: &WorldState) -> [u8; 32] {
+    let mut batch = WriteBatch::new();
+    let root = state.write_to(&mut batch);
+    store.write(batch).unwrap();
+    root
+}
+
+#[test]
+fn lru_evicts_least_recently_used_by_weight() {
+    let cache = LruCache::new(10);
+    cache.insert(1, "a", 4);
+    cache.insert(2, "b", 4);
+    assert_eq!(cache.get(&1), Some("a"));
+    // Needs 2 more bytes, so the entry used longest ago goes
+    cache.insert(3, "c", 4);
+    assert_eq!(cache.get(&2), None);
+    assert_eq!(cache.get(&1), Some("a"));
+    assert_eq!(cache.get(&3), Some("c"));
+    // Too heavy to ever fit
+    cache.insert(4, "d", 11);
+    assert_eq!(cache.get(&4), None);
+
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.evictions), (3, 2, 1));
+    assert_eq!((stats.entries, stats.bytes), (2, 8));
+    assert_eq!(stats.hit_rate(), 0.6);
+
+    cache.remove(&1);
+    cache.clear();
+    assert_eq!(cache.stats().bytes, 0);
+}
+
+#[test]
+fn accounts_are_read_per_state_root() {
+    let store = Arc::new(MemoryStore::new());
+    let cached = CachedStore::new(store.clone(), CacheConfig::with_total(1 << 20));
+    let alice = Address([1; 20]);
+    let contract = Address([0xcc; 20]);
+
+    let mut state = WorldState::new();
+    state.set_balance(&alice, 100);
+    state.set_code(&contract, vec![0x60, 0x00]);
+    state.commit();
+    let first = persist(&cached, &state);
+    state.set_balance(&alice, 250);
+    state.set_nonce(&alice, 3);
+    state.commit();
+    let second = persist(&cached, &state);
+
+    let old = cached.account(&first, &alice).unwrap().unwrap();
+    let new = cached.account(&second, &alice).unwrap().unwrap();
+    assert_eq!((old.balance, old.nonce), (100, 0));
+    assert_eq!((new.balance, new.nonce), (250, 3));
+    assert_eq!(cached.account(&second, &Address([9; 20])).unwrap(), None);
+    assert_eq!(cached.account(&first, &alice).unwrap(), Some(old));
+
+    let code_hash = cached.account(&second, &contract).unwrap().unwrap().code_hash;
+    assert_eq!(*cached.code(&code_hash).unwrap().unwrap(), vec![0x60, 0x00]);
+    assert_eq!(*cached.code(&code_hash).unwrap().unwrap(), vec![0x60, 0x00]);
+
+    let stats = cached.stats();
+    assert_eq!((stats.accounts.hits, stats.accounts.misses), (1, 4));
+    assert_eq!((stats.code.hits, stats.code.misses), (1, 1));
+    assert!(stats.nodes.hits > 0);
+
+    // Loading a whole state reads its nodes through the cache too
+    let loaded = WorldState::load(&cached, &second).unwrap();
+    assert_eq!(loaded.state_root(), second);
+}
+
+#[test]
+fn deletions_invalidate_cached_entries() {
+    let store = Arc::new(MemoryStore::new());
+    let cached = CachedStore::new(store.clone(), CacheConfig::with_total(1 << 20));
+    let alice = Address([1; 20]);
+    let mut state = WorldState::new();
+    state.set_balance(&alice, 100);
+    state.commit();
+    let root = persist(&cached, &state);
+    assert!(cached.account(&root, &alice).unwrap().is_some());
+    assert!(cached.get(Column::TrieNodes, &root).unwrap().is_some());
+
+    // What the pruner deletes must not be served from the cache
+    cached.delete(Column::TrieNodes, &root).unwrap();
+    assert_eq!(cached.get(Column::TrieNodes, &root).unwrap(), None);
+    assert!(cached.account(&root, &alice).is_err());
+    assert_eq!(cached.stats().accounts.entries, 0);
+
+    // Writing the node again makes it readable
+    persist(&cached, &state);
+    assert!(cached.account(&root, &alice).unwrap().is_some());
+
+    // Changes behind the cache's back need an explicit invalidation
+    store.delete(Column::TrieNodes, &root).unwrap();
+    assert!(cached.account(&root, &alice).unwrap().is_some());
+    cached.invalidate_all();
+    assert!(cached.account(&root, &alice).is_err());
+    let stats = cached.stats();
+    assert_eq!(stats.nodes.entries + stats.accounts.entries + stats.code.entries, 0);
+}
+
+#[test]
+fn rewriting_nodes_keeps_them_cached() {
+    let store = Arc::new(MemoryStore::new());
+    let cached = CachedStore::new(store.clone(), CacheConfig::with_total(1 << 20));
+    let alice = Address([1; 20]);
+    let mut state = WorldState::new();
+    state.set_balance(&alice, 100);
+    state.commit();
+    let root = persist(&cached, &state);
+    assert!(cached.account(&root, &alice).unwrap().is_some());
+    let before = cached.stats();
+
+    // Puts store the content their hash names, so nothing goes stale
+    persist(&cached, &state);
+    assert!(cached.account(&root, &alice).unwrap().is_some());
+    let after = cached.stats();
+    assert_eq!(after.nodes.entries, before.nodes.entries);
+    assert_eq!(after.accounts.hits, before.accounts.hits + 1);
+}
+
+/// Store that reads `key` back through `cache` while applying a write,
+/// as a reader racing the write would.
+#[derive(Debug, Default)]
+struct RacingStore {
+    inner: MemoryStore,
+    cache: OnceLock<Weak<CachedStore>>,
+    key: OnceLock<[u8; 32]>,
+}
+
+impl KvStore for RacingStore {
+    fn get(&self, column: Column, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        self.inner.get(column, key)
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError> {
+        if let (Some(cache), Some(key)) = (self.cache.get().and_then(Weak::upgrade), self.key.get()) {
+            cache.get(Column::TrieNodes, key)?;
+        }
+        self.inner.write(batch)
+    }
+
+    fn entries(&self, column: Column) -> Result<Vec<Entry>, StorageError> {
+        self.inner.entries(column)
+    }
+
+    fn for_each(&self, column: Column, visit: &mut dyn FnMut(&[u8], &[u8])) -> Result<(), StorageError> {
+        self.inner.for_each(column, visit)
+    }
+}
+
+#[test]
+fn reads_during_a_delete_do_not_cache_the_deleted_node() {
+    let store = Arc::new(RacingStore::default());
+    let cached = Arc::new(CachedStore::new(store.clone(), CacheConfig::with_total(1 << 20)));
+    let alice = Address([1; 20]);
+    let mut state = WorldState::new();
+    state.set_balance(&alice, 100);
+    state.commit();
+    let root = persist(cached.as_ref(), &state);
+    store.cache.set(Arc::downgrade(&cached)).unwrap();
+    store.key.set(root).unwrap();
+
+    cached.delete(Column::TrieNodes, &root).unwrap();
+    assert_eq!(cached.get(Column::TrieNodes, &root).unwrap(), None);
+    assert!(cached.account(&root, &alice).is_err());
+}
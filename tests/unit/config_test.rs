@@ -0,0 +1,142 @@
+use std::path::Path;
+
+use tburn_chain_v4_0::core::config::{ConfigError, PruningMode};
+use tburn_chain_v4_0::core::producer::build_block;
+use tburn_chain_v4_0::core::{
+    Address, Block, ChainParams, GasSchedule, MempoolConfig, NetworkProfile, NodeConfig, WorldState,
+};
+
+const MAINNET: &str = include_str!("../../config/mainnet.toml");
+const TESTNET: &str = include_str!("../../config/testnet.toml");
+const DEVNET: &str = include_str!("../../config/devnet.toml");
+
+fn with_env(contents: &str, vars: &[(&str, &str)]) -> Result<NodeConfig, ConfigError> {
+    let env = vars.iter().map(|(k, v)| (k.to_string(), v.to_string()));
+    NodeConfig::from_toml_with_env(Path::new("test.toml"), contents, env)
+}
+
+fn invalid_key(result: Result<NodeConfig, ConfigError>) -> String {
+    match result {
+        Err(ConfigError::Invalid { key, .. }) => key,
+        other => panic!("expected invalid config, got {other:?}"),
+    }
+}
+
+#[test]
+fn every_profile_loads() {
+    let mainnet = NodeConfig::from_toml(MAINNET).unwrap();
+    assert_eq!(mainnet.network.chain_id, 1);
+    assert_eq!(mainnet.network.performance.transaction_pool_size, 100_000);
+    assert_eq!(mainnet.smart_contracts.gas_limit_per_block, 30_000_000);
+    assert_eq!(mainnet.smart_contracts.max_contract_size_bytes(), 24 * 1024);
+    assert_eq!(mainnet.validators.min_stake(), 32_000);
+    assert_eq!(mainnet.api.rest_addr().to_string(), "127.0.0.1:3000");
+    assert_eq!(mainnet.database.pruning, PruningMode::Full);
+    assert_eq!(mainnet.database.cache_size_mb, 2048);
+    assert!(mainnet.deployment.backup_enabled);
+    assert_eq!(mainnet.deployment.backup_dir, std::path::Path::new("data/snapshots/mainnet"));
+
+    assert_eq!(NodeConfig::from_toml(TESTNET).unwrap().network.chain_id, 2);
+    assert_eq!(NodeConfig::from_toml(DEVNET).unwrap().network.chain_id, 1337);
+}
+
+#[test]
+fn gas_schedule_overrides_keep_defaults() {
+    let mainnet = NodeConfig::from_toml(MAINNET).unwrap();
+    assert_eq!(mainnet.smart_contracts.gas_schedule, GasSchedule::default());
+
+    let overridden = format!("{MAINNET}\n[smart_contracts.gas_schedule]\nsstore_set = 25000\n");
+    let config = NodeConfig::from_toml(&overridden).unwrap();
+    let schedule = config.smart_contracts.gas_schedule;
+    assert_eq!(schedule.sstore_set, 25_000);
+    assert_eq!(schedule.tx_base, GasSchedule::default().tx_base);
+    assert_eq!(MempoolConfig::from_node_config(&config).schedule, schedule);
+    assert_eq!(ChainParams::from_node_config(&config).schedule, schedule);
+}
+
+#[test]
+fn gas_schedule_divisors_must_be_positive() {
+    for key in ["memory_quad_divisor", "max_refund_quotient", "modexp_divisor"] {
+        let zeroed = format!("{MAINNET}\n[smart_contracts.gas_schedule]\n{key} = 0\n");
+        assert_eq!(
+            invalid_key(NodeConfig::from_toml(&zeroed)),
+            format!("smart_contracts.gas_schedule.{key}")
+        );
+    }
+}
+
+#[test]
+fn profile_names_parse() {
+    assert_eq!("Devnet".parse::<NetworkProfile>().unwrap(), NetworkProfile::Devnet);
+    assert_eq!(NetworkProfile::Testnet.file_name(), "testnet.toml");
+    assert!("localnet".parse::<NetworkProfile>().is_err());
+}
+
+#[test]
+fn environment_overrides_keep_types() {
+    let config = with_env(
+        MAINNET,
+        &[
+            ("TBURN_API__REST_PORT", "4000"),
+            ("TBURN_API__HOST", "0.0.0.0"),
+            ("TBURN_NETWORK__PERFORMANCE__TRANSACTION_POOL_SIZE", "500"),
+            ("TBURN_DATABASE__URL", "sqlite::memory:"),
+            ("UNRELATED__VAR", "ignored"),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(config.api.rest_addr().to_string(), "0.0.0.0:4000");
+    assert_eq!(config.network.performance.transaction_pool_size, 500);
+    assert_eq!(config.database.url, "sqlite::memory:");
+}
+
+#[test]
+fn errors_name_the_offending_key() {
+    assert_eq!(
+        invalid_key(with_env(MAINNET, &[("TBURN_API__REST_PORT", "http")])),
+        "api.rest_port"
+    );
+    assert_eq!(
+        invalid_key(with_env(MAINNET, &[("TBURN_API__WEBSOCKET_PORT", "3000")])),
+        "api.websocket_port"
+    );
+    assert_eq!(
+        invalid_key(NodeConfig::from_toml(&MAINNET.replace("total_shards = 5", "total_shards = 4"))),
+        "sharding.shard_names"
+    );
+    assert_eq!(
+        invalid_key(NodeConfig::from_toml(&MAINNET.replace("gas_limit_per_block = 30000000", "gas_limit_per_block = -1"))),
+        "smart_contracts.gas_limit_per_block"
+    );
+    assert_eq!(
+        invalid_key(with_env(MAINNET, &[("TBURN_SMART_CONTRACTS__BASE_GAS_PRICE_EMB", "2000000")])),
+        "smart_contracts.max_gas_price_emb"
+    );
+    assert_eq!(
+        invalid_key(with_env(MAINNET, &[("TBURN_DATABASE__PRUNING_KEEP_RECENT", "0")])),
+        "database.pruning_keep_recent"
+    );
+    assert_eq!(
+        invalid_key(with_env(MAINNET, &[("TBURN_DATABASE__PRUNING", "sometimes")])),
+        "database.pruning"
+    );
+}
+
+#[test]
+fn large_base_gas_prices_build_blocks() {
+    let config = with_env(
+        MAINNET,
+        &[
+            ("TBURN_SMART_CONTRACTS__BASE_GAS_PRICE_EMB", "2000000"),
+            ("TBURN_SMART_CONTRACTS__MAX_GAS_PRICE_EMB", "5000000"),
+        ],
+    )
+    .unwrap();
+    let params = ChainParams::from_node_config(&config);
+    let parent = Block::genesis(1_704_067_200_000);
+    let mut state = WorldState::new();
+    let executed = build_block(&params, Address([0xfe; 20]), &parent, &mut state, Vec::new(), 1_704_067_201_000);
+    assert_eq!(executed.block.header.base_fee, 2_000_000);
+    assert_eq!(params.gas.next_base_fee(&executed.block.header), 2_000_000);
+}
@@ -0,0 +1,242 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use secp256k1::SecretKey;
+use tburn_chain_v4_0::contracts::deployer::ContractDeployer;
+use tburn_chain_v4_0::contracts::executor::{
+    compute_balance_slot, flags, generate_system_address, registered_token, selectors, slots, u128_to_u256,
+    Tbc20FastPathExecutor, Tbc20Registry, TokenStandard,
+};
+use tburn_chain_v4_0::core::config::SmartContractsConfig;
+use tburn_chain_v4_0::core::executor::{create_address, execute_transaction, BlockEnv};
+use tburn_chain_v4_0::core::receipt::Receipt;
+use tburn_chain_v4_0::core::transaction::secret_key_to_address;
+use tburn_chain_v4_0::core::types::H256;
+use tburn_chain_v4_0::core::vm::{Evm, ExitReason, VmError};
+use tburn_chain_v4_0::core::{Address, GasSchedule, StateDB, Transaction, WorldState};
+use tburn_chain_v4_0::storage::rocksdb::{KvStore, MemoryStore, WriteBatch};
+
+#[path = "../common/mod.rs"]
+mod common;
+
+use common::{storage_init_code, CHAIN_ID};
+
+fn key() -> SecretKey {
+    SecretKey::from_slice(&[7; 32]).unwrap()
+}
+
+fn env(deployer: ContractDeployer) -> BlockEnv {
+    BlockEnv {
+        number: 12,
+        deployer: Arc::new(deployer),
+        ..common::env()
+    }
+}
+
+fn funded() -> WorldState {
+    let mut state = WorldState::new();
+    state.set_balance(&secret_key_to_address(&key()), 1_000_000_000_000);
+    state
+}
+
+fn send(state: &mut WorldState, env: &BlockEnv, to: Option<Address>, data: Vec<u8>) -> Receipt {
+    let sender = secret_key_to_address(&key());
+    let tx = Transaction {
+        chain_id: CHAIN_ID,
+        nonce: state.get_nonce(&sender),
+        gas_price: 1,
+        gas_limit: 2_000_000,
+        to,
+        value: 0,
+        data,
+    };
+    execute_transaction(state, env, &tx.sign(&key()), 0).unwrap()
+}
+
+fn slot(index: u8) -> H256 {
+    let mut slot = [0u8; 32];
+    slot[31] = index;
+    slot
+}
+
+/// Single-slot string encoding: bytes left-aligned, twice the length last.
+fn short_string(s: &str) -> H256 {
+    let mut word = [0u8; 32];
+    word[..s.len()].copy_from_slice(s.as_bytes());
+    word[31] = 2 * s.len() as u8;
+    word
+}
+
+fn token_init_code(owner: &Address) -> Vec<u8> {
+    storage_init_code(&[
+        (slot(slots::NAME), short_string("Ember Coin")),
+        (slot(slots::SYMBOL), short_string("EMB")),
+        (slot(slots::DECIMALS), slot(9)),
+        (slot(slots::TOTAL_SUPPLY), u128_to_u256(1_000)),
+        (slot(slots::FLAGS), slot(flags::MINTABLE | flags::BURNABLE)),
+        (compute_balance_slot(owner), u128_to_u256(1_000)),
+    ])
+}
+
+#[test]
+fn factories_resolve_by_address() {
+    let deployer = ContractDeployer::new();
+    let factory = generate_system_address("TBC20_FACTORY");
+    assert_eq!(deployer.factory_standard(&factory), Some(TokenStandard::TBC20));
+    assert_eq!(
+        deployer.factory_standard(&generate_system_address("TBC1155_FACTORY")),
+        Some(TokenStandard::TBC1155)
+    );
+    assert_eq!(deployer.factory_standard(&Address([1; 20])), None);
+
+    // The same salt gives each sender its own address
+    let salt = [3u8; 32];
+    let a = deployer.factory_address(&factory, &Address([1; 20]), &salt, b"code");
+    let b = deployer.factory_address(&factory, &Address([2; 20]), &salt, b"code");
+    assert_ne!(a, b);
+    assert_eq!(a, deployer.factory_address(&factory, &Address([1; 20]), &salt, b"code"));
+}
+
+#[test]
+fn plain_deployments_use_sender_and_nonce() {
+    let env = env(ContractDeployer::new());
+    let mut state = funded();
+    let sender = secret_key_to_address(&key());
+    let receipt = send(&mut state, &env, None, storage_init_code(&[(slot(1), slot(2))]));
+    assert!(receipt.success);
+    let address = create_address(&sender, 0);
+    assert_eq!(receipt.contract_address, Some(address));
+    assert_eq!(state.code(&address), vec![0x00]);
+    assert_eq!(state.code_by_hash(&state.code_hash(&address)), Some(&[0x00][..]));
+    assert_eq!(state.get_storage(&address, &slot(1)), slot(2));
+    // Not a factory deployment, so nothing is registered
+    assert!(registered_token(&state, &address).is_none());
+}
+
+#[test]
+fn reverted_calls_keep_their_revert_data() {
+    let env = env(ContractDeployer::new());
+    let mut state = funded();
+    let contract = Address([0xc0; 20]);
+    // MSTORE8(0, 0xaa), REVERT(0, 1)
+    state.set_code(&contract, vec![0x60, 0xaa, 0x5f, 0x53, 0x60, 0x01, 0x5f, 0xfd]);
+    let receipt = send(&mut state, &env, Some(contract), Vec::new());
+    assert!(!receipt.success);
+    assert_eq!(receipt.revert_data, vec![0xaa]);
+    assert_eq!(Receipt::decode(&receipt.encode()).unwrap(), receipt);
+
+    let receipt = send(&mut state, &env, None, storage_init_code(&[(slot(1), slot(2))]));
+    assert!(receipt.success);
+    assert!(receipt.revert_data.is_empty());
+}
+
+#[test]
+fn contract_size_limit_comes_from_config() {
+    let config = SmartContractsConfig {
+        evm_compatible: true,
+        max_contract_size_kb: 1,
+        gas_limit_per_block: 30_000_000,
+        base_gas_price_emb: 1,
+        max_gas_price_emb: 1_000_000,
+        gas_schedule: GasSchedule::default(),
+    };
+    let deployer = ContractDeployer::from_config(&config);
+    assert_eq!(deployer.max_code_size(), 1024);
+    let limited = env(deployer);
+
+    // RETURN(0, 2048) deploys 2 KiB of zeros
+    let big = vec![0x61, 0x08, 0x00, 0x5f, 0xf3];
+    let mut state = funded();
+    let sender = secret_key_to_address(&key());
+    let mut evm = Evm::new(&mut state, &limited, sender, 1);
+    let output = limited.deployer.create(&mut evm, sender, 0, 0, big.clone(), 1_000_000);
+    assert_eq!(output.exit, ExitReason::Halt(VmError::CodeSizeLimit));
+    drop(evm);
+
+    let receipt = send(&mut state, &limited, None, big.clone());
+    assert!(!receipt.success);
+
+    // The default limit is 24 KiB
+    let env = env(ContractDeployer::new());
+    let receipt = send(&mut state, &env, None, big);
+    assert!(receipt.success);
+}
+
+#[test]
+fn factory_deployments_register_tokens_for_the_fast_path() {
+    let env = env(ContractDeployer::new());
+    let mut state = funded();
+    let sender = secret_key_to_address(&key());
+    let factory = generate_system_address("TBC20_FACTORY");
+    let salt = [5u8; 32];
+    let init = token_init_code(&sender);
+
+    let receipt = send(&mut state, &env, Some(factory), [&salt[..], &init].concat());
+    assert!(receipt.success);
+    let token = env.deployer.factory_address(&factory, &sender, &salt, &init);
+    assert_eq!(receipt.contract_address, Some(token));
+    assert_eq!(state.code(&token), vec![0x00]);
+
+    // Registration lives in state, not in the node's in-memory registry
+    assert_eq!(env.fast_path.registry().stats(), (0, 0));
+    let info = registered_token(&state, &token).unwrap();
+    assert_eq!(info.name, "Ember Coin");
+    assert_eq!(info.symbol, "EMB");
+    assert_eq!(info.decimals, 9);
+    assert_eq!(info.initial_supply, 1_000);
+    assert!(info.mintable && info.burnable && !info.pausable);
+    assert_eq!(info.deployed_at_block, 12);
+
+    // Transfers now take the fast path
+    let to = Address([0x42; 20]);
+    let mut data = selectors::TRANSFER.to_vec();
+    data.extend_from_slice(&[0u8; 12]);
+    data.extend_from_slice(&to.0);
+    data.extend_from_slice(&u128_to_u256(250));
+    let receipt = send(&mut state, &env, Some(token), data);
+    assert!(receipt.success);
+    assert_eq!(state.get_storage(&token, &compute_balance_slot(&to)), u128_to_u256(250));
+    assert_eq!(env.fast_path.stats().transfer_count.load(Ordering::Relaxed), 1);
+
+    // Redeploying under the same salt collides
+    let receipt = send(&mut state, &env, Some(factory), [&salt[..], &init].concat());
+    assert!(!receipt.success);
+    assert_eq!(registered_token(&state, &token).unwrap().deployed_at_block, 12);
+
+    // A node started from this state, with an empty registry, agrees
+    let store = MemoryStore::new();
+    let mut batch = WriteBatch::new();
+    let root = state.write_to(&mut batch);
+    store.write(batch).unwrap();
+    let restarted = WorldState::load(&store, &root).unwrap();
+    let fresh = Tbc20FastPathExecutor::new(Arc::new(Tbc20Registry::new()));
+    assert_eq!(fresh.token_info(&restarted, &token).unwrap().symbol, "EMB");
+}
+
+#[test]
+fn reverted_factory_deployments_leave_no_registration() {
+    let env = env(ContractDeployer::new());
+    let mut state = funded();
+    let token = Address([0x77; 20]);
+    let checkpoint = state.checkpoint();
+    env.deployer.register_token(&mut state, &token, 3);
+    assert_eq!(registered_token(&state, &token).unwrap().deployed_at_block, 3);
+    state.revert_to(checkpoint);
+    assert!(registered_token(&state, &token).is_none());
+}
+
+#[test]
+fn other_factories_deploy_without_registering() {
+    let env = env(ContractDeployer::new());
+    let mut state = funded();
+    let factory = generate_system_address("TBC721_FACTORY");
+    let init = token_init_code(&secret_key_to_address(&key()));
+
+    let receipt = send(&mut state, &env, Some(factory), [&[1u8; 32][..], &init].concat());
+    assert!(receipt.success);
+    assert!(registered_token(&state, &receipt.contract_address.unwrap()).is_none());
+
+    // Calldata without a salt fails
+    let receipt = send(&mut state, &env, Some(factory), vec![1, 2, 3]);
+    assert!(!receipt.success);
+}
@@ -0,0 +1,120 @@
+use tburn_chain_v4_0::core::gas::{
+    EmberGasSystem, GasConfig, GasMeter, GasPriceQuote, GasSchedule, OutOfGas, TransactionPriority, TX_BASE_GAS,
+};
+use tburn_chain_v4_0::core::{Address, BlockHeader, Transaction};
+
+fn call(data: Vec<u8>) -> Transaction {
+    Transaction {
+        chain_id: 1,
+        nonce: 0,
+        gas_price: 1,
+        gas_limit: 100_000,
+        to: Some(Address([0x11; 20])),
+        value: 0,
+        data,
+    }
+}
+
+fn parent(base_fee: u128, gas_used: u64) -> BlockHeader {
+    BlockHeader {
+        gas_limit: 30_000_000,
+        gas_used,
+        base_fee,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn intrinsic_gas_prices_calldata_and_creation() {
+    let schedule = GasSchedule::default();
+    assert_eq!(schedule.intrinsic_gas(&call(Vec::new())), TX_BASE_GAS);
+    assert_eq!(schedule.intrinsic_gas(&call(vec![0, 0, 1])), TX_BASE_GAS + 2 * 4 + 16);
+
+    let create = Transaction {
+        to: None,
+        ..call(vec![0xff; 10])
+    };
+    assert_eq!(schedule.intrinsic_gas(&create), TX_BASE_GAS + 32_000 + 10 * 16);
+}
+
+#[test]
+fn meter_runs_out_of_gas_and_caps_refunds() {
+    let schedule = GasSchedule::default();
+    let mut meter = GasMeter::new(50_000);
+    meter.charge(40_000).unwrap();
+    assert_eq!(meter.remaining(), 10_000);
+
+    meter.add_refund(20_000);
+    assert_eq!(meter.finalize(&schedule), 40_000 - 40_000 / 5);
+    meter.clear_refund();
+    assert_eq!(meter.finalize(&schedule), 40_000);
+
+    assert_eq!(meter.charge(10_001), Err(OutOfGas));
+    assert_eq!(meter.used(), 50_000);
+    assert_eq!(meter.remaining(), 0);
+}
+
+#[test]
+fn sstore_cost_depends_on_current_value() {
+    let schedule = GasSchedule::default();
+    let zero = [0u8; 32];
+    let one = {
+        let mut v = [0u8; 32];
+        v[31] = 1;
+        v
+    };
+    let two = {
+        let mut v = [0u8; 32];
+        v[31] = 2;
+        v
+    };
+    assert_eq!(schedule.sstore_cost(&zero, &one), (schedule.sstore_set, 0));
+    assert_eq!(schedule.sstore_cost(&one, &two), (schedule.sstore_reset, 0));
+    assert_eq!(schedule.sstore_cost(&one, &zero), (schedule.sstore_reset, schedule.sstore_clear_refund));
+    assert_eq!(schedule.sstore_cost(&one, &one), (schedule.sload, 0));
+}
+
+#[test]
+fn base_fee_follows_parent_gas_used() {
+    let config = GasConfig::default();
+    // Target is half the gas limit
+    assert_eq!(config.next_base_fee(&parent(1_000, 15_000_000)), 1_000);
+    assert_eq!(config.next_base_fee(&parent(1_000, 30_000_000)), 1_125);
+    assert_eq!(config.next_base_fee(&parent(1_000, 0)), 875);
+    assert_eq!(config.next_base_fee(&parent(1_000, 22_500_000)), 1_062);
+
+    // Small fees still rise, and never fall below the minimum
+    assert_eq!(config.next_base_fee(&parent(1, 15_000_001)), 2);
+    assert_eq!(config.next_base_fee(&parent(1, 0)), 1);
+    let bounded = GasConfig {
+        min_gas_price: 900,
+        max_gas_price: 1_100,
+        ..GasConfig::default()
+    };
+    assert_eq!(bounded.next_base_fee(&parent(1_000, 0)), 900);
+    assert_eq!(bounded.next_base_fee(&parent(1_000, 30_000_000)), 1_100);
+}
+
+#[test]
+fn gas_price_quotes_are_base_fee_plus_tip() {
+    let system = EmberGasSystem::new(GasConfig {
+        priority_fee: 5,
+        ..GasConfig::default()
+    });
+    let head = parent(1_000, 30_000_000);
+    assert_eq!(
+        system.get_gas_price(TransactionPriority::Normal, &head),
+        GasPriceQuote {
+            base_fee: 1_125,
+            priority_fee: 5,
+            gas_price: 1_130,
+        }
+    );
+    assert_eq!(system.get_gas_price(TransactionPriority::Low, &head).gas_price, 1_125);
+    assert_eq!(system.get_gas_price(TransactionPriority::Urgent, &head).gas_price, 1_145);
+
+    assert_eq!(system.tburn_to_ember(3), 3_000_000_000);
+    let estimate = system.estimate_transaction_cost(1_000_000, TransactionPriority::Low, &parent(1_000, 15_000_000));
+    assert_eq!(estimate.total_cost_ember, 1_000_000_000);
+    assert_eq!(estimate.total_cost_tburn, 1);
+}
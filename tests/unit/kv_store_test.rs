@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tburn_chain_v4_0::core::blockchain::{ChainError, CACHED_BLOCKS};
+use tburn_chain_v4_0::core::{Address, Block, Blockchain, StateDB, WorldState};
+use tburn_chain_v4_0::storage::rocksdb::{Column, KvStore, MemoryStore, RocksStore, WriteBatch};
+
+/// Fresh database directory under the system temp dir.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tburn-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn exercise(store: &dyn KvStore) {
+    let mut batch = WriteBatch::new();
+    batch
+        .put(Column::Headers, b"a".to_vec(), b"1".to_vec())
+        .put(Column::Headers, b"b".to_vec(), b"2".to_vec())
+        .put(Column::Metadata, b"a".to_vec(), b"meta".to_vec())
+        .delete(Column::Headers, b"b".to_vec());
+    assert_eq!(batch.len(), 4);
+    store.write(batch).unwrap();
+
+    // Columns are separate keyspaces and later operations win
+    assert_eq!(store.get(Column::Headers, b"a").unwrap(), Some(b"1".to_vec()));
+    assert_eq!(store.get(Column::Metadata, b"a").unwrap(), Some(b"meta".to_vec()));
+    assert_eq!(store.get(Column::Headers, b"b").unwrap(), None);
+    assert!(!store.contains(Column::Bodies, b"a").unwrap());
+
+    store.put(Column::TrieNodes, &[2], b"two").unwrap();
+    store.put(Column::TrieNodes, &[1], b"one").unwrap();
+    assert_eq!(
+        store.entries(Column::TrieNodes).unwrap(),
+        vec![(vec![1], b"one".to_vec()), (vec![2], b"two".to_vec())]
+    );
+    store.delete(Column::TrieNodes, &[1]).unwrap();
+    assert_eq!(store.entries(Column::TrieNodes).unwrap().len(), 1);
+}
+
+#[test]
+fn memory_store_batches_by_column() {
+    let store = MemoryStore::new();
+    exercise(&store);
+    assert_eq!(store.len(Column::Headers), 1);
+    assert_eq!(store.len(Column::Receipts), 0);
+}
+
+#[test]
+fn rocks_store_persists_across_reopen() {
+    let dir = temp_dir("kv-reopen");
+    {
+        let store = RocksStore::open(&dir).unwrap();
+        exercise(&store);
+        store.flush().unwrap();
+    }
+    let store = RocksStore::open(&dir).unwrap();
+    assert_eq!(store.get(Column::Headers, b"a").unwrap(), Some(b"1".to_vec()));
+    assert_eq!(
+        store.entries(Column::TrieNodes).unwrap(),
+        vec![(vec![2], b"two".to_vec())]
+    );
+    drop(store);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn chain_and_state_reload_from_storage() {
+    let mut state = WorldState::new();
+    state.set_balance(&Address([1; 20]), 500);
+    state.set_code(&Address([2; 20]), vec![0x60, 0x00]);
+    state.set_storage(&Address([2; 20]), &[3; 32], [4; 32]);
+    let mut genesis = Block::genesis(1_704_067_200_000);
+    genesis.header.state_root = state.state_root();
+
+    let db: Arc<dyn KvStore> = Arc::new(MemoryStore::new());
+    let chain = Blockchain::open(db.clone(), genesis.clone(), &state).unwrap();
+    let mut child = Block::genesis(1_704_067_201_000);
+    child.header.number = 1;
+    child.header.parent_hash = genesis.hash();
+    state.set_balance(&Address([1; 20]), 400);
+    child.header.state_root = state.state_root();
+    chain.append_executed(child.clone(), Vec::new(), &state).await.unwrap();
+
+    let reopened = Blockchain::open(db.clone(), genesis.clone(), &WorldState::new()).unwrap();
+    assert_eq!(reopened.head().await.hash(), child.hash());
+    assert_eq!(reopened.get_receipts(1).await.unwrap().len(), 0);
+    let loaded = WorldState::load(db.as_ref(), &child.header.state_root).unwrap();
+    assert_eq!(loaded.state_root(), state.state_root());
+    assert_eq!(loaded.get_balance(&Address([1; 20])), 400);
+    assert_eq!(loaded.code(&Address([2; 20])), vec![0x60, 0x00]);
+    assert_eq!(loaded.get_storage(&Address([2; 20]), &[3; 32]), [4; 32]);
+
+    // The genesis state stays loadable until pruned
+    let original = WorldState::load(db.as_ref(), &genesis.header.state_root).unwrap();
+    assert_eq!(original.get_balance(&Address([1; 20])), 500);
+
+    let other = Block::genesis(1);
+    assert!(matches!(
+        Blockchain::open(db, other, &WorldState::new()),
+        Err(ChainError::GenesisMismatch { .. })
+    ));
+}
+
+#[tokio::test]
+async fn reopened_chain_reads_old_blocks_from_storage() {
+    let mut state = WorldState::new();
+    state.set_balance(&Address([1; 20]), 500);
+    state.commit();
+    let mut genesis = Block::genesis(1_704_067_200_000);
+    genesis.header.state_root = state.state_root();
+
+    let db: Arc<dyn KvStore> = Arc::new(MemoryStore::new());
+    let chain = Blockchain::open(db.clone(), genesis.clone(), &state).unwrap();
+    let mut blocks = vec![genesis.clone()];
+    for number in 1..=CACHED_BLOCKS as u64 + 5 {
+        let parent = blocks.last().unwrap();
+        let mut block = Block::genesis(parent.header.timestamp + 1_000);
+        block.header.number = number;
+        block.header.parent_hash = parent.hash();
+        block.header.state_root = state.state_root();
+        chain.append_executed(block.clone(), Vec::new(), &state).await.unwrap();
+        blocks.push(block);
+    }
+
+    let reopened = Blockchain::open(db, genesis.clone(), &state).unwrap();
+    assert_eq!(reopened.head().await.hash(), blocks.last().unwrap().hash());
+    for block in [&blocks[0], &blocks[3], blocks.last().unwrap()] {
+        assert_eq!(*reopened.get_block_by_number(block.number()).await.unwrap(), *block);
+        assert_eq!(*reopened.get_block_by_hash(&block.hash()).await.unwrap(), *block);
+        assert_eq!(reopened.get_receipts(block.number()).await.unwrap().len(), 0);
+    }
+    let recent = reopened.recent_blocks(blocks.len() + 1).await;
+    assert_eq!(recent.len(), blocks.len());
+    assert_eq!(*recent[recent.len() - 1], genesis);
+    assert!(matches!(
+        reopened.append(blocks[3].clone()).await,
+        Err(ChainError::AlreadyKnown(_))
+    ));
+}
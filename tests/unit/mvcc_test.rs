@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use tburn_chain_v4_0::core::types::ZERO_HASH;
+use tburn_chain_v4_0::core::vm::{Evm, Message};
+use tburn_chain_v4_0::core::{Address, Block, Blockchain, StateDB, WorldState};
+use tburn_chain_v4_0::storage::mvcc::{MvccError, VersionedState};
+
+#[path = "../common/mod.rs"]
+mod common;
+
+use common::env;
+
+const ALICE: Address = Address([1; 20]);
+const CONTRACT: Address = Address([0xcc; 20]);
+
+/// SLOAD slot 0 and return it.
+const RETURN_SLOT_ZERO: [u8; 11] = [0x60, 0x00, 0x54, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+
+fn word(value: u8) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[31] = value;
+    word
+}
+
+/// Version `n` of the test state: Alice holds `100 * (n + 1)` and slot 0
+/// of the contract holds `n`.
+fn step(state: &mut WorldState, n: u8) {
+    state.set_balance(&ALICE, 100 * (n as u128 + 1));
+    state.set_storage(&CONTRACT, &ZERO_HASH, word(n));
+}
+
+fn base() -> WorldState {
+    let mut state = WorldState::new();
+    state.set_code(&CONTRACT, RETURN_SLOT_ZERO.to_vec());
+    step(&mut state, 0);
+    state.commit();
+    state
+}
+
+#[test]
+fn snapshots_are_isolated_from_later_commits() {
+    let mut state = base();
+    let versions = Arc::new(VersionedState::new(0, &state, 100));
+    let mut roots = vec![state.state_root()];
+    let first = versions.snapshot(0).unwrap();
+    for n in 1..=3 {
+        step(&mut state, n);
+        if n == 2 {
+            state.set_balance(&Address([2; 20]), 7);
+        }
+        versions.commit(n as u64, state.pending_changes()).unwrap();
+        state.commit();
+        roots.push(state.state_root());
+    }
+
+    assert_eq!(first.get_balance(&ALICE), 100);
+    assert!(!first.exists(&Address([2; 20])));
+    for n in 0..=3u8 {
+        let snapshot = versions.snapshot(n as u64).unwrap();
+        assert_eq!(snapshot.get_balance(&ALICE), 100 * (n as u128 + 1));
+        assert_eq!(snapshot.get_storage(&CONTRACT, &ZERO_HASH), word(n));
+        assert_eq!(snapshot.code(&CONTRACT), RETURN_SLOT_ZERO.to_vec());
+        assert_eq!(snapshot.to_world_state().state_root(), roots[n as usize]);
+    }
+    assert_eq!(versions.snapshot(3).unwrap().get_balance(&Address([2; 20])), 7);
+
+    assert_eq!(
+        versions.snapshot(4).unwrap_err(),
+        MvccError::Future { height: 4, latest: 3 }
+    );
+    assert_eq!(
+        versions.commit(5, Default::default()),
+        Err(MvccError::NonSequential { latest: 3, got: 5 })
+    );
+}
+
+#[test]
+fn retention_keeps_pinned_heights() {
+    let mut state = base();
+    let versions = Arc::new(VersionedState::new(0, &state, 2));
+    let pinned = versions.snapshot(0).unwrap();
+    for n in 1..=5 {
+        step(&mut state, n);
+        versions.commit(n as u64, state.pending_changes()).unwrap();
+        state.commit();
+    }
+    // Nothing newer than the pin was dropped while it was held
+    assert_eq!(versions.range(), (0, 5));
+    assert_eq!(pinned.clone().get_balance(&ALICE), 100);
+    assert_eq!(versions.pinned(), 1);
+    drop(pinned);
+    assert_eq!(versions.pinned(), 0);
+
+    step(&mut state, 6);
+    versions.commit(6, state.pending_changes()).unwrap();
+    assert_eq!(versions.range(), (5, 6));
+    assert_eq!(
+        versions.snapshot(4).unwrap_err(),
+        MvccError::Pruned { height: 4, oldest: 5 }
+    );
+    // Values last written before the cutoff are still visible
+    let oldest = versions.snapshot(5).unwrap();
+    assert_eq!(oldest.get_balance(&ALICE), 600);
+    assert_eq!(oldest.code(&CONTRACT), RETURN_SLOT_ZERO.to_vec());
+    assert_eq!(versions.latest_snapshot().get_storage(&CONTRACT, &ZERO_HASH), word(6));
+}
+
+#[tokio::test]
+async fn chain_serves_state_at_past_blocks() {
+    let mut state = base();
+    let genesis = Block::genesis(1_704_067_200_000);
+    let chain = Blockchain::new(genesis.clone());
+    assert_eq!(chain.latest_state().await.unwrap_err(), MvccError::Disabled);
+    chain.track_history(&state, 100).await;
+
+    let mut parent = genesis;
+    for n in 1..=2 {
+        step(&mut state, n);
+        let mut block = Block::genesis(parent.header.timestamp + 1_000);
+        block.header.number = n as u64;
+        block.header.parent_hash = parent.hash();
+        block.header.state_root = state.state_root();
+        chain.append_executed(block.clone(), Vec::new(), &state).await.unwrap();
+        state.commit();
+        parent = block;
+    }
+    assert_eq!(chain.latest_state().await.unwrap().height(), 2);
+
+    // Call the contract against block 1 while the head has moved on
+    let snapshot = chain.state_at(1).await.unwrap();
+    let mut world = snapshot.to_world_state();
+    let env = env();
+    let output = Evm::new(&mut world, &env, ALICE, 1).execute(Message::call(ALICE, CONTRACT, 0, Vec::new(), 100_000));
+    assert!(output.is_success());
+    assert_eq!(output.output, word(1).to_vec());
+    assert_eq!(state.get_storage(&CONTRACT, &ZERO_HASH), word(2));
+
+    // A block appended without its state ends tracking
+    let mut block = Block::genesis(parent.header.timestamp + 1_000);
+    block.header.number = 3;
+    block.header.parent_hash = parent.hash();
+    chain.append(block).await.unwrap();
+    assert_eq!(chain.state_at(1).await.unwrap_err(), MvccError::Disabled);
+    assert_eq!(snapshot.get_balance(&ALICE), 200);
+}
@@ -0,0 +1,145 @@
+use tburn_chain_v4_0::contracts::executor::{compute_balance_slot, flags, generate_system_address, slots, u128_to_u256};
+use tburn_chain_v4_0::core::executor::{self, BlockEnv, ExecutionError};
+use tburn_chain_v4_0::core::parallel::{self, ParallelStats};
+use tburn_chain_v4_0::core::types::H256;
+use tburn_chain_v4_0::core::{Address, SignedTransaction, StateDB, Transaction, WorldState};
+
+#[path = "../common/mod.rs"]
+mod common;
+
+use common::{
+    genesis, key, sender, storage_init_code, token_env, token_transfer, transfer, transfer_of, CHAIN_ID, COUNTER,
+    PROPOSER,
+};
+
+fn slot(index: u8) -> H256 {
+    let mut slot = [0u8; 32];
+    slot[31] = index;
+    slot
+}
+
+/// Run `txs` serially and in parallel on copies of `state`, check that
+/// both agree, and return the parallel result.
+fn compare(
+    state: &WorldState,
+    env: &BlockEnv,
+    txs: &[SignedTransaction],
+) -> Result<ParallelStats, (usize, ExecutionError)> {
+    let mut serial = state.clone();
+    let mut parallel = state.clone();
+    let expected = executor::execute_transactions(&mut serial, env, txs);
+    let output = parallel::execute_transactions(&mut parallel, env, txs);
+    match (expected, output) {
+        (Ok(receipts), Ok(output)) => {
+            assert_eq!(output.receipts, receipts);
+            assert_eq!(parallel.state_root(), serial.state_root());
+            assert_eq!(parallel.pending_changes(), serial.pending_changes());
+            assert_eq!(output.stats.speculative + output.stats.reexecuted, txs.len());
+            Ok(output.stats)
+        }
+        (Err(expected), Err(err)) => {
+            assert_eq!(err, expected);
+            Err(err)
+        }
+        (expected, output) => panic!("serial {expected:?}, parallel {:?}", output.map(|o| o.stats)),
+    }
+}
+
+#[test]
+fn independent_transactions_keep_their_parallel_run() {
+    let state = genesis();
+    let env = token_env(30_000_000);
+    let mut txs = Vec::new();
+    for byte in 1..=10 {
+        txs.push(transfer(byte, 0, Address([0x90 + byte; 20]), 1_000));
+    }
+    for byte in 11..=20 {
+        txs.push(token_transfer(byte, 0, Address([0xa0 + byte; 20]), 250));
+    }
+
+    let stats = compare(&state, &env, &txs).unwrap();
+    // Everyone pays the proposer, which is not a conflict
+    assert_eq!((stats.speculative, stats.reexecuted), (20, 0));
+    assert_eq!(stats.parallelism(), 1.0);
+}
+
+#[test]
+fn conflicting_transactions_are_reexecuted_in_order() {
+    let state = genesis();
+    let env = token_env(30_000_000);
+    let txs = vec![
+        // Chain of nonces from one sender
+        transfer(1, 0, Address([0x91; 20]), 10),
+        transfer(1, 1, Address([0x91; 20]), 20),
+        transfer(1, 2, Address([0x92; 20]), 30),
+        // Tokens passed on by their recipient
+        token_transfer(2, 0, sender(3), 600),
+        token_transfer(3, 0, sender(4), 1_500),
+        // Every call increments the same slot
+        transfer(5, 0, COUNTER, 0),
+        transfer(6, 0, COUNTER, 0),
+        transfer(7, 0, COUNTER, 0),
+        // Reads the proposer balance, which earlier fees changed
+        transfer(8, 0, PROPOSER, 5),
+        transfer(9, 0, Address([0x99; 20]), 1),
+    ];
+
+    let stats = compare(&state, &env, &txs).unwrap();
+    assert_eq!((stats.speculative, stats.reexecuted), (4, 6));
+}
+
+#[test]
+fn tokens_deployed_through_the_factory_take_the_fast_path_in_the_same_block() {
+    let state = genesis();
+    let env = token_env(30_000_000);
+    let factory = generate_system_address("TBC20_FACTORY");
+    let salt = [9u8; 32];
+    let init = storage_init_code(&[
+        (slot(slots::FLAGS), slot(flags::BURNABLE)),
+        (compute_balance_slot(&sender(1)), u128_to_u256(500)),
+    ]);
+    let token = env.deployer.factory_address(&factory, &sender(1), &salt, &init);
+    let deploy = Transaction {
+        chain_id: CHAIN_ID,
+        nonce: 0,
+        gas_price: 2,
+        gas_limit: 1_000_000,
+        to: Some(factory),
+        value: 0,
+        data: [&salt[..], &init].concat(),
+    }
+    .sign(&key(1));
+    let spend = transfer_of(token, 1, 1, sender(2), 200);
+    let txs = vec![transfer(3, 0, Address([0x93; 20]), 1), deploy, spend];
+
+    compare(&state, &env, &txs).unwrap();
+    let mut after = state.clone();
+    parallel::execute_transactions(&mut after, &env, &txs).unwrap();
+    assert_eq!(after.get_storage(&token, &compute_balance_slot(&sender(2))), u128_to_u256(200));
+    assert!(env.fast_path.is_eligible(&after, txs[2].tx()));
+}
+
+#[test]
+fn invalid_transactions_fail_at_the_same_index() {
+    let state = genesis();
+    let txs = vec![
+        transfer(1, 0, Address([0x91; 20]), 10),
+        transfer(2, 0, Address([0x92; 20]), 10),
+        transfer(1, 0, Address([0x93; 20]), 10),
+    ];
+    assert!(matches!(
+        compare(&state, &token_env(30_000_000), &txs),
+        Err((2, ExecutionError::NonceMismatch { expected: 1, got: 0 }))
+    ));
+
+    // Only two transactions fit, which speculative runs cannot know
+    let txs: Vec<_> = (1..=3).map(|byte| transfer(byte, 0, Address([0x90; 20]), 1)).collect();
+    assert!(matches!(
+        compare(&state, &token_env(140_000), &txs),
+        Err((2, ExecutionError::BlockGasExceeded { .. }))
+    ));
+    assert_eq!(
+        compare(&state, &token_env(30_000_000), &[]).unwrap(),
+        ParallelStats::default()
+    );
+}
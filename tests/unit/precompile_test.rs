@@ -0,0 +1,194 @@
+use secp256k1::{Message as Digest, Secp256k1, SecretKey};
+use tburn_chain_v4_0::core::config::NetworkProfile;
+use tburn_chain_v4_0::core::transaction::secret_key_to_address;
+use tburn_chain_v4_0::core::types::keccak256;
+use tburn_chain_v4_0::core::vm::precompiles::{self, BECH32M, BLAKE3, ECRECOVER, KECCAK256, MODEXP, SHA256};
+use tburn_chain_v4_0::core::vm::{CallOutput, Evm, ExitReason, Message, VmError};
+use tburn_chain_v4_0::core::{Address, GasSchedule, WorldState};
+
+#[path = "../common/mod.rs"]
+mod common;
+
+use common::env;
+
+const CALLER: Address = Address([0xaa; 20]);
+const CONTRACT: Address = Address([0xcc; 20]);
+
+fn call(state: &mut WorldState, to: Address, input: &[u8], gas: u64) -> CallOutput {
+    let env = env();
+    let mut evm = Evm::new(state, &env, CALLER, 1);
+    evm.execute(Message::call(CALLER, to, 0, input.to_vec(), gas))
+}
+
+fn word(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+#[test]
+fn registry_covers_reserved_addresses() {
+    let addresses: Vec<Address> = precompiles::all().iter().map(|p| p.address).collect();
+    assert_eq!(addresses, vec![ECRECOVER, SHA256, MODEXP, KECCAK256, BLAKE3, BECH32M]);
+    assert_eq!(ECRECOVER.to_hex(), "0x0000000000000000000000000000000000000001");
+    assert_eq!(precompiles::get(&KECCAK256).unwrap().name, "keccak256");
+    assert!(!precompiles::is_precompile(&CONTRACT));
+    assert!(!precompiles::is_precompile(&Address::default()));
+}
+
+#[test]
+fn hash_precompiles_charge_per_word() {
+    let mut state = WorldState::new();
+    let schedule = GasSchedule::default();
+
+    let output = call(&mut state, SHA256, b"abc", 10_000);
+    assert!(output.is_success());
+    assert_eq!(
+        hex::encode(&output.output),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(10_000 - output.gas_left, schedule.sha256 + schedule.sha256_word);
+
+    let output = call(&mut state, KECCAK256, &[0u8; 33], 10_000);
+    assert_eq!(output.output, keccak256(&[0u8; 33]).to_vec());
+    assert_eq!(
+        10_000 - output.gas_left,
+        schedule.keccak256 + 2 * schedule.keccak256_word
+    );
+
+    let output = call(&mut state, BLAKE3, b"abc", 10_000);
+    assert_eq!(output.output, blake3::hash(b"abc").as_bytes().to_vec());
+
+    let output = call(&mut state, SHA256, b"abc", schedule.sha256);
+    assert_eq!(output.exit, ExitReason::Halt(VmError::OutOfGas));
+}
+
+#[test]
+fn ecrecover_returns_signer() {
+    let secret_key = SecretKey::from_slice(&[0x42; 32]).unwrap();
+    let digest = keccak256(b"ported from ethereum");
+    let signature = Secp256k1::new().sign_ecdsa_recoverable(&Digest::from_slice(&digest).unwrap(), &secret_key);
+    let (recovery_id, rs) = signature.serialize_compact();
+
+    let mut input = digest.to_vec();
+    input.extend_from_slice(&word(27 + recovery_id.to_i32() as u64));
+    input.extend_from_slice(&rs);
+
+    let mut state = WorldState::new();
+    let output = call(&mut state, ECRECOVER, &input, 10_000);
+    assert!(output.is_success());
+    assert_eq!(output.output[12..], secret_key_to_address(&secret_key).0);
+    assert_eq!(10_000 - output.gas_left, GasSchedule::default().ecrecover);
+
+    // A bad recovery id is not an error, it just recovers nothing
+    input[63] = 29;
+    let output = call(&mut state, ECRECOVER, &input, 10_000);
+    assert!(output.is_success());
+    assert!(output.output.is_empty());
+}
+
+fn modexp_input(base: &[u8], exp: &[u8], modulus: &[u8]) -> Vec<u8> {
+    let mut input = Vec::new();
+    input.extend_from_slice(&word(base.len() as u64));
+    input.extend_from_slice(&word(exp.len() as u64));
+    input.extend_from_slice(&word(modulus.len() as u64));
+    input.extend_from_slice(base);
+    input.extend_from_slice(exp);
+    input.extend_from_slice(modulus);
+    input
+}
+
+#[test]
+fn modexp_follows_eip_2565() {
+    let mut state = WorldState::new();
+    let schedule = GasSchedule::default();
+
+    // 3 ** 5 % 7 = 5, padded to the modulus length
+    let output = call(&mut state, MODEXP, &modexp_input(&[3], &[5], &[0, 7]), 10_000);
+    assert!(output.is_success());
+    assert_eq!(output.output, vec![0, 5]);
+    assert_eq!(10_000 - output.gas_left, schedule.modexp_min);
+
+    // A zero modulus yields zero
+    let output = call(&mut state, MODEXP, &modexp_input(&[3], &[5], &[0]), 10_000);
+    assert_eq!(output.output, vec![0]);
+
+    // 64-byte operands with a 32-byte all-ones exponent: 8 * 8 * 255 / 3
+    let input = modexp_input(&[0xff; 64], &[0xff; 32], &[0xff; 64]);
+    let cost = precompiles::get(&MODEXP).unwrap().gas(&schedule, &input);
+    assert_eq!(cost, 64 * 255 / 3);
+
+    // Absurd lengths price themselves out instead of allocating
+    let mut input = modexp_input(&[2], &[], &[7]);
+    input[32..64].copy_from_slice(&[0xff; 32]);
+    let output = call(&mut state, MODEXP, &input, 1_000_000);
+    assert_eq!(output.exit, ExitReason::Halt(VmError::OutOfGas));
+}
+
+#[test]
+fn bech32m_converts_both_ways() {
+    let mut state = WorldState::new();
+    let address = Address([0x11; 20]);
+
+    let mut input = vec![0, 1];
+    input.extend_from_slice(&address.0);
+    let output = call(&mut state, BECH32M, &input, 10_000);
+    assert!(output.is_success());
+    let encoded = String::from_utf8(output.output).unwrap();
+    assert_eq!(encoded, address.to_bech32(NetworkProfile::Testnet));
+
+    let mut input = vec![1];
+    input.extend_from_slice(encoded.as_bytes());
+    let output = call(&mut state, BECH32M, &input, 10_000);
+    let mut expected = vec![1];
+    expected.extend_from_slice(&address.0);
+    assert_eq!(output.output, expected);
+
+    // Unknown network or bad checksum halts
+    let output = call(&mut state, BECH32M, &[vec![0, 9], address.0.to_vec()].concat(), 10_000);
+    assert_eq!(output.exit, ExitReason::Halt(VmError::PrecompileFailed));
+    input.pop();
+    let output = call(&mut state, BECH32M, &input, 10_000);
+    assert_eq!(output.exit, ExitReason::Halt(VmError::PrecompileFailed));
+    assert_eq!(output.gas_left, 0);
+}
+
+#[test]
+fn bytecode_and_wasm_contracts_reach_precompiles() {
+    let mut state = WorldState::new();
+    // STATICCALL(gas, KECCAK256, 0, 0, 0, 32), then return the 32 bytes written
+    let code = [
+        0x60, 0x20, 0x5f, 0x5f, 0x5f, 0x61, 0x01, 0x00, 0x5a, 0xfa, 0x50, 0x60, 0x20, 0x5f, 0xf3,
+    ];
+    state.set_code(&CONTRACT, code.to_vec());
+    let output = call(&mut state, CONTRACT, &[], 100_000);
+    assert!(output.is_success());
+    assert_eq!(output.output, keccak256(&[]).to_vec());
+
+    let address: String = SHA256.0.iter().map(|b| format!("\\{b:02x}")).collect();
+    let wasm = wat::parse_str(format!(
+        r#"
+        (module
+          (import "env" "call" (func $call (param i32 i32 i32 i32 i64) (result i32)))
+          (import "env" "return_data_size" (func $size (result i32)))
+          (import "env" "return_data_copy" (func $copy (param i32)))
+          (import "env" "set_return" (func $return (param i32 i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "{address}")
+          (data (i32.const 64) "abc")
+          (func (export "call")
+            (if (call $call (i32.const 0) (i32.const 32) (i32.const 64) (i32.const 3) (i64.const 10000))
+              (then unreachable))
+            (call $copy (i32.const 128))
+            (call $return (i32.const 128) (call $size))))
+        "#
+    ))
+    .unwrap();
+    state.set_code(&CONTRACT, wasm);
+    let output = call(&mut state, CONTRACT, &[], 200_000);
+    assert!(output.is_success(), "{output:?}");
+    assert_eq!(
+        hex::encode(&output.output),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
@@ -0,0 +1,139 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tburn_chain_v4_0::core::receipt::receipts_root;
+use tburn_chain_v4_0::core::{Address, Block, Blockchain, Receipt, StateDB, WorldState};
+use tburn_chain_v4_0::storage::pruning::{PruneReport, Pruner, PruningConfig, PruningMode};
+use tburn_chain_v4_0::storage::rocksdb::{Column, KvStore, MemoryStore};
+
+const CONTRACT: Address = Address([0xcc; 20]);
+
+/// Chain of `blocks` blocks after genesis in a fresh store. Every block
+/// changes a balance and a storage slot and burns 10 Ember; block 2 also
+/// deploys code. Returns the state root after each block.
+async fn build_chain(blocks: u64) -> (Arc<MemoryStore>, Arc<Blockchain>, Block, Vec<[u8; 32]>) {
+    let mut state = WorldState::new();
+    state.set_balance(&Address([1; 20]), 1_000);
+    state.commit();
+    let mut genesis = Block::genesis(1_704_067_200_000);
+    genesis.header.state_root = state.state_root();
+
+    let store = Arc::new(MemoryStore::new());
+    let chain = Arc::new(Blockchain::open(store.clone(), genesis.clone(), &state).unwrap());
+    let mut roots = vec![genesis.header.state_root];
+    let mut parent = genesis.clone();
+    for number in 1..=blocks {
+        state.set_balance(&Address([1; 20]), 1_000 + number as u128);
+        state.set_storage(&CONTRACT, &[number as u8; 32], [number as u8; 32]);
+        if number == 2 {
+            state.set_code(&CONTRACT, vec![0x60, 0x00]);
+        }
+        let receipts = vec![Receipt {
+            tx_hash: [number as u8; 32],
+            success: true,
+            gas_used: 21_000,
+            cumulative_gas_used: 21_000,
+            burned: 10,
+            contract_address: None,
+            logs: Vec::new(),
+            revert_data: Vec::new(),
+        }];
+        let mut block = Block::genesis(parent.header.timestamp + 1_000);
+        block.header.number = number;
+        block.header.parent_hash = parent.hash();
+        block.header.state_root = state.state_root();
+        block.header.receipts_root = receipts_root(&receipts);
+        chain.append_executed(block.clone(), receipts, &state).await.unwrap();
+        state.commit();
+        roots.push(block.header.state_root);
+        parent = block;
+    }
+    (store, chain, genesis, roots)
+}
+
+fn config(mode: PruningMode, keep_recent: u64) -> PruningConfig {
+    PruningConfig {
+        mode,
+        keep_recent,
+        interval: Duration::from_secs(60),
+    }
+}
+
+#[tokio::test]
+async fn full_mode_keeps_only_recent_states() {
+    let (store, chain, _, roots) = build_chain(6).await;
+    let nodes_before = store.len(Column::TrieNodes);
+    let pruner = Pruner::new(chain.clone(), store.clone(), config(PruningMode::Full, 2));
+
+    let report = pruner.prune().await.unwrap();
+    assert_eq!(report.kept_from, 5);
+    assert!(report.nodes > 0 && report.bytes_reclaimed > 0);
+    assert_eq!(store.len(Column::TrieNodes) as u64, nodes_before as u64 - report.nodes);
+    assert_eq!(store.len(Column::TrieNodes) as u64, report.live_nodes);
+
+    for (number, root) in roots.iter().enumerate() {
+        let loaded = WorldState::load(store.as_ref(), root);
+        assert_eq!(loaded.is_ok(), number >= 5, "state of block {number}");
+    }
+    let head = WorldState::load(store.as_ref(), &roots[6]).unwrap();
+    assert_eq!(head.code(&CONTRACT), vec![0x60, 0x00]);
+    assert_eq!(head.get_storage(&CONTRACT, &[1; 32]), [1; 32]);
+
+    // Blocks are untouched, and nothing is left to prune
+    assert_eq!(store.len(Column::Bodies), 7);
+    assert_eq!(chain.get_receipts(1).await.unwrap().len(), 1);
+    let again = pruner.prune().await.unwrap();
+    assert_eq!((again.nodes, again.bytes_reclaimed), (0, 0));
+    assert_eq!(pruner.stats(), (2, report.bytes_reclaimed));
+}
+
+#[tokio::test]
+async fn light_mode_drops_old_bodies_and_receipts() {
+    let (store, chain, genesis, roots) = build_chain(5).await;
+    let pruner = Pruner::new(chain.clone(), store.clone(), config(PruningMode::Light, 2));
+    let report = pruner.prune().await.unwrap();
+    assert_eq!(report.kept_from, 4);
+    assert_eq!(report.bodies, 3);
+
+    assert_eq!(chain.pruned().await, 4);
+    assert_eq!(store.len(Column::Bodies), 3);
+    assert_eq!(store.len(Column::Receipts), 3);
+    assert!(chain.get_receipts(3).await.is_none());
+    assert_eq!(chain.get_receipts(4).await.unwrap().len(), 1);
+    // Headers stay, so the chain still links up
+    let pruned = chain.get_block_by_number(2).await.unwrap();
+    assert_eq!(pruned.header.state_root, roots[2]);
+    assert_eq!(pruned.tx_count(), 0);
+    assert_eq!(chain.total_burned().await, 50);
+
+    let reopened = Blockchain::open(store.clone(), genesis, &WorldState::new()).unwrap();
+    assert_eq!(reopened.pruned().await, 4);
+    assert_eq!(reopened.head().await.number(), 5);
+    assert_eq!(reopened.total_burned().await, 50);
+    assert!(WorldState::load(store.as_ref(), &roots[5]).is_ok());
+}
+
+#[tokio::test]
+async fn archive_mode_deletes_nothing() {
+    let (store, chain, _, roots) = build_chain(3).await;
+    let pruner = Pruner::new(chain, store.clone(), config(PruningMode::Archive, 1));
+    assert_eq!(pruner.prune().await.unwrap(), PruneReport::default());
+    for root in &roots {
+        assert!(WorldState::load(store.as_ref(), root).is_ok());
+    }
+    assert!(store.contains(Column::Bodies, &1u64.to_be_bytes()).unwrap());
+}
+
+#[tokio::test]
+async fn stale_body_prunes_are_dropped() {
+    let (store, chain, _, _) = build_chain(5).await;
+    let stale = chain.bodies_to_prune(3).await.unwrap();
+    let current = chain.bodies_to_prune(4).await.unwrap();
+
+    let mut pause = chain.pause_import().await;
+    assert_eq!(pause.prune_bodies(current).unwrap().blocks, 3);
+    assert_eq!(pause.prune_bodies(stale).unwrap(), Default::default());
+    drop(pause);
+    assert_eq!(chain.pruned().await, 4);
+    assert_eq!(store.len(Column::Bodies), 3);
+}
@@ -0,0 +1,177 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tburn_chain_v4_0::core::blockchain::ChainError;
+use tburn_chain_v4_0::core::{Address, Block, Blockchain, StateDB, WorldState};
+use tburn_chain_v4_0::storage::rocksdb::{KvStore, MemoryStore};
+use tburn_chain_v4_0::storage::snapshot::{self, Compression, SnapshotError, SnapshotOptions};
+
+/// Fresh path under the system temp dir.
+fn temp_file(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("tburn-{name}-{}.tbsnap", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn sample_state() -> WorldState {
+    let mut state = WorldState::new();
+    for byte in 1..=50u8 {
+        let address = Address([byte; 20]);
+        state.set_balance(&address, byte as u128 * 1_000);
+        state.set_nonce(&address, byte as u64);
+    }
+    for contract in [Address([0xc1; 20]), Address([0xc2; 20])] {
+        state.set_code(&contract, vec![0x60, 0x00, 0x54, contract.0[0]]);
+        for slot in 0..20u8 {
+            state.set_storage(&contract, &[slot; 32], [slot + 1; 32]);
+        }
+    }
+    // Shares code with an existing contract
+    state.set_code(&Address([0xc3; 20]), vec![0x60, 0x00, 0x54, 0xc1]);
+    state.commit();
+    state
+}
+
+fn block_at(number: u64, state: &WorldState) -> Block {
+    let mut block = Block::genesis(1_704_067_200_000 + number * 1_000);
+    block.header.number = number;
+    block.header.parent_hash = [number as u8; 32];
+    block.header.state_root = state.state_root();
+    block
+}
+
+#[test]
+fn snapshots_round_trip_in_chunks() {
+    let state = sample_state();
+    let block = block_at(42, &state);
+    for compression in [Compression::Zstd, Compression::Snappy] {
+        let path = temp_file(&format!("snapshot-{compression:?}"));
+        let options = SnapshotOptions {
+            compression,
+            chunk_size: 512,
+        };
+        let written = snapshot::write(&path, &[9; 32], &block, &state, options).unwrap();
+        assert!(written.chunks.len() > 1);
+        assert_eq!((written.accounts, written.slots, written.codes), (53, 40, 2));
+        assert!(written.chunks.iter().all(|chunk| chunk.size < chunk.raw_size));
+
+        assert_eq!(snapshot::read_manifest(&path).unwrap(), written);
+        let (manifest, restored) = snapshot::read(&path).unwrap();
+        assert_eq!(manifest.height(), 42);
+        assert_eq!(manifest.genesis_hash, [9; 32]);
+        assert_eq!(restored.state_root(), state.state_root());
+        assert_eq!(restored.code(&Address([0xc3; 20])), state.code(&Address([0xc1; 20])));
+        assert_eq!(restored.get_nonce(&Address([7; 20])), 7);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[test]
+fn damaged_snapshots_are_rejected() {
+    let state = sample_state();
+    let path = temp_file("snapshot-damaged");
+    let options = SnapshotOptions {
+        chunk_size: 512,
+        ..SnapshotOptions::default()
+    };
+
+    // The state must belong to the block
+    let stale = block_at(1, &WorldState::new());
+    assert!(matches!(
+        snapshot::write(&path, &[0; 32], &stale, &state, options),
+        Err(SnapshotError::StateRootMismatch { .. })
+    ));
+    assert!(!path.exists());
+
+    let manifest = snapshot::write(&path, &[0; 32], &block_at(1, &state), &state, options).unwrap();
+    let original = std::fs::read(&path).unwrap();
+    let damage = |offset: usize| {
+        let mut bytes = original.clone();
+        bytes[offset] ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+        snapshot::read(&path).unwrap_err()
+    };
+
+    let second_chunk = snapshot::MAGIC.len() + manifest.chunks[0].size as usize;
+    assert!(matches!(damage(second_chunk + 3), SnapshotError::ChunkChecksum(1)));
+    let manifest_start = original.len() - 40 - 100;
+    assert!(matches!(damage(manifest_start), SnapshotError::ManifestChecksum));
+    assert!(matches!(damage(0), SnapshotError::BadMagic));
+    assert!(matches!(damage(original.len() - 8), SnapshotError::TooLarge(_)));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn chain_bootstraps_from_snapshot() {
+    let state = sample_state();
+    let genesis = Block::genesis(1_704_067_200_000);
+    let block = block_at(5, &state);
+    let path = temp_file("snapshot-bootstrap");
+    snapshot::write(&path, &genesis.hash(), &block, &state, SnapshotOptions::default()).unwrap();
+    let (manifest, restored) = snapshot::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let db: Arc<dyn KvStore> = Arc::new(MemoryStore::new());
+    let mismatched = block_at(5, &WorldState::new());
+    assert!(matches!(
+        Blockchain::bootstrap(db.clone(), genesis.clone(), mismatched, &restored),
+        Err(ChainError::StateRootMismatch { .. })
+    ));
+
+    let chain = Blockchain::bootstrap(db.clone(), genesis.clone(), manifest.block, &restored).unwrap();
+    assert_eq!(chain.base().await, 5);
+    assert_eq!(chain.head().await.hash(), block.hash());
+    assert!(chain.get_block_by_number(3).await.is_none());
+    assert_eq!(chain.get_block_by_hash(&block.hash()).await.unwrap().number(), 5);
+
+    // The chain extends from the snapshot block like any other head
+    let mut child = block_at(6, &state);
+    child.header.parent_hash = block.hash();
+    chain.append_executed(child.clone(), Vec::new(), &state).await.unwrap();
+    assert_eq!(chain.get_receipts(6).await.unwrap().len(), 0);
+
+    let reopened = Blockchain::open(db.clone(), genesis.clone(), &WorldState::new()).unwrap();
+    assert_eq!(reopened.base().await, 5);
+    assert_eq!(reopened.head().await.hash(), child.hash());
+    let loaded = WorldState::load(db.as_ref(), &child.header.state_root).unwrap();
+    assert_eq!(loaded.state_root(), state.state_root());
+
+    assert!(matches!(
+        Blockchain::bootstrap(db, genesis, block, &restored),
+        Err(ChainError::NotEmpty)
+    ));
+}
+
+#[test]
+fn failed_writes_leave_no_partial_file() {
+    let state = sample_state();
+    let block = block_at(42, &state);
+    // A non-empty directory in the way makes the final rename fail
+    let path = temp_file("blocked");
+    std::fs::create_dir_all(path.join("occupied")).unwrap();
+    assert!(matches!(
+        snapshot::write(&path, &[9; 32], &block, &state, SnapshotOptions::default()),
+        Err(SnapshotError::Io(_))
+    ));
+    assert!(!path.with_extension("partial").exists());
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn rotation_keeps_the_highest_snapshots() {
+    let dir = std::env::temp_dir().join(format!("tburn-rotate-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for height in [5, 100, 20, 1_000] {
+        std::fs::write(dir.join(snapshot::file_name(height)), b"").unwrap();
+    }
+    std::fs::write(dir.join("notes.txt"), b"").unwrap();
+
+    let removed = snapshot::rotate(&dir, 2).unwrap();
+    assert_eq!(removed, vec![dir.join(snapshot::file_name(5)), dir.join(snapshot::file_name(20))]);
+    assert!(dir.join(snapshot::file_name(100)).exists());
+    assert!(dir.join(snapshot::file_name(1_000)).exists());
+    assert!(dir.join("notes.txt").exists());
+    assert!(snapshot::rotate(&dir, 2).unwrap().is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
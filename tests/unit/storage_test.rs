@@ -0,0 +1,108 @@
+use tburn_chain_v4_0::core::{Address, StateDB, WorldState};
+
+fn addr(byte: u8) -> Address {
+    Address([byte; 20])
+}
+
+#[test]
+fn revert_restores_state_before_checkpoint() {
+    let mut state = WorldState::new();
+    state.set_balance(&addr(1), 100);
+    state.commit();
+    let root_before = state.state_root();
+
+    let checkpoint = state.checkpoint();
+    state.transfer(&addr(1), &addr(2), 40).unwrap();
+    state.set_nonce(&addr(1), 1);
+    state.set_storage(&addr(3), &[1u8; 32], [2u8; 32]);
+    state.set_code(&addr(3), vec![0x60, 0x00]);
+    assert_ne!(state.state_root(), root_before);
+
+    state.revert_to(checkpoint);
+    assert_eq!(state.get_balance(&addr(1)), 100);
+    assert_eq!(state.get_balance(&addr(2)), 0);
+    assert_eq!(state.get_nonce(&addr(1)), 0);
+    assert_eq!(state.get_storage(&addr(3), &[1u8; 32]), [0u8; 32]);
+    assert!(state.code(&addr(3)).is_empty());
+    assert!(!state.exists(&addr(2)));
+    assert_eq!(state.state_root(), root_before);
+}
+
+#[test]
+fn nested_checkpoints_revert_independently() {
+    let mut state = WorldState::new();
+    let outer = state.checkpoint();
+    state.set_balance(&addr(1), 10);
+
+    let inner = state.checkpoint();
+    state.set_balance(&addr(1), 20);
+    state.revert_to(inner);
+    assert_eq!(state.get_balance(&addr(1)), 10);
+
+    state.revert_to(outer);
+    assert_eq!(state.get_balance(&addr(1)), 0);
+}
+
+#[test]
+fn transfer_fails_without_funds_and_leaves_state_untouched() {
+    let mut state = WorldState::new();
+    state.set_balance(&addr(1), 5);
+    assert!(state.transfer(&addr(1), &addr(2), 6).is_err());
+    assert_eq!(state.get_balance(&addr(1)), 5);
+    assert_eq!(state.get_balance(&addr(2)), 0);
+}
+
+#[test]
+fn state_root_commits_to_storage() {
+    let mut a = WorldState::new();
+    let mut b = WorldState::new();
+    a.set_storage(&addr(1), &[1u8; 32], [1u8; 32]);
+    b.set_storage(&addr(1), &[1u8; 32], [2u8; 32]);
+    assert_ne!(a.state_root(), b.state_root());
+
+    // Clearing a slot is the same as never writing it
+    b.set_storage(&addr(1), &[1u8; 32], [0u8; 32]);
+    b.set_storage(&addr(1), &[1u8; 32], [1u8; 32]);
+    assert_eq!(a.state_root(), b.state_root());
+
+    // Including on an address that had no account before
+    let empty = WorldState::new();
+    a.set_storage(&addr(1), &[1u8; 32], [0u8; 32]);
+    assert!(!a.exists(&addr(1)));
+    assert_eq!(a.state_root(), empty.state_root());
+    let checkpoint = a.checkpoint();
+    a.set_storage(&addr(2), &[1u8; 32], [1u8; 32]);
+    a.set_storage(&addr(2), &[1u8; 32], [0u8; 32]);
+    assert_eq!(a.state_root(), empty.state_root());
+    a.revert_to(checkpoint);
+    assert_eq!(a.state_root(), empty.state_root());
+}
+
+#[test]
+fn cached_roots_follow_writes_between_roots() {
+    let mut incremental = WorldState::new();
+    let mut fresh = WorldState::new();
+    for round in 1..=3u8 {
+        incremental.set_balance(&addr(round), round as u128);
+        incremental.set_storage(&addr(9), &[round; 32], [round; 32]);
+        let storage_root = incremental.storage_root(&addr(9));
+        let root = incremental.state_root();
+
+        fresh.set_balance(&addr(round), round as u128);
+        fresh.set_storage(&addr(9), &[round; 32], [round; 32]);
+        let copy = fresh.clone();
+        assert_eq!(storage_root, copy.storage_root(&addr(9)));
+        assert_eq!(root, copy.state_root());
+    }
+
+    let checkpoint = incremental.checkpoint();
+    incremental.set_storage(&addr(9), &[1; 32], [0; 32]);
+    incremental.set_balance(&addr(1), 0);
+    assert_ne!(incremental.state_root(), fresh.state_root());
+    incremental.revert_to(checkpoint);
+    assert_eq!(incremental.state_root(), fresh.state_root());
+    assert_eq!(
+        incremental.account(&addr(9)).unwrap().storage_root,
+        fresh.storage_root(&addr(9))
+    );
+}
@@ -0,0 +1,244 @@
+use secp256k1::SecretKey;
+use tburn_chain_v4_0::core::mempool::{MempoolError, DEFAULT_PRICE_BUMP_PERCENT};
+use tburn_chain_v4_0::core::transaction::{secret_key_to_address, TxError};
+use tburn_chain_v4_0::core::{
+    Address, GasSchedule, Mempool, MempoolConfig, SignedTransaction, StateDB, Transaction, WorldState,
+};
+
+const CHAIN_ID: u64 = 1;
+
+fn key(byte: u8) -> SecretKey {
+    SecretKey::from_slice(&[byte; 32]).unwrap()
+}
+
+fn transfer(nonce: u64) -> Transaction {
+    Transaction {
+        chain_id: CHAIN_ID,
+        nonce,
+        gas_price: 10,
+        gas_limit: 25_000,
+        to: Some(Address([0xaa; 20])),
+        value: 1_000,
+        data: vec![1, 2, 3],
+    }
+}
+
+#[test]
+fn sender_is_recovered_from_signature() {
+    let signed = transfer(0).sign(&key(1));
+    assert_eq!(signed.verify(CHAIN_ID).unwrap(), secret_key_to_address(&key(1)));
+    assert_ne!(signed.recover_sender().unwrap(), secret_key_to_address(&key(2)));
+}
+
+#[test]
+fn hash_is_deterministic_and_survives_round_trip() {
+    let signed = transfer(7).sign(&key(1));
+    assert_eq!(signed.hash(), transfer(7).sign(&key(1)).hash());
+    assert_ne!(signed.hash(), transfer(8).sign(&key(1)).hash());
+
+    let decoded = SignedTransaction::decode(&signed.encode()).unwrap();
+    assert_eq!(decoded, signed);
+    assert_eq!(decoded.hash(), signed.hash());
+}
+
+#[test]
+fn other_chain_ids_are_rejected() {
+    let mut tx = transfer(0);
+    tx.chain_id = 2;
+    let signed = tx.sign(&key(1));
+    assert_eq!(
+        signed.verify(CHAIN_ID),
+        Err(TxError::WrongChainId { expected: CHAIN_ID, got: 2 })
+    );
+}
+
+#[test]
+fn tampered_payload_recovers_a_different_sender() {
+    let signed = transfer(0).sign(&key(1));
+    let mut tx = signed.tx().clone();
+    tx.value += 1;
+    let tampered = SignedTransaction::new(tx, *signed.signature());
+    assert_ne!(tampered.hash(), signed.hash());
+    assert_ne!(tampered.recover_sender().ok(), Some(secret_key_to_address(&key(1))));
+}
+
+#[test]
+fn high_s_signatures_are_rejected() {
+    let signed = transfer(0).sign(&key(1));
+    let mut signature = *signed.signature();
+    signature.s = [0xff; 32];
+    let malleated = SignedTransaction::new(signed.tx().clone(), signature);
+    assert_eq!(malleated.recover_sender(), Err(TxError::NonCanonicalSignature));
+}
+
+fn pool(capacity: usize) -> Mempool {
+    Mempool::new(MempoolConfig {
+        chain_id: CHAIN_ID,
+        capacity,
+        min_gas_price: 1,
+        max_tx_gas: 1_000_000,
+        price_bump_percent: DEFAULT_PRICE_BUMP_PERCENT,
+        schedule: GasSchedule::default(),
+    })
+}
+
+fn funded(keys: &[u8]) -> WorldState {
+    let mut state = WorldState::new();
+    for byte in keys {
+        state.set_balance(&secret_key_to_address(&key(*byte)), 1_000_000_000);
+    }
+    state
+}
+
+fn priced(nonce: u64, gas_price: u128) -> Transaction {
+    Transaction { gas_price, ..transfer(nonce) }
+}
+
+#[test]
+fn mempool_admission_checks_nonce_and_balance() {
+    let mut state = funded(&[1]);
+    state.set_nonce(&secret_key_to_address(&key(1)), 3);
+    let mut mempool = pool(10);
+
+    assert_eq!(
+        mempool.add(transfer(2).sign(&key(1)), &state),
+        Err(MempoolError::NonceTooLow { expected: 3, got: 2 })
+    );
+    assert!(matches!(
+        mempool.add(transfer(0).sign(&key(9)), &state),
+        Err(MempoolError::InsufficientBalance { balance: 0, .. })
+    ));
+
+    // 21000 base plus 16 per non-zero calldata byte
+    let mut underfunded_gas = transfer(3);
+    underfunded_gas.gas_limit = 21_000;
+    assert_eq!(
+        mempool.add(underfunded_gas.sign(&key(1)), &state),
+        Err(MempoolError::IntrinsicGas { gas_limit: 21_000, intrinsic: 21_048 })
+    );
+
+    let mut wrong_chain = transfer(3);
+    wrong_chain.chain_id = 2;
+    assert!(matches!(
+        mempool.add(wrong_chain.sign(&key(1)), &state),
+        Err(MempoolError::InvalidTransaction(TxError::WrongChainId { .. }))
+    ));
+
+    let hash = mempool.add(transfer(3).sign(&key(1)), &state).unwrap();
+    assert!(mempool.contains(&hash));
+    assert!(matches!(
+        mempool.add(transfer(3).sign(&key(1)), &state),
+        Err(MempoolError::AlreadyKnown(_))
+    ));
+}
+
+#[test]
+fn mempool_balance_check_covers_all_queued_transactions() {
+    let sender = secret_key_to_address(&key(1));
+    let mut state = WorldState::new();
+    // Each transfer costs at most 25_000 * 10 + 1_000
+    state.set_balance(&sender, 600_000);
+    let mut mempool = pool(10);
+    mempool.add(transfer(0).sign(&key(1)), &state).unwrap();
+    let second = mempool.add(transfer(1).sign(&key(1)), &state).unwrap();
+    assert_eq!(
+        mempool.add(transfer(2).sign(&key(1)), &state),
+        Err(MempoolError::InsufficientBalance { balance: 600_000, cost: 753_000 })
+    );
+    assert_eq!(mempool.sender(&second), Some(sender));
+
+    // A replacement only needs to cover itself in place of the original
+    let replacement = priced(1, 11).sign(&key(1));
+    mempool.add(replacement.clone(), &state).unwrap();
+    assert!(!mempool.contains(&second));
+    assert!(mempool.contains(&replacement.hash()));
+}
+
+#[test]
+fn pending_orders_by_price_and_nonce_and_queues_gaps() {
+    let state = funded(&[1, 2]);
+    let mut mempool = pool(10);
+
+    mempool.add(priced(0, 5).sign(&key(1)), &state).unwrap();
+    mempool.add(priced(1, 50).sign(&key(1)), &state).unwrap();
+    mempool.add(priced(0, 20).sign(&key(2)), &state).unwrap();
+    // Nonce 3 of key 2 waits on the missing nonce 1 and 2
+    mempool.add(priced(3, 100).sign(&key(2)), &state).unwrap();
+
+    let pending: Vec<(u128, u64)> = mempool
+        .pending(&state)
+        .iter()
+        .map(|tx| (tx.tx().gas_price, tx.tx().nonce))
+        .collect();
+    // Key 1's nonce 1 pays more but cannot run before its nonce 0
+    assert_eq!(pending, vec![(20, 0), (5, 0), (50, 1)]);
+
+    let queued = mempool.queued(&state);
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].tx().nonce, 3);
+}
+
+#[test]
+fn replacement_needs_price_bump() {
+    let state = funded(&[1]);
+    let mut mempool = pool(10);
+    let original = mempool.add(priced(0, 100).sign(&key(1)), &state).unwrap();
+
+    assert_eq!(
+        mempool.add(priced(0, 105).sign(&key(1)), &state),
+        Err(MempoolError::ReplacementUnderpriced { required: 110 })
+    );
+    let replacement = mempool.add(priced(0, 110).sign(&key(1)), &state).unwrap();
+    assert!(!mempool.contains(&original));
+    assert!(mempool.contains(&replacement));
+    assert_eq!(mempool.len(), 1);
+}
+
+#[test]
+fn full_pool_evicts_cheapest_transaction() {
+    let state = funded(&[1, 2, 3, 4]);
+    let mut mempool = pool(2);
+    let cheap = mempool.add(priced(0, 10).sign(&key(1)), &state).unwrap();
+    mempool.add(priced(0, 30).sign(&key(2)), &state).unwrap();
+
+    assert_eq!(
+        mempool.add(priced(0, 10).sign(&key(3)), &state),
+        Err(MempoolError::PoolFull(10))
+    );
+    let better = mempool.add(priced(0, 20).sign(&key(4)), &state).unwrap();
+    assert_eq!(mempool.len(), 2);
+    assert!(!mempool.contains(&cheap));
+    assert!(mempool.contains(&better));
+}
+
+#[test]
+fn base_fee_rejects_and_evicts_underpriced_transactions() {
+    let state = funded(&[1, 2]);
+    let mut mempool = pool(10);
+    mempool.set_base_fee(5);
+    assert_eq!(
+        mempool.add(priced(0, 4).sign(&key(1)), &state),
+        Err(MempoolError::GasPriceTooLow { min: 5, got: 4 })
+    );
+    let cheap = mempool.add(priced(0, 5).sign(&key(1)), &state).unwrap();
+    let dear = mempool.add(priced(0, 8).sign(&key(2)), &state).unwrap();
+
+    // A rising base fee drops what the next block could no longer include
+    mempool.set_base_fee(6);
+    assert!(!mempool.contains(&cheap));
+    assert!(mempool.contains(&dear));
+    assert_eq!(mempool.min_gas_price(), 6);
+}
+
+#[test]
+fn prune_drops_executed_nonces() {
+    let mut state = funded(&[1]);
+    let mut mempool = pool(10);
+    mempool.add(transfer(0).sign(&key(1)), &state).unwrap();
+    mempool.add(transfer(1).sign(&key(1)), &state).unwrap();
+
+    state.set_nonce(&secret_key_to_address(&key(1)), 1);
+    mempool.prune(&state);
+    assert_eq!(mempool.len(), 1);
+    assert_eq!(mempool.pending(&state)[0].tx().nonce, 1);
+}
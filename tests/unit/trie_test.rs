@@ -0,0 +1,211 @@
+use tburn_chain_v4_0::core::state::{
+    stored_account_proof, stored_storage_proof, verify_account_proof, verify_storage_proof,
+};
+use tburn_chain_v4_0::core::types::ZERO_HASH;
+use tburn_chain_v4_0::core::{Address, StateDB, WorldState};
+use tburn_chain_v4_0::storage::rocksdb::{KvStore, MemoryStore, WriteBatch};
+use tburn_chain_v4_0::storage::trie::{verify_proof, MerkleTrie, ProofError};
+
+/// Keys sharing prefixes of every length, including one that is a prefix
+/// of others.
+const KEYS: [&[u8]; 8] = [b"do", b"dog", b"doge", b"horse", b"d", b"", b"\x00\x01", b"\x00\x02"];
+
+fn filled(keys: &[&[u8]]) -> MerkleTrie {
+    let mut trie = MerkleTrie::new();
+    for key in keys {
+        trie.insert(key, [b"value:", *key].concat());
+    }
+    trie
+}
+
+#[test]
+fn insert_get_and_remove() {
+    let mut trie = filled(&KEYS);
+    assert_eq!(trie.len(), KEYS.len());
+    for key in KEYS {
+        assert_eq!(trie.get(key), Some(&[b"value:", key].concat()[..]));
+    }
+    assert_eq!(trie.get(b"dogs"), None);
+    assert_eq!(trie.get(b"hor"), None);
+
+    assert_eq!(trie.insert(b"dog", b"puppy".to_vec()), Some(b"value:dog".to_vec()));
+    assert_eq!(trie.get(b"dog"), Some(&b"puppy"[..]));
+    assert_eq!(trie.len(), KEYS.len());
+
+    assert_eq!(trie.remove(b"dog"), Some(b"puppy".to_vec()));
+    assert_eq!(trie.remove(b"dog"), None);
+    assert_eq!(trie.get(b"doge"), Some(&b"value:doge"[..]));
+    for key in KEYS {
+        trie.remove(key);
+    }
+    assert!(trie.is_empty());
+    assert_eq!(trie.root(), ZERO_HASH);
+}
+
+#[test]
+fn root_depends_on_contents_not_history() {
+    let forward = filled(&KEYS);
+    let mut reversed: Vec<&[u8]> = KEYS.to_vec();
+    reversed.reverse();
+    assert_eq!(filled(&reversed).root(), forward.root());
+
+    // Adding then removing a key restores the previous shape exactly
+    let mut trie = forward.clone();
+    trie.insert(b"dot", b"x".to_vec());
+    assert_ne!(trie.root(), forward.root());
+    trie.remove(b"dot");
+    assert_eq!(trie, forward);
+
+    let mut changed = forward.clone();
+    changed.insert(b"horse", b"pony".to_vec());
+    assert_ne!(changed.root(), forward.root());
+}
+
+#[test]
+fn proofs_show_presence_and_absence() {
+    let trie = filled(&KEYS);
+    let root = trie.root();
+    for key in KEYS {
+        let proof = trie.prove(key);
+        assert_eq!(verify_proof(&root, key, &proof), Ok(Some([b"value:", key].concat())));
+    }
+    for missing in [&b"dogs"[..], b"hor", b"cat", b"\x00", b"\x00\x03"] {
+        let proof = trie.prove(missing);
+        assert_eq!(verify_proof(&root, missing, &proof), Ok(None), "{missing:?}");
+    }
+    assert_eq!(verify_proof(&ZERO_HASH, b"dog", &[]), Ok(None));
+    assert_eq!(MerkleTrie::new().prove(b"dog"), Vec::<Vec<u8>>::new());
+}
+
+#[test]
+fn tampered_proofs_are_rejected() {
+    let trie = filled(&KEYS);
+    let root = trie.root();
+    let proof = trie.prove(b"doge");
+
+    // A proof for one key says nothing about a different value
+    let mut other = trie.clone();
+    other.insert(b"doge", b"wow".to_vec());
+    assert_eq!(
+        verify_proof(&root, b"doge", &other.prove(b"doge")),
+        Err(ProofError::HashMismatch)
+    );
+
+    let mut flipped = proof.clone();
+    let last = flipped.last_mut().unwrap();
+    *last.last_mut().unwrap() ^= 1;
+    assert_eq!(verify_proof(&root, b"doge", &flipped), Err(ProofError::HashMismatch));
+
+    assert_eq!(
+        verify_proof(&root, b"doge", &proof[..proof.len() - 1]),
+        Err(ProofError::Incomplete)
+    );
+
+    let mut padded = proof.clone();
+    padded.push(proof[0].clone());
+    assert_eq!(verify_proof(&root, b"doge", &padded), Err(ProofError::TrailingNodes(1)));
+    assert_eq!(
+        verify_proof(&ZERO_HASH, b"doge", &proof),
+        Err(ProofError::TrailingNodes(proof.len()))
+    );
+}
+
+#[test]
+fn node_store_round_trip() {
+    let trie = filled(&KEYS);
+    let nodes = trie.nodes();
+    assert!(nodes.contains_key(&trie.root()));
+    let loaded = MerkleTrie::from_nodes(&trie.root(), &nodes).unwrap();
+    assert_eq!(loaded, trie);
+    assert_eq!(loaded.len(), KEYS.len());
+
+    let mut partial = nodes.clone();
+    partial.retain(|hash, _| *hash == trie.root());
+    assert!(MerkleTrie::from_nodes(&trie.root(), &partial).is_none());
+    assert_eq!(MerkleTrie::from_nodes(&ZERO_HASH, &partial), Some(MerkleTrie::new()));
+}
+
+#[test]
+fn nodes_along_changed_keys_complete_the_store() {
+    let before = filled(&KEYS);
+    // Each change splits, rewrites or merges nodes next to its path
+    let changes: [&[u8]; 6] = [b"dot", b"do", b"doge", b"\x00\x03", b"horses", b"hz"];
+    for key in changes {
+        let mut trie = before.clone();
+        match trie.get(key) {
+            Some(_) => trie.remove(key),
+            None => trie.insert(key, b"new".to_vec()),
+        };
+        let mut store = before.nodes();
+        store.extend(trie.nodes_along([key]));
+        assert_eq!(MerkleTrie::from_nodes(&trie.root(), &store), Some(trie));
+    }
+}
+
+#[test]
+fn world_state_proofs_verify_against_roots() {
+    let mut state = WorldState::new();
+    let contract = Address([0xcc; 20]);
+    for byte in 1..=20 {
+        state.set_balance(&Address([byte; 20]), byte as u128 * 1_000);
+    }
+    state.set_code(&contract, vec![0x00]);
+    state.set_storage(&contract, &[1; 32], [7; 32]);
+    state.set_storage(&contract, &[2; 32], [8; 32]);
+    let root = state.state_root();
+
+    let proof = state.account_proof(&Address([5; 20]));
+    let account = verify_account_proof(&root, &Address([5; 20]), &proof).unwrap().unwrap();
+    assert_eq!(account.balance, 5_000);
+
+    let absent = Address([0xee; 20]);
+    assert_eq!(
+        verify_account_proof(&root, &absent, &state.account_proof(&absent)),
+        Ok(None)
+    );
+
+    let account = verify_account_proof(&root, &contract, &state.account_proof(&contract))
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.storage_root, state.storage_root(&contract));
+    let slot_proof = state.storage_proof(&contract, &[1; 32]);
+    assert_eq!(
+        verify_storage_proof(&account.storage_root, &[1; 32], &slot_proof),
+        Ok([7; 32])
+    );
+    let empty_proof = state.storage_proof(&contract, &[3; 32]);
+    assert_eq!(
+        verify_storage_proof(&account.storage_root, &[3; 32], &empty_proof),
+        Ok(ZERO_HASH)
+    );
+
+    // A proof from an older state does not verify against the new root
+    state.set_balance(&Address([5; 20]), 1);
+    assert!(verify_account_proof(&state.state_root(), &Address([5; 20]), &proof).is_err());
+}
+
+#[test]
+fn stored_proofs_match_in_memory_proofs() {
+    let mut state = WorldState::new();
+    let contract = Address([0xcc; 20]);
+    for byte in 1..=20 {
+        state.set_balance(&Address([byte; 20]), byte as u128);
+    }
+    state.set_storage(&contract, &[1; 32], [7; 32]);
+    let store = MemoryStore::new();
+    let mut batch = WriteBatch::new();
+    let root = state.write_to(&mut batch);
+    store.write(batch).unwrap();
+
+    for address in [Address([5; 20]), contract, Address([0xee; 20])] {
+        let (account, proof) = stored_account_proof(&store, &root, &address).unwrap();
+        assert_eq!(proof, state.account_proof(&address));
+        assert_eq!(account, state.account(&address));
+    }
+    let storage_root = state.storage_root(&contract);
+    for slot in [[1; 32], [2; 32]] {
+        let (value, proof) = stored_storage_proof(&store, &storage_root, &slot).unwrap();
+        assert_eq!(proof, state.storage_proof(&contract, &slot));
+        assert_eq!(value, state.get_storage(&contract, &slot));
+    }
+}
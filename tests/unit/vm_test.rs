@@ -0,0 +1,222 @@
+use tburn_chain_v4_0::core::executor::{create2_address, create_address};
+use tburn_chain_v4_0::core::types::{keccak256, H256};
+use tburn_chain_v4_0::core::vm::{CallOutput, Evm, ExitReason, Message, VmError};
+use tburn_chain_v4_0::core::{Address, GasSchedule, StateDB, WorldState};
+
+#[path = "../common/mod.rs"]
+mod common;
+
+use common::{env, init_code};
+
+const CALLER: Address = Address([0xaa; 20]);
+const CONTRACT: Address = Address([0xcc; 20]);
+
+fn word(value: u64) -> H256 {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Two's complement encoding of `-value`.
+fn negative(value: u64) -> H256 {
+    let mut word = [0xff; 32];
+    word[24..].copy_from_slice(&(!(value - 1)).to_be_bytes());
+    word
+}
+
+/// `code` followed by returning the top of the stack as one word.
+fn returning(code: &[u8]) -> Vec<u8> {
+    let mut code = code.to_vec();
+    code.extend_from_slice(&[0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3]);
+    code
+}
+
+fn run(state: &mut WorldState, code: &[u8], gas: u64) -> CallOutput {
+    state.set_code(&CONTRACT, code.to_vec());
+    let env = env();
+    let mut evm = Evm::new(state, &env, CALLER, 1);
+    evm.execute(Message::call(CALLER, CONTRACT, 0, Vec::new(), gas))
+}
+
+fn eval(code: &[u8]) -> H256 {
+    let output = run(&mut WorldState::new(), &returning(code), 100_000);
+    assert_eq!(output.exit, ExitReason::Success);
+    output.output.try_into().unwrap()
+}
+
+#[test]
+fn arithmetic_matches_evm_semantics() {
+    // 7 - 10 wraps, (2 + 3) * 4, 2 ** 10
+    assert_eq!(eval(&[0x60, 0x0a, 0x60, 0x07, 0x03]), negative(3));
+    assert_eq!(eval(&[0x60, 0x04, 0x60, 0x03, 0x60, 0x02, 0x01, 0x02]), word(20));
+    assert_eq!(eval(&[0x60, 0x0a, 0x60, 0x02, 0x0a]), word(1024));
+    // Division by zero is zero
+    assert_eq!(eval(&[0x60, 0x00, 0x60, 0x05, 0x04]), word(0));
+    // -8 / 2 = -4, -8 >> 1 (arithmetic) = -4, sign extending 0xfc from byte 0 = -4
+    assert_eq!(eval(&[0x60, 0x02, 0x60, 0x08, 0x5f, 0x03, 0x05]), negative(4));
+    assert_eq!(eval(&[0x60, 0x08, 0x5f, 0x03, 0x60, 0x01, 0x1d]), negative(4));
+    assert_eq!(eval(&[0x60, 0xfc, 0x5f, 0x0b]), negative(4));
+    // (10 * 10) % 7 without overflow
+    assert_eq!(eval(&[0x60, 0x07, 0x60, 0x0a, 0x60, 0x0a, 0x09]), word(2));
+}
+
+#[test]
+fn jumps_must_land_on_jumpdest() {
+    // PUSH1 4, JUMP, INVALID, JUMPDEST, PUSH1 42
+    assert_eq!(eval(&[0x60, 0x04, 0x56, 0xfe, 0x5b, 0x60, 0x2a]), word(42));
+
+    // Jumping into PUSH data halts and consumes all gas
+    let output = run(&mut WorldState::new(), &[0x60, 0x04, 0x56, 0x60, 0x5b], 10_000);
+    assert_eq!(output.exit, ExitReason::Halt(VmError::InvalidJump));
+    assert_eq!(output.gas_left, 0);
+}
+
+#[test]
+fn storage_writes_are_metered_and_refunded() {
+    let mut state = WorldState::new();
+    // SSTORE(1, 5), then SSTORE(1, 0)
+    let code = [0x60, 0x05, 0x60, 0x01, 0x55, 0x5f, 0x60, 0x01, 0x55];
+    state.set_code(&CONTRACT, code.to_vec());
+    let env = env();
+    let mut evm = Evm::new(&mut state, &env, CALLER, 1);
+    let output = evm.execute(Message::call(CALLER, CONTRACT, 0, Vec::new(), 100_000));
+    assert!(output.is_success());
+    let schedule = GasSchedule::default();
+    let pushes = 3 * schedule.very_low + schedule.base;
+    assert_eq!(
+        100_000 - output.gas_left,
+        pushes + schedule.sstore_set + schedule.sstore_reset
+    );
+    assert_eq!(evm.refund(), schedule.sstore_clear_refund);
+    drop(evm);
+    assert_eq!(state.get_storage(&CONTRACT, &word(1)), word(0));
+}
+
+#[test]
+fn revert_undoes_state_and_logs_but_returns_gas() {
+    let mut state = WorldState::new();
+    // SSTORE(0, 1), LOG0 of empty data, REVERT with 32 bytes of memory
+    let code = [
+        0x60, 0x01, 0x5f, 0x55, 0x5f, 0x5f, 0xa0, 0x60, 0x2a, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xfd,
+    ];
+    state.set_code(&CONTRACT, code.to_vec());
+    let env = env();
+    let mut evm = Evm::new(&mut state, &env, CALLER, 1);
+    let output = evm.execute(Message::call(CALLER, CONTRACT, 0, Vec::new(), 100_000));
+    assert_eq!(output.exit, ExitReason::Revert);
+    assert_eq!(output.output, word(42).to_vec());
+    assert!(output.gas_left > 0);
+    assert!(evm.logs().is_empty());
+    assert_eq!(evm.refund(), 0);
+    drop(evm);
+    assert_eq!(state.get_storage(&CONTRACT, &word(0)), word(0));
+}
+
+#[test]
+fn create_deploys_returned_code_and_charges_deposit() {
+    let mut state = WorldState::new();
+    state.set_balance(&CALLER, 1_000);
+    let runtime = returning(&[0x60, 0x2a]);
+    let address = create_address(&CALLER, 0);
+    let env = env();
+    let mut evm = Evm::new(&mut state, &env, CALLER, 1);
+    let output = evm.execute(Message::create(CALLER, address, 100, init_code(&runtime), 200_000));
+    assert!(output.is_success());
+    assert_eq!(output.created, Some(address));
+    drop(evm);
+    assert_eq!(state.code(&address), runtime);
+    assert_eq!(state.get_nonce(&address), 1);
+    assert_eq!(state.get_balance(&address), 100);
+
+    // Deploying to the same address again collides
+    let mut evm = Evm::new(&mut state, &env, CALLER, 1);
+    let output = evm.execute(Message::create(CALLER, address, 0, init_code(&runtime), 200_000));
+    assert_eq!(output.exit, ExitReason::Halt(VmError::CreateCollision));
+}
+
+#[test]
+fn create2_uses_salt_and_init_code_hash() {
+    let mut state = WorldState::new();
+    let init = init_code(&returning(&[0x60, 0x07]));
+    // Copy the init code into memory, then CREATE2(0, 0, len, salt = 9)
+    let mut code = Vec::new();
+    code.extend_from_slice(&[0x60, init.len() as u8, 0x60, 0x00, 0x5f, 0x39]);
+    code.extend_from_slice(&[0x60, 0x09, 0x60, init.len() as u8, 0x5f, 0x5f, 0xf5]);
+    let code_len = code.len() + 8;
+    code[3] = code_len as u8;
+    let mut code = returning(&code);
+    code.extend_from_slice(&init);
+
+    let output = run(&mut state, &code, 500_000);
+    assert!(output.is_success());
+    let expected = create2_address(&CONTRACT, &word(9), &keccak256(&init));
+    assert_eq!(output.output[12..], expected.0);
+    assert_eq!(state.code(&expected), returning(&[0x60, 0x07]));
+    assert_eq!(state.get_nonce(&CONTRACT), 1);
+}
+
+#[test]
+fn static_calls_cannot_write_state() {
+    let mut state = WorldState::new();
+    let target = Address([0xdd; 20]);
+    state.set_code(&target, vec![0x60, 0x01, 0x5f, 0x55]);
+    // STATICCALL(gas, target, 0, 0, 0, 0) and return its success flag
+    let mut code = vec![0x5f, 0x5f, 0x5f, 0x5f, 0x73];
+    code.extend_from_slice(&target.0);
+    code.extend_from_slice(&[0x5a, 0xfa]);
+    let output = run(&mut state, &returning(&code), 100_000);
+    assert!(output.is_success());
+    assert_eq!(output.output, word(0).to_vec());
+    assert_eq!(state.get_storage(&target, &word(0)), word(0));
+}
+
+#[test]
+fn calls_transfer_value_and_delegatecall_keeps_context() {
+    let mut state = WorldState::new();
+    let library = Address([0xdd; 20]);
+    // Stores CALLER at slot 0 of whichever account runs it
+    state.set_code(&library, vec![0x33, 0x5f, 0x55]);
+    state.set_balance(&CONTRACT, 500);
+
+    // CALL(gas, library, 50, 0, 0, 0, 0), then DELEGATECALL(gas, library, 0, 0, 0, 0)
+    let mut code = vec![0x5f, 0x5f, 0x5f, 0x5f, 0x60, 0x32, 0x73];
+    code.extend_from_slice(&library.0);
+    code.extend_from_slice(&[0x5a, 0xf1, 0x50, 0x5f, 0x5f, 0x5f, 0x5f, 0x73]);
+    code.extend_from_slice(&library.0);
+    code.extend_from_slice(&[0x5a, 0xf4]);
+    let output = run(&mut state, &returning(&code), 200_000);
+    assert!(output.is_success());
+    assert_eq!(output.output, word(1).to_vec());
+
+    assert_eq!(state.get_balance(&library), 50);
+    assert_eq!(state.get_balance(&CONTRACT), 450);
+    let mut caller = [0u8; 32];
+    caller[12..].copy_from_slice(&CONTRACT.0);
+    assert_eq!(state.get_storage(&library, &word(0)), caller);
+    let mut origin = [0u8; 32];
+    origin[12..].copy_from_slice(&CALLER.0);
+    assert_eq!(state.get_storage(&CONTRACT, &word(0)), origin);
+}
+
+#[test]
+fn deep_recursion_is_bounded_by_gas() {
+    let mut state = WorldState::new();
+    // Call itself with all available gas until the 63/64 rule runs it dry
+    let code = [0x5f, 0x5f, 0x5f, 0x5f, 0x5f, 0x30, 0x5a, 0xf1, 0x00];
+    let output = run(&mut state, &code, 30_000_000);
+    assert!(output.is_success());
+}
+
+#[test]
+fn stack_and_gas_limits_halt() {
+    let output = run(&mut WorldState::new(), &[0x01], 10_000);
+    assert_eq!(output.exit, ExitReason::Halt(VmError::StackUnderflow));
+
+    // JUMPDEST, PUSH0, PUSH0, JUMP loops until the stack is full
+    let output = run(&mut WorldState::new(), &[0x5b, 0x5f, 0x5f, 0x56], 1_000_000);
+    assert_eq!(output.exit, ExitReason::Halt(VmError::StackOverflow));
+
+    let output = run(&mut WorldState::new(), &[0x5b, 0x5f, 0x56], 1_000);
+    assert_eq!(output.exit, ExitReason::Halt(VmError::OutOfGas));
+    assert_eq!(output.gas_left, 0);
+}
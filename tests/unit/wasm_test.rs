@@ -0,0 +1,227 @@
+use tburn_chain_v4_0::core::executor::create_address;
+use tburn_chain_v4_0::core::types::H256;
+use tburn_chain_v4_0::core::vm::wasm_vm;
+use tburn_chain_v4_0::core::vm::{CallOutput, Evm, ExitReason, Message, VmError};
+use tburn_chain_v4_0::core::{Address, GasSchedule, StateDB, WorldState};
+
+#[path = "../common/mod.rs"]
+mod common;
+
+use common::env;
+
+const CALLER: Address = Address([0xaa; 20]);
+const CONTRACT: Address = Address([0xcc; 20]);
+
+fn word(value: u8) -> H256 {
+    let mut word = [0u8; 32];
+    word[31] = value;
+    word
+}
+
+fn call(state: &mut WorldState, to: Address, gas: u64) -> CallOutput {
+    let env = env();
+    let mut evm = Evm::new(state, &env, CALLER, 1);
+    evm.execute(Message::call(CALLER, to, 0, Vec::new(), gas))
+}
+
+fn install(state: &mut WorldState, wat: &str) {
+    let code = wat::parse_str(wat).unwrap();
+    state.set_code(&CONTRACT, code);
+}
+
+const COUNTER: &str = r#"
+(module
+  (import "env" "storage_read" (func $read (param i32 i32)))
+  (import "env" "storage_write" (func $write (param i32 i32)))
+  (import "env" "emit_event" (func $emit (param i32 i32 i32 i32)))
+  (import "env" "set_return" (func $return (param i32 i32)))
+  (memory (export "memory") 1)
+  ;; Slot 0 lives at 0..32 and its value at 32..64
+  (func (export "call")
+    (call $read (i32.const 0) (i32.const 32))
+    (i32.store8 (i32.const 63) (i32.add (i32.load8_u (i32.const 63)) (i32.const 1)))
+    (call $write (i32.const 0) (i32.const 32))
+    (call $emit (i32.const 32) (i32.const 1) (i32.const 0) (i32.const 0))
+    (call $return (i32.const 32) (i32.const 32)))
+  (func (export "deploy")
+    (i32.store8 (i32.const 63) (i32.const 10))
+    (call $write (i32.const 0) (i32.const 32))))
+"#;
+
+#[test]
+fn wasm_contracts_deploy_and_keep_state() {
+    let mut state = WorldState::new();
+    let code = wat::parse_str(COUNTER).unwrap();
+    let address = create_address(&CALLER, 0);
+    let env = env();
+    let mut evm = Evm::new(&mut state, &env, CALLER, 1);
+    let output = evm.execute(Message::create(CALLER, address, 0, code.clone(), 1_000_000));
+    assert!(output.is_success(), "{output:?}");
+    assert_eq!(output.created, Some(address));
+    drop(evm);
+    assert_eq!(state.code(&address), code);
+    assert_eq!(state.get_storage(&address, &word(0)), word(10));
+
+    let mut evm = Evm::new(&mut state, &env, CALLER, 1);
+    let output = evm.execute(Message::call(CALLER, address, 0, Vec::new(), 100_000));
+    assert!(output.is_success());
+    assert_eq!(output.output, word(11).to_vec());
+    assert_eq!(evm.logs().len(), 1);
+    assert_eq!(evm.logs()[0].address, address);
+    assert_eq!(evm.logs()[0].topics, vec![word(11)]);
+    let used = 100_000 - output.gas_left;
+    let schedule = GasSchedule::default();
+    assert!(used > schedule.sload + schedule.sstore_reset + schedule.log_cost(1, 0));
+    drop(evm);
+    assert_eq!(state.get_storage(&address, &word(0)), word(11));
+
+    // Metering is deterministic
+    let mut replay = state.clone();
+    let first = call(&mut state, address, 100_000);
+    let second = call(&mut replay, address, 100_000);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn modules_without_entry_point_or_with_floats_are_rejected() {
+    let floats =
+        wat::parse_str(r#"(module (memory (export "memory") 1) (func (export "call") (drop (f32.const 1.5))))"#)
+            .unwrap();
+    assert_eq!(wasm_vm::validate(&floats), Err(VmError::InvalidWasm));
+
+    let no_entry = wat::parse_str(r#"(module (memory (export "memory") 1) (func (export "main")))"#).unwrap();
+    assert_eq!(wasm_vm::validate(&no_entry), Err(VmError::InvalidWasm));
+
+    let mut state = WorldState::new();
+    let env = env();
+    let mut evm = Evm::new(&mut state, &env, CALLER, 1);
+    let output = evm.execute(Message::create(CALLER, CONTRACT, 0, floats, 1_000_000));
+    assert_eq!(output.exit, ExitReason::Halt(VmError::InvalidWasm));
+}
+
+#[test]
+fn infinite_loops_run_out_of_fuel() {
+    let mut state = WorldState::new();
+    install(
+        &mut state,
+        r#"(module (memory (export "memory") 1) (func (export "call") (loop $l (br $l))))"#,
+    );
+    let output = call(&mut state, CONTRACT, 50_000);
+    assert_eq!(output.exit, ExitReason::Halt(VmError::OutOfGas));
+    assert_eq!(output.gas_left, 0);
+}
+
+#[test]
+fn code_size_and_initial_memory_are_charged_up_front() {
+    let schedule = GasSchedule::default();
+    let module = |pages: u32| format!(r#"(module (memory (export "memory") {pages}) (func (export "call")))"#);
+    let code_len = wat::parse_str(module(1)).unwrap().len() as u64;
+
+    let mut state = WorldState::new();
+    install(&mut state, &module(1));
+    let small = 100_000 - call(&mut state, CONTRACT, 100_000).gas_left;
+    assert!(small >= code_len * schedule.wasm_code_byte + schedule.wasm_memory_page);
+
+    install(&mut state, &module(64));
+    let large = call(&mut state, CONTRACT, 1_000_000);
+    assert!(large.is_success());
+    assert_eq!(1_000_000 - large.gas_left - small, 63 * schedule.wasm_memory_page);
+
+    // Loading alone exhausts the gas; cached modules are charged the same
+    let output = call(&mut state, CONTRACT, 64 * schedule.wasm_memory_page);
+    assert_eq!(output.exit, ExitReason::Halt(VmError::OutOfGas));
+    assert_eq!(call(&mut state, CONTRACT, 1_000_000), large);
+}
+
+#[test]
+fn memory_growth_is_charged_per_page() {
+    let schedule = GasSchedule::default();
+    let module = |pages: u32| {
+        format!(
+            r#"(module (memory (export "memory") 0)
+                 (func (export "call") (drop (memory.grow (i32.const {pages})))))"#
+        )
+    };
+    let mut state = WorldState::new();
+    install(&mut state, &module(1));
+    let one = 1_000_000 - call(&mut state, CONTRACT, 1_000_000).gas_left;
+    assert!(one >= schedule.wasm_memory_page);
+
+    install(&mut state, &module(63));
+    let grown = call(&mut state, CONTRACT, 1_000_000);
+    assert!(grown.is_success());
+    // The interpreter's own fuel for `memory.grow` comes on top of the per-page price
+    assert!(1_000_000 - grown.gas_left - one >= 62 * schedule.wasm_memory_page);
+
+    // Growth the gas cannot pay for is refused, not granted for free
+    let refused = call(&mut state, CONTRACT, 10 * schedule.wasm_memory_page);
+    assert!(refused.is_success());
+    assert!(10 * schedule.wasm_memory_page - refused.gas_left < schedule.wasm_memory_page);
+}
+
+#[test]
+fn revert_undoes_storage_and_returns_data() {
+    let mut state = WorldState::new();
+    install(
+        &mut state,
+        r#"
+        (module
+          (import "env" "storage_write" (func $write (param i32 i32)))
+          (import "env" "revert" (func $revert (param i32 i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 63) "\01")
+          (func (export "call")
+            (call $write (i32.const 0) (i32.const 32))
+            (call $revert (i32.const 60) (i32.const 4))))
+        "#,
+    );
+    let output = call(&mut state, CONTRACT, 100_000);
+    assert_eq!(output.exit, ExitReason::Revert);
+    assert_eq!(output.output, vec![0, 0, 0, 1]);
+    assert!(output.gas_left > 0);
+    assert_eq!(state.get_storage(&CONTRACT, &word(0)), word(0));
+}
+
+#[test]
+fn wasm_contracts_call_bytecode_contracts() {
+    let mut state = WorldState::new();
+    let target = Address([0xdd; 20]);
+    // PUSH1 42, then return it as one word
+    state.set_code(&target, vec![0x60, 0x2a, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xf3]);
+    let address: String = target.0.iter().map(|b| format!("\\{b:02x}")).collect();
+    install(
+        &mut state,
+        &format!(
+            r#"
+            (module
+              (import "env" "call" (func $call (param i32 i32 i32 i32 i64) (result i32)))
+              (import "env" "return_data_size" (func $size (result i32)))
+              (import "env" "return_data_copy" (func $copy (param i32)))
+              (import "env" "set_return" (func $return (param i32 i32)))
+              (memory (export "memory") 1)
+              (data (i32.const 0) "{address}")
+              (func (export "call")
+                (if (call $call (i32.const 0) (i32.const 32) (i32.const 0) (i32.const 0) (i64.const 50000))
+                  (then unreachable))
+                (call $copy (i32.const 64))
+                (call $return (i32.const 64) (call $size))))
+            "#
+        ),
+    );
+    let output = call(&mut state, CONTRACT, 200_000);
+    assert!(output.is_success(), "{output:?}");
+    assert_eq!(output.output, word(42).to_vec());
+}
+
+#[test]
+fn static_calls_into_wasm_cannot_write() {
+    let mut state = WorldState::new();
+    install(&mut state, COUNTER);
+    let env = env();
+    let mut evm = Evm::new(&mut state, &env, CALLER, 1);
+    let output = evm.execute(Message {
+        is_static: true,
+        ..Message::call(CALLER, CONTRACT, 0, Vec::new(), 100_000)
+    });
+    assert_eq!(output.exit, ExitReason::Halt(VmError::StaticViolation));
+}